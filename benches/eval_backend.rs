@@ -0,0 +1,76 @@
+#![feature(test)]
+
+///! Test evaluating scripts with different backends
+extern crate test;
+
+use rhai::{Backend, Engine, OptimizationLevel};
+use test::Bencher;
+
+const SCRIPT_LOOP: &str = r#"
+let sum = 0;
+let i = 0;
+
+while i < 1_000 {
+    if i % 3 == 0 { sum += i; }
+    i += 1;
+}
+"#;
+
+const SCRIPT_FOR: &str = r#"
+let sum = 0;
+
+for x in range(0, 1_000) {
+    for y in range(0, 10) {
+        if y > x { break; }
+        sum += y;
+    }
+}
+"#;
+
+const SCRIPT_FN: &str = r#"
+fn fib(n) {
+    if n < 2 { n } else { fib(n - 1) + fib(n - 2) }
+}
+
+fib(15)
+"#;
+
+fn bench_script(bench: &mut Bencher, script: &str, backend: Backend) {
+    let mut engine = Engine::new();
+    engine.set_optimization_level(OptimizationLevel::None);
+    engine.set_backend(backend);
+
+    let ast = engine.compile(script).unwrap();
+
+    bench.iter(|| engine.consume_ast(&ast).unwrap());
+}
+
+#[bench]
+fn bench_eval_backend_loop_tree_walking(bench: &mut Bencher) {
+    bench_script(bench, SCRIPT_LOOP, Backend::TreeWalking);
+}
+
+#[bench]
+fn bench_eval_backend_loop_bytecode(bench: &mut Bencher) {
+    bench_script(bench, SCRIPT_LOOP, Backend::Bytecode);
+}
+
+#[bench]
+fn bench_eval_backend_for_tree_walking(bench: &mut Bencher) {
+    bench_script(bench, SCRIPT_FOR, Backend::TreeWalking);
+}
+
+#[bench]
+fn bench_eval_backend_for_bytecode(bench: &mut Bencher) {
+    bench_script(bench, SCRIPT_FOR, Backend::Bytecode);
+}
+
+#[bench]
+fn bench_eval_backend_fn_tree_walking(bench: &mut Bencher) {
+    bench_script(bench, SCRIPT_FN, Backend::TreeWalking);
+}
+
+#[bench]
+fn bench_eval_backend_fn_bytecode(bench: &mut Bencher) {
+    bench_script(bench, SCRIPT_FN, Backend::Bytecode);
+}
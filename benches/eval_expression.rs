@@ -156,3 +156,22 @@ fn bench_eval_loop_strings_no_build(bench: &mut Bencher) {
 
     bench.iter(|| engine.consume_ast(&ast).unwrap());
 }
+
+#[bench]
+fn bench_eval_loop_inlined_fn(bench: &mut Bencher) {
+    let script = r#"
+        fn scale(x, factor) { x * factor + 1 }
+
+        let s = 0;
+        for x in range(0, 10000) {
+            s += scale(x, 2);
+        }
+    "#;
+
+    let mut engine = Engine::new();
+    engine.set_optimization_level(OptimizationLevel::Full);
+
+    let ast = engine.compile(script).unwrap();
+
+    bench.iter(|| engine.consume_ast(&ast).unwrap());
+}
@@ -75,3 +75,60 @@ fn bench_eval_scope_complex(bench: &mut Bencher) {
 
     bench.iter(|| engine.consume_ast_with_scope(&mut scope, &ast).unwrap());
 }
+
+#[bench]
+fn bench_eval_scope_locals(bench: &mut Bencher) {
+    let mut script = String::from("let x = 0; let sum = 0;");
+
+    for i in 0..50 {
+        script.push_str(&format!("let var_{} = {};", i, i));
+    }
+
+    script.push_str(
+        r#"
+            while x < 100 {
+                sum += x * 2;
+                x += 1;
+            }
+            sum
+        "#,
+    );
+
+    let mut engine = Engine::new();
+    engine.set_optimization_level(OptimizationLevel::None);
+
+    let ast = engine.compile(&script).unwrap();
+
+    bench.iter(|| {
+        let mut scope = Scope::new();
+        engine.consume_ast_with_scope(&mut scope, &ast).unwrap()
+    });
+}
+
+#[bench]
+fn bench_eval_scope_host_pushed(bench: &mut Bencher) {
+    let script = r#"
+            while x < 100 {
+                sum += x * 2;
+                x += 1;
+            }
+            sum
+        "#;
+
+    let mut engine = Engine::new();
+    engine.set_optimization_level(OptimizationLevel::None);
+
+    let ast = engine.compile(script).unwrap();
+
+    bench.iter(|| {
+        let mut scope = Scope::new();
+        scope.push("x", 0 as INT);
+        scope.push("sum", 0 as INT);
+
+        for i in 0..50 {
+            scope.push(format!("var_{}", i), i as INT);
+        }
+
+        engine.consume_ast_with_scope(&mut scope, &ast).unwrap()
+    });
+}
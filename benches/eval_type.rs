@@ -98,3 +98,28 @@ fn bench_type_method_nested(bench: &mut Bencher) {
 
     bench.iter(|| engine.consume_ast_with_scope(&mut scope, &ast).unwrap());
 }
+
+#[bench]
+fn bench_type_method_loop(bench: &mut Bencher) {
+    let script = r#"
+            let i = 0;
+            while i < 100 {
+                foo.update(i);
+                i += 1;
+            }
+        "#;
+
+    let mut engine = Engine::new();
+    engine.set_optimization_level(OptimizationLevel::None);
+
+    engine.register_type_with_name::<Test>("Test");
+    engine.register_fn("update", Test::update);
+
+    let ast = engine.compile(script).unwrap();
+
+    bench.iter(|| {
+        let mut scope = Scope::new();
+        scope.push("foo", Test { x: 42 });
+        engine.consume_ast_with_scope(&mut scope, &ast).unwrap()
+    });
+}
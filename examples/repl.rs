@@ -1,9 +1,10 @@
-use rhai::{Dynamic, Engine, EvalAltResult, Scope, AST};
+use rhai::{DebugContext, DebugEvent, DebuggerCommand, Dynamic, Engine, EvalAltResult, Scope, AST};
 
 #[cfg(not(feature = "no_optimize"))]
 use rhai::OptimizationLevel;
 
 use std::io::{stdin, stdout, Write};
+use std::sync::{Arc, RwLock};
 
 fn print_error(input: &str, err: EvalAltResult) {
     let lines: Vec<_> = input.trim().split('\n').collect();
@@ -51,13 +52,92 @@ fn print_help() {
     println!("scope      => print all variables in the scope");
     println!("ast        => print the last AST");
     println!("astu       => print the last raw, un-optimized AST");
-    println!(r"end a line with '\' to continue to the next line.");
+    println!("break <line> [if <condition>] => set a breakpoint, optionally with a condition");
+    println!("clear [<line>] => clear the breakpoints on a line, or all breakpoints");
+    println!("breakpoints => list all breakpoints");
+    println!("incomplete input (e.g. an unclosed block) continues on the next line.");
     println!();
 }
 
+fn print_debugger_help() {
+    println!("continue, c => continue to the next breakpoint");
+    println!("step, s     => step into the next statement or expression");
+    println!("next, n     => step over to the next statement");
+    println!("out, o      => step out of the current function");
+    println!("scope       => print all variables in the scope");
+    println!("this        => print the value of 'this'");
+    println!("stack       => print the call stack");
+    println!("abort       => abort the script");
+    println!();
+}
+
+/// Pause at a breakpoint or step, reading debugger commands until the script should continue.
+fn debugger(source: &str, context: &DebugContext) -> DebuggerCommand {
+    let pos = context.position();
+
+    match context.event() {
+        DebugEvent::Breakpoint(line) => {
+            println!("Breakpoint at line {} ({:?})", line, context.node())
+        }
+        DebugEvent::Step => println!("Step at {} ({:?})", pos, context.node()),
+    }
+
+    if let Some(line) = pos.line().and_then(|line| source.lines().nth(line - 1)) {
+        println!("{}: {}", pos.line().unwrap(), line);
+    }
+
+    let mut input = String::new();
+
+    loop {
+        print!("debug> ");
+        stdout().flush().expect("couldn't flush stdout");
+
+        input.clear();
+
+        if let Err(err) = stdin().read_line(&mut input) {
+            panic!("input error: {}", err);
+        }
+
+        match input.trim() {
+            "continue" | "c" => break DebuggerCommand::Continue,
+            "step" | "s" => break DebuggerCommand::StepInto,
+            "next" | "n" => break DebuggerCommand::StepOver,
+            "out" | "o" => break DebuggerCommand::StepOut,
+            "abort" => break DebuggerCommand::Abort,
+            "scope" => context
+                .scope()
+                .iter()
+                .enumerate()
+                .for_each(|(i, (name, value))| println!("[{}] {} = {:?}", i + 1, name, value)),
+            "this" => match context.this_ptr() {
+                Some(value) => println!("this = {:?}", value),
+                None => println!("'this' is not bound"),
+            },
+            "stack" => {
+                if context.call_stack().is_empty() {
+                    println!("Not in a function");
+                }
+                context.call_stack().iter().rev().for_each(|frame| {
+                    println!(
+                        "{}({:?}) called at {}",
+                        frame.fn_name, frame.args, frame.pos
+                    )
+                });
+            }
+            _ => print_debugger_help(),
+        }
+    }
+}
+
 fn main() {
     let mut engine = Engine::new();
 
+    // Source of the script being run, for printing the lines paused at
+    let source = Arc::new(RwLock::new(String::new()));
+    let debugger_source = source.clone();
+
+    engine.attach_debugger(move |context| debugger(&debugger_source.read().unwrap(), context));
+
     #[cfg(not(feature = "no_optimize"))]
     engine.set_optimization_level(OptimizationLevel::None);
 
@@ -79,18 +159,14 @@ fn main() {
         input.clear();
 
         loop {
-            if let Err(err) = stdin().read_line(&mut input) {
-                panic!("input error: {}", err);
+            match stdin().read_line(&mut input) {
+                Ok(0) => break,
+                Ok(_) => (),
+                Err(err) => panic!("input error: {}", err),
             }
 
-            let line = input.as_str().trim_end();
-
-            // Allow line continuation
-            if line.ends_with('\\') {
-                let len = line.len();
-                input.truncate(len - 1);
-                input.push('\n');
-            } else {
+            // Keep reading lines until the input is complete - syntax errors are reported when compiling
+            if engine.check_complete(&input) != Ok(false) {
                 break;
             }
 
@@ -98,6 +174,11 @@ fn main() {
             stdout().flush().expect("couldn't flush stdout");
         }
 
+        // End of input
+        if input.is_empty() {
+            break;
+        }
+
         let script = input.trim();
 
         if script.is_empty() {
@@ -128,9 +209,51 @@ fn main() {
                 println!("{:#?}", &ast);
                 continue;
             }
+            "breakpoints" => {
+                engine
+                    .breakpoints()
+                    .for_each(|line| println!("Breakpoint at line {}", line));
+                continue;
+            }
+            "clear" => {
+                engine.clear_breakpoints();
+                continue;
+            }
             _ => (),
         }
 
+        if let Some(args) = script.strip_prefix("break ") {
+            let (line, condition) = match args.find(" if ") {
+                Some(index) => (&args[..index], Some(&args[index + 4..])),
+                None => (args, None),
+            };
+
+            match (line.trim().parse(), condition) {
+                (Ok(line), None) => {
+                    engine.set_breakpoint(line);
+                }
+                (Ok(line), Some(condition)) => {
+                    if let Err(err) = engine.set_conditional_breakpoint(line, condition) {
+                        println!("Invalid condition: {}", err);
+                    }
+                }
+                (Err(_), _) => println!("Invalid line number: {}", line.trim()),
+            }
+            continue;
+        }
+
+        if let Some(line) = script.strip_prefix("clear ") {
+            match line.trim().parse() {
+                Ok(line) => {
+                    engine.clear_breakpoint(line);
+                }
+                Err(_) => println!("Invalid line number: {}", line.trim()),
+            }
+            continue;
+        }
+
+        *source.write().unwrap() = script.to_string();
+
         match engine
             .compile_with_scope(&scope, &script)
             .map_err(Into::into)
@@ -24,15 +24,16 @@ fn eprint_error(input: &str, err: EvalAltResult) {
 
     let lines: Vec<_> = input.split('\n').collect();
 
-    // Print error
-    let pos = err.position();
+    // Print the error where it first occurred
+    let root = err.root_cause();
+    let pos = root.position();
 
     if pos.is_none() {
         // No position
-        eprintln!("{}", err);
+        eprintln!("{}", root);
     } else {
         // Specific position
-        let err_text = match err {
+        let err_text = match root {
             EvalAltResult::ErrorRuntime(err, _) if !err.is_empty() => {
                 format!("Runtime error: {}", err)
             }
@@ -41,6 +42,17 @@ fn eprint_error(input: &str, err: EvalAltResult) {
 
         eprint_line(&lines, pos, &err_text)
     }
+
+    // Print the function calls leading to the error
+    for frame in err.backtrace() {
+        let text = format!("in call to function '{}'", frame);
+
+        if frame.pos.is_none() {
+            eprintln!("{}", text);
+        } else {
+            eprint_line(&lines, frame.pos, &text);
+        }
+    }
 }
 
 fn main() {
@@ -250,6 +250,24 @@ impl Dynamic {
         }
     }
 
+    /// Apply a function to the address of the shared cell held by this `Dynamic`, and the value in it.
+    /// Returns `None` if the `Dynamic` is not shared, or the value is currently locked for writing.
+    #[cfg(not(feature = "unchecked"))]
+    pub(crate) fn peek_shared<R>(&self, f: impl FnOnce(usize, &Dynamic) -> R) -> Option<R> {
+        match self.0 {
+            Union::Shared(ref cell) => {
+                let addr = &*cell.container as *const _ as usize;
+
+                #[cfg(not(feature = "sync"))]
+                return cell.container.try_borrow().ok().map(|value| f(addr, &value));
+
+                #[cfg(feature = "sync")]
+                return cell.container.try_read().ok().map(|value| f(addr, &value));
+            }
+            _ => None,
+        }
+    }
+
     /// Is the value held by this `Dynamic` a particular type?
     ///
     /// If the `Dynamic` is a Shared variant checking is performed on
@@ -1,15 +1,22 @@
 //! Module that defines the extern API of `Engine`.
 
 use crate::any::{Dynamic, Variant};
+use crate::bytecode::Program;
+use crate::calc_fn_hash;
+use crate::debugger::{Breakpoint, DebugContext, DebuggerCommand};
 use crate::engine::{Engine, Imports, State};
-use crate::error::ParseError;
-use crate::fn_native::{IteratorFn, SendSync};
+use crate::diagnostic::Diagnostic;
+use crate::error::{LexError, ParseError};
+use crate::fn_native::{IteratorFn, SendSync, Shared};
+use crate::interrupt::InterruptHandle;
 use crate::module::{FuncReturn, Module};
 use crate::optimize::{optimize_into_ast, OptimizationLevel};
+use crate::options::EvalOptions;
 use crate::parser::AST;
 use crate::result::EvalAltResult;
 use crate::scope::Scope;
-use crate::token::{lex, Position};
+use crate::stats::EvalStats;
+use crate::token::{lex, Position, Token, TokenStream};
 
 #[cfg(not(feature = "no_index"))]
 #[cfg(not(feature = "no_object"))]
@@ -24,16 +31,30 @@ use crate::{
 #[cfg(not(feature = "no_function"))]
 use crate::{engine::get_script_function_by_signature, fn_args::FuncArgs, utils::StaticVec};
 
+#[cfg(not(feature = "no_optimize"))]
+use crate::visitor::AstVisitor;
+
 use crate::stdlib::{
     any::{type_name, TypeId},
     boxed::Box,
+    iter::empty,
     mem,
+    string::ToString,
+    vec::Vec,
 };
 
 #[cfg(not(feature = "no_std"))]
 #[cfg(not(target_arch = "wasm32"))]
 use crate::stdlib::{fs::File, io::prelude::*, path::PathBuf};
 
+#[cfg(not(feature = "no_std"))]
+#[cfg(not(target_arch = "wasm32"))]
+use crate::stdlib::time::Instant;
+
+#[cfg(not(feature = "no_std"))]
+#[cfg(target_arch = "wasm32")]
+use instant::Instant;
+
 /// Engine public API
 impl Engine {
     /// Register a function of the `Engine`.
@@ -300,6 +321,106 @@ impl Engine {
         self
     }
 
+    /// Register a function reporting the approximate number of bytes held by a value of a custom type,
+    /// including any data it owns.  It is used to track the memory held by the values of a script
+    /// (see `Engine::set_max_memory`).
+    ///
+    /// Without it, only the size of the type itself is counted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// #[derive(Clone)]
+    /// struct Buffer(Vec<u8>);
+    ///
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, EvalAltResult, RegisterFn};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// engine.register_type::<Buffer>();
+    /// engine.register_fn("new_buffer", |size: i64| Buffer(vec![0; size as usize]));
+    ///
+    /// // Report the bytes held by the buffer
+    /// engine.register_type_size(|buf: &Buffer| std::mem::size_of::<Buffer>() + buf.0.capacity());
+    ///
+    /// engine.set_max_memory(10_000);
+    ///
+    /// assert!(matches!(
+    ///     *engine.eval::<()>("let a = new_buffer(8000); let b = new_buffer(8000);").expect_err("should error"),
+    ///     EvalAltResult::ErrorDataTooLarge(_, _, _, _)
+    /// ));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(feature = "unchecked"))]
+    pub fn register_type_size<T: Variant + Clone>(
+        &mut self,
+        size: impl Fn(&T) -> usize + SendSync + 'static,
+    ) -> &mut Self {
+        if self.type_sizes.is_none() {
+            self.type_sizes = Some(Default::default());
+        }
+        // Add the size function into the map
+        self.type_sizes.as_mut().unwrap().insert(
+            TypeId::of::<T>(),
+            Box::new(move |value: &Dynamic| value.read_lock::<T>().map_or(0, |v| size(&v))),
+        );
+        self
+    }
+
+    /// Register a function calculating the extra number of operations that a call to a native
+    /// function counts as, from the arguments of the call.  It is added to the fixed cost of the
+    /// function (see `Engine::set_fn_cost`).
+    ///
+    /// The function is identified by its name and a list of `TypeId`'s indicating the actual types
+    /// of its parameters, so each overload of a function may have a different cost.
+    ///
+    /// This allows native functions to be charged according to the size of their input, e.g. the
+    /// length of an array to sort.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// # #[cfg(not(feature = "unchecked"))]
+    /// # {
+    /// use std::any::TypeId;
+    /// use rhai::{Engine, EvalAltResult, ImmutableString};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// engine.set_max_operations(100);
+    ///
+    /// // Charge one operation for each character of a string to print
+    /// engine.register_fn_cost("print", &[TypeId::of::<ImmutableString>()], |args| {
+    ///     args[0].as_str().map_or(0, |s| s.len() as u64)
+    /// });
+    ///
+    /// engine.consume(r#"print("hello");"#)?;
+    ///
+    /// assert!(matches!(
+    ///     *engine.consume(&format!(r#"print("{}");"#, "x".repeat(200))).expect_err("should error"),
+    ///     EvalAltResult::ErrorTooManyOperations(_)
+    /// ));
+    /// # }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn register_fn_cost(
+        &mut self,
+        name: &str,
+        arg_types: &[TypeId],
+        cost: impl Fn(&[&Dynamic]) -> u64 + SendSync + 'static,
+    ) -> &mut Self {
+        let hash_fn = calc_fn_hash(empty(), name, arg_types.len(), arg_types.iter().cloned());
+
+        self.costs
+            .get_or_insert_with(Default::default)
+            .set_fn_cost_callback(hash_fn, cost);
+        self
+    }
+
     /// Register an iterator adapter for a type with the `Engine`.
     /// This is an advanced feature.
     pub fn register_iterator<T: Variant + Clone>(&mut self, f: IteratorFn) -> &mut Self {
@@ -670,6 +791,195 @@ impl Engine {
         self.compile_scripts_with_scope(scope, &[script])
     }
 
+    /// Compile a string into an `AST`, recording the name of its source (e.g. a file name).
+    ///
+    /// The source name is available via `AST::source`.  Functions defined in the script keep it,
+    /// so errors inside them report it even when they are called from another script, e.g. as
+    /// functions of a module (see `EvalAltResult::root_source`).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::Engine;
+    ///
+    /// let engine = Engine::new();
+    ///
+    /// let ast = engine.compile_with_source("hello.rhai", "let x = 42;\nx + y")?;
+    ///
+    /// assert_eq!(ast.source(), Some("hello.rhai"));
+    ///
+    /// let err = engine.eval_ast::<i64>(&ast).expect_err("should error");
+    ///
+    /// assert_eq!(err.to_string(), "Variable not found: 'y' (line 2, position 5) in 'hello.rhai'");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn compile_with_source(&self, source: &str, script: &str) -> Result<AST, ParseError> {
+        self.compile_with_scope_and_source(&Scope::new(), source, script)
+    }
+
+    /// Compile a string into an `AST` using own scope, recording the name of its source.
+    fn compile_with_scope_and_source(
+        &self,
+        scope: &Scope,
+        source: &str,
+        script: &str,
+    ) -> Result<AST, ParseError> {
+        let scripts = [script];
+        let mut stream = TokenStream::new(lex(&scripts, self)).with_source(source);
+        self.parse(&mut stream, scope, self.optimization_level)
+    }
+
+    /// Compile a string into an `AST`, also returning diagnostics (i.e. warnings) for code that
+    /// is valid but probably does not do what is intended.
+    ///
+    /// Diagnostics are reported on the script as written, before optimization, and are sorted by position.
+    /// See `DiagnosticType` for the kinds of diagnostics.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, DiagnosticType};
+    ///
+    /// let engine = Engine::new();
+    ///
+    /// let (ast, diagnostics) = engine.compile_with_diagnostics(r"
+    ///     {
+    ///         let x = 40;
+    ///         let y = 123;        // 'y' is never used
+    ///         x + 2
+    ///     }
+    /// ")?;
+    ///
+    /// assert_eq!(diagnostics.len(), 1);
+    /// assert_eq!(diagnostics[0].0, DiagnosticType::UnusedVariable("y".into()));
+    /// assert_eq!(diagnostics[0].1.line(), Some(4));
+    ///
+    /// assert_eq!(engine.eval_ast::<i64>(&ast)?, 42);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn compile_with_diagnostics(
+        &self,
+        script: &str,
+    ) -> Result<(AST, Vec<Diagnostic>), ParseError> {
+        let scripts = [script];
+        let mut stream = TokenStream::new(lex(&scripts, self));
+        let result =
+            self.parse_with_diagnostics(&mut stream, &Scope::new(), self.optimization_level, true);
+        result
+    }
+
+    /// Compile a string into an `AST`, recovering from parse errors in order to find all of them
+    /// instead of stopping at the first one.
+    ///
+    /// Returns a partial `AST`, leaving out the statements in error, together with all parse errors
+    /// found (in order of position).  The script compiles without errors if the list is empty.
+    ///
+    /// After an error, the parser skips to the start of the next statement, i.e. after the next `;`,
+    /// or up to the next `}` closing the current block or keyword starting a statement.
+    /// A missing `;` between statements is reported, but parsing continues as if it is there.
+    /// Errors later in the script may be caused by the parser skipping too much or too little,
+    /// so only the first error is guaranteed to be the same as the one returned by `compile`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, ParseErrorType};
+    ///
+    /// let engine = Engine::new();
+    ///
+    /// let (ast, errors) = engine.compile_with_recovery(r"
+    ///     let x = 40;
+    ///     let y = ;               // error
+    ///     x += 2;
+    ///     let z = x +* 1;         // error
+    ///     x
+    /// ");
+    ///
+    /// assert_eq!(errors.len(), 2);
+    /// assert_eq!(errors[0].1.line(), Some(3));
+    /// assert_eq!(errors[1].1.line(), Some(5));
+    ///
+    /// // The partial AST is still usable
+    /// assert_eq!(engine.eval_ast::<i64>(&ast)?, 42);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn compile_with_recovery(&self, script: &str) -> (AST, Vec<ParseError>) {
+        let scripts = [script];
+        let mut stream = TokenStream::new(lex(&scripts, self));
+        self.parse_with_recovery(&mut stream, &Scope::new(), self.optimization_level)
+    }
+
+    /// Check whether a script is complete, or whether it needs more input before it can be
+    /// compiled, e.g. to support multi-line input in an interactive shell.
+    ///
+    /// Returns `Ok(true)` if the script parses, `Ok(false)` if it ends prematurely (e.g. in an
+    /// unclosed block, bracket, string or comment, or after an operator) so more input may complete it,
+    /// and the parse error if the script has a syntax error before its end.
+    ///
+    /// The script is only parsed, not compiled, so a complete script may still fail to compile.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), rhai::ParseError> {
+    /// use rhai::Engine;
+    ///
+    /// let engine = Engine::new();
+    ///
+    /// assert!(engine.check_complete("let x = 42;")?);
+    ///
+    /// // More input is needed
+    /// assert!(!engine.check_complete("if x > 0 {")?);
+    /// assert!(!engine.check_complete("let x = 1 +")?);
+    /// assert!(!engine.check_complete("/* comment")?);
+    ///
+    /// // Syntax error
+    /// assert!(engine.check_complete("let x = ) {").is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn check_complete(&self, script: &str) -> Result<bool, ParseError> {
+        let scripts = [script];
+
+        // Find the last token and the end of the input
+        let mut tokens = lex(&scripts, self);
+        let mut last = None;
+        let mut eof = Position::none();
+
+        for (token, pos) in tokens.by_ref() {
+            if token.is_eof() {
+                eof = pos;
+                break;
+            }
+            last = Some((token, pos));
+        }
+
+        let mut stream = TokenStream::new(lex(&scripts, self));
+
+        match self.parse_global_level(&mut stream, None) {
+            Ok(_) => Ok(!tokens.is_in_comment()),
+            // The parser ran out of input
+            Err(err) if err.1 >= eof => Ok(false),
+            // A string is not terminated at the end of the input (strings cannot span lines)
+            Err(err) => match last {
+                Some((Token::LexError(e), pos))
+                    if matches!(*e, LexError::UnterminatedString)
+                        && err.1 == pos
+                        && pos.line() == Some(script.split('\n').count()) =>
+                {
+                    Ok(false)
+                }
+                _ => Err(err),
+            },
+        }
+    }
+
     /// When passed a list of strings, first join the strings into one large script,
     /// and then compile them into an `AST` using own scope, which can be used later for evaluation.
     ///
@@ -731,8 +1041,8 @@ impl Engine {
         scripts: &[&str],
         optimization_level: OptimizationLevel,
     ) -> Result<AST, ParseError> {
-        let stream = lex(scripts, self);
-        self.parse(&mut stream.peekable(), scope, optimization_level)
+        let mut stream = TokenStream::new(lex(scripts, self));
+        self.parse(&mut stream, scope, optimization_level)
     }
 
     /// Read the contents of a file into a string.
@@ -762,6 +1072,8 @@ impl Engine {
 
     /// Compile a script file into an `AST`, which can be used later for evaluation.
     ///
+    /// The path of the file is recorded as the source name of the `AST` (see `compile_with_source`).
+    ///
     /// # Example
     ///
     /// ```no_run
@@ -824,7 +1136,10 @@ impl Engine {
         scope: &Scope,
         path: PathBuf,
     ) -> Result<AST, Box<EvalAltResult>> {
-        Self::read_file(path).and_then(|contents| Ok(self.compile_with_scope(scope, &contents)?))
+        let source = path.to_string_lossy().into_owned();
+
+        Self::read_file(path)
+            .and_then(|contents| Ok(self.compile_with_scope_and_source(scope, &source, &contents)?))
     }
 
     /// Parse a JSON string into a map.
@@ -856,9 +1171,8 @@ impl Engine {
 
         // Trims the JSON string and add a '#' in front
         let scripts = ["#", json.trim()];
-        let stream = lex(&scripts, self);
-        let ast =
-            self.parse_global_expr(&mut stream.peekable(), &scope, OptimizationLevel::None)?;
+        let mut stream = TokenStream::new(lex(&scripts, self));
+        let ast = self.parse_global_expr(&mut stream, &scope, OptimizationLevel::None)?;
 
         // Handle null - map to ()
         if has_null {
@@ -937,11 +1251,8 @@ impl Engine {
         script: &str,
     ) -> Result<AST, ParseError> {
         let scripts = [script];
-        let stream = lex(&scripts, self);
-        {
-            let mut peekable = stream.peekable();
-            self.parse_global_expr(&mut peekable, scope, self.optimization_level)
-        }
+        let mut stream = TokenStream::new(lex(&scripts, self));
+        self.parse_global_expr(&mut stream, scope, self.optimization_level)
     }
 
     /// Evaluate a script file.
@@ -1092,10 +1403,10 @@ impl Engine {
         script: &str,
     ) -> Result<T, Box<EvalAltResult>> {
         let scripts = [script];
-        let stream = lex(&scripts, self);
+        let mut stream = TokenStream::new(lex(&scripts, self));
 
         // No need to optimize a lone expression
-        let ast = self.parse_global_expr(&mut stream.peekable(), scope, OptimizationLevel::None)?;
+        let ast = self.parse_global_expr(&mut stream, scope, OptimizationLevel::None)?;
 
         self.eval_ast_with_scope(scope, &ast)
     }
@@ -1155,9 +1466,158 @@ impl Engine {
         &self,
         scope: &mut Scope,
         ast: &AST,
+    ) -> Result<T, Box<EvalAltResult>> {
+        let mut state = self.new_state(scope, ast.uses_global());
+        self.eval_ast_with_state(scope, &mut state, ast)
+    }
+
+    /// Evaluate an `AST` with own scope, aborting with `EvalAltResult::ErrorTimeout` if it is still
+    /// running at `deadline`.
+    ///
+    /// If the `Engine` limits the execution time, the earlier of the two deadlines applies.
+    ///
+    /// Not available under the `unchecked` or `no_std` features.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use std::time::{Duration, Instant};
+    /// use rhai::{Engine, EvalAltResult, Scope};
+    ///
+    /// let engine = Engine::new();
+    ///
+    /// let ast = engine.compile("loop {}")?;
+    ///
+    /// let deadline = Instant::now() + Duration::from_millis(10);
+    ///
+    /// let result = engine.eval_ast_with_scope_and_deadline::<()>(&mut Scope::new(), &ast, deadline);
+    ///
+    /// assert!(matches!(*result.expect_err("should error"), EvalAltResult::ErrorTimeout(_)));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(feature = "unchecked"))]
+    #[cfg(not(feature = "no_std"))]
+    pub fn eval_ast_with_scope_and_deadline<T: Variant + Clone>(
+        &self,
+        scope: &mut Scope,
+        ast: &AST,
+        deadline: Instant,
+    ) -> Result<T, Box<EvalAltResult>> {
+        let mut state = self.new_state(scope, ast.uses_global());
+        state.set_deadline(deadline);
+        self.eval_ast_with_state(scope, &mut state, ast)
+    }
+
+    /// Evaluate an `AST` with own scope, overriding the limits and optimization level of the `Engine`
+    /// for this evaluation only.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// # #[cfg(not(feature = "unchecked"))]
+    /// # {
+    /// use rhai::{Engine, EvalAltResult, EvalOptions, Scope};
+    ///
+    /// let engine = Engine::new();
+    ///
+    /// let ast = engine.compile(r#"let s = "x"; s.pad(100, 'x'); s"#)?;
+    ///
+    /// let options = EvalOptions {
+    ///     max_string_size: Some(10),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let result = engine.eval_ast_with_scope_and_options::<String>(&mut Scope::new(), &ast, &options);
+    ///
+    /// assert!(matches!(*result.expect_err("should error"), EvalAltResult::ErrorDataTooLarge(_, 10, _, _)));
+    /// # }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn eval_ast_with_scope_and_options<T: Variant + Clone>(
+        &self,
+        scope: &mut Scope,
+        ast: &AST,
+        options: &EvalOptions,
+    ) -> Result<T, Box<EvalAltResult>> {
+        let mut state = self.new_state_with_options(scope, ast.uses_global(), options);
+
+        #[cfg(not(feature = "no_optimize"))]
+        if let Some(optimization_level) = options.optimization_level {
+            let ast = self.optimize_ast(scope, ast.clone(), optimization_level);
+            return self.eval_ast_with_state(scope, &mut state, &ast);
+        }
+
+        self.eval_ast_with_state(scope, &mut state, ast)
+    }
+
+    /// Evaluate an `AST` with own scope, also returning statistics of the evaluation, such as the
+    /// number of operations performed and the deepest level of function calls.
+    ///
+    /// The statistics are returned even when the evaluation fails, e.g. because it exceeds a limit.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// # #[cfg(not(feature = "no_function"))]
+    /// # {
+    /// use rhai::{Engine, Scope};
+    ///
+    /// let engine = Engine::new();
+    ///
+    /// let ast = engine.compile(r"
+    ///     fn fact(n) { if n <= 1 { 1 } else { n * fact(n - 1) } }
+    ///     fact(5)
+    /// ")?;
+    ///
+    /// let (result, stats) = engine.eval_ast_with_stats::<i64>(&mut Scope::new(), &ast);
+    ///
+    /// assert_eq!(result?, 120);
+    /// assert_eq!(stats.max_call_depth, 5);
+    /// assert_eq!(stats.fn_calls.get("fact"), Some(&5));
+    /// assert!(stats.operations > 0);
+    /// # }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn eval_ast_with_stats<T: Variant + Clone>(
+        &self,
+        scope: &mut Scope,
+        ast: &AST,
+    ) -> (Result<T, Box<EvalAltResult>>, EvalStats) {
+        #[cfg(not(feature = "no_std"))]
+        let start = Instant::now();
+
+        let mut state = self.new_state(scope, ast.uses_global());
+        state.stats = Some(Default::default());
+        state.record_scope_size(scope.len());
+
+        let result = self.eval_ast_with_state(scope, &mut state, ast);
+
+        let mut stats = *state.stats.take().unwrap();
+        stats.operations = state.operations;
+
+        #[cfg(not(feature = "no_std"))]
+        {
+            stats.elapsed = start.elapsed();
+        }
+
+        (result, stats)
+    }
+
+    /// Evaluate an `AST` with own scope and evaluation state.
+    fn eval_ast_with_state<T: Variant + Clone>(
+        &self,
+        scope: &mut Scope,
+        state: &mut State,
+        ast: &AST,
     ) -> Result<T, Box<EvalAltResult>> {
         let mut mods = Imports::new();
-        let (result, _) = self.eval_ast_with_scope_raw(scope, &mut mods, ast)?;
+        let result = self.eval_ast_with_scope_raw(scope, &mut mods, state, ast)?;
 
         let typ = self.map_type_name(result.type_name());
 
@@ -1175,20 +1635,92 @@ impl Engine {
         &self,
         scope: &mut Scope,
         mods: &mut Imports,
+        state: &mut State,
         ast: &'a AST,
-    ) -> Result<(Dynamic, u64), Box<EvalAltResult>> {
-        let mut state = State::new();
-
-        ast.statements()
-            .iter()
-            .try_fold(().into(), |_, stmt| {
-                self.eval_stmt(scope, mods, &mut state, ast.lib(), &mut None, stmt, 0)
-            })
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        self.eval_statements(scope, mods, state, ast)
             .or_else(|err| match *err {
                 EvalAltResult::Return(out, _) => Ok(out),
                 _ => Err(err),
             })
-            .map(|v| (v, state.operations))
+    }
+
+    /// Evaluate the global statements of an `AST` with the backend of the `Engine`.
+    ///
+    /// Errors in statements from a source are wrapped in `EvalAltResult::ErrorInScript`.
+    fn eval_statements(
+        &self,
+        scope: &mut Scope,
+        mods: &mut Imports,
+        state: &mut State,
+        ast: &AST,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let statements = ast.statements();
+
+        if self.backend.is_bytecode() {
+            // Statements from different sources (in a merged AST) are run separately,
+            // so that an error can be located in the source of the statement in error
+            let mut starts: Vec<usize> = Vec::new();
+            starts.push(0);
+
+            for index in 1..statements.len() {
+                if ast.source_of_stmt(index) != ast.source_of_stmt(index - 1) {
+                    starts.push(index);
+                }
+            }
+
+            let mut result = Ok(().into());
+
+            for (i, &start) in starts.iter().enumerate() {
+                let end = starts.get(i + 1).copied().unwrap_or(statements.len());
+
+                // Compile on the fly if the AST has no bytecode
+                let program = match ast.program() {
+                    Some(program) if starts.len() == 1 => program.clone(),
+                    _ => Shared::new(Program::new(&statements[start..end])),
+                };
+                state.program = Some(program.clone());
+
+                result = self
+                    .run_code(scope, mods, state, ast.lib(), &mut None, program.main(), 0)
+                    .map_err(|err| Self::locate_error(ast, start, err));
+
+                if result.is_err() {
+                    break;
+                }
+            }
+
+            return result;
+        }
+
+        statements
+            .iter()
+            .enumerate()
+            .try_fold(().into(), |_, (index, stmt)| {
+                self.eval_stmt(scope, mods, state, ast.lib(), &mut None, stmt, 0)
+                    .map_err(|err| Self::locate_error(ast, index, err))
+            })
+    }
+
+    /// Wrap an error in a statement of an `AST` in `EvalAltResult::ErrorInScript` if the statement
+    /// has a source, with the span of the code in which the error that occurred first was raised.
+    fn locate_error(ast: &AST, index: usize, err: Box<EvalAltResult>) -> Box<EvalAltResult> {
+        let source = match (ast.source_of_stmt(index), err.as_ref()) {
+            // Not an error
+            (_, EvalAltResult::Return(_, _)) => return err,
+            (Some(source), _) => source,
+            (None, _) => return err,
+        };
+
+        let mut err = Box::new(EvalAltResult::ErrorInScript(source.to_string(), None, err));
+
+        let span = ast.span_in(err.root_source(), err.root_cause().position());
+
+        if let EvalAltResult::ErrorInScript(_, s, _) = err.as_mut() {
+            *s = span;
+        }
+
+        err
     }
 
     /// Evaluate a file, but throw away the result and only return error (if any).
@@ -1225,8 +1757,8 @@ impl Engine {
         script: &str,
     ) -> Result<(), Box<EvalAltResult>> {
         let scripts = [script];
-        let stream = lex(&scripts, self);
-        let ast = self.parse(&mut stream.peekable(), scope, self.optimization_level)?;
+        let mut stream = TokenStream::new(lex(&scripts, self));
+        let ast = self.parse(&mut stream, scope, self.optimization_level)?;
         self.consume_ast_with_scope(scope, &ast)
     }
 
@@ -1243,14 +1775,40 @@ impl Engine {
         scope: &mut Scope,
         ast: &AST,
     ) -> Result<(), Box<EvalAltResult>> {
-        let mut state = State::new();
+        let mut state = self.new_state(scope, ast.uses_global());
+        self.consume_ast_with_state(scope, &mut state, ast)
+    }
+
+    /// Evaluate an `AST` with own scope, overriding the limits and optimization level of the `Engine`
+    /// for this evaluation only, but throw away the result and only return error (if any).
+    /// Useful for when you don't need the result, but still need to keep track of possible errors.
+    pub fn consume_ast_with_scope_and_options(
+        &self,
+        scope: &mut Scope,
+        ast: &AST,
+        options: &EvalOptions,
+    ) -> Result<(), Box<EvalAltResult>> {
+        let mut state = self.new_state_with_options(scope, ast.uses_global(), options);
+
+        #[cfg(not(feature = "no_optimize"))]
+        if let Some(optimization_level) = options.optimization_level {
+            let ast = self.optimize_ast(scope, ast.clone(), optimization_level);
+            return self.consume_ast_with_state(scope, &mut state, &ast);
+        }
+
+        self.consume_ast_with_state(scope, &mut state, ast)
+    }
+
+    /// Evaluate an `AST` with own scope and evaluation state, throwing away the result.
+    fn consume_ast_with_state(
+        &self,
+        scope: &mut Scope,
+        state: &mut State,
+        ast: &AST,
+    ) -> Result<(), Box<EvalAltResult>> {
         let mut mods = Default::default();
 
-        ast.statements()
-            .iter()
-            .try_fold(().into(), |_, stmt| {
-                self.eval_stmt(scope, &mut mods, &mut state, ast.lib(), &mut None, stmt, 0)
-            })
+        self.eval_statements(scope, &mut mods, state, ast)
             .map_or_else(
                 |err| match *err {
                     EvalAltResult::Return(_, _) => Ok(()),
@@ -1303,9 +1861,47 @@ impl Engine {
         ast: &AST,
         name: &str,
         args: A,
+    ) -> Result<T, Box<EvalAltResult>> {
+        let mut state = self.new_state(scope, ast.uses_global());
+        self.call_fn_with_state(scope, &mut state, ast, name, args)
+    }
+
+    /// Call a script function defined in an `AST` with multiple arguments, aborting with
+    /// `EvalAltResult::ErrorTimeout` if it is still running at `deadline`.
+    /// Arguments are passed as a tuple.
+    ///
+    /// If the `Engine` limits the execution time, the earlier of the two deadlines applies.
+    ///
+    /// Not available under the `no_function`, `unchecked` or `no_std` features.
+    #[cfg(not(feature = "no_function"))]
+    #[cfg(not(feature = "unchecked"))]
+    #[cfg(not(feature = "no_std"))]
+    pub fn call_fn_with_deadline<A: FuncArgs, T: Variant + Clone>(
+        &self,
+        scope: &mut Scope,
+        ast: &AST,
+        name: &str,
+        args: A,
+        deadline: Instant,
+    ) -> Result<T, Box<EvalAltResult>> {
+        let mut state = self.new_state(scope, ast.uses_global());
+        state.set_deadline(deadline);
+        self.call_fn_with_state(scope, &mut state, ast, name, args)
+    }
+
+    /// Call a script function defined in an `AST` with multiple arguments and evaluation state.
+    #[cfg(not(feature = "no_function"))]
+    fn call_fn_with_state<A: FuncArgs, T: Variant + Clone>(
+        &self,
+        scope: &mut Scope,
+        state: &mut State,
+        ast: &AST,
+        name: &str,
+        args: A,
     ) -> Result<T, Box<EvalAltResult>> {
         let mut arg_values = args.into_vec();
-        let result = self.call_fn_dynamic_raw(scope, ast, name, &mut None, arg_values.as_mut())?;
+        let result =
+            self.call_fn_dynamic_raw(scope, state, ast, name, &mut None, arg_values.as_mut())?;
 
         let typ = self.map_type_name(result.type_name());
 
@@ -1376,7 +1972,77 @@ impl Engine {
         mut this_ptr: Option<&mut Dynamic>,
         mut arg_values: impl AsMut<[Dynamic]>,
     ) -> FuncReturn<Dynamic> {
-        self.call_fn_dynamic_raw(scope, lib, name, &mut this_ptr, arg_values.as_mut())
+        // Any of the functions may refer to the `global` namespace
+        let mut state = self.new_state(scope, true);
+        self.call_fn_dynamic_raw(
+            scope,
+            &mut state,
+            lib,
+            name,
+            &mut this_ptr,
+            arg_values.as_mut(),
+        )
+    }
+
+    /// Call a script function defined in an `AST` with multiple `Dynamic` arguments, overriding
+    /// the limits of the `Engine` for this call only.
+    ///
+    /// The `optimization_level` of the options is not used, as the `AST` is not optimized again.
+    ///
+    /// ## WARNING
+    ///
+    /// All the arguments are _consumed_, meaning that they're replaced by `()`.
+    /// This is to avoid unnecessarily cloning the arguments.
+    /// Do not use the arguments after this call. If they are needed afterwards,
+    /// clone them _before_ calling this function.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// # #[cfg(not(feature = "no_function"))]
+    /// # #[cfg(not(feature = "unchecked"))]
+    /// # {
+    /// use rhai::{Engine, EvalAltResult, EvalOptions, Scope};
+    ///
+    /// let engine = Engine::new();
+    ///
+    /// let ast = engine.compile("fn fib(n) { if n < 2 { n } else { fib(n - 1) + fib(n - 2) } }")?;
+    ///
+    /// let options = EvalOptions {
+    ///     max_call_levels: Some(5),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let result = engine.call_fn_dynamic_with_options(&mut Scope::new(), &ast, "fib", None, [ 4_i64.into() ], &options)?;
+    /// assert_eq!(result.cast::<i64>(), 3);
+    ///
+    /// let result = engine.call_fn_dynamic_with_options(&mut Scope::new(), &ast, "fib", None, [ 10_i64.into() ], &options);
+    /// assert!(matches!(*result.expect_err("should error"), EvalAltResult::ErrorInFunctionCall(_, _, _, _, _)));
+    /// # }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(feature = "no_function"))]
+    pub fn call_fn_dynamic_with_options(
+        &self,
+        scope: &mut Scope,
+        lib: impl AsRef<Module>,
+        name: &str,
+        mut this_ptr: Option<&mut Dynamic>,
+        mut arg_values: impl AsMut<[Dynamic]>,
+        options: &EvalOptions,
+    ) -> FuncReturn<Dynamic> {
+        // Any of the functions may refer to the `global` namespace
+        let mut state = self.new_state_with_options(scope, true, options);
+        self.call_fn_dynamic_raw(
+            scope,
+            &mut state,
+            lib,
+            name,
+            &mut this_ptr,
+            arg_values.as_mut(),
+        )
     }
 
     /// Call a script function defined in an `AST` with multiple `Dynamic` arguments.
@@ -1391,6 +2057,7 @@ impl Engine {
     pub(crate) fn call_fn_dynamic_raw(
         &self,
         scope: &mut Scope,
+        state: &mut State,
         lib: impl AsRef<Module>,
         name: &str,
         this_ptr: &mut Option<&mut Dynamic>,
@@ -1406,12 +2073,11 @@ impl Engine {
                 ))
             })?;
 
-        let mut state = State::new();
         let mut mods = Imports::new();
         let args = args.as_mut();
 
         self.call_script_fn(
-            scope, &mut mods, &mut state, lib, this_ptr, name, fn_def, args, 0,
+            scope, &mut mods, state, lib, this_ptr, name, fn_def, args, 0,
         )
     }
 
@@ -1437,15 +2103,61 @@ impl Engine {
         let lib = ast
             .lib()
             .iter_fn()
-            .filter(|(_, _, _, f)| f.is_script())
-            .map(|(_, _, _, f)| f.get_fn_def().clone())
+            .filter(|(_, _, _, f, _)| f.is_script())
+            .map(|(_, _, _, f, _)| f.get_fn_def().clone())
             .collect();
 
         #[cfg(feature = "no_function")]
         let lib = Default::default();
 
+        let uses_global = ast.uses_global();
         let stmt = mem::take(ast.statements_mut());
-        optimize_into_ast(self, scope, stmt, lib, optimization_level)
+        let mut ast = optimize_into_ast(self, scope, stmt, lib, optimization_level);
+        ast.set_uses_global(uses_global);
+        ast
+    }
+
+    /// Register a custom optimizer pass, which runs alongside the built-in passes of the optimizer
+    /// whenever an `AST` is optimized.
+    ///
+    /// Custom passes run in the order that they are registered.
+    ///
+    /// Not available under the `no_optimize` feature.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, AstVisitor, ExpressionMut, INT};
+    ///
+    /// // Constant-fold calls to the domain function 'kilo'
+    /// struct FoldKilo;
+    ///
+    /// impl AstVisitor for FoldKilo {
+    ///     fn visit_expr(&self, expr: &mut ExpressionMut) {
+    ///         if expr.get_fn_name() == Some("kilo") {
+    ///             if let Some(args) = expr.get_arg_values() {
+    ///                 if args.len() == 1 && args[0].is::<INT>() {
+    ///                     expr.set_constant_value((args[0].as_int().unwrap() * 1000).into());
+    ///                 }
+    ///             }
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// engine.register_optimizer_pass(FoldKilo);
+    ///
+    /// // 'kilo' is never called at run-time
+    /// assert_eq!(engine.eval::<INT>("kilo(42)")?, 42000);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(feature = "no_optimize"))]
+    pub fn register_optimizer_pass(&mut self, pass: impl AstVisitor + 'static) -> &mut Self {
+        self.optimizer_passes.push(Box::new(pass));
+        self
     }
 
     /// Register a callback for script evaluation progress.
@@ -1490,6 +2202,147 @@ impl Engine {
         self
     }
 
+    /// Get a handle to interrupt scripts run by this `Engine`, possibly from another thread.
+    ///
+    /// All handles obtained from the same `Engine` are clones of each other.
+    /// See `InterruptHandle` for details.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt.clone()
+    }
+
+    /// Attach a debugger, replacing any debugger already attached.
+    ///
+    /// The callback is called whenever the evaluation pauses before a statement or expression -
+    /// at a breakpoint, or after stepping.  It receives a `DebugContext` with the position, the
+    /// current `Scope`, the call stack and `this`, and returns a `DebuggerCommand` telling the
+    /// `Engine` how to continue.  The evaluation stays paused until the callback returns.
+    ///
+    /// An evaluation runs until the first breakpoint, so set breakpoints via `Engine::set_breakpoint`
+    /// or `Engine::set_conditional_breakpoint`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// # use std::sync::{Arc, RwLock};
+    /// use rhai::{DebuggerCommand, Engine, INT};
+    ///
+    /// let values = Arc::new(RwLock::new(Vec::new()));
+    /// let logger = values.clone();
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// // Log the value of 'x' at every statement after the breakpoint
+    /// engine.attach_debugger(move |context| {
+    ///     let x = context.scope().get_value::<INT>("x").unwrap();
+    ///     logger.write().unwrap().push(x);
+    ///     DebuggerCommand::StepOver
+    /// });
+    ///
+    /// engine.set_breakpoint(3);
+    ///
+    /// engine.consume(r"
+    ///     let x = 1;
+    ///     x += 1;
+    ///     x *= 10;
+    ///     x -= 1;
+    /// ")?;
+    ///
+    /// assert_eq!(*values.read().unwrap(), vec![1, 2, 20]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn attach_debugger(
+        &mut self,
+        callback: impl Fn(&DebugContext) -> DebuggerCommand + SendSync + 'static,
+    ) -> &mut Self {
+        self.debugger = Some(Box::new(callback));
+        self
+    }
+
+    /// Detach the debugger, if any.  Breakpoints are kept for the next debugger attached.
+    pub fn detach_debugger(&mut self) -> &mut Self {
+        self.debugger = None;
+        self
+    }
+
+    /// Set a breakpoint on a line, pausing the evaluation before each statement starting on it.
+    pub fn set_breakpoint(&mut self, line: usize) -> &mut Self {
+        self.breakpoints.push(Breakpoint {
+            line,
+            condition: None,
+        });
+        self
+    }
+
+    /// Set a breakpoint on a line, pausing the evaluation before each statement starting on it
+    /// only when an expression, evaluated in the current scope, is `true`.
+    ///
+    /// The expression is evaluated on a copy of the variables in scope, so it cannot change them.
+    /// Errors in the expression, or values other than `bool`, do not pause the evaluation.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// # use std::sync::{Arc, RwLock};
+    /// use rhai::{DebuggerCommand, Engine, INT};
+    ///
+    /// let values = Arc::new(RwLock::new(Vec::new()));
+    /// let logger = values.clone();
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// engine.attach_debugger(move |context| {
+    ///     let x = context.scope().get_value::<INT>("x").unwrap();
+    ///     logger.write().unwrap().push(x);
+    ///     DebuggerCommand::Continue
+    /// });
+    ///
+    /// engine.set_conditional_breakpoint(4, "x % 3 == 0")?;
+    ///
+    /// engine.consume(r"
+    ///     let x = 0;
+    ///     while x < 10 {
+    ///         x += 1;
+    ///     }
+    /// ")?;
+    ///
+    /// assert_eq!(*values.read().unwrap(), vec![0, 3, 6, 9]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_conditional_breakpoint(
+        &mut self,
+        line: usize,
+        condition: &str,
+    ) -> Result<&mut Self, ParseError> {
+        let condition = self.compile_expression(condition)?;
+
+        self.breakpoints.push(Breakpoint {
+            line,
+            condition: Some(condition),
+        });
+        Ok(self)
+    }
+
+    /// Clear all breakpoints on a line.
+    pub fn clear_breakpoint(&mut self, line: usize) -> &mut Self {
+        self.breakpoints.retain(|bp| bp.line != line);
+        self
+    }
+
+    /// Clear all breakpoints.
+    pub fn clear_breakpoints(&mut self) -> &mut Self {
+        self.breakpoints.clear();
+        self
+    }
+
+    /// Get the lines with breakpoints, in the order the breakpoints are set.
+    pub fn breakpoints(&self) -> impl Iterator<Item = usize> + '_ {
+        self.breakpoints.iter().map(|bp| bp.line)
+    }
+
     /// Override default action of `print` (print to stdout using `println!`)
     ///
     /// # Example
@@ -0,0 +1,1062 @@
+//! Module implementing the bytecode backend of the `Engine`.
+//!
+//! Statements and expressions are lowered into a flat stream of instructions which run on a
+//! stack-based virtual machine. Constructs that do not benefit from being lowered (e.g. dot/index
+//! chains, module-qualified calls and custom syntax) are kept as `AST` nodes and evaluated
+//! by the tree-walking interpreter, so both backends always share the same semantics.
+
+use crate::any::{Dynamic, Union};
+use crate::cost::NodeType;
+use crate::engine::{
+    search_namespace, Engine, Imports, State, KEYWORD_EVAL, KEYWORD_FN_PTR, KEYWORD_FN_PTR_CALL,
+    KEYWORD_FN_PTR_CURRY, KEYWORD_SHARED, KEYWORD_THIS,
+};
+use crate::fn_native::FnPtr;
+use crate::module::Module;
+use crate::parser::{Expr, ReturnType, Stmt, AST};
+use crate::r#unsafe::unsafe_cast_var_name_to_lifetime;
+use crate::result::EvalAltResult;
+use crate::scope::{EntryType as ScopeEntryType, Scope};
+use crate::token::Position;
+use crate::utils::StaticVec;
+
+#[cfg(not(feature = "no_function"))]
+use crate::{calc_fn_hash, fn_native::Shared, parser::ScriptFnDef, utils::StraightHasherBuilder};
+
+#[cfg(not(feature = "no_module"))]
+use crate::fn_call::has_imported_fn;
+
+#[cfg(not(feature = "no_object"))]
+use crate::utils::ImmutableString;
+
+use crate::stdlib::{
+    boxed::Box,
+    iter::once,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+#[cfg(not(feature = "no_function"))]
+use crate::stdlib::{collections::HashMap, iter::empty, ptr};
+
+/// Backend used by an `Engine` to run scripts.
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub enum Backend {
+    /// Evaluate the `AST` directly by walking it.
+    TreeWalking,
+    /// Lower the `AST` into bytecode and run it on a stack-based virtual machine.
+    Bytecode,
+}
+
+impl Backend {
+    /// Is the `Backend` TreeWalking.
+    pub fn is_tree_walking(self) -> bool {
+        self == Self::TreeWalking
+    }
+    /// Is the `Backend` Bytecode.
+    pub fn is_bytecode(self) -> bool {
+        self == Self::Bytecode
+    }
+}
+
+/// A single bytecode instruction.
+///
+/// Instructions standing for an `AST` node count the operations of its type, just like evaluating
+/// the node does in the tree-walking interpreter. Jump targets are offsets into the same code.
+#[derive(Debug, Clone)]
+pub(crate) enum Instr {
+    /// Count the operations of a type of statement, which the debugger may pause at.
+    Step(NodeType, Position),
+    /// Count the operations of a type of expression, which the debugger may pause at.
+    Tick(NodeType, Position),
+    /// Push a constant value.
+    Push(Dynamic, Position),
+    /// Push `()` without counting an operation.
+    Unit,
+    /// Discard the value on top of the stack.
+    Pop,
+    /// Push the value of `this`.
+    This(Position),
+    /// Push the value of a variable.
+    Load(Box<Expr>),
+    /// Evaluate an expression with the tree-walking interpreter and push its value.
+    Expr(Box<Expr>),
+    /// Evaluate a statement with the tree-walking interpreter and push its value.
+    Stmt(Box<Stmt>),
+    /// Jump to an instruction.
+    Jump(usize),
+    /// Pop a boolean guard and jump to an instruction if it is false.
+    JumpIfFalse(usize, Position),
+    /// Pop the left operand of `&&` and, if it is false, push it back and jump to an instruction.
+    And(usize, Position),
+    /// Pop the left operand of `||` and, if it is true, push it back and jump to an instruction.
+    Or(usize, Position),
+    /// Pop the right operand of `&&` or `||` and push it back as a boolean.
+    Bool(&'static str, Position),
+    /// Enter a statement block.
+    EnterBlock,
+    /// Leave a statement block, removing all variables and modules defined within it.
+    LeaveBlock,
+    /// Pop a value into a new variable.
+    Let(Box<((String, Position), Option<String>)>),
+    /// Pop a value into a new constant.
+    Const(Box<((String, Position), Option<String>)>),
+    /// Pop a value and assign it to a variable, with an optional operator - the assignment expression.
+    Assign(Box<Expr>),
+    /// Call a function with all arguments on the stack - the function call expression.
+    Call(Box<Expr>),
+    /// Call a function whose first argument is a variable, passed by reference,
+    /// with all other arguments on the stack - the function call expression.
+    CallRef(Box<Expr>),
+    /// Evaluate a call to a function imported into the local namespace directly (if there is one),
+    /// push its value and jump to an instruction.
+    #[cfg(not(feature = "no_module"))]
+    CallImported(Box<Expr>, usize),
+    /// Collect values on the stack into an array.
+    #[cfg(not(feature = "no_index"))]
+    Array(usize, Position),
+    /// Collect values on the stack into an object map with the specified property names.
+    #[cfg(not(feature = "no_object"))]
+    Map(Box<StaticVec<ImmutableString>>, Position),
+    /// Start a loop, with the instructions to jump to on `break` and `continue`.
+    LoopStart(usize, usize),
+    /// End a loop.
+    LoopEnd,
+    /// Pop a value and start iterating over it, with the loop variable in a new scope.
+    ForInit(String, Position),
+    /// Put the next item of the current iteration into the loop variable, or jump to an instruction
+    /// when there are no more items.
+    ForNext(usize, Position),
+    /// End the current iteration, removing the loop variable.
+    ForEnd,
+    /// `break` (true) or `continue` (false).
+    Break(bool, Position),
+    /// Pop a value and return it.
+    Return(Position),
+    /// Pop a value and throw it as an exception.
+    Throw(Position),
+}
+
+/// Compiled bytecode of an `AST`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Program {
+    /// Global statements.
+    main: Vec<Instr>,
+    /// Bodies of script-defined functions, keyed by the hash of the function name and number of
+    /// parameters, together with the function definitions they are compiled from.
+    #[cfg(not(feature = "no_function"))]
+    functions: HashMap<u64, (Shared<ScriptFnDef>, Vec<Instr>), StraightHasherBuilder>,
+}
+
+impl Program {
+    /// Compile the global statements of an `AST` without its functions.
+    pub fn new(statements: &[Stmt]) -> Self {
+        let mut compiler = Compiler::default();
+        compiler.statements(statements);
+
+        Self {
+            main: compiler.code,
+            #[cfg(not(feature = "no_function"))]
+            functions: Default::default(),
+        }
+    }
+
+    /// Compile the global statements of an `AST` together with all its functions.
+    pub fn from_ast(ast: &AST) -> Self {
+        #[cfg(not(feature = "no_function"))]
+        let mut program = Self::new(ast.statements());
+        #[cfg(feature = "no_function")]
+        let program = Self::new(ast.statements());
+
+        #[cfg(not(feature = "no_function"))]
+        ast.lib().iter_script_fn().for_each(|fn_def| {
+            let mut compiler = Compiler::default();
+            compiler.stmt(&fn_def.body);
+            let hash = calc_fn_hash(empty(), &fn_def.name, fn_def.params.len(), empty());
+            program.functions.insert(hash, (fn_def, compiler.code));
+        });
+
+        program
+    }
+
+    /// Get the bytecode of the global statements.
+    pub fn main(&self) -> &[Instr] {
+        &self.main
+    }
+
+    /// Get the bytecode of the body of a script-defined function, if it was compiled.
+    ///
+    /// The bytecode is only returned for the same function definition that it is compiled from,
+    /// which is kept alive by the `Program`.
+    #[cfg(not(feature = "no_function"))]
+    pub fn get_fn(&self, fn_def: &ScriptFnDef) -> Option<&[Instr]> {
+        let hash = calc_fn_hash(empty(), &fn_def.name, fn_def.params.len(), empty());
+
+        self.functions
+            .get(&hash)
+            .filter(|(f, _)| ptr::eq(f.as_ref(), fn_def))
+            .map(|(_, code)| code.as_slice())
+    }
+}
+
+/// Lower statements and expressions into bytecode.
+///
+/// Every statement and expression leaves exactly one value on the stack.
+#[derive(Debug, Default)]
+struct Compiler {
+    code: Vec<Instr>,
+}
+
+impl Compiler {
+    /// Add an instruction, returning its offset.
+    fn emit(&mut self, instr: Instr) -> usize {
+        self.code.push(instr);
+        self.code.len() - 1
+    }
+
+    /// Point the jump of an instruction to the next instruction to be added.
+    fn patch(&mut self, offset: usize) {
+        let target = self.code.len();
+
+        match &mut self.code[offset] {
+            Instr::Jump(x) | Instr::JumpIfFalse(x, _) | Instr::And(x, _) | Instr::Or(x, _) => {
+                *x = target
+            }
+            Instr::ForNext(x, _) => *x = target,
+            #[cfg(not(feature = "no_module"))]
+            Instr::CallImported(_, x) => *x = target,
+            Instr::LoopStart(x, _) => *x = target,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Compile a list of statements, keeping only the value of the last one.
+    fn statements(&mut self, statements: &[Stmt]) {
+        if statements.is_empty() {
+            self.emit(Instr::Unit);
+            return;
+        }
+
+        statements.iter().enumerate().for_each(|(i, stmt)| {
+            if i > 0 {
+                self.emit(Instr::Pop);
+            }
+            self.stmt(stmt);
+        });
+    }
+
+    /// Compile a loop body, with the loop guard (if any) already compiled.
+    fn loop_body(&mut self, body: &Stmt, guard: Option<usize>, start: usize, top: usize) {
+        self.stmt(body);
+        self.emit(Instr::Pop);
+        self.emit(Instr::Jump(top));
+
+        if let Some(guard) = guard {
+            self.patch(guard);
+        }
+        self.patch(start);
+        self.emit(Instr::LoopEnd);
+    }
+
+    /// Compile a statement.
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Noop(pos) => {
+                self.emit(Instr::Step(NodeType::of_stmt(stmt), *pos));
+                self.emit(Instr::Unit);
+            }
+
+            Stmt::Expr(expr) => {
+                self.emit(Instr::Step(NodeType::of_stmt(stmt), stmt.position()));
+                self.expr(expr);
+            }
+
+            Stmt::Block(x) => {
+                self.emit(Instr::Step(NodeType::of_stmt(stmt), x.1));
+                self.emit(Instr::EnterBlock);
+                self.statements(&x.0);
+                self.emit(Instr::LeaveBlock);
+            }
+
+            Stmt::IfThenElse(x) => {
+                let (expr, if_block, else_block) = x.as_ref();
+
+                self.emit(Instr::Step(NodeType::of_stmt(stmt), stmt.position()));
+                self.expr(expr);
+                let guard = self.emit(Instr::JumpIfFalse(0, expr.position()));
+                self.stmt(if_block);
+                let end = self.emit(Instr::Jump(0));
+                self.patch(guard);
+
+                if let Some(else_block) = else_block {
+                    self.stmt(else_block);
+                } else {
+                    self.emit(Instr::Unit);
+                }
+                self.patch(end);
+            }
+
+            Stmt::While(x) => {
+                let (expr, body) = x.as_ref();
+
+                self.emit(Instr::Step(NodeType::of_stmt(stmt), stmt.position()));
+                let top = self.code.len() + 1;
+                let start = self.emit(Instr::LoopStart(0, top));
+                self.expr(expr);
+                let guard = self.emit(Instr::JumpIfFalse(0, expr.position()));
+                self.loop_body(body, Some(guard), start, top);
+                self.emit(Instr::Unit);
+            }
+
+            Stmt::Loop(body) => {
+                self.emit(Instr::Step(NodeType::of_stmt(stmt), stmt.position()));
+                let top = self.code.len() + 1;
+                let start = self.emit(Instr::LoopStart(0, top));
+                self.loop_body(body, None, start, top);
+                self.emit(Instr::Unit);
+            }
+
+            Stmt::For(x) => {
+                let (name, expr, body) = x.as_ref();
+
+                self.emit(Instr::Step(NodeType::of_stmt(stmt), stmt.position()));
+                self.expr(expr);
+                self.emit(Instr::ForInit(name.clone(), expr.position()));
+                let top = self.code.len() + 1;
+                let start = self.emit(Instr::LoopStart(0, top));
+                let next = self.emit(Instr::ForNext(0, body.position()));
+                self.loop_body(body, Some(next), start, top);
+                self.emit(Instr::ForEnd);
+                self.emit(Instr::Unit);
+            }
+
+            Stmt::Continue(pos) => {
+                self.emit(Instr::Step(NodeType::of_stmt(stmt), *pos));
+                self.emit(Instr::Break(false, *pos));
+            }
+
+            Stmt::Break(pos) => {
+                self.emit(Instr::Step(NodeType::of_stmt(stmt), *pos));
+                self.emit(Instr::Break(true, *pos));
+            }
+
+            // Returning a function call may be a tail call, which is made by the tree-walking interpreter
+            #[cfg(not(feature = "no_function"))]
+            Stmt::ReturnWithVal(x)
+                if (x.0).0 == ReturnType::Return
+                    && matches!(&x.1, Some(Expr::FnCall(f)) if f.1.is_none() && !(f.0).1) =>
+            {
+                self.emit(Instr::Stmt(Box::new(stmt.clone())));
+            }
+
+            Stmt::ReturnWithVal(x) => {
+                let ((typ, pos), expr) = x.as_ref();
+
+                self.emit(Instr::Step(NodeType::of_stmt(stmt), *pos));
+
+                if let Some(expr) = expr {
+                    self.expr(expr);
+                } else {
+                    self.emit(Instr::Unit);
+                }
+
+                self.emit(match typ {
+                    ReturnType::Return => Instr::Return(*pos),
+                    ReturnType::Exception => Instr::Throw(*pos),
+                });
+            }
+
+            Stmt::Let(x) => {
+                let ((name, pos), expr, typ, _) = x.as_ref();
+
+                self.emit(Instr::Step(NodeType::of_stmt(stmt), *pos));
+
+                if let Some(expr) = expr {
+                    self.expr(expr);
+                    self.emit(Instr::Let(Box::new(((name.clone(), *pos), typ.clone()))));
+                } else {
+                    // Variables without an initial value are not checked against their annotations
+                    self.emit(Instr::Unit);
+                    self.emit(Instr::Let(Box::new(((name.clone(), *pos), None))));
+                }
+            }
+
+            Stmt::Const(x) => {
+                let ((name, pos), expr, typ, _) = x.as_ref();
+
+                self.emit(Instr::Step(NodeType::of_stmt(stmt), *pos));
+                self.expr(expr);
+                self.emit(Instr::Const(Box::new(((name.clone(), *pos), typ.clone()))));
+            }
+
+            // Modules are loaded by the tree-walking interpreter
+            #[cfg(not(feature = "no_module"))]
+            Stmt::Import(_) | Stmt::Export(_) => {
+                self.emit(Instr::Stmt(Box::new(stmt.clone())));
+            }
+        }
+    }
+
+    /// Compile an expression.
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Expr(x) => {
+                self.emit(Instr::Tick(NodeType::of_expr(expr), expr.position()));
+                self.expr(x);
+            }
+
+            Expr::IntegerConstant(_)
+            | Expr::CharConstant(_)
+            | Expr::StringConstant(_)
+            | Expr::True(_)
+            | Expr::False(_)
+            | Expr::Unit(_) => {
+                self.emit(Instr::Push(expr.get_constant_value(), expr.position()));
+            }
+            #[cfg(not(feature = "no_float"))]
+            Expr::FloatConstant(_) => {
+                self.emit(Instr::Push(expr.get_constant_value(), expr.position()));
+            }
+            Expr::FnPointer(x) => {
+                let fn_ptr = FnPtr::new_unchecked(x.0.clone(), Default::default());
+                self.emit(Instr::Push(fn_ptr.into(), x.1));
+            }
+
+            Expr::Variable(x) if (x.0).0 == KEYWORD_THIS => {
+                self.emit(Instr::This((x.0).1));
+            }
+            Expr::Variable(_) => {
+                self.emit(Instr::Load(Box::new(expr.clone())));
+            }
+            Expr::Property(_) => unreachable!(),
+
+            Expr::Stmt(x) => {
+                self.emit(Instr::Tick(NodeType::of_expr(expr), x.1));
+                self.stmt(&x.0);
+            }
+
+            // var op= rhs
+            Expr::Assignment(x) if matches!(x.0, Expr::Variable(_)) => {
+                self.emit(Instr::Tick(NodeType::of_expr(expr), expr.position()));
+                self.expr(&x.2);
+                self.emit(Instr::Assign(Box::new(expr.clone())));
+            }
+
+            #[cfg(not(feature = "no_index"))]
+            Expr::Array(x) => {
+                self.emit(Instr::Tick(NodeType::of_expr(expr), x.1));
+                x.0.iter().for_each(|item| self.expr(item));
+                self.emit(Instr::Array(x.0.len(), x.1));
+            }
+
+            #[cfg(not(feature = "no_object"))]
+            Expr::Map(x) => {
+                self.emit(Instr::Tick(NodeType::of_expr(expr), x.1));
+                x.0.iter().for_each(|(_, value)| self.expr(value));
+                let keys = x.0.iter().map(|((key, _), _)| key.clone()).collect();
+                self.emit(Instr::Map(Box::new(keys), x.1));
+            }
+
+            // Normal function call - except for special functions which are handled by the
+            // tree-walking interpreter
+            Expr::FnCall(x)
+                if x.1.is_none()
+                    && !matches!(
+                        (x.0).0.as_ref(),
+                        KEYWORD_FN_PTR
+                            | KEYWORD_FN_PTR_CURRY
+                            | KEYWORD_FN_PTR_CALL
+                            | KEYWORD_SHARED
+                            | KEYWORD_EVAL
+                    ) =>
+            {
+                let ((_, _native, pos), _, _, args_expr, _, _) = x.as_ref();
+
+                self.emit(Instr::Tick(NodeType::of_expr(expr), *pos));
+
+                // Functions imported into the local namespace - always pass arguments by value
+                #[cfg(not(feature = "no_module"))]
+                let imported = if !_native {
+                    Some(self.emit(Instr::CallImported(Box::new(expr.clone()), 0)))
+                } else {
+                    None
+                };
+
+                match args_expr.iter().next() {
+                    // func(x, ...) -> x.func(...)
+                    Some(Expr::Variable(_)) => {
+                        args_expr.iter().skip(1).for_each(|arg| self.expr(arg));
+                        self.emit(Instr::CallRef(Box::new(expr.clone())));
+                    }
+                    // func(..., ...)
+                    _ => {
+                        args_expr.iter().for_each(|arg| self.expr(arg));
+                        self.emit(Instr::Call(Box::new(expr.clone())));
+                    }
+                }
+
+                #[cfg(not(feature = "no_module"))]
+                if let Some(imported) = imported {
+                    self.patch(imported);
+                }
+            }
+
+            Expr::And(x) => {
+                self.emit(Instr::Tick(NodeType::of_expr(expr), x.2));
+                self.expr(&x.0);
+                let end = self.emit(Instr::And(0, x.0.position()));
+                self.expr(&x.1);
+                self.emit(Instr::Bool("AND", x.1.position()));
+                self.patch(end);
+            }
+
+            Expr::Or(x) => {
+                self.emit(Instr::Tick(NodeType::of_expr(expr), x.2));
+                self.expr(&x.0);
+                let end = self.emit(Instr::Or(0, x.0.position()));
+                self.expr(&x.1);
+                self.emit(Instr::Bool("OR", x.1.position()));
+                self.patch(end);
+            }
+
+            // Everything else is evaluated by the tree-walking interpreter
+            _ => {
+                self.emit(Instr::Expr(Box::new(expr.clone())));
+            }
+        }
+    }
+}
+
+/// Kind of scope opened by the virtual machine.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum ScopeKind {
+    /// A statement block.
+    Block,
+    /// The loop variable of a `for` statement.
+    For,
+}
+
+/// A scope opened by the virtual machine.
+#[derive(Debug, Clone, Copy)]
+struct ScopeMark {
+    kind: ScopeKind,
+    scope_len: usize,
+    mods_len: usize,
+}
+
+/// A loop being run by the virtual machine.
+#[derive(Debug, Clone, Copy)]
+struct LoopMark {
+    /// Instruction to jump to on `break`.
+    break_to: usize,
+    /// Instruction to jump to on `continue`.
+    continue_to: usize,
+    /// Number of scopes opened when the loop started.
+    scopes: usize,
+    /// Size of the stack when the loop started.
+    stack: usize,
+    /// Number of iterations running when the loop started.
+    iters: usize,
+}
+
+/// Running state of a piece of bytecode.
+#[derive(Default)]
+struct Frame {
+    /// Instruction pointer.
+    ip: usize,
+    /// Stack of values.
+    stack: Vec<Dynamic>,
+    /// Scopes opened.
+    scopes: Vec<ScopeMark>,
+    /// Loops running.
+    loops: Vec<LoopMark>,
+    /// Iterations running, with the index of the loop variable in the `Scope`.
+    iters: Vec<(Box<dyn Iterator<Item = Dynamic>>, usize)>,
+}
+
+impl Frame {
+    /// Pop a value off the stack.
+    fn pop(&mut self) -> Dynamic {
+        self.stack.pop().unwrap()
+    }
+
+    /// Close all scopes opened after a certain number of them.
+    fn close_scopes(
+        &mut self,
+        scope: &mut Scope,
+        mods: &mut Imports,
+        state: &mut State,
+        len: usize,
+    ) {
+        while self.scopes.len() > len {
+            let mark = self.scopes.pop().unwrap();
+
+            scope.rewind(mark.scope_len);
+            state.scope_level -= 1;
+
+            if mark.kind == ScopeKind::Block {
+                mods.truncate(mark.mods_len);
+
+                // The impact of an eval statement goes away at the end of a block
+                // because any new variables introduced will go out of scope
+                state.always_search = false;
+            }
+        }
+    }
+}
+
+impl Engine {
+    /// Run a piece of bytecode.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn run_code(
+        &self,
+        scope: &mut Scope,
+        mods: &mut Imports,
+        state: &mut State,
+        lib: &Module,
+        this_ptr: &mut Option<&mut Dynamic>,
+        code: &[Instr],
+        level: usize,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let mut frame: Frame = Default::default();
+
+        loop {
+            let err =
+                match self.run_frame(scope, mods, state, lib, this_ptr, code, &mut frame, level) {
+                    Ok(value) => return Ok(value),
+                    Err(err) => err,
+                };
+
+            match *err {
+                // Jump out of the current loop, closing everything opened within it
+                EvalAltResult::ErrorLoopBreak(is_break, _) if !frame.loops.is_empty() => {
+                    let mark = *frame.loops.last().unwrap();
+
+                    frame.close_scopes(scope, mods, state, mark.scopes);
+                    frame.stack.truncate(mark.stack);
+                    frame.iters.truncate(mark.iters);
+
+                    frame.ip = if is_break {
+                        mark.break_to
+                    } else {
+                        mark.continue_to
+                    };
+                }
+                _ => {
+                    frame.close_scopes(scope, mods, state, 0);
+                    return Err(err);
+                }
+            }
+        }
+    }
+
+    /// Check a value to be pushed onto the stack against the data size and memory limits.
+    fn check_value(
+        &self,
+        scope: &Scope,
+        state: &mut State,
+        result: Result<Dynamic, Box<EvalAltResult>>,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let result = self.check_data_size(state, result);
+        self.check_memory(scope, state, result)
+    }
+
+    /// Run instructions until the end of the bytecode, or until an error occurs.
+    #[allow(clippy::too_many_arguments)]
+    fn run_frame(
+        &self,
+        scope: &mut Scope,
+        mods: &mut Imports,
+        state: &mut State,
+        lib: &Module,
+        this_ptr: &mut Option<&mut Dynamic>,
+        code: &[Instr],
+        frame: &mut Frame,
+        level: usize,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        while let Some(instr) = code.get(frame.ip) {
+            frame.ip += 1;
+
+            match instr {
+                Instr::Step(node, pos) => {
+                    self.inc_node_operations(state, *node)
+                        .map_err(|err| err.new_position(*pos))?;
+                    self.debug_hook(scope, mods, state, lib, this_ptr, *node, true, *pos, level)?;
+                }
+
+                Instr::Tick(node, pos) => {
+                    self.inc_node_operations(state, *node)
+                        .map_err(|err| err.new_position(*pos))?;
+                    self.debug_hook(scope, mods, state, lib, this_ptr, *node, false, *pos, level)?;
+                }
+
+                Instr::Push(value, pos) => {
+                    self.inc_node_operations(state, NodeType::Constant)
+                        .map_err(|err| err.new_position(*pos))?;
+                    self.debug_hook(
+                        scope,
+                        mods,
+                        state,
+                        lib,
+                        this_ptr,
+                        NodeType::Constant,
+                        false,
+                        *pos,
+                        level,
+                    )?;
+                    let value = self
+                        .check_value(scope, state, Ok(value.clone()))
+                        .map_err(|err| err.new_position(*pos))?;
+                    frame.stack.push(value);
+                }
+
+                Instr::Unit => frame.stack.push(Default::default()),
+
+                Instr::Pop => {
+                    frame.pop();
+                }
+
+                Instr::This(pos) => {
+                    self.inc_node_operations(state, NodeType::Variable)
+                        .map_err(|err| err.new_position(*pos))?;
+                    self.debug_hook(
+                        scope,
+                        mods,
+                        state,
+                        lib,
+                        this_ptr,
+                        NodeType::Variable,
+                        false,
+                        *pos,
+                        level,
+                    )?;
+
+                    if let Some(val) = this_ptr {
+                        frame.stack.push(val.clone());
+                    } else {
+                        return Err(Box::new(EvalAltResult::ErrorUnboundedThis(*pos)));
+                    }
+                }
+
+                Instr::Load(expr) => {
+                    let pos = expr.position();
+                    self.inc_node_operations(state, NodeType::Variable)
+                        .map_err(|err| err.new_position(pos))?;
+                    self.debug_hook(
+                        scope,
+                        mods,
+                        state,
+                        lib,
+                        this_ptr,
+                        NodeType::Variable,
+                        false,
+                        pos,
+                        level,
+                    )?;
+                    let (val, _, _, _) = search_namespace(scope, mods, state, this_ptr, expr)
+                        .map_err(|err| err.new_position(pos))?;
+                    let value = val.clone_into_dynamic();
+                    let value = self
+                        .check_value(scope, state, Ok(value))
+                        .map_err(|err| err.new_position(pos))?;
+                    frame.stack.push(value);
+                }
+
+                Instr::Expr(expr) => {
+                    let value = self.eval_expr(scope, mods, state, lib, this_ptr, expr, level)?;
+                    frame.stack.push(value);
+                }
+
+                Instr::Stmt(stmt) => {
+                    let value = self.eval_stmt(scope, mods, state, lib, this_ptr, stmt, level)?;
+                    frame.stack.push(value);
+                }
+
+                Instr::Jump(target) => frame.ip = *target,
+
+                Instr::JumpIfFalse(target, pos) => {
+                    let guard = frame
+                        .pop()
+                        .as_bool()
+                        .map_err(|_| Box::new(EvalAltResult::ErrorLogicGuard(*pos)))?;
+
+                    if !guard {
+                        frame.ip = *target;
+                    }
+                }
+
+                Instr::And(target, pos) => {
+                    let lhs = frame
+                        .pop()
+                        .as_bool()
+                        .map_err(|_| EvalAltResult::ErrorBooleanArgMismatch("AND".into(), *pos))?;
+
+                    // Short-circuit
+                    if !lhs {
+                        frame.stack.push(false.into());
+                        frame.ip = *target;
+                    }
+                }
+
+                Instr::Or(target, pos) => {
+                    let lhs = frame
+                        .pop()
+                        .as_bool()
+                        .map_err(|_| EvalAltResult::ErrorBooleanArgMismatch("OR".into(), *pos))?;
+
+                    // Short-circuit
+                    if lhs {
+                        frame.stack.push(true.into());
+                        frame.ip = *target;
+                    }
+                }
+
+                Instr::Bool(op, pos) => {
+                    let rhs = frame.pop().as_bool().map_err(|_| {
+                        EvalAltResult::ErrorBooleanArgMismatch(op.to_string(), *pos)
+                    })?;
+                    frame.stack.push(rhs.into());
+                }
+
+                Instr::EnterBlock => {
+                    frame.scopes.push(ScopeMark {
+                        kind: ScopeKind::Block,
+                        scope_len: scope.len(),
+                        mods_len: mods.len(),
+                    });
+                    state.scope_level += 1;
+                }
+
+                Instr::LeaveBlock => {
+                    let len = frame.scopes.len() - 1;
+                    frame.close_scopes(scope, mods, state, len);
+                }
+
+                Instr::Let(x) => {
+                    let ((name, pos), typ) = x.as_ref();
+                    let val = frame.pop();
+                    if let Some(typ) = typ {
+                        self.ensure_type(&val, typ, *pos)?;
+                    }
+                    let var_name = unsafe_cast_var_name_to_lifetime(name, state);
+                    scope.push_dynamic_value(var_name, ScopeEntryType::Normal, val, false);
+                    state.record_scope_size(scope.len());
+                    frame.stack.push(Default::default());
+                }
+
+                Instr::Const(x) => {
+                    let ((name, pos), typ) = x.as_ref();
+                    let val = frame.pop();
+                    if let Some(typ) = typ {
+                        self.ensure_type(&val, typ, *pos)?;
+                    }
+                    // Constants at global level are also visible inside functions
+                    if state.scope_level == 0 {
+                        state.global_constants.set_var(name.clone(), val.clone());
+                    }
+                    let var_name = unsafe_cast_var_name_to_lifetime(name, state);
+                    scope.push_dynamic_value(var_name, ScopeEntryType::Constant, val, true);
+                    state.record_scope_size(scope.len());
+                    frame.stack.push(Default::default());
+                }
+
+                Instr::Assign(expr) => {
+                    let rhs_val = frame.pop();
+                    let value = self
+                        .eval_var_assignment(
+                            scope, mods, state, lib, this_ptr, expr, rhs_val, level,
+                        )
+                        .map_err(|err| err.new_position(expr.position()))?;
+                    frame.stack.push(value);
+                }
+
+                Instr::Call(expr) => {
+                    let ((name, native, pos), _, hash, args_expr, def_val, cache) = match expr.as_ref() {
+                        Expr::FnCall(x) => x.as_ref(),
+                        _ => unreachable!(),
+                    };
+                    let base = frame.stack.len() - args_expr.len();
+
+                    let result = {
+                        let mut args: StaticVec<_> = frame.stack[base..].iter_mut().collect();
+                        self.exec_fn_call(
+                            state,
+                            lib,
+                            name,
+                            *native,
+                            *hash,
+                            args.as_mut(),
+                            false,
+                            false,
+                            *def_val,
+                            Some(cache),
+                            level,
+                        )
+                        .map(|(v, _)| v)
+                    };
+
+                    frame.stack.truncate(base);
+
+                    let value = self
+                        .check_value(scope, state, result)
+                        .map_err(|err| err.new_position(*pos))?;
+                    frame.stack.push(value);
+                }
+
+                Instr::CallRef(expr) => {
+                    let ((name, native, pos), _, hash, args_expr, def_val, cache) = match expr.as_ref() {
+                        Expr::FnCall(x) => x.as_ref(),
+                        _ => unreachable!(),
+                    };
+                    let base = frame.stack.len() - (args_expr.len() - 1);
+
+                    let (mut target, _, _, var_pos) =
+                        search_namespace(scope, mods, state, this_ptr, &args_expr[0])
+                            .map_err(|err| err.new_position(*pos))?;
+
+                    self.inc_node_operations(state, NodeType::Variable)
+                        .map_err(|err| err.new_position(var_pos))?;
+
+                    let result = {
+                        let mut args: StaticVec<_> = once(target.as_mut())
+                            .chain(frame.stack[base..].iter_mut())
+                            .collect();
+                        self.exec_fn_call(
+                            state,
+                            lib,
+                            name,
+                            *native,
+                            *hash,
+                            args.as_mut(),
+                            true,
+                            false,
+                            *def_val,
+                            Some(cache),
+                            level,
+                        )
+                        .map(|(v, _)| v)
+                    };
+
+                    frame.stack.truncate(base);
+
+                    let value = self
+                        .check_value(scope, state, result)
+                        .map_err(|err| err.new_position(*pos))?;
+                    frame.stack.push(value);
+                }
+
+                #[cfg(not(feature = "no_module"))]
+                Instr::CallImported(expr, target) => {
+                    let ((name, native, pos), _, hash, args_expr, def_val, cache) = match expr.as_ref() {
+                        Expr::FnCall(x) => x.as_ref(),
+                        _ => unreachable!(),
+                    };
+
+                    if !lib.contains_fn(*hash) && has_imported_fn(mods, name, args_expr.len()) {
+                        let result = self.make_function_call(
+                            scope, mods, state, lib, this_ptr, name, args_expr, *def_val, *hash,
+                            *native, Some(cache), level,
+                        );
+                        let value = self
+                            .check_value(scope, state, result)
+                            .map_err(|err| err.new_position(*pos))?;
+                        frame.stack.push(value);
+                        frame.ip = *target;
+                    }
+                }
+
+                #[cfg(not(feature = "no_index"))]
+                Instr::Array(len, pos) => {
+                    let items = frame.stack.split_off(frame.stack.len() - len);
+                    let value = self
+                        .check_value(scope, state, Ok(Dynamic(Union::Array(Box::new(items)))))
+                        .map_err(|err| err.new_position(*pos))?;
+                    frame.stack.push(value);
+                }
+
+                #[cfg(not(feature = "no_object"))]
+                Instr::Map(keys, pos) => {
+                    let values = frame.stack.split_off(frame.stack.len() - keys.len());
+                    let map = keys.iter().cloned().zip(values).collect();
+                    let value = self
+                        .check_value(scope, state, Ok(Dynamic(Union::Map(Box::new(map)))))
+                        .map_err(|err| err.new_position(*pos))?;
+                    frame.stack.push(value);
+                }
+
+                Instr::LoopStart(break_to, continue_to) => frame.loops.push(LoopMark {
+                    break_to: *break_to,
+                    continue_to: *continue_to,
+                    scopes: frame.scopes.len(),
+                    stack: frame.stack.len(),
+                    iters: frame.iters.len(),
+                }),
+
+                Instr::LoopEnd => {
+                    frame.loops.pop();
+                }
+
+                Instr::ForInit(name, pos) => {
+                    let iter_type = frame.pop();
+                    let tid = iter_type.type_id();
+
+                    let func = self
+                        .global_module
+                        .get_iter(tid)
+                        .or_else(|| self.packages.get_iter(tid))
+                        .ok_or_else(|| Box::new(EvalAltResult::ErrorFor(*pos)))?;
+
+                    // Add the loop variable
+                    let var_name = unsafe_cast_var_name_to_lifetime(name, state);
+                    scope.push(var_name, ());
+                    state.record_scope_size(scope.len());
+                    let index = scope.len() - 1;
+                    state.scope_level += 1;
+
+                    frame.scopes.push(ScopeMark {
+                        kind: ScopeKind::For,
+                        scope_len: index,
+                        mods_len: mods.len(),
+                    });
+                    frame.iters.push((func(iter_type), index));
+                }
+
+                Instr::ForNext(target, pos) => {
+                    let (iter, index) = frame.iters.last_mut().unwrap();
+
+                    if let Some(loop_var) = iter.next() {
+                        *scope.get_mut(*index).0 = loop_var;
+                        self.inc_node_operations(state, NodeType::Iteration)
+                            .map_err(|err| err.new_position(*pos))?;
+                    } else {
+                        frame.ip = *target;
+                    }
+                }
+
+                Instr::ForEnd => {
+                    frame.iters.pop();
+                    let len = frame.scopes.len() - 1;
+                    frame.close_scopes(scope, mods, state, len);
+                }
+
+                Instr::Break(is_break, pos) => {
+                    return Err(Box::new(EvalAltResult::ErrorLoopBreak(*is_break, *pos)))
+                }
+
+                Instr::Return(pos) => {
+                    return Err(Box::new(EvalAltResult::Return(frame.pop(), *pos)))
+                }
+
+                Instr::Throw(pos) => {
+                    let val = frame.pop();
+                    return Err(Box::new(EvalAltResult::ErrorRuntime(
+                        val.take_string().unwrap_or_else(|_| "".into()),
+                        *pos,
+                    )));
+                }
+            }
+        }
+
+        Ok(frame.stack.pop().unwrap_or_default())
+    }
+}
@@ -0,0 +1,163 @@
+//! Module defining the cost of operations, for metering the evaluation of scripts.
+
+use crate::any::Dynamic;
+use crate::fn_native::SendSync;
+use crate::parser::{Expr, Stmt};
+use crate::utils::StraightHasherBuilder;
+
+use crate::stdlib::{boxed::Box, collections::HashMap, string::String};
+
+/// Type of a node in an `AST`, each counting as one operation unless given another cost via
+/// `Engine::set_node_cost`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum NodeType {
+    /// Literal value, e.g. `42`, `"hello"` or `true`.
+    Constant,
+    /// Access to a variable, including the targets of method calls.
+    Variable,
+    /// Property access or indexing, e.g. `x.y` or `x[0]`, counted once for each level.
+    Access,
+    /// Function call or operator, not including the cost of the function itself.
+    FnCall,
+    /// Assignment, including compound assignment such as `+=`.
+    Assignment,
+    /// Array literal.
+    Array,
+    /// Object map literal.
+    Map,
+    /// `in` expression.
+    In,
+    /// `&&` or `||` expression.
+    Logic,
+    /// Custom syntax.
+    Custom,
+    /// `let` or `const` statement.
+    Let,
+    /// `if` statement.
+    If,
+    /// `while` statement.
+    While,
+    /// `loop` statement.
+    Loop,
+    /// `for` statement.
+    For,
+    /// Iteration of a `for` loop.
+    Iteration,
+    /// Statement block.
+    Block,
+    /// `return`, `throw`, `break` or `continue` statement.
+    Jump,
+    /// `import` or `export` statement.
+    Import,
+    /// Any other statement or expression, e.g. an expression statement or a parenthesized expression.
+    Other,
+}
+
+/// Number of variants of `NodeType`.
+const NUM_NODE_TYPES: usize = NodeType::Other as usize + 1;
+
+impl NodeType {
+    /// Get the type of a statement.
+    pub(crate) fn of_stmt(stmt: &Stmt) -> Self {
+        match stmt {
+            Stmt::IfThenElse(_) => Self::If,
+            Stmt::While(_) => Self::While,
+            Stmt::Loop(_) => Self::Loop,
+            Stmt::For(_) => Self::For,
+            Stmt::Let(_) | Stmt::Const(_) => Self::Let,
+            Stmt::Block(_) => Self::Block,
+            Stmt::Continue(_) | Stmt::Break(_) | Stmt::ReturnWithVal(_) => Self::Jump,
+            #[cfg(not(feature = "no_module"))]
+            Stmt::Import(_) | Stmt::Export(_) => Self::Import,
+            _ => Self::Other,
+        }
+    }
+
+    /// Get the type of an expression.
+    pub(crate) fn of_expr(expr: &Expr) -> Self {
+        match expr {
+            #[cfg(not(feature = "no_float"))]
+            Expr::FloatConstant(_) => Self::Constant,
+            Expr::IntegerConstant(_)
+            | Expr::CharConstant(_)
+            | Expr::StringConstant(_)
+            | Expr::FnPointer(_)
+            | Expr::True(_)
+            | Expr::False(_)
+            | Expr::Unit(_) => Self::Constant,
+            Expr::Variable(_) => Self::Variable,
+            Expr::Property(_) | Expr::Dot(_) | Expr::Index(_) => Self::Access,
+            Expr::FnCall(_) => Self::FnCall,
+            Expr::Assignment(_) => Self::Assignment,
+            Expr::Array(_) => Self::Array,
+            Expr::Map(_) => Self::Map,
+            Expr::In(_) => Self::In,
+            Expr::And(_) | Expr::Or(_) => Self::Logic,
+            Expr::Custom(_) => Self::Custom,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// A function calculating the cost of a function call from its arguments.
+#[cfg(not(feature = "sync"))]
+pub(crate) type FnCostCallback = dyn Fn(&[&Dynamic]) -> u64;
+/// A function calculating the cost of a function call from its arguments.
+#[cfg(feature = "sync")]
+pub(crate) type FnCostCallback = dyn Fn(&[&Dynamic]) -> u64 + Send + Sync;
+
+/// Table of the costs of operations, replacing the default of one operation each.
+pub(crate) struct CostTable {
+    /// Cost of each type of `AST` node.
+    nodes: [u64; NUM_NODE_TYPES],
+    /// Cost of functions and operators, by name.
+    fns: HashMap<String, u64>,
+    /// Callbacks calculating the extra cost of calling functions, by the hash of the function
+    /// (name and parameter types).
+    fn_callbacks: HashMap<u64, Box<FnCostCallback>, StraightHasherBuilder>,
+}
+
+impl Default for CostTable {
+    fn default() -> Self {
+        Self {
+            nodes: [1; NUM_NODE_TYPES],
+            fns: Default::default(),
+            fn_callbacks: Default::default(),
+        }
+    }
+}
+
+impl CostTable {
+    /// Set the cost of a type of `AST` node.
+    pub fn set_node_cost(&mut self, node: NodeType, cost: u64) {
+        self.nodes[node as usize] = cost;
+    }
+    /// Get the cost of a type of `AST` node.
+    pub fn node_cost(&self, node: NodeType) -> u64 {
+        self.nodes[node as usize]
+    }
+    /// Set the fixed cost of calling a function.
+    pub fn set_fn_cost(&mut self, name: String, cost: u64) {
+        self.fns.insert(name, cost);
+    }
+    /// Get the fixed cost of calling a function, if it is not the default.
+    pub fn fn_cost(&self, name: &str) -> Option<u64> {
+        self.fns.get(name).cloned()
+    }
+    /// Register a callback calculating the extra cost of calling the function with a particular hash.
+    pub fn set_fn_cost_callback(
+        &mut self,
+        hash_fn: u64,
+        callback: impl Fn(&[&Dynamic]) -> u64 + SendSync + 'static,
+    ) {
+        self.fn_callbacks.insert(hash_fn, Box::new(callback));
+    }
+    /// Get the callback calculating the extra cost of calling the function with a particular hash, if any.
+    pub fn fn_cost_callback(&self, hash_fn: u64) -> Option<&FnCostCallback> {
+        self.fn_callbacks.get(&hash_fn).map(|f| f.as_ref())
+    }
+    /// Are there any callbacks calculating the extra cost of calling functions?
+    pub fn has_fn_cost_callbacks(&self) -> bool {
+        !self.fn_callbacks.is_empty()
+    }
+}
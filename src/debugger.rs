@@ -0,0 +1,307 @@
+//! Module implementing the debugger interface of the `Engine`.
+
+use crate::any::Dynamic;
+use crate::cost::NodeType;
+use crate::engine::{Engine, Imports, State};
+use crate::module::Module;
+use crate::parser::{Stmt, AST};
+use crate::result::EvalAltResult;
+use crate::scope::Scope;
+use crate::token::Position;
+
+use crate::stdlib::{boxed::Box, string::String, vec::Vec};
+
+/// Command returned by a debugger callback, telling the `Engine` how to continue.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum DebuggerCommand {
+    /// Run until the next breakpoint.
+    Continue,
+    /// Pause at the next statement or expression, entering function calls.
+    StepInto,
+    /// Pause at the next statement, without entering function calls.
+    StepOver,
+    /// Pause at the next statement after returning from the current function.
+    StepOut,
+    /// Abort the evaluation with `EvalAltResult::ErrorTerminated`.
+    Abort,
+}
+
+/// Reason for pausing the evaluation and calling the debugger.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum DebugEvent {
+    /// Stepping via `DebuggerCommand::StepInto`, `StepOver` or `StepOut`.
+    Step,
+    /// A breakpoint is hit.  Wrapped value is the line of the breakpoint.
+    Breakpoint(usize),
+}
+
+/// A call to a script-defined function on the call stack.
+#[derive(Debug, Clone)]
+pub struct CallFrame {
+    /// Name of the function.
+    pub fn_name: String,
+    /// Values of the arguments passed to the function.
+    pub args: Vec<Dynamic>,
+    /// Position of the statement making the call.
+    pub pos: Position,
+}
+
+/// State of the evaluation passed to a debugger callback.
+pub struct DebugContext<'a> {
+    event: DebugEvent,
+    node: NodeType,
+    pos: Position,
+    scope: &'a Scope<'a>,
+    this_ptr: Option<&'a Dynamic>,
+    call_stack: &'a [CallFrame],
+}
+
+impl<'a> DebugContext<'a> {
+    /// Reason for pausing the evaluation.
+    pub fn event(&self) -> DebugEvent {
+        self.event
+    }
+    /// Type of the statement or expression about to be evaluated.
+    pub fn node(&self) -> NodeType {
+        self.node
+    }
+    /// Position of the statement or expression about to be evaluated.
+    pub fn position(&self) -> Position {
+        self.pos
+    }
+    /// The current `Scope`, holding the variables of the current function (or the global scope).
+    pub fn scope(&self) -> &Scope<'a> {
+        self.scope
+    }
+    /// The value bound to `this`, if any.
+    pub fn this_ptr(&self) -> Option<&Dynamic> {
+        self.this_ptr
+    }
+    /// Calls to script-defined functions currently running, the innermost last.
+    pub fn call_stack(&self) -> &[CallFrame] {
+        self.call_stack
+    }
+}
+
+/// A debugger callback.
+#[cfg(not(feature = "sync"))]
+pub(crate) type DebuggerCallback = dyn Fn(&DebugContext) -> DebuggerCommand;
+/// A debugger callback.
+#[cfg(feature = "sync")]
+pub(crate) type DebuggerCallback = dyn Fn(&DebugContext) -> DebuggerCommand + Send + Sync;
+
+/// A line breakpoint, with an optional condition.
+pub(crate) struct Breakpoint {
+    /// Line of the breakpoint.
+    pub line: usize,
+    /// Expression which must evaluate to `true` for the breakpoint to be hit, if any.
+    pub condition: Option<AST>,
+}
+
+/// How the evaluation proceeds until the debugger is next called.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum StepMode {
+    /// Pause only at breakpoints.
+    Run,
+    /// Pause at the next statement or expression.
+    Into,
+    /// Pause at the next statement at the specified call level or above.
+    Over(usize),
+    /// Pause at the next statement above the specified call level.
+    Out(usize),
+    /// Never pause, e.g. while evaluating the condition of a breakpoint.
+    Disabled,
+}
+
+/// State of the debugger during an evaluation.
+#[derive(Debug, Clone)]
+pub(crate) struct DebugState {
+    /// How the evaluation proceeds.
+    mode: StepMode,
+    /// Calls to script-defined functions currently running.
+    call_stack: Vec<CallFrame>,
+    /// Position of the statement being evaluated.
+    stmt_pos: Position,
+}
+
+impl Default for DebugState {
+    fn default() -> Self {
+        Self {
+            mode: StepMode::Run,
+            call_stack: Default::default(),
+            stmt_pos: Position::none(),
+        }
+    }
+}
+
+impl DebugState {
+    /// Create a `DebugState` which never pauses.
+    fn disabled() -> Self {
+        Self {
+            mode: StepMode::Disabled,
+            ..Default::default()
+        }
+    }
+    /// Push a call to a script-defined function onto the call stack.
+    pub fn push_call(&mut self, fn_name: &str, args: Vec<Dynamic>) {
+        self.call_stack.push(CallFrame {
+            fn_name: fn_name.into(),
+            args,
+            pos: self.stmt_pos,
+        });
+    }
+    /// Pop the innermost call off the call stack, returning to the statement making the call.
+    pub fn pop_call(&mut self) {
+        if let Some(frame) = self.call_stack.pop() {
+            self.stmt_pos = frame.pos;
+        }
+    }
+}
+
+impl Engine {
+    /// Call the debugger, if one is attached, before evaluating a statement or expression.
+    #[inline(always)]
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn debug_hook(
+        &self,
+        scope: &mut Scope,
+        mods: &mut Imports,
+        state: &mut State,
+        lib: &Module,
+        this_ptr: &mut Option<&mut Dynamic>,
+        node: NodeType,
+        is_stmt: bool,
+        pos: Position,
+        level: usize,
+    ) -> Result<(), Box<EvalAltResult>> {
+        match &self.debugger {
+            None => Ok(()),
+            Some(callback) => self.run_debugger(
+                callback, scope, mods, state, lib, this_ptr, node, is_stmt, pos, level,
+            ),
+        }
+    }
+
+    /// Decide whether to pause at a statement or expression and, if so, call the debugger.
+    #[allow(clippy::too_many_arguments)]
+    fn run_debugger(
+        &self,
+        callback: &DebuggerCallback,
+        scope: &mut Scope,
+        mods: &mut Imports,
+        state: &mut State,
+        lib: &Module,
+        this_ptr: &mut Option<&mut Dynamic>,
+        node: NodeType,
+        is_stmt: bool,
+        pos: Position,
+        level: usize,
+    ) -> Result<(), Box<EvalAltResult>> {
+        let debug = state.debug.get_or_insert_with(Default::default);
+
+        if is_stmt {
+            debug.stmt_pos = pos;
+        }
+
+        let mode = debug.mode;
+
+        let step = match mode {
+            StepMode::Disabled => return Ok(()),
+            StepMode::Run => false,
+            StepMode::Into => true,
+            StepMode::Over(l) => is_stmt && level <= l,
+            StepMode::Out(l) => is_stmt && level < l,
+        };
+
+        let event = if step {
+            DebugEvent::Step
+        } else if is_stmt {
+            let this_ptr = this_ptr.as_deref();
+            let epoch = state.fn_cache_epoch;
+
+            match self.hit_breakpoint(scope, mods, lib, this_ptr, epoch, pos, level) {
+                Some(line) => DebugEvent::Breakpoint(line),
+                None => return Ok(()),
+            }
+        } else {
+            return Ok(());
+        };
+
+        let debug = state.debug.as_mut().unwrap();
+
+        let context = DebugContext {
+            event,
+            node,
+            pos,
+            scope,
+            this_ptr: this_ptr.as_ref().map(|v| &**v),
+            call_stack: &debug.call_stack,
+        };
+
+        let command = callback(&context);
+
+        debug.mode = match command {
+            DebuggerCommand::Continue => StepMode::Run,
+            DebuggerCommand::StepInto => StepMode::Into,
+            DebuggerCommand::StepOver => StepMode::Over(level),
+            DebuggerCommand::StepOut => StepMode::Out(level),
+            DebuggerCommand::Abort => return Err(Box::new(EvalAltResult::ErrorTerminated(pos))),
+        };
+
+        Ok(())
+    }
+
+    /// Find the first breakpoint hit at a position, returning its line.
+    ///
+    /// Conditions are evaluated on copies of the variables in scope and of `this`, so they cannot
+    /// change the state of the script.  Call-site caches stay valid as `epoch` is reused.
+    #[allow(clippy::too_many_arguments)]
+    fn hit_breakpoint(
+        &self,
+        scope: &Scope,
+        mods: &mut Imports,
+        lib: &Module,
+        this_ptr: Option<&Dynamic>,
+        epoch: usize,
+        pos: Position,
+        level: usize,
+    ) -> Option<usize> {
+        let line = pos.line()?;
+
+        self.breakpoints
+            .iter()
+            .filter(|bp| bp.line == line)
+            .find(|bp| match &bp.condition {
+                None => true,
+                Some(condition) => {
+                    // The condition is evaluated without pausing, and errors count as false
+                    let expr = match condition.statements() {
+                        [Stmt::Expr(expr)] => expr,
+                        _ => return false,
+                    };
+
+                    let mut state = State {
+                        fn_cache_epoch: epoch,
+                        debug: Some(Box::new(DebugState::disabled())),
+                        ..Default::default()
+                    };
+                    let mut scope = scope.clone();
+                    let mut this = this_ptr.cloned();
+
+                    self.eval_expr(
+                        &mut scope,
+                        mods,
+                        &mut state,
+                        lib,
+                        &mut this.as_mut(),
+                        expr,
+                        level,
+                    )
+                    .ok()
+                    .and_then(|value| value.as_bool().ok())
+                    .unwrap_or(false)
+                }
+            })
+            .map(|bp| bp.line)
+    }
+}
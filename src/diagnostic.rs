@@ -0,0 +1,305 @@
+//! Module implementing diagnostics (i.e. warnings) for scripts.
+
+use crate::engine::KEYWORD_EVAL;
+use crate::parser::{Expr, ScriptFnDef, Stmt};
+use crate::token::Position;
+
+use crate::stdlib::{fmt, string::String, vec::Vec};
+
+/// Type of warning about a script that compiles, but probably does not do what is intended.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+#[non_exhaustive]
+pub enum DiagnosticType {
+    /// A variable is defined but never used. Wrapped value is the variable name.
+    ///
+    /// Variables with names starting with an underscore (`_`), and variables at global level
+    /// (which may be read via the `Scope`), are never reported.
+    UnusedVariable(String),
+    /// A statement can never be reached because a previous statement always returns,
+    /// throws, breaks or continues.
+    UnreachableCode,
+    /// The condition of an `if` or `while` statement is always `true` or always `false`.
+    /// Wrapped value is the value of the condition.
+    ConstantCondition(bool),
+    /// A variable or constant is defined with the same name as another one that is still in scope.
+    /// Wrapped value is the variable name.
+    ShadowedVariable(String),
+}
+
+impl fmt::Display for DiagnosticType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnusedVariable(s) => write!(f, "Variable '{}' is never used", s),
+            Self::UnreachableCode => f.write_str("Unreachable code"),
+            Self::ConstantCondition(b) => write!(f, "Condition is always {}", b),
+            Self::ShadowedVariable(s) => write!(f, "Variable '{}' shadows a previous definition", s),
+        }
+    }
+}
+
+/// A warning about a script, together with its position.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct Diagnostic(pub DiagnosticType, pub Position);
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)?;
+
+        // Do not write any position if None
+        if !self.1.is_none() {
+            write!(f, " ({})", self.1)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A variable in scope during analysis.
+struct Var<'a> {
+    /// Name of the variable.
+    name: &'a str,
+    /// Position of the definition, or `None` if the variable is not defined in script
+    /// (e.g. a parameter or a loop variable).
+    pos: Position,
+    /// Has the variable been used?
+    used: bool,
+    /// Value of the variable if it is a boolean constant.
+    value: Option<bool>,
+}
+
+/// Mutable state throughout an analysis pass.
+struct State<'a> {
+    /// Stack of variables in scope.
+    stack: Vec<Var<'a>>,
+    /// Unused variables in the current function, reported at the end.
+    unused: Vec<Diagnostic>,
+    /// Does the current function call `eval`, which may use any variable?
+    has_eval: bool,
+    /// Is the analysis at global level (i.e. not inside a function)?
+    global: bool,
+    /// Number of nested blocks.
+    depth: usize,
+    /// All the diagnostics found.
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> State<'a> {
+    /// Add a new variable to the stack.
+    fn push_var(&mut self, name: &'a str, pos: Position, value: Option<bool>) {
+        if !pos.is_none() && self.stack.iter().any(|v| v.name == name) {
+            self.diagnostics
+                .push(Diagnostic(DiagnosticType::ShadowedVariable(name.into()), pos));
+        }
+
+        self.stack.push(Var {
+            name,
+            pos,
+            used: (self.global && self.depth == 0) || pos.is_none() || name.starts_with('_'),
+            value,
+        });
+    }
+    /// Find a variable in scope, marking it as used.
+    fn use_var(&mut self, name: &str) -> Option<&Var<'a>> {
+        let var = self.stack.iter_mut().rev().find(|v| v.name == name)?;
+        var.used = true;
+        Some(var)
+    }
+    /// Prune the stack of variables back to a specified size, collecting the unused variables.
+    fn restore_stack(&mut self, len: usize) {
+        let unused = &mut self.unused;
+
+        self.stack
+            .drain(len..)
+            .filter(|v| !v.used)
+            .for_each(|v| {
+                unused.push(Diagnostic(DiagnosticType::UnusedVariable(v.name.into()), v.pos))
+            });
+    }
+    /// Finish analyzing a function (or the global level), reporting the unused variables.
+    fn finish(&mut self) {
+        self.restore_stack(0);
+
+        if !self.has_eval {
+            self.diagnostics.append(&mut self.unused);
+        }
+
+        self.unused.clear();
+        self.has_eval = false;
+    }
+}
+
+/// Analyze a script for diagnostics, which are returned sorted by position.
+///
+/// The script must not have been optimized, so that the diagnostics refer to the original script.
+pub(crate) fn diagnose(statements: &[Stmt], functions: &[ScriptFnDef]) -> Vec<Diagnostic> {
+    let mut state = State {
+        stack: Default::default(),
+        unused: Default::default(),
+        has_eval: false,
+        global: true,
+        depth: 0,
+        diagnostics: Default::default(),
+    };
+
+    diagnose_stmts(statements, &mut state);
+    state.finish();
+
+    state.global = false;
+
+    functions.iter().for_each(|fn_def| {
+        fn_def
+            .params
+            .iter()
+            .for_each(|name| state.push_var(name, Position::none(), None));
+
+        diagnose_stmt(&fn_def.body, &mut state);
+        state.finish();
+    });
+
+    state.diagnostics.sort_by_key(|d| d.1);
+    state.diagnostics
+}
+
+/// Analyze a list of statements in a block.
+fn diagnose_stmts<'a>(statements: &'a [Stmt], state: &mut State<'a>) {
+    let mut diverged = false;
+
+    for stmt in statements {
+        if diverged && !matches!(stmt, Stmt::Noop(_)) {
+            state
+                .diagnostics
+                .push(Diagnostic(DiagnosticType::UnreachableCode, stmt.position()));
+            // Only report the first unreachable statement
+            diverged = false;
+        }
+
+        diagnose_stmt(stmt, state);
+
+        if matches!(stmt, Stmt::ReturnWithVal(_) | Stmt::Break(_) | Stmt::Continue(_)) {
+            diverged = true;
+        }
+    }
+}
+
+/// Analyze a statement.
+fn diagnose_stmt<'a>(stmt: &'a Stmt, state: &mut State<'a>) {
+    match stmt {
+        Stmt::IfThenElse(x) => {
+            diagnose_condition(&x.0, state);
+            diagnose_stmt(&x.1, state);
+            if let Some(stmt) = &x.2 {
+                diagnose_stmt(stmt, state);
+            }
+        }
+        Stmt::While(x) => {
+            diagnose_condition(&x.0, state);
+            diagnose_stmt(&x.1, state);
+        }
+        Stmt::Loop(x) => diagnose_stmt(x, state),
+        Stmt::For(x) => {
+            diagnose_expr(&x.1, state);
+            let len = state.stack.len();
+            state.depth += 1;
+            state.push_var(&x.0, Position::none(), None);
+            diagnose_stmt(&x.2, state);
+            state.depth -= 1;
+            state.restore_stack(len);
+        }
+        Stmt::Let(x) => {
+            if let Some(expr) = &x.1 {
+                diagnose_expr(expr, state);
+            }
+            let (name, pos) = &x.0;
+            state.push_var(name, *pos, None);
+        }
+        Stmt::Const(x) => {
+            diagnose_expr(&x.1, state);
+            let (name, pos) = &x.0;
+            let value = match x.1 {
+                Expr::True(_) => Some(true),
+                Expr::False(_) => Some(false),
+                _ => None,
+            };
+            state.push_var(name, *pos, value);
+        }
+        Stmt::Block(x) => {
+            let len = state.stack.len();
+            state.depth += 1;
+            diagnose_stmts(&x.0, state);
+            state.depth -= 1;
+            state.restore_stack(len);
+        }
+        Stmt::Expr(x) => diagnose_expr(x, state),
+        Stmt::ReturnWithVal(x) => {
+            if let Some(expr) = &x.1 {
+                diagnose_expr(expr, state);
+            }
+        }
+        #[cfg(not(feature = "no_module"))]
+        Stmt::Import(x) => diagnose_expr(&x.0, state),
+        #[cfg(not(feature = "no_module"))]
+        Stmt::Export(x) => x.0.iter().for_each(|((name, _), modules, _)| {
+            if modules.is_none() {
+                state.use_var(name);
+            }
+        }),
+        Stmt::Noop(_) | Stmt::Continue(_) | Stmt::Break(_) => (),
+    }
+}
+
+/// Analyze the condition of an `if` or `while` statement.
+fn diagnose_condition<'a>(expr: &'a Expr, state: &mut State<'a>) {
+    let value = match expr {
+        Expr::True(_) => Some(true),
+        Expr::False(_) => Some(false),
+        Expr::Variable(x) if x.1.is_none() => state
+            .stack
+            .iter()
+            .rev()
+            .find(|v| v.name == (x.0).0)
+            .and_then(|v| v.value),
+        _ => None,
+    };
+
+    if let Some(value) = value {
+        state.diagnostics.push(Diagnostic(
+            DiagnosticType::ConstantCondition(value),
+            expr.position(),
+        ));
+    }
+
+    diagnose_expr(expr, state);
+}
+
+/// Analyze an expression.
+fn diagnose_expr<'a>(expr: &'a Expr, state: &mut State<'a>) {
+    match expr {
+        Expr::Variable(x) if x.1.is_none() => {
+            state.use_var(&(x.0).0);
+        }
+        Expr::Stmt(x) => diagnose_stmt(&x.0, state),
+        Expr::Expr(x) => diagnose_expr(x, state),
+        Expr::FnCall(x) => {
+            if x.1.is_none() && (x.0).0 == KEYWORD_EVAL {
+                state.has_eval = true;
+            }
+            x.3.iter().for_each(|expr| diagnose_expr(expr, state));
+        }
+        Expr::Assignment(x) => {
+            match &x.0 {
+                // Assigning a new value to a variable is not a use of it
+                Expr::Variable(v) if v.1.is_none() && x.1.is_empty() => (),
+                lhs => diagnose_expr(lhs, state),
+            }
+            diagnose_expr(&x.2, state);
+        }
+        Expr::Dot(x) | Expr::Index(x) | Expr::In(x) | Expr::And(x) | Expr::Or(x) => {
+            diagnose_expr(&x.0, state);
+            diagnose_expr(&x.1, state);
+        }
+        Expr::Array(x) => x.0.iter().for_each(|expr| diagnose_expr(expr, state)),
+        Expr::Map(x) => x.0.iter().for_each(|(_, expr)| diagnose_expr(expr, state)),
+        Expr::Custom(x) => (x.0).0.iter().for_each(|expr| diagnose_expr(expr, state)),
+        _ => (),
+    }
+}
@@ -1,28 +1,37 @@
 //! Main module defining the script evaluation `Engine`.
 
-use crate::any::{map_std_type_name, Dynamic, Union};
+use crate::any::{map_std_type_name, Dynamic, Union, Variant};
+use crate::bytecode::{Backend, Program};
 use crate::calc_fn_hash;
+use crate::cost::{CostTable, NodeType};
+use crate::debugger::{Breakpoint, DebugState, DebuggerCallback};
 use crate::fn_call::run_builtin_op_assignment;
-use crate::fn_native::{CallableFunction, Callback, FnPtr};
+use crate::fn_native::{CallableFunction, Callback, FnPtr, Shared};
+use crate::interrupt::InterruptHandle;
 use crate::module::{Module, ModuleRef};
 use crate::optimize::OptimizationLevel;
+use crate::options::EvalOptions;
 use crate::packages::{Package, PackagesCollection, StandardPackage};
-use crate::parser::{Expr, ReturnType, Stmt};
+use crate::parser::{Expr, ReturnType, Stmt, INT};
 use crate::r#unsafe::unsafe_cast_var_name_to_lifetime;
 use crate::result::EvalAltResult;
 use crate::scope::{EntryType as ScopeEntryType, Scope};
+use crate::stats::EvalStats;
 use crate::syntax::{CustomSyntax, EvalContext};
 use crate::token::Position;
 use crate::utils::StaticVec;
 
-#[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
-use crate::any::Variant;
-
 #[cfg(not(feature = "no_function"))]
 use crate::parser::{FnAccess, ScriptFnDef};
 
+#[cfg(not(feature = "no_function"))]
+use crate::memo::{LockedMemoCache, MemoCache, MAX_MEMO_ENTRIES};
+
+#[cfg(not(feature = "no_float"))]
+use crate::parser::FLOAT;
+
 #[cfg(not(feature = "no_module"))]
-use crate::module::ModuleResolver;
+use crate::{error::ParseErrorType, module::ModuleResolver, parser::ImportNames};
 
 #[cfg(not(feature = "no_std"))]
 #[cfg(not(feature = "no_module"))]
@@ -31,19 +40,42 @@ use crate::module::resolvers;
 #[cfg(any(not(feature = "no_object"), not(feature = "no_module")))]
 use crate::utils::ImmutableString;
 
+#[cfg(not(feature = "no_optimize"))]
+use crate::visitor::AstVisitor;
+
 use crate::stdlib::{
+    any::type_name,
     borrow::Cow,
     boxed::Box,
     collections::{HashMap, HashSet},
     fmt, format,
+    hash::{Hash, Hasher},
     iter::{empty, once},
     string::{String, ToString},
+    sync::atomic::{AtomicUsize, Ordering},
     vec::Vec,
 };
 
-#[cfg(not(feature = "no_index"))]
+#[cfg(any(not(feature = "no_index"), not(feature = "unchecked")))]
 use crate::stdlib::any::TypeId;
 
+#[cfg(not(feature = "unchecked"))]
+use crate::stdlib::mem;
+
+#[cfg(not(feature = "unchecked"))]
+#[cfg(not(feature = "no_std"))]
+use crate::stdlib::time::Duration;
+
+#[cfg(not(feature = "unchecked"))]
+#[cfg(not(feature = "no_std"))]
+#[cfg(not(target_arch = "wasm32"))]
+use crate::stdlib::time::Instant;
+
+#[cfg(not(feature = "unchecked"))]
+#[cfg(not(feature = "no_std"))]
+#[cfg(target_arch = "wasm32")]
+use instant::Instant;
+
 /// Variable-sized array of `Dynamic` values.
 ///
 /// Not available under the `no_index` feature.
@@ -93,6 +125,9 @@ pub const KEYWORD_FN_PTR_CALL: &str = "call";
 pub const KEYWORD_FN_PTR_CURRY: &str = "curry";
 pub const KEYWORD_SHARED: &str = "shared";
 pub const KEYWORD_THIS: &str = "this";
+pub const KEYWORD_GLOBAL: &str = "global";
+#[cfg(not(feature = "no_module"))]
+pub const KEYWORD_FROM: &str = "from";
 pub const FN_TO_STRING: &str = "to_string";
 #[cfg(not(feature = "no_object"))]
 pub const FN_GET: &str = "get$";
@@ -107,6 +142,10 @@ pub const FN_ANONYMOUS: &str = "anon$";
 pub const MARKER_EXPR: &str = "$expr$";
 pub const MARKER_BLOCK: &str = "$block$";
 pub const MARKER_IDENT: &str = "$ident$";
+#[cfg(not(feature = "no_module"))]
+pub const MARKER_IMPORT: &str = "*";
+#[cfg(not(feature = "no_module"))]
+pub const MARKER_EXPORT: &str = "$export$";
 
 /// A type specifying the method of chaining.
 #[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
@@ -118,7 +157,6 @@ pub enum ChainType {
 }
 
 /// A type that encapsulates a mutation target for an expression with side effects.
-#[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
 #[derive(Debug)]
 pub enum Target<'a> {
     /// The target is a mutable reference to a `Dynamic` value somewhere.
@@ -131,7 +169,6 @@ pub enum Target<'a> {
     StringChar(&'a mut Dynamic, usize, Dynamic),
 }
 
-#[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
 impl Target<'_> {
     /// Is the `Target` a reference pointing to other data?
     pub fn is_ref(&self) -> bool {
@@ -213,13 +250,11 @@ impl Target<'_> {
     }
 }
 
-#[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
 impl<'a> From<&'a mut Dynamic> for Target<'a> {
     fn from(value: &'a mut Dynamic) -> Self {
         Self::Ref(value)
     }
 }
-#[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
 impl<T: Into<Dynamic>> From<T> for Target<'_> {
     fn from(value: T) -> Self {
         Self::Value(value.into())
@@ -237,7 +272,7 @@ impl<T: Into<Dynamic>> From<T> for Target<'_> {
 /// ## WARNING
 ///
 /// This type is volatile and may change.
-#[derive(Debug, Clone, Eq, PartialEq, Hash, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct State {
     /// Normally, access to variables are parsed with a relative offset into the scope to avoid a lookup.
     /// In some situation, e.g. after running an `eval` statement, subsequent offsets become mis-aligned.
@@ -250,12 +285,110 @@ pub struct State {
     pub operations: u64,
     /// Number of modules loaded.
     pub modules: usize,
+    /// Constants defined at global level, accessible from functions via the `global` namespace.
+    pub global_constants: Module,
+    /// Bytecode being run, if the bytecode backend is used.
+    pub(crate) program: Option<Shared<Program>>,
+    /// Identifies the functions that can be resolved, for validating the call-site caches.
+    /// It is unique to each evaluation and changes whenever a module is imported.
+    pub fn_cache_epoch: usize,
+    /// Pending tail call (hash of the script-defined function and argument values) to be made by
+    /// the calling function in place of returning.
+    #[cfg(not(feature = "no_function"))]
+    pub(crate) tail_call: Option<(u64, StaticVec<Dynamic>)>,
+    /// Results of memoized functions cached during this evaluation.
+    #[cfg(not(feature = "no_function"))]
+    pub(crate) memo_cache: MemoCache,
+    /// Point in time after which the evaluation is aborted, if any.
+    #[cfg(not(feature = "unchecked"))]
+    #[cfg(not(feature = "no_std"))]
+    pub deadline: Option<Instant>,
+    /// Approximate number of bytes held by the variables in scope, as of the last scan.
+    #[cfg(not(feature = "unchecked"))]
+    pub memory: usize,
+    /// Approximate number of bytes allocated since the last scan of the variables in scope.
+    #[cfg(not(feature = "unchecked"))]
+    pub memory_allocated: usize,
+    /// Approximate number of bytes held by the variables of all calling functions, as of their last scans.
+    #[cfg(not(feature = "unchecked"))]
+    pub memory_outer: usize,
+    /// Limits overriding those of the `Engine` for this evaluation, if any.
+    #[cfg(not(feature = "unchecked"))]
+    pub limits: Option<Limits>,
+    /// Statistics collected during this evaluation, if requested.
+    pub(crate) stats: Option<Box<EvalStats>>,
+    /// State of the debugger, once it is called during this evaluation.
+    pub(crate) debug: Option<Box<DebugState>>,
+}
+
+/// Two `State`'s are equal when the evaluations are at the same point, i.e. their flags, levels
+/// and counters are equal.  Data held for the duration of an evaluation, such as global constants,
+/// caches, limits and statistics, is not compared.
+impl PartialEq for State {
+    fn eq(&self, other: &Self) -> bool {
+        self.always_search == other.always_search
+            && self.scope_level == other.scope_level
+            && self.operations == other.operations
+            && self.modules == other.modules
+    }
+}
+
+impl Eq for State {}
+
+impl Hash for State {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.always_search.hash(state);
+        self.scope_level.hash(state);
+        self.operations.hash(state);
+        self.modules.hash(state);
+    }
 }
 
 impl State {
     /// Create a new `State`.
     pub fn new() -> Self {
-        Default::default()
+        let mut state: Self = Default::default();
+        state.invalidate_fn_cache();
+        state
+    }
+    /// Record the number of variables in scope, if statistics are collected.
+    pub(crate) fn record_scope_size(&mut self, size: usize) {
+        if let Some(stats) = &mut self.stats {
+            stats.record_scope_size(size);
+        }
+    }
+    /// Invalidate all call-site caches, e.g. because a module is imported.
+    pub fn invalidate_fn_cache(&mut self) {
+        static NEXT_EPOCH: AtomicUsize = AtomicUsize::new(1);
+        self.fn_cache_epoch = NEXT_EPOCH.fetch_add(1, Ordering::Relaxed);
+    }
+    /// Create a new `State`, making all constants in a `Scope` visible in the `global` namespace.
+    pub fn from_scope(scope: &Scope) -> Self {
+        let mut state = Self::new();
+
+        scope
+            .to_iter()
+            .filter(|entry| entry.typ == ScopeEntryType::Constant)
+            .for_each(|entry| {
+                // The scope is searched in reverse order, so skip constants that are shadowed
+                if !state.global_constants.contains_var(&entry.name) {
+                    state
+                        .global_constants
+                        .set_var(entry.name.to_string(), entry.value.clone());
+                }
+            });
+
+        state
+    }
+    /// Set the point in time after which the evaluation is aborted.
+    /// An earlier deadline already set is kept.
+    #[cfg(not(feature = "unchecked"))]
+    #[cfg(not(feature = "no_std"))]
+    pub fn set_deadline(&mut self, deadline: Instant) {
+        self.deadline = Some(match self.deadline {
+            Some(current) if current < deadline => current,
+            _ => deadline,
+        });
     }
 }
 
@@ -288,6 +421,7 @@ pub fn get_script_function_by_signature<'a>(
 ///
 /// This type is volatile and may change.
 #[cfg(not(feature = "unchecked"))]
+#[derive(Debug, Clone)]
 pub struct Limits {
     /// Maximum levels of call-stack to prevent infinite recursion.
     ///
@@ -299,6 +433,11 @@ pub struct Limits {
     pub max_function_expr_depth: usize,
     /// Maximum number of operations allowed to run.
     pub max_operations: u64,
+    /// Maximum time allowed for a script to run (zero for unlimited).
+    ///
+    /// Not available under the `no_std` feature.
+    #[cfg(not(feature = "no_std"))]
+    pub max_execution_time: Duration,
     /// Maximum number of modules allowed to load.
     pub max_modules: usize,
     /// Maximum length of a string.
@@ -307,6 +446,8 @@ pub struct Limits {
     pub max_array_size: usize,
     /// Maximum number of properties in a map.
     pub max_map_size: usize,
+    /// Maximum number of bytes (approximately) held by the values of a script.
+    pub max_memory: usize,
 }
 
 /// Rhai main scripting engine.
@@ -340,6 +481,9 @@ pub struct Engine {
 
     /// A hashmap mapping type names to pretty-print names.
     pub(crate) type_names: Option<HashMap<String, String>>,
+    /// A hashmap mapping custom types to functions reporting the size of their values.
+    #[cfg(not(feature = "unchecked"))]
+    pub(crate) type_sizes: Option<HashMap<TypeId, Callback<Dynamic, usize>>>,
 
     /// A hashset containing symbols to disable.
     pub(crate) disabled_symbols: Option<HashSet<String>>,
@@ -354,9 +498,38 @@ pub struct Engine {
     pub(crate) debug: Callback<str, ()>,
     /// Callback closure for progress reporting.
     pub(crate) progress: Option<Callback<u64, bool>>,
+    /// Handle to interrupt running scripts.
+    pub(crate) interrupt: InterruptHandle,
+    /// Costs of operations other than the default of one operation each.
+    pub(crate) costs: Option<Box<CostTable>>,
+    /// Callback closure of the debugger attached to the Engine, if any.
+    pub(crate) debugger: Option<Box<DebuggerCallback>>,
+    /// Breakpoints of the debugger.
+    pub(crate) breakpoints: Vec<Breakpoint>,
 
     /// Optimize the AST after compilation.
     pub(crate) optimization_level: OptimizationLevel,
+    /// Custom optimizer passes.
+    #[cfg(not(feature = "no_optimize"))]
+    pub(crate) optimizer_passes: Vec<Box<dyn AstVisitor>>,
+
+    /// Backend used to run scripts.
+    pub(crate) backend: Backend,
+    /// Check calls to registered functions against their signatures at compile time?
+    pub(crate) check_fn_signatures: bool,
+
+    /// A hashset containing names of script-defined functions to memoize.
+    #[cfg(not(feature = "no_function"))]
+    pub(crate) memoized_fns: Option<HashSet<String>>,
+    /// Keep the results of memoized functions across evaluations?
+    #[cfg(not(feature = "no_function"))]
+    pub(crate) memo_persistent: bool,
+    /// Maximum number of results of memoized functions to cache.
+    #[cfg(not(feature = "no_function"))]
+    pub(crate) max_memo_entries: usize,
+    /// Results of memoized functions kept across evaluations.
+    #[cfg(not(feature = "no_function"))]
+    pub(crate) memo_cache: LockedMemoCache,
 
     /// Max limits.
     #[cfg(not(feature = "unchecked"))]
@@ -390,6 +563,8 @@ impl Default for Engine {
             module_resolver: None,
 
             type_names: None,
+            #[cfg(not(feature = "unchecked"))]
+            type_sizes: None,
             disabled_symbols: None,
             custom_keywords: None,
             custom_syntax: None,
@@ -400,6 +575,10 @@ impl Default for Engine {
 
             // progress callback
             progress: None,
+            interrupt: Default::default(),
+            costs: None,
+            debugger: None,
+            breakpoints: Default::default(),
 
             // optimization level
             #[cfg(feature = "no_optimize")]
@@ -407,6 +586,20 @@ impl Default for Engine {
 
             #[cfg(not(feature = "no_optimize"))]
             optimization_level: OptimizationLevel::Simple,
+            #[cfg(not(feature = "no_optimize"))]
+            optimizer_passes: Default::default(),
+
+            backend: Backend::TreeWalking,
+            check_fn_signatures: false,
+
+            #[cfg(not(feature = "no_function"))]
+            memoized_fns: None,
+            #[cfg(not(feature = "no_function"))]
+            memo_persistent: false,
+            #[cfg(not(feature = "no_function"))]
+            max_memo_entries: MAX_MEMO_ENTRIES,
+            #[cfg(not(feature = "no_function"))]
+            memo_cache: Default::default(),
 
             #[cfg(not(feature = "unchecked"))]
             limits: Limits {
@@ -414,10 +607,13 @@ impl Default for Engine {
                 max_expr_depth: MAX_EXPR_DEPTH,
                 max_function_expr_depth: MAX_FUNCTION_EXPR_DEPTH,
                 max_operations: 0,
+                #[cfg(not(feature = "no_std"))]
+                max_execution_time: Duration::from_secs(0),
                 max_modules: usize::MAX,
                 max_string_size: 0,
                 max_array_size: 0,
                 max_map_size: 0,
+                max_memory: 0,
             },
         };
 
@@ -514,6 +710,40 @@ pub fn search_imports_mut<'s>(
     })
 }
 
+/// Make sure that none of the members of a module to be imported into the local namespace collides
+/// with a variable, an imported module or member, or a script-defined function.
+#[cfg(not(feature = "no_module"))]
+fn check_imported_names(
+    scope: &Scope,
+    mods: &Imports,
+    _lib: &Module,
+    module: &Module,
+    pos: Position,
+) -> Result<(), Box<EvalAltResult>> {
+    #[cfg(not(feature = "no_function"))]
+    let is_script_fn = |name: &str| _lib.iter_script_fn().any(|f| f.name.as_str() == name);
+    #[cfg(feature = "no_function")]
+    let is_script_fn = |_: &str| false;
+
+    match module.iter_member_names().find(|&name| {
+        scope.contains(name)
+            || mods.iter().any(|(n, m)| {
+                if n == MARKER_IMPORT {
+                    m.contains_member(name)
+                } else {
+                    n == name
+                }
+            })
+            || is_script_fn(name)
+    }) {
+        Some(name) => Err(Box::new(EvalAltResult::ErrorParsing(
+            ParseErrorType::DuplicatedImport(name.into()),
+            pos,
+        ))),
+        None => Ok(()),
+    }
+}
+
 /// Search for a variable within the scope and imports
 pub fn search_namespace<'s, 'a>(
     scope: &'s mut Scope,
@@ -521,9 +751,25 @@ pub fn search_namespace<'s, 'a>(
     state: &mut State,
     this_ptr: &'s mut Option<&mut Dynamic>,
     expr: &'a Expr,
-) -> Result<(&'s mut Dynamic, &'a str, ScopeEntryType, Position), Box<EvalAltResult>> {
+) -> Result<(Target<'s>, &'a str, ScopeEntryType, Position), Box<EvalAltResult>> {
     match expr {
         Expr::Variable(v) => match v.as_ref() {
+            // Global constant - unless shadowed by a module imported under the same name
+            ((name, pos), Some(modules), _, _)
+                if modules.len() == 1
+                    && modules.get(0).0 == KEYWORD_GLOBAL
+                    && mods.iter().all(|(n, _)| n != KEYWORD_GLOBAL) =>
+            {
+                let value = state.global_constants.get_var(name).ok_or_else(|| {
+                    Box::new(EvalAltResult::ErrorVariableNotFound(
+                        format!("{}{}", modules, name),
+                        *pos,
+                    ))
+                })?;
+
+                // Global constants are copied so they cannot be modified
+                Ok((value.into(), name, ScopeEntryType::Constant, *pos))
+            }
             // Qualified variable
             ((name, pos), Some(modules), hash_var, _) => {
                 let module = search_imports_mut(mods, state, modules)?;
@@ -540,10 +786,27 @@ pub fn search_namespace<'s, 'a>(
                     })?;
 
                 // Module variables are constant
-                Ok((target, name, ScopeEntryType::Constant, *pos))
+                Ok((target.into(), name, ScopeEntryType::Constant, *pos))
             }
             // Normal variable access
-            _ => search_scope_only(scope, state, this_ptr, expr),
+            _ => match search_scope_only(scope, state, this_ptr, expr) {
+                // Search variables imported into the local namespace
+                #[cfg(not(feature = "no_module"))]
+                Err(err) if matches!(*err, EvalAltResult::ErrorVariableNotFound(_, _)) => {
+                    let ((name, pos), _, _, _) = v.as_ref();
+
+                    let target = mods
+                        .iter_mut()
+                        .rev()
+                        .filter(|(n, _)| n == MARKER_IMPORT)
+                        .find_map(|(_, m)| m.get_var_mut(name))
+                        .ok_or(err)?;
+
+                    // Imported variables are constant
+                    Ok((target.into(), name, ScopeEntryType::Constant, *pos))
+                }
+                r => r.map(|(target, name, typ, pos)| (target.into(), name, typ, pos)),
+            },
         },
         _ => unreachable!(),
     }
@@ -561,26 +824,26 @@ pub fn search_scope_only<'s, 'a>(
         _ => unreachable!(),
     };
 
-    // Check if the variable is `this`
-    if name == KEYWORD_THIS {
-        if let Some(val) = this_ptr {
-            return Ok(((*val).into(), KEYWORD_THIS, ScopeEntryType::Normal, *pos));
-        } else {
-            return Err(Box::new(EvalAltResult::ErrorUnboundedThis(*pos)));
-        }
-    }
-
-    // Check if it is directly indexed
-    let index = if state.always_search { None } else { *index };
+    // Check if it is directly indexed - local variables are resolved to a slot during parsing,
+    // so only variables pushed by the host (or after an `eval`) need to be searched by name
+    let index = match index {
+        Some(index) if !state.always_search => scope.len() - index.get(),
+        _ => {
+            // Check if the variable is `this`
+            if name == KEYWORD_THIS {
+                if let Some(val) = this_ptr {
+                    return Ok(((*val).into(), KEYWORD_THIS, ScopeEntryType::Normal, *pos));
+                } else {
+                    return Err(Box::new(EvalAltResult::ErrorUnboundedThis(*pos)));
+                }
+            }
 
-    let index = if let Some(index) = index {
-        scope.len() - index.get()
-    } else {
-        // Find the variable in the scope
-        scope
-            .get_index(name)
-            .ok_or_else(|| Box::new(EvalAltResult::ErrorVariableNotFound(name.into(), *pos)))?
-            .0
+            // Find the variable in the scope
+            scope
+                .get_index(name)
+                .ok_or_else(|| Box::new(EvalAltResult::ErrorVariableNotFound(name.into(), *pos)))?
+                .0
+        }
     };
 
     let (val, typ) = scope.get_mut(index);
@@ -606,6 +869,8 @@ impl Engine {
             module_resolver: None,
 
             type_names: None,
+            #[cfg(not(feature = "unchecked"))]
+            type_sizes: None,
             disabled_symbols: None,
             custom_keywords: None,
             custom_syntax: None,
@@ -613,12 +878,30 @@ impl Engine {
             print: Box::new(|_| {}),
             debug: Box::new(|_| {}),
             progress: None,
+            interrupt: Default::default(),
+            costs: None,
+            debugger: None,
+            breakpoints: Default::default(),
 
             #[cfg(feature = "no_optimize")]
             optimization_level: OptimizationLevel::None,
 
             #[cfg(not(feature = "no_optimize"))]
             optimization_level: OptimizationLevel::Simple,
+            #[cfg(not(feature = "no_optimize"))]
+            optimizer_passes: Default::default(),
+
+            backend: Backend::TreeWalking,
+            check_fn_signatures: false,
+
+            #[cfg(not(feature = "no_function"))]
+            memoized_fns: None,
+            #[cfg(not(feature = "no_function"))]
+            memo_persistent: false,
+            #[cfg(not(feature = "no_function"))]
+            max_memo_entries: MAX_MEMO_ENTRIES,
+            #[cfg(not(feature = "no_function"))]
+            memo_cache: Default::default(),
 
             #[cfg(not(feature = "unchecked"))]
             limits: Limits {
@@ -626,10 +909,13 @@ impl Engine {
                 max_expr_depth: MAX_EXPR_DEPTH,
                 max_function_expr_depth: MAX_FUNCTION_EXPR_DEPTH,
                 max_operations: 0,
+                #[cfg(not(feature = "no_std"))]
+                max_execution_time: Duration::from_secs(0),
                 max_modules: usize::MAX,
                 max_string_size: 0,
                 max_array_size: 0,
                 max_map_size: 0,
+                max_memory: 0,
             },
         }
     }
@@ -639,6 +925,7 @@ impl Engine {
     #[cfg(any(not(feature = "no_index"), not(feature = "no_object")))]
     fn eval_dot_index_chain_helper(
         &self,
+        mods: &Imports,
         state: &mut State,
         lib: &Module,
         this_ptr: &mut Option<&mut Dynamic>,
@@ -678,8 +965,8 @@ impl Engine {
                             .get_indexed_mut(state, lib, target, idx_val, idx_pos, false, level)?;
 
                         self.eval_dot_index_chain_helper(
-                            state, lib, this_ptr, obj_ptr, expr, idx_values, next_chain, level,
-                            _new_val,
+                            mods, state, lib, this_ptr, obj_ptr, expr, idx_values, next_chain,
+                            level, _new_val,
                         )
                         .map_err(|err| err.new_position(*pos))
                     }
@@ -696,7 +983,7 @@ impl Engine {
                                 let args = &mut [target.as_mut(), &mut idx_val2, &mut new_val];
 
                                 self.exec_fn_call(
-                                    state, lib, FN_IDX_SET, true, 0, args, is_ref, true, None,
+                                    state, lib, FN_IDX_SET, true, 0, args, is_ref, true, None, None,
                                     level,
                                 )
                                 .or_else(|err| match *err {
@@ -720,7 +1007,7 @@ impl Engine {
                                     let args = &mut [target.as_mut(), &mut idx_val2, &mut new_val];
 
                                     self.exec_fn_call(
-                                        state, lib, FN_IDX_SET, true, 0, args, is_ref, true, None,
+                                        state, lib, FN_IDX_SET, true, 0, args, is_ref, true, None, None,
                                         level,
                                     )?;
                                 }
@@ -742,7 +1029,7 @@ impl Engine {
                 match rhs {
                     // xxx.fn_name(arg_expr_list)
                     Expr::FnCall(x) if x.1.is_none() => {
-                        self.make_method_call(state, lib, target, rhs, idx_val, level)
+                        self.make_method_call(mods, state, lib, target, rhs, idx_val, level)
                     }
                     // xxx.module::fn_name(...) - syntax error
                     Expr::FnCall(_) => unreachable!(),
@@ -771,7 +1058,8 @@ impl Engine {
                         let ((_, _, setter), pos) = x.as_ref();
                         let mut args = [target.as_mut(), _new_val.as_mut().unwrap()];
                         self.exec_fn_call(
-                            state, lib, setter, true, 0, &mut args, is_ref, true, None, level,
+                            state, lib, setter, true, 0, &mut args, is_ref, true, None, None,
+                            level,
                         )
                         .map(|(v, _)| (v, true))
                         .map_err(|err| err.new_position(*pos))
@@ -781,7 +1069,8 @@ impl Engine {
                         let ((_, getter, _), pos) = x.as_ref();
                         let mut args = [target.as_mut()];
                         self.exec_fn_call(
-                            state, lib, getter, true, 0, &mut args, is_ref, true, None, level,
+                            state, lib, getter, true, 0, &mut args, is_ref, true, None, None,
+                            level,
                         )
                         .map(|(v, _)| (v, false))
                         .map_err(|err| err.new_position(*pos))
@@ -799,7 +1088,7 @@ impl Engine {
                             // {xxx:map}.fn_name(arg_expr_list)[expr] | {xxx:map}.fn_name(arg_expr_list).expr
                             Expr::FnCall(x) if x.1.is_none() => {
                                 let (val, _) = self.make_method_call(
-                                    state, lib, target, sub_lhs, idx_val, level,
+                                    mods, state, lib, target, sub_lhs, idx_val, level,
                                 )?;
                                 val.into()
                             }
@@ -810,8 +1099,8 @@ impl Engine {
                         };
 
                         self.eval_dot_index_chain_helper(
-                            state, lib, this_ptr, &mut val, expr, idx_values, next_chain, level,
-                            _new_val,
+                            mods, state, lib, this_ptr, &mut val, expr, idx_values, next_chain,
+                            level, _new_val,
                         )
                         .map_err(|err| err.new_position(*pos))
                     }
@@ -827,7 +1116,7 @@ impl Engine {
                                 let args = &mut arg_values[..1];
                                 let (mut val, updated) = self
                                     .exec_fn_call(
-                                        state, lib, getter, true, 0, args, is_ref, true, None,
+                                        state, lib, getter, true, 0, args, is_ref, true, None, None,
                                         level,
                                     )
                                     .map_err(|err| err.new_position(*pos))?;
@@ -837,8 +1126,8 @@ impl Engine {
 
                                 let (result, may_be_changed) = self
                                     .eval_dot_index_chain_helper(
-                                        state, lib, this_ptr, target, expr, idx_values, next_chain,
-                                        level, _new_val,
+                                        mods, state, lib, this_ptr, target, expr, idx_values,
+                                        next_chain, level, _new_val,
                                     )
                                     .map_err(|err| err.new_position(*pos))?;
 
@@ -848,7 +1137,7 @@ impl Engine {
                                     arg_values[1] = val;
                                     self.exec_fn_call(
                                         state, lib, setter, true, 0, arg_values, is_ref, true,
-                                        None, level,
+                                        None, None, level,
                                     )
                                     .or_else(
                                         |err| match *err {
@@ -866,14 +1155,14 @@ impl Engine {
                             // xxx.fn_name(arg_expr_list)[expr] | xxx.fn_name(arg_expr_list).expr
                             Expr::FnCall(x) if x.1.is_none() => {
                                 let (mut val, _) = self.make_method_call(
-                                    state, lib, target, sub_lhs, idx_val, level,
+                                    mods, state, lib, target, sub_lhs, idx_val, level,
                                 )?;
                                 let val = &mut val;
                                 let target = &mut val.into();
 
                                 self.eval_dot_index_chain_helper(
-                                    state, lib, this_ptr, target, expr, idx_values, next_chain,
-                                    level, _new_val,
+                                    mods, state, lib, this_ptr, target, expr, idx_values,
+                                    next_chain, level, _new_val,
                                 )
                                 .map_err(|err| err.new_position(*pos))
                             }
@@ -925,11 +1214,28 @@ impl Engine {
             Expr::Variable(x) => {
                 let (var_name, var_pos) = &x.0;
 
-                self.inc_operations(state)
+                self.inc_node_operations(state, NodeType::Variable)
                     .map_err(|err| err.new_position(*var_pos))?;
 
-                let (target, _, typ, pos) =
-                    search_namespace(scope, mods, state, this_ptr, dot_lhs)?;
+                // Variables in imported modules are borrowed from `mods`, so functions imported
+                // into the local namespace are only available to method calls on other variables
+                let no_mods = Imports::new();
+
+                let (mut target, mods, typ, pos) = match x.1 {
+                    None => match search_scope_only(scope, state, this_ptr, dot_lhs) {
+                        Ok((target, _, typ, pos)) => (target.into(), &*mods, typ, pos),
+                        Err(_) => {
+                            let (target, _, typ, pos) =
+                                search_namespace(scope, mods, state, this_ptr, dot_lhs)?;
+                            (target, &no_mods, typ, pos)
+                        }
+                    },
+                    Some(_) => {
+                        let (target, _, typ, pos) =
+                            search_namespace(scope, mods, state, this_ptr, dot_lhs)?;
+                        (target, &no_mods, typ, pos)
+                    }
+                };
 
                 // Constants cannot be modified
                 match typ {
@@ -942,9 +1248,10 @@ impl Engine {
                     ScopeEntryType::Constant | ScopeEntryType::Normal => (),
                 }
 
-                let obj_ptr = &mut target.into();
+                let obj_ptr = &mut target;
                 self.eval_dot_index_chain_helper(
-                    state, lib, &mut None, obj_ptr, dot_rhs, idx_values, chain_type, level, new_val,
+                    mods, state, lib, &mut None, obj_ptr, dot_rhs, idx_values, chain_type, level,
+                    new_val,
                 )
                 .map(|(v, _)| v)
                 .map_err(|err| err.new_position(*op_pos))
@@ -960,7 +1267,8 @@ impl Engine {
                 let val = self.eval_expr(scope, mods, state, lib, this_ptr, expr, level)?;
                 let obj_ptr = &mut val.into();
                 self.eval_dot_index_chain_helper(
-                    state, lib, this_ptr, obj_ptr, dot_rhs, idx_values, chain_type, level, new_val,
+                    mods, state, lib, this_ptr, obj_ptr, dot_rhs, idx_values, chain_type, level,
+                    new_val,
                 )
                 .map(|(v, _)| v)
                 .map_err(|err| err.new_position(*op_pos))
@@ -987,7 +1295,7 @@ impl Engine {
         size: usize,
         level: usize,
     ) -> Result<(), Box<EvalAltResult>> {
-        self.inc_operations(state)
+        self.inc_node_operations(state, NodeType::Access)
             .map_err(|err| err.new_position(expr.position()))?;
 
         match expr {
@@ -1055,7 +1363,7 @@ impl Engine {
         _create: bool,
         _level: usize,
     ) -> Result<Target<'a>, Box<EvalAltResult>> {
-        self.inc_operations(state)?;
+        self.inc_node_operations(state, NodeType::Access)?;
 
         #[cfg(not(feature = "no_index"))]
         #[cfg(not(feature = "no_object"))]
@@ -1133,7 +1441,8 @@ impl Engine {
                 let type_name = val.type_name();
                 let args = &mut [val, &mut _idx];
                 self.exec_fn_call(
-                    state, _lib, FN_IDX_GET, true, 0, args, is_ref, true, None, _level,
+                    state, _lib, FN_IDX_GET, true, 0, args, is_ref, true, None, None,
+                    _level,
                 )
                 .map(|(v, _)| v.into())
                 .map_err(|err| match *err {
@@ -1164,7 +1473,7 @@ impl Engine {
         rhs: &Expr,
         level: usize,
     ) -> Result<Dynamic, Box<EvalAltResult>> {
-        self.inc_operations(state)
+        self.inc_node_operations(state, NodeType::In)
             .map_err(|err| err.new_position(rhs.position()))?;
 
         let lhs_value = self.eval_expr(scope, mods, state, lib, this_ptr, lhs, level)?;
@@ -1181,16 +1490,10 @@ impl Engine {
                     let def_value = Some(false);
                     let args = &mut [&mut lhs_value.clone(), value];
 
-                    let hashes = (
-                        // Qualifiers (none) + function name + number of arguments + argument `TypeId`'s.
-                        calc_fn_hash(empty(), op, args.len(), args.iter().map(|a| a.type_id())),
-                        0,
-                    );
-
                     let (r, _) = self
                         .call_fn_raw(
-                            &mut scope, mods, state, lib, op, hashes, args, false, false,
-                            def_value, level,
+                            &mut scope, mods, state, lib, op, 0, args, false, false, def_value,
+                            None, level,
                         )
                         .map_err(|err| err.new_position(rhs.position()))?;
                     if r.as_bool().unwrap_or(false) {
@@ -1219,6 +1522,121 @@ impl Engine {
         }
     }
 
+    /// Assign a value to a variable, or run an op-assignment on it.
+    ///
+    /// `expr` is the `Expr::Assignment` and `rhs_val` the value of its right-hand side.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn eval_var_assignment(
+        &self,
+        scope: &mut Scope,
+        mods: &mut Imports,
+        state: &mut State,
+        lib: &Module,
+        this_ptr: &mut Option<&mut Dynamic>,
+        expr: &Expr,
+        mut rhs_val: Dynamic,
+        level: usize,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let (lhs_expr, op, _, op_pos, annotation) = match expr {
+            Expr::Assignment(x) => x.as_ref(),
+            _ => unreachable!(),
+        };
+        let op_pos = *op_pos;
+        let (mut lhs_target, name, typ, pos) =
+            search_namespace(scope, mods, state, this_ptr, lhs_expr)?;
+        let lhs_ptr = lhs_target.as_mut();
+        self.inc_node_operations(state, NodeType::Assignment)
+            .map_err(|err| err.new_position(pos))?;
+
+        match typ {
+            // Assignment to constant variable
+            ScopeEntryType::Constant => Err(Box::new(EvalAltResult::ErrorAssignmentToConstant(
+                name.to_string(),
+                pos,
+            ))),
+            // Normal assignment
+            ScopeEntryType::Normal if op.is_empty() => {
+                if let Some(typ) = annotation {
+                    self.ensure_type(&rhs_val, typ, pos)?;
+                }
+                *lhs_ptr = rhs_val;
+                Ok(Default::default())
+            }
+            // Op-assignment - in order of precedence:
+            ScopeEntryType::Normal => {
+                // 1) Native registered overriding function
+                // 2) Built-in implementation
+                // 3) Map to `var = var op rhs`
+
+                // Qualifiers (none) + function name + number of arguments + argument `TypeId`'s.
+                let arg_types = once(lhs_ptr.type_id()).chain(once(rhs_val.type_id()));
+                let hash_fn = calc_fn_hash(empty(), op, 2, arg_types);
+
+                if let Some(CallableFunction::Method(func)) = self
+                    .global_module
+                    .get_fn(hash_fn)
+                    .or_else(|| self.packages.get_fn(hash_fn))
+                {
+                    self.inc_op_assignment_operations(state, op, lhs_ptr, &rhs_val)
+                        .map_err(|err| err.new_position(op_pos))?;
+                    // Overriding exact implementation
+                    func(self, lib, &mut [lhs_ptr, &mut rhs_val])?;
+                } else if run_builtin_op_assignment(op, lhs_ptr, &rhs_val)?.is_some() {
+                    self.inc_op_assignment_operations(state, op, lhs_ptr, &rhs_val)
+                        .map_err(|err| err.new_position(op_pos))?;
+                } else {
+                    // Not built in, map to `var = var op rhs`
+                    let op = &op[..op.len() - 1]; // extract operator without =
+                    let hash = calc_fn_hash(empty(), op, 2, empty());
+                    // Clone the LHS value
+                    let args = &mut [&mut lhs_ptr.clone(), &mut rhs_val];
+                    // Run function
+                    let (value, _) = self
+                        .exec_fn_call(
+                            state, lib, op, true, hash, args, false, false, None, None, level,
+                        )
+                        .map_err(|err| err.new_position(op_pos))?;
+                    // Set value to LHS
+                    *lhs_ptr = value;
+                }
+                if let Some(typ) = annotation {
+                    self.ensure_type(lhs_ptr, typ, pos)?;
+                }
+                Ok(Default::default())
+            }
+        }
+    }
+
+    /// Count the operations of an op-assignment that is not mapped to a function call, charged as
+    /// a call to the operator (e.g. `+=`, or else `+`) if it has a cost.
+    fn inc_op_assignment_operations(
+        &self,
+        state: &mut State,
+        op: &str,
+        lhs: &Dynamic,
+        rhs: &Dynamic,
+    ) -> Result<(), Box<EvalAltResult>> {
+        if let Some(costs) = &self.costs {
+            let base_op = &op[..op.len() - 1];
+
+            let has_cost = |name: &str| {
+                let arg_types = once(lhs.type_id()).chain(once(rhs.type_id()));
+
+                costs.fn_cost(name).is_some()
+                    || (costs.has_fn_cost_callbacks()
+                        && costs
+                            .fn_cost_callback(calc_fn_hash(empty(), name, 2, arg_types))
+                            .is_some())
+            };
+
+            if let Some(name) = [op, base_op].iter().find(|name| has_cost(name)) {
+                return self.inc_fn_operations(state, name, true, once(lhs).chain(once(rhs)));
+            }
+        }
+
+        Ok(())
+    }
+
     /// Evaluate an expression
     pub(crate) fn eval_expr(
         &self,
@@ -1230,8 +1648,19 @@ impl Engine {
         expr: &Expr,
         level: usize,
     ) -> Result<Dynamic, Box<EvalAltResult>> {
-        self.inc_operations(state)
+        self.inc_node_operations(state, NodeType::of_expr(expr))
             .map_err(|err| err.new_position(expr.position()))?;
+        self.debug_hook(
+            scope,
+            mods,
+            state,
+            lib,
+            this_ptr,
+            NodeType::of_expr(expr),
+            false,
+            expr.position(),
+            level,
+        )?;
 
         let result = match expr {
             Expr::Expr(x) => self.eval_expr(scope, mods, state, lib, this_ptr, x.as_ref(), level),
@@ -1251,7 +1680,7 @@ impl Engine {
             }
             Expr::Variable(_) => {
                 let (val, _, _, _) = search_namespace(scope, mods, state, this_ptr, expr)?;
-                Ok(val.clone())
+                Ok(val.clone_into_dynamic())
             }
             Expr::Property(_) => unreachable!(),
 
@@ -1260,64 +1689,13 @@ impl Engine {
 
             // var op= rhs
             Expr::Assignment(x) if matches!(x.0, Expr::Variable(_)) => {
-                let (lhs_expr, op, rhs_expr, op_pos) = x.as_ref();
-                let mut rhs_val =
-                    self.eval_expr(scope, mods, state, lib, this_ptr, rhs_expr, level)?;
-                let (lhs_ptr, name, typ, pos) =
-                    search_namespace(scope, mods, state, this_ptr, lhs_expr)?;
-                self.inc_operations(state)
-                    .map_err(|err| err.new_position(pos))?;
-
-                match typ {
-                    // Assignment to constant variable
-                    ScopeEntryType::Constant => Err(Box::new(
-                        EvalAltResult::ErrorAssignmentToConstant(name.to_string(), pos),
-                    )),
-                    // Normal assignment
-                    ScopeEntryType::Normal if op.is_empty() => {
-                        *lhs_ptr = rhs_val;
-                        Ok(Default::default())
-                    }
-                    // Op-assignment - in order of precedence:
-                    ScopeEntryType::Normal => {
-                        // 1) Native registered overriding function
-                        // 2) Built-in implementation
-                        // 3) Map to `var = var op rhs`
-
-                        // Qualifiers (none) + function name + number of arguments + argument `TypeId`'s.
-                        let arg_types = once(lhs_ptr.type_id()).chain(once(rhs_val.type_id()));
-                        let hash_fn = calc_fn_hash(empty(), op, 2, arg_types);
-
-                        if let Some(CallableFunction::Method(func)) = self
-                            .global_module
-                            .get_fn(hash_fn)
-                            .or_else(|| self.packages.get_fn(hash_fn))
-                        {
-                            // Overriding exact implementation
-                            func(self, lib, &mut [lhs_ptr, &mut rhs_val])?;
-                        } else if run_builtin_op_assignment(op, lhs_ptr, &rhs_val)?.is_none() {
-                            // Not built in, map to `var = var op rhs`
-                            let op = &op[..op.len() - 1]; // extract operator without =
-                            let hash = calc_fn_hash(empty(), op, 2, empty());
-                            // Clone the LHS value
-                            let args = &mut [&mut lhs_ptr.clone(), &mut rhs_val];
-                            // Run function
-                            let (value, _) = self
-                                .exec_fn_call(
-                                    state, lib, op, true, hash, args, false, false, None, level,
-                                )
-                                .map_err(|err| err.new_position(*op_pos))?;
-                            // Set value to LHS
-                            *lhs_ptr = value;
-                        }
-                        Ok(Default::default())
-                    }
-                }
+                let rhs_val = self.eval_expr(scope, mods, state, lib, this_ptr, &x.2, level)?;
+                self.eval_var_assignment(scope, mods, state, lib, this_ptr, expr, rhs_val, level)
             }
 
             // lhs op= rhs
             Expr::Assignment(x) => {
-                let (lhs_expr, op, rhs_expr, op_pos) = x.as_ref();
+                let (lhs_expr, op, rhs_expr, op_pos, _) = x.as_ref();
                 let mut rhs_val =
                     self.eval_expr(scope, mods, state, lib, this_ptr, rhs_expr, level)?;
 
@@ -1332,7 +1710,9 @@ impl Engine {
                         &mut self.eval_expr(scope, mods, state, lib, this_ptr, lhs_expr, level)?,
                         &mut rhs_val,
                     ];
-                    self.exec_fn_call(state, lib, op, true, hash, args, false, false, None, level)
+                    self.exec_fn_call(
+                        state, lib, op, true, hash, args, false, false, None, None, level,
+                    )
                         .map(|(v, _)| v)
                         .map_err(|err| err.new_position(*op_pos))?
                 });
@@ -1401,17 +1781,17 @@ impl Engine {
 
             // Normal function call
             Expr::FnCall(x) if x.1.is_none() => {
-                let ((name, native, pos), _, hash, args_expr, def_val) = x.as_ref();
+                let ((name, native, pos), _, hash, args_expr, def_val, cache) = x.as_ref();
                 self.make_function_call(
                     scope, mods, state, lib, this_ptr, name, args_expr, *def_val, *hash, *native,
-                    level,
+                    Some(cache), level,
                 )
                 .map_err(|err| err.new_position(*pos))
             }
 
             // Module-qualified function call
             Expr::FnCall(x) if x.1.is_some() => {
-                let ((name, _, pos), modules, hash, args_expr, def_val) = x.as_ref();
+                let ((name, _, pos), modules, hash, args_expr, def_val, _) = x.as_ref();
                 self.make_qualified_function_call(
                     scope, mods, state, lib, this_ptr, modules, name, args_expr, *def_val, *hash,
                     level,
@@ -1477,7 +1857,9 @@ impl Engine {
             _ => unreachable!(),
         };
 
-        self.check_data_size(result)
+        let result = self.check_data_size(state, result);
+
+        self.check_memory(scope, state, result)
             .map_err(|err| err.new_position(expr.position()))
     }
 
@@ -1492,8 +1874,19 @@ impl Engine {
         stmt: &Stmt,
         level: usize,
     ) -> Result<Dynamic, Box<EvalAltResult>> {
-        self.inc_operations(state)
+        self.inc_node_operations(state, NodeType::of_stmt(stmt))
             .map_err(|err| err.new_position(stmt.position()))?;
+        self.debug_hook(
+            scope,
+            mods,
+            state,
+            lib,
+            this_ptr,
+            NodeType::of_stmt(stmt),
+            true,
+            stmt.position(),
+            level,
+        )?;
 
         let result = match stmt {
             // No-op
@@ -1594,12 +1987,13 @@ impl Engine {
                     // Add the loop variable
                     let var_name = unsafe_cast_var_name_to_lifetime(name, &state);
                     scope.push(var_name, ());
+                    state.record_scope_size(scope.len());
                     let index = scope.len() - 1;
                     state.scope_level += 1;
 
                     for loop_var in func(iter_type) {
                         *scope.get_mut(index).0 = loop_var;
-                        self.inc_operations(state)
+                        self.inc_node_operations(state, NodeType::Iteration)
                             .map_err(|err| err.new_position(stmt.position()))?;
 
                         match self.eval_stmt(scope, mods, state, lib, this_ptr, stmt, level) {
@@ -1626,6 +2020,27 @@ impl Engine {
             // Break statement
             Stmt::Break(pos) => Err(Box::new(EvalAltResult::ErrorLoopBreak(true, *pos))),
 
+            // Return a call to a script-defined function from within a function - tail call
+            #[cfg(not(feature = "no_function"))]
+            Stmt::ReturnWithVal(x) if level > 0 && (x.0).0 == ReturnType::Return => match &x.1 {
+                Some(Expr::FnCall(f)) if f.1.is_none() && !(f.0).1 && lib.contains_fn(f.2) => {
+                    let args = f
+                        .3
+                        .iter()
+                        .map(|expr| self.eval_expr(scope, mods, state, lib, this_ptr, expr, level))
+                        .collect::<Result<_, _>>()?;
+
+                    // The calling function makes the call after the current one returns
+                    state.tail_call = Some((f.2, args));
+                    Err(Box::new(EvalAltResult::Return(Default::default(), (x.0).1)))
+                }
+                Some(expr) => Err(Box::new(EvalAltResult::Return(
+                    self.eval_expr(scope, mods, state, lib, this_ptr, expr, level)?,
+                    (x.0).1,
+                ))),
+                None => Err(Box::new(EvalAltResult::Return(Default::default(), (x.0).1))),
+            },
+
             // Return value
             Stmt::ReturnWithVal(x) if x.1.is_some() && (x.0).0 == ReturnType::Return => {
                 Err(Box::new(EvalAltResult::Return(
@@ -1673,7 +2088,7 @@ impl Engine {
 
             // Let statement
             Stmt::Let(x) if x.1.is_some() => {
-                let ((var_name, _), expr) = x.as_ref();
+                let ((var_name, pos), expr, typ, _) = x.as_ref();
                 let val = self.eval_expr(
                     scope,
                     mods,
@@ -1683,24 +2098,39 @@ impl Engine {
                     expr.as_ref().unwrap(),
                     level,
                 )?;
+                if let Some(typ) = typ {
+                    self.ensure_type(&val, typ, *pos)?;
+                }
                 let var_name = unsafe_cast_var_name_to_lifetime(var_name, &state);
                 scope.push_dynamic_value(var_name, ScopeEntryType::Normal, val, false);
+                state.record_scope_size(scope.len());
                 Ok(Default::default())
             }
 
             Stmt::Let(x) => {
-                let ((var_name, _), _) = x.as_ref();
+                let ((var_name, _), _, _, _) = x.as_ref();
                 let var_name = unsafe_cast_var_name_to_lifetime(var_name, &state);
                 scope.push(var_name, ());
+                state.record_scope_size(scope.len());
                 Ok(Default::default())
             }
 
             // Const statement
             Stmt::Const(x) if x.1.is_constant() => {
-                let ((var_name, _), expr) = x.as_ref();
+                let ((var_name, pos), expr, typ, _) = x.as_ref();
                 let val = self.eval_expr(scope, mods, state, lib, this_ptr, &expr, level)?;
+                if let Some(typ) = typ {
+                    self.ensure_type(&val, typ, *pos)?;
+                }
+                // Constants at global level are also visible inside functions
+                if state.scope_level == 0 {
+                    state
+                        .global_constants
+                        .set_var(var_name.clone(), val.clone());
+                }
                 let var_name = unsafe_cast_var_name_to_lifetime(var_name, &state);
                 scope.push_dynamic_value(var_name, ScopeEntryType::Constant, val, true);
+                state.record_scope_size(scope.len());
                 Ok(Default::default())
             }
 
@@ -1710,12 +2140,12 @@ impl Engine {
             // Import statement
             #[cfg(not(feature = "no_module"))]
             Stmt::Import(x) => {
-                let (expr, (name, _pos)) = x.as_ref();
+                let (expr, names, pos) = x.as_ref();
 
                 // Guard against too many modules
                 #[cfg(not(feature = "unchecked"))]
-                if state.modules >= self.limits.max_modules {
-                    return Err(Box::new(EvalAltResult::ErrorTooManyModules(*_pos)));
+                if state.modules >= self.limits(state).max_modules {
+                    return Err(Box::new(EvalAltResult::ErrorTooManyModules(*pos)));
                 }
 
                 if let Some(path) = self
@@ -1724,10 +2154,50 @@ impl Engine {
                 {
                     if let Some(resolver) = &self.module_resolver {
                         let mut module = resolver.resolve(self, &path, expr.position())?;
-                        module.index_all_sub_modules();
-                        mods.push((name.clone().into(), module));
+
+                        match names {
+                            // import expr as name
+                            ImportNames::Alias(name) => {
+                                module.index_all_sub_modules();
+                                mods.push((name.clone().into(), module));
+                            }
+                            // import { id as name, ... } from expr
+                            ImportNames::Members(members) => {
+                                // The module is found but not the member
+                                if let Some(((id, id_pos), _)) = members
+                                    .iter()
+                                    .find(|((id, _), _)| !module.contains_member(id))
+                                {
+                                    return Err(Box::new(EvalAltResult::ErrorVariableNotFound(
+                                        format!("{}::{}", path, id),
+                                        *id_pos,
+                                    )));
+                                }
+
+                                let module = module.select_members(|id| {
+                                    members.iter().find(|((id2, _), _)| id2 == id).map(
+                                        |((id, _), rename)| {
+                                            rename.as_ref().map_or(id, |(name, _)| name).clone()
+                                        },
+                                    )
+                                });
+                                check_imported_names(scope, mods, lib, &module, *pos)?;
+                                mods.push((MARKER_IMPORT.into(), module));
+                            }
+                            // import * from expr
+                            ImportNames::All => {
+                                let module = module.select_members(|id| Some(id.into()));
+                                check_imported_names(scope, mods, lib, &module, *pos)?;
+                                mods.push((MARKER_IMPORT.into(), module));
+                            }
+                        }
 
                         state.modules += 1;
+                        state.invalidate_fn_cache();
+
+                        if let Some(stats) = &mut state.stats {
+                            stats.modules += 1;
+                        }
 
                         Ok(Default::default())
                     } else {
@@ -1744,11 +2214,46 @@ impl Engine {
             // Export statement
             #[cfg(not(feature = "no_module"))]
             Stmt::Export(list) => {
-                for ((id, id_pos), rename) in list.iter() {
-                    // Mark scope variables as public
-                    if let Some(index) = scope.get_index(id).map(|(i, _)| i) {
-                        let alias = rename.as_ref().map(|(n, _)| n).unwrap_or_else(|| id);
+                for ((id, id_pos), modules, rename) in list.0.iter() {
+                    let alias = rename.as_ref().map(|(n, _)| n).unwrap_or_else(|| id);
+
+                    if let Some(modules) = modules {
+                        // Re-export a member of an imported module
+                        let mut module = search_imports(mods, state, modules)?;
+
+                        for (name, pos) in modules.iter().skip(1) {
+                            module = module.get_sub_module(name).ok_or_else(|| {
+                                Box::new(EvalAltResult::ErrorModuleNotFound(name.into(), *pos))
+                            })?;
+                        }
+
+                        let mut exports = module.select_members(|name| {
+                            if name == id {
+                                Some(alias.clone())
+                            } else {
+                                None
+                            }
+                        });
+
+                        if let Some(sub_module) = module.get_sub_module(id) {
+                            exports.set_sub_module(alias.clone(), sub_module.clone());
+                        } else if !module.contains_member(id) {
+                            return Err(Box::new(EvalAltResult::ErrorVariableNotFound(
+                                format!("{}{}", modules, id),
+                                *id_pos,
+                            )));
+                        }
+
+                        // Keep re-exports below all imports so module indices stay valid
+                        mods.insert(0, (MARKER_EXPORT.into(), exports));
+                    } else if let Some(index) = scope.get_index(id).map(|(i, _)| i) {
+                        // Mark scope variables as public
                         scope.set_entry_alias(index, alias.clone());
+                    } else if let Some((_, module)) = mods.iter().rev().find(|(n, _)| n == id) {
+                        // Re-export an imported module as a sub-module
+                        let mut exports = Module::new();
+                        exports.set_sub_module(alias.clone(), module.clone());
+                        mods.insert(0, (MARKER_EXPORT.into(), exports));
                     } else {
                         return Err(Box::new(EvalAltResult::ErrorVariableNotFound(
                             id.into(),
@@ -1760,14 +2265,17 @@ impl Engine {
             }
         };
 
-        self.check_data_size(result)
+        let result = self.check_data_size(state, result);
+
+        self.check_memory(scope, state, result)
             .map_err(|err| err.new_position(stmt.position()))
     }
 
     #[cfg(feature = "unchecked")]
     #[inline(always)]
-    fn check_data_size(
+    pub(crate) fn check_data_size(
         &self,
+        _state: &State,
         result: Result<Dynamic, Box<EvalAltResult>>,
     ) -> Result<Dynamic, Box<EvalAltResult>> {
         return result;
@@ -1776,17 +2284,30 @@ impl Engine {
     /// Check a result to ensure that the data size is within allowable limit.
     /// Position in `EvalAltResult` may be None and should be set afterwards.
     #[cfg(not(feature = "unchecked"))]
-    fn check_data_size(
+    pub(crate) fn check_data_size(
         &self,
+        state: &State,
         result: Result<Dynamic, Box<EvalAltResult>>,
     ) -> Result<Dynamic, Box<EvalAltResult>> {
-        #[cfg(feature = "unchecked")]
-        return result;
+        match &result {
+            Ok(value) => self.check_value_size(state, value).map(|_| result.unwrap()),
+            Err(_) => result,
+        }
+    }
+
+    /// Check a value to ensure that the data size is within allowable limit.
+    /// Position in `EvalAltResult` is `None` and must be set afterwards.
+    #[cfg(not(feature = "unchecked"))]
+    pub(crate) fn check_value_size(
+        &self,
+        state: &State,
+        value: &Dynamic,
+    ) -> Result<(), Box<EvalAltResult>> {
+        let limits = self.limits(state);
 
         // If no data size limits, just return
-        if self.limits.max_string_size + self.limits.max_array_size + self.limits.max_map_size == 0
-        {
-            return result;
+        if limits.max_string_size + limits.max_array_size + limits.max_map_size == 0 {
+            return Ok(());
         }
 
         // Recursively calculate the size of a value (especially `Array` and `Map`)
@@ -1841,62 +2362,335 @@ impl Engine {
             }
         }
 
-        match result {
-            // Simply return all errors
-            Err(_) => return result,
+        match value {
             // String with limit
-            Ok(Dynamic(Union::Str(_))) if self.limits.max_string_size > 0 => (),
+            Dynamic(Union::Str(_)) if limits.max_string_size > 0 => (),
             // Array with limit
             #[cfg(not(feature = "no_index"))]
-            Ok(Dynamic(Union::Array(_))) if self.limits.max_array_size > 0 => (),
+            Dynamic(Union::Array(_)) if limits.max_array_size > 0 => (),
             // Map with limit
             #[cfg(not(feature = "no_object"))]
-            Ok(Dynamic(Union::Map(_))) if self.limits.max_map_size > 0 => (),
+            Dynamic(Union::Map(_)) if limits.max_map_size > 0 => (),
             // Everything else is simply returned
-            Ok(_) => return result,
+            _ => return Ok(()),
         };
 
-        let (arr, map, s) = calc_size(result.as_ref().unwrap());
+        let (arr, map, s) = calc_size(value);
 
-        if s > self.limits.max_string_size {
+        if s > limits.max_string_size {
             Err(Box::new(EvalAltResult::ErrorDataTooLarge(
                 "Length of string".to_string(),
-                self.limits.max_string_size,
+                limits.max_string_size,
                 s,
                 Position::none(),
             )))
-        } else if arr > self.limits.max_array_size {
+        } else if arr > limits.max_array_size {
             Err(Box::new(EvalAltResult::ErrorDataTooLarge(
                 "Size of array".to_string(),
-                self.limits.max_array_size,
+                limits.max_array_size,
                 arr,
                 Position::none(),
             )))
-        } else if map > self.limits.max_map_size {
+        } else if map > limits.max_map_size {
             Err(Box::new(EvalAltResult::ErrorDataTooLarge(
                 "Number of properties in object map".to_string(),
-                self.limits.max_map_size,
+                limits.max_map_size,
                 map,
                 Position::none(),
             )))
+        } else {
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "unchecked")]
+    #[inline(always)]
+    pub(crate) fn check_memory(
+        &self,
+        _scope: &Scope,
+        _state: &mut State,
+        result: Result<Dynamic, Box<EvalAltResult>>,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        result
+    }
+
+    /// Check a result to ensure that the memory held by the values of a script is within allowable limit.
+    /// Position in `EvalAltResult` may be None and should be set afterwards.
+    ///
+    /// The sizes of all results are added up, and all variables in scope are only scanned
+    /// when the sum may have exceeded the limit.  Variables of calling functions are not in scope,
+    /// so the memory they held as of their last scans is counted instead.
+    #[cfg(not(feature = "unchecked"))]
+    pub(crate) fn check_memory(
+        &self,
+        scope: &Scope,
+        state: &mut State,
+        result: Result<Dynamic, Box<EvalAltResult>>,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let max_memory = self.limits(state).max_memory;
+
+        let size = match &result {
+            Ok(value) if max_memory > 0 => self.calc_memory(value, &mut HashSet::new()),
+            _ => return result,
+        };
+
+        state.memory_allocated = state.memory_allocated.saturating_add(size);
+
+        // Results of memoized functions cached are also counted
+        #[cfg(not(feature = "no_function"))]
+        let cached = if self.memo_persistent {
+            self.memo_cache.memory()
+        } else {
+            state.memo_cache.memory()
+        };
+        #[cfg(feature = "no_function")]
+        let cached = 0;
+
+        let outer = state.memory_outer.saturating_add(cached);
+
+        if outer
+            .saturating_add(state.memory)
+            .saturating_add(state.memory_allocated)
+            <= max_memory
+        {
+            return result;
+        }
+
+        // Values allocated may have been dropped since the last scan, so scan the scope again
+        let mut seen = HashSet::new();
+        state.memory = scope
+            .iter()
+            .map(|(_, value)| self.calc_memory(value, &mut seen))
+            .sum();
+        state.memory_allocated = size;
+
+        let memory = outer.saturating_add(state.memory).saturating_add(size);
+
+        if memory > max_memory {
+            Err(Box::new(EvalAltResult::ErrorDataTooLarge(
+                "Memory used by script".to_string(),
+                max_memory,
+                memory,
+                Position::none(),
+            )))
         } else {
             result
         }
     }
 
-    /// Check if the number of operations stay within limit.
+    /// Calculate the approximate number of bytes held by a value, including its elements.
+    /// The value of a shared cell is only counted if its address is not already `seen`.
+    #[cfg(not(feature = "unchecked"))]
+    pub(crate) fn calc_memory(&self, value: &Dynamic, seen: &mut HashSet<usize>) -> usize {
+        let size = match &value.0 {
+            Union::Str(s) => s.len(),
+            #[cfg(not(feature = "no_index"))]
+            Union::Array(arr) => arr.iter().map(|v| self.calc_memory(v, seen)).sum(),
+            #[cfg(not(feature = "no_object"))]
+            Union::Map(map) => map
+                .iter()
+                .map(|(k, v)| {
+                    mem::size_of::<ImmutableString>() + k.len() + self.calc_memory(v, seen)
+                })
+                .sum(),
+            Union::FnPtr(f) => {
+                f.get_fn_name().len()
+                    + f.curry()
+                        .iter()
+                        .map(|v| self.calc_memory(v, seen))
+                        .sum::<usize>()
+            }
+            Union::Variant(v) => match self
+                .type_sizes
+                .as_ref()
+                .and_then(|sizes| sizes.get(&value.type_id()))
+            {
+                Some(calc_size) => calc_size(value),
+                None => mem::size_of_val(&***v),
+            },
+            Union::Shared(_) => value
+                .peek_shared(|addr, value| {
+                    if seen.insert(addr) {
+                        self.calc_memory(value, seen)
+                    } else {
+                        0
+                    }
+                })
+                .unwrap_or(0),
+            _ => 0,
+        };
+
+        mem::size_of::<Dynamic>() + size
+    }
+
+    /// Calculate the approximate number of bytes held by a value, not counting the data held by
+    /// its elements.
+    #[cfg(not(feature = "unchecked"))]
+    pub(crate) fn calc_shallow_memory(value: &Dynamic) -> usize {
+        match &value.0 {
+            Union::Str(s) => s.len(),
+            #[cfg(not(feature = "no_index"))]
+            Union::Array(arr) => arr.len() * mem::size_of::<Dynamic>(),
+            #[cfg(not(feature = "no_object"))]
+            Union::Map(map) => {
+                map.len() * (mem::size_of::<ImmutableString>() + mem::size_of::<Dynamic>())
+            }
+            _ => 0,
+        }
+    }
+
+    /// Get the limits in effect for an evaluation, which are those of the `Engine` unless
+    /// overridden by `EvalOptions`.
+    #[cfg(not(feature = "unchecked"))]
+    #[inline(always)]
+    pub(crate) fn limits<'a>(&'a self, state: &'a State) -> &'a Limits {
+        state.limits.as_ref().unwrap_or(&self.limits)
+    }
+
+    /// Create a new `State` for an evaluation, starting the clock if the execution time is limited.
+    ///
+    /// Constants in the `Scope` are made visible in the `global` namespace only if `uses_global`
+    /// is `true`, i.e. the script may refer to it.
+    pub(crate) fn new_state(&self, scope: &Scope, uses_global: bool) -> State {
+        #[allow(unused_mut)]
+        let mut state = Self::new_global_state(scope, uses_global);
+        self.start_clock(&mut state);
+        state
+    }
+
+    /// Create a new `State` for an evaluation with limits overridden by `EvalOptions`,
+    /// starting the clock if the execution time is limited.
+    pub(crate) fn new_state_with_options(
+        &self,
+        scope: &Scope,
+        uses_global: bool,
+        _options: &EvalOptions,
+    ) -> State {
+        #[allow(unused_mut)]
+        let mut state = Self::new_global_state(scope, uses_global);
+
+        #[cfg(not(feature = "unchecked"))]
+        {
+            state.limits = Some(_options.apply_to(&self.limits));
+        }
+
+        self.start_clock(&mut state);
+        state
+    }
+
+    /// Create a new `State`, making constants in the `Scope` visible in the `global` namespace
+    /// only if `uses_global` is `true`.
+    fn new_global_state(scope: &Scope, uses_global: bool) -> State {
+        if uses_global {
+            State::from_scope(scope)
+        } else {
+            State::new()
+        }
+    }
+
+    /// Set the deadline of an evaluation if the execution time is limited.
+    #[inline(always)]
+    fn start_clock(&self, _state: &mut State) {
+        #[cfg(not(feature = "unchecked"))]
+        #[cfg(not(feature = "no_std"))]
+        {
+            let max_execution_time = self.limits(_state).max_execution_time;
+
+            if max_execution_time > Duration::from_secs(0) {
+                _state.set_deadline(Instant::now() + max_execution_time);
+            }
+        }
+    }
+
+    /// Count one operation, and check if the number of operations stay within limit.
     /// Position in `EvalAltResult` is `None` and must be set afterwards.
+    #[inline(always)]
     pub(crate) fn inc_operations(&self, state: &mut State) -> Result<(), Box<EvalAltResult>> {
-        state.operations += 1;
+        self.add_operations(state, 1)
+    }
+
+    /// Count the operations of evaluating a type of `AST` node, and check if the number of
+    /// operations stay within limit.
+    /// Position in `EvalAltResult` is `None` and must be set afterwards.
+    pub(crate) fn inc_node_operations(
+        &self,
+        state: &mut State,
+        node: NodeType,
+    ) -> Result<(), Box<EvalAltResult>> {
+        let cost = self.costs.as_ref().map_or(1, |costs| costs.node_cost(node));
+        self.add_operations(state, cost)
+    }
+
+    /// Count the operations of calling a function, and check if the number of operations stay
+    /// within limit.
+    /// The extra cost calculated from the arguments (see `Engine::register_fn_cost`) is only
+    /// charged for native functions.
+    /// Position in `EvalAltResult` is `None` and must be set afterwards.
+    pub(crate) fn inc_fn_operations<'a>(
+        &self,
+        state: &mut State,
+        fn_name: &str,
+        native: bool,
+        args: impl Iterator<Item = &'a Dynamic>,
+    ) -> Result<(), Box<EvalAltResult>> {
+        if let Some(stats) = &mut state.stats {
+            stats.record_fn_call(fn_name);
+        }
+
+        let cost = match &self.costs {
+            None => 1,
+            Some(costs) if native && costs.has_fn_cost_callbacks() => {
+                let cost = costs.fn_cost(fn_name).unwrap_or(1);
+                let args: StaticVec<_> = args.collect();
+                let arg_types = args.iter().map(|a| a.type_id());
+                let hash_fn = calc_fn_hash(empty(), fn_name, args.len(), arg_types);
+
+                match costs.fn_cost_callback(hash_fn) {
+                    Some(callback) => cost.saturating_add(callback(args.as_ref())),
+                    None => cost,
+                }
+            }
+            Some(costs) => costs.fn_cost(fn_name).unwrap_or(1),
+        };
+        self.add_operations(state, cost)
+    }
+
+    /// Count a number of operations, and check if the number of operations stay within limit.
+    /// Position in `EvalAltResult` is `None` and must be set afterwards.
+    pub(crate) fn add_operations(
+        &self,
+        state: &mut State,
+        cost: u64,
+    ) -> Result<(), Box<EvalAltResult>> {
+        state.operations = state.operations.saturating_add(cost);
 
         #[cfg(not(feature = "unchecked"))]
         // Guard against too many operations
-        if self.limits.max_operations > 0 && state.operations > self.limits.max_operations {
+        if self.limits(state).max_operations > 0
+            && state.operations > self.limits(state).max_operations
+        {
             return Err(Box::new(EvalAltResult::ErrorTooManyOperations(
                 Position::none(),
             )));
         }
 
+        #[cfg(not(feature = "unchecked"))]
+        #[cfg(not(feature = "no_std"))]
+        // Guard against running for too long
+        if let Some(deadline) = state.deadline {
+            if Instant::now() >= deadline {
+                return Err(Box::new(EvalAltResult::ErrorTimeout(Position::none())));
+            }
+        }
+
+        // Abort the script if interrupted
+        if let Some(reason) = self.interrupt.take() {
+            return Err(Box::new(EvalAltResult::ErrorInterrupted(
+                reason,
+                Position::none(),
+            )));
+        }
+
         // Report progress - only in steps
         if let Some(progress) = &self.progress {
             if !progress(&state.operations) {
@@ -1915,4 +2709,42 @@ impl Engine {
             .and_then(|t| t.get(name).map(String::as_str))
             .unwrap_or(map_std_type_name(name))
     }
+
+    /// Does a type name match a type annotation?
+    pub(crate) fn matches_type(&self, name: &str, typ: &str) -> bool {
+        let typ = map_type_annotation(typ);
+        typ == "Dynamic" || name == typ || self.map_type_name(name) == typ
+    }
+
+    /// Make sure that a value matches a type annotation.
+    pub(crate) fn ensure_type(
+        &self,
+        value: &Dynamic,
+        typ: &str,
+        pos: Position,
+    ) -> Result<(), Box<EvalAltResult>> {
+        if self.matches_type(value.type_name(), typ) {
+            Ok(())
+        } else {
+            Err(Box::new(EvalAltResult::ErrorMismatchDataType(
+                typ.into(),
+                self.map_type_name(value.type_name()).into(),
+                pos,
+            )))
+        }
+    }
+}
+
+/// Map a type annotation into the name of the actual type.
+///
+/// `int` and `float` are aliases to the system integer and floating-point types, and `unit` to `()`.
+/// `Dynamic` matches values of any type.
+pub(crate) fn map_type_annotation(typ: &str) -> &str {
+    match typ {
+        "unit" => "()",
+        "int" => type_name::<INT>(),
+        #[cfg(not(feature = "no_float"))]
+        "float" => type_name::<FLOAT>(),
+        _ => typ,
+    }
 }
@@ -1,7 +1,7 @@
 //! Module containing error definitions for the parsing process.
 
 use crate::result::EvalAltResult;
-use crate::token::Position;
+use crate::token::{Position, Span};
 
 use crate::stdlib::{
     boxed::Box,
@@ -61,7 +61,7 @@ impl fmt::Display for LexError {
 impl LexError {
     /// Convert a `LexError` into a `ParseError`.
     pub fn into_err(&self, pos: Position) -> ParseError {
-        ParseError(Box::new(self.into()), pos)
+        ParseError(Box::new(self.into()), pos, None)
     }
 }
 
@@ -103,6 +103,14 @@ pub enum ParseErrorType {
     PropertyExpected,
     /// Missing a variable name after the `let`, `const` or `for` keywords.
     VariableExpected,
+    /// Missing a type name in a type annotation.
+    TypeExpected,
+    /// A value does not match its type annotation.
+    /// Wrapped values are the type requested and the type of the actual value.
+    MismatchedType(String, String),
+    /// A call to a registered function with argument types that do not match any of its signatures.
+    /// Wrapped value is the signature of the call.
+    MismatchedFnArgs(String),
     /// An identifier is a reserved keyword.
     Reserved(String),
     /// Missing an expression. Wrapped value is the expression type.
@@ -131,6 +139,10 @@ pub enum ParseErrorType {
     ///
     /// Never appears under the `no_module` feature.
     DuplicatedExport(String),
+    /// A name imported into the local namespace collides with another name. Wrapped value is the name.
+    ///
+    /// Never appears under the `no_module` feature.
+    DuplicatedImport(String),
     /// Export statement not at global level.
     ///
     /// Never appears under the `no_module` feature.
@@ -154,7 +166,7 @@ pub enum ParseErrorType {
 impl ParseErrorType {
     /// Make a `ParseError` using the current type and position.
     pub(crate) fn into_err(self, pos: Position) -> ParseError {
-        ParseError(Box::new(self), pos)
+        ParseError(Box::new(self), pos, None)
     }
 
     pub(crate) fn desc(&self) -> &str {
@@ -170,6 +182,9 @@ impl ParseErrorType {
             Self::ForbiddenConstantExpr(_) => "Expecting a constant",
             Self::PropertyExpected => "Expecting name of a property",
             Self::VariableExpected => "Expecting name of a variable",
+            Self::TypeExpected => "Expecting name of a type",
+            Self::MismatchedType(_, _) => "Data type is incorrect",
+            Self::MismatchedFnArgs(_) => "No registered function matches the argument types",
             Self::Reserved(_) => "Invalid use of reserved keyword",
             Self::ExprExpected(_) => "Expecting an expression",
             Self::FnMissingName => "Expecting name in function declaration",
@@ -178,6 +193,7 @@ impl ParseErrorType {
             Self::FnMissingBody(_) => "Expecting body statement block for function declaration",
             Self::WrongFnDefinition => "Function definitions must be at global level and cannot be inside a block or another function",
             Self::DuplicatedExport(_) => "Duplicated variable/function in export statement",
+            Self::DuplicatedImport(_) => "Imported variable/function collides with an existing name",
             Self::WrongExport => "Export statement can only appear at global level",
             Self::AssignmentToCopy => "Only a copy of the value is change with this assignment",
             Self::AssignmentToConstant(_) => "Cannot assign to a constant value",
@@ -227,6 +243,11 @@ impl fmt::Display for ParseErrorType {
                 "Duplicated variable/function '{}' in export statement",
                 s
             ),
+            Self::DuplicatedImport(s) => write!(
+                f,
+                "Imported variable/function '{}' collides with an existing name",
+                s
+            ),
 
             Self::MissingToken(token, s) => write!(f, "Expecting '{}' {}", token, s),
 
@@ -236,6 +257,8 @@ impl fmt::Display for ParseErrorType {
                 write!(f, "{} exceeds the maximum limit ({})", typ, max)
             }
             Self::Reserved(s) => write!(f, "'{}' is a reserved keyword", s),
+            Self::MismatchedType(r, a) => write!(f, "{}: {} (expecting {})", self.desc(), a, r),
+            Self::MismatchedFnArgs(s) => write!(f, "{}: '{}'", self.desc(), s),
             _ => f.write_str(self.desc()),
         }
     }
@@ -253,8 +276,40 @@ impl From<&LexError> for ParseErrorType {
 }
 
 /// Error when parsing a script.
+///
+/// Wrapped values are the type of error and its position.  The name of the source of the script
+/// and the span of the code in error are available via `source` and `span`.
 #[derive(Debug, Eq, PartialEq, Clone, Hash)]
-pub struct ParseError(pub Box<ParseErrorType>, pub Position);
+pub struct ParseError(
+    pub Box<ParseErrorType>,
+    pub Position,
+    /// Name of the source (e.g. file name) of the script, if any, and the span of the code in error,
+    /// once known.
+    pub Option<Box<(Option<String>, Option<Span>)>>,
+);
+
+impl ParseError {
+    /// Get the name of the source (e.g. file name) of the script in error, if any.
+    pub fn source(&self) -> Option<&str> {
+        self.2.as_ref().and_then(|x| x.0.as_deref())
+    }
+
+    /// Get the span of the code in error, if known.
+    ///
+    /// This is the span of the statement or expression in error if it is parsed completely,
+    /// otherwise the span of the token at the position of the error.
+    pub fn span(&self) -> Option<Span> {
+        self.2.as_ref().and_then(|x| x.1)
+    }
+
+    /// Attach the name of the source of the script, and the span of the code in error, if not already attached.
+    pub(crate) fn with_location(mut self, source: Option<String>, span: Option<Span>) -> Self {
+        if self.2.is_none() {
+            self.2 = Some(Box::new((source, span)));
+        }
+        self
+    }
+}
 
 impl Error for ParseError {}
 
@@ -263,8 +318,10 @@ impl fmt::Display for ParseError {
         fmt::Display::fmt(&self.0, f)?;
 
         // Do not write any position if None
-        if !self.1.is_none() {
-            write!(f, " ({})", self.1)?;
+        match (self.source(), self.1.is_none()) {
+            (_, true) => (),
+            (Some(source), false) => write!(f, " ({} in '{}')", self.1, source)?,
+            (None, false) => write!(f, " ({})", self.1)?,
         }
 
         Ok(())
@@ -279,6 +336,13 @@ impl From<ParseErrorType> for Box<EvalAltResult> {
 
 impl From<ParseError> for Box<EvalAltResult> {
     fn from(err: ParseError) -> Self {
-        Box::new(EvalAltResult::ErrorParsing(*err.0, err.1))
+        let (source, span) = err.2.map_or((None, None), |x| *x);
+        let err = Box::new(EvalAltResult::ErrorParsing(*err.0, err.1));
+
+        // Errors in scripts with a source are wrapped with the name of the source
+        match source {
+            Some(source) => Box::new(EvalAltResult::ErrorInScript(source, span, err)),
+            None => err,
+        }
     }
 }
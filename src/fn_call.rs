@@ -2,13 +2,14 @@
 
 use crate::any::Dynamic;
 use crate::calc_fn_hash;
+use crate::cost::NodeType;
 use crate::engine::{
-    search_imports, search_namespace, search_scope_only, Engine, Imports, State, KEYWORD_DEBUG,
-    KEYWORD_EVAL, KEYWORD_FN_PTR, KEYWORD_FN_PTR_CALL, KEYWORD_FN_PTR_CURRY, KEYWORD_PRINT,
-    KEYWORD_TYPE_OF, KEYWORD_SHARED
+    search_imports, search_namespace, search_scope_only, Engine, Imports, State, Target,
+    KEYWORD_DEBUG, KEYWORD_EVAL, KEYWORD_FN_PTR, KEYWORD_FN_PTR_CALL, KEYWORD_FN_PTR_CURRY,
+    KEYWORD_PRINT, KEYWORD_TYPE_OF, KEYWORD_SHARED
 };
 use crate::error::ParseErrorType;
-use crate::fn_native::{FnCallArgs, FnPtr};
+use crate::fn_native::{FnCallArgs, FnCallCache, FnPtr};
 use crate::module::{Module, ModuleRef};
 use crate::optimize::OptimizationLevel;
 use crate::parser::{Expr, ImmutableString, AST, INT};
@@ -20,8 +21,9 @@ use crate::stdlib::ops::Deref;
 
 #[cfg(not(feature = "no_function"))]
 use crate::{
-    parser::ScriptFnDef, r#unsafe::unsafe_cast_var_name_to_lifetime,
-    scope::EntryType as ScopeEntryType,
+    fn_native::Shared, memo::MemoKey, parser::ScriptFnDef,
+    r#unsafe::unsafe_cast_var_name_to_lifetime, scope::EntryType as ScopeEntryType, stdlib::ptr,
+    token::Token,
 };
 
 #[cfg(not(feature = "no_float"))]
@@ -30,8 +32,19 @@ use crate::parser::FLOAT;
 #[cfg(not(feature = "no_index"))]
 use crate::engine::{FN_IDX_GET, FN_IDX_SET};
 
+#[cfg(not(feature = "no_module"))]
+use crate::engine::MARKER_IMPORT;
+
+#[cfg(not(feature = "no_function"))]
+#[cfg(not(feature = "no_module"))]
+use crate::fn_native::CallableFunction;
+
 #[cfg(not(feature = "no_object"))]
-use crate::engine::{Map, Target, FN_GET, FN_SET};
+use crate::engine::{Map, FN_GET, FN_SET};
+
+#[cfg(not(feature = "no_function"))]
+#[cfg(not(feature = "unchecked"))]
+use crate::stdlib::collections::HashSet;
 
 use crate::stdlib::{
     any::{type_name, TypeId},
@@ -66,6 +79,22 @@ fn extract_prop_from_setter(_fn_name: &str) -> Option<&str> {
     None
 }
 
+/// Is there a function with a particular name and number of parameters imported into the local namespace?
+#[cfg(not(feature = "no_module"))]
+pub(crate) fn has_imported_fn(mods: &Imports, fn_name: &str, num_params: usize) -> bool {
+    mods.iter()
+        .filter(|(n, _)| n == MARKER_IMPORT)
+        .flat_map(|(_, m)| m.iter_fn())
+        .any(|(name, _, params, func, _)| {
+            name == fn_name
+                && match func {
+                    #[cfg(not(feature = "no_function"))]
+                    CallableFunction::Script(f) => f.params.len() == num_params,
+                    _ => params.len() == num_params,
+                }
+        })
+}
+
 /// This function replaces the first argument of a method call with a clone copy.
 /// This is to prevent a pure function unintentionally consuming the first argument.
 fn normalize_first_arg<'a>(
@@ -122,21 +151,20 @@ impl Engine {
         state: &mut State,
         lib: &Module,
         fn_name: &str,
-        (hash_fn, hash_script): (u64, u64),
+        hash_script: u64,
         args: &mut FnCallArgs,
         is_ref: bool,
         _is_method: bool,
         def_val: Option<bool>,
+        cache: Option<&FnCallCache>,
         _level: usize,
     ) -> Result<(Dynamic, bool), Box<EvalAltResult>> {
-        self.inc_operations(state)?;
-
         let native_only = hash_script == 0;
 
         // Check for stack overflow
         #[cfg(not(feature = "no_function"))]
         #[cfg(not(feature = "unchecked"))]
-        if _level > self.limits.max_call_stack_depth {
+        if _level > self.limits(state).max_call_stack_depth {
             return Err(Box::new(
                 EvalAltResult::ErrorStackOverflow(Position::none()),
             ));
@@ -145,22 +173,45 @@ impl Engine {
         let mut this_copy: Dynamic = Default::default();
         let mut old_this_ptr: Option<&mut Dynamic> = None;
 
-        // Search for the function
-        // First search in script-defined functions (can override built-in)
-        // Then search registered native functions (can override packages)
-        // Then search packages
-        // NOTE: We skip script functions for global_module and packages, and native functions for lib
-        let func = if !native_only {
-            lib.get_fn(hash_script) //.or_else(|| lib.get_fn(hash_fn))
-        } else {
-            None
-        }
-        //.or_else(|| self.global_module.get_fn(hash_script))
-        .or_else(|| self.global_module.get_fn(hash_fn))
-        //.or_else(|| self.packages.get_fn(hash_script))
-        .or_else(|| self.packages.get_fn(hash_fn));
+        // Check the call-site cache for the function resolved previously
+        let cached = cache.and_then(|c| c.get(state.fn_cache_epoch, hash_script, args));
+
+        let func = cached.unwrap_or_else(|| {
+            // Qualifiers (none) + function name + number of arguments + argument `TypeId`'s.
+            let arg_types = args.iter().map(|a| a.type_id());
+            let hash_fn = calc_fn_hash(empty(), fn_name, args.len(), arg_types);
+
+            // Search for the function
+            // First search in script-defined functions (can override built-in)
+            // Then search registered native functions (can override packages)
+            // Then search packages
+            // NOTE: We skip script functions for global_module and packages, and native functions for lib
+            let func = if !native_only {
+                lib.get_fn(hash_script) //.or_else(|| lib.get_fn(hash_fn))
+            } else {
+                None
+            }
+            //.or_else(|| self.global_module.get_fn(hash_script))
+            .or_else(|| self.global_module.get_fn(hash_fn))
+            //.or_else(|| self.packages.get_fn(hash_script))
+            .or_else(|| self.packages.get_fn(hash_fn))
+            .cloned();
+
+            if let Some(cache) = cache {
+                cache.set(state.fn_cache_epoch, hash_script, args, func.clone());
+            }
+
+            func
+        });
+
+        #[cfg(not(feature = "no_function"))]
+        let native = !matches!(func, Some(ref f) if f.is_script());
+        #[cfg(feature = "no_function")]
+        let native = true;
+
+        self.inc_fn_operations(state, fn_name, native, args.iter().map(|a| &**a))?;
 
-        if let Some(func) = func {
+        if let Some(ref func) = func {
             #[cfg(not(feature = "no_function"))]
             let need_normalize = is_ref && (func.is_pure() || (func.is_script() && !_is_method));
             #[cfg(feature = "no_function")]
@@ -192,6 +243,18 @@ impl Engine {
                         false,
                     ))
                 } else {
+                    // Memoized function - use the cached result, if any
+                    let memo_key = match &self.memoized_fns {
+                        Some(fns) if fns.contains(fn_name) => MemoKey::new(fn_def, args),
+                        _ => None,
+                    };
+
+                    if let Some(value) = memo_key.as_ref().and_then(|key| self.get_memo(state, key)) {
+                        // Restore the original reference
+                        restore_first_arg(old_this_ptr, args);
+                        return Ok((value, false));
+                    }
+
                     let result = self.call_script_fn(
                         _scope, _mods, state, lib, &mut None, fn_name, fn_def, args, _level,
                     )?;
@@ -199,13 +262,46 @@ impl Engine {
                     // Restore the original reference
                     restore_first_arg(old_this_ptr, args);
 
+                    let result = match memo_key {
+                        // Shared values cannot be cached because they would be shared by all callers
+                        Some(key) if !result.is_shared() => {
+                            // Only results within data size limits are cached
+                            let result = self.check_data_size(state, Ok(result))?;
+                            self.set_memo(state, key, func.get_shared_fn_def(), result.clone());
+                            result
+                        }
+                        _ => result,
+                    };
+
                     Ok((result, false))
                 };
             }
 
+            // Growth of the first argument in place (e.g. via `push` or `pad`) is not seen
+            // in the result, so keep track of its size
+            #[cfg(not(feature = "unchecked"))]
+            let size = if is_ref && self.limits(state).max_memory > 0 {
+                Some(Self::calc_shallow_memory(args[0]))
+            } else {
+                None
+            };
+
             // Run external function
             let result = func.get_native_fn()(self, lib, args)?;
 
+            #[cfg(not(feature = "unchecked"))]
+            if let Some(size) = size {
+                let growth = Self::calc_shallow_memory(args[0]).saturating_sub(size);
+                state.memory_allocated = state.memory_allocated.saturating_add(growth);
+            }
+
+            // Native functions only check the limits of the `Engine`, so check the first argument
+            // again if they are overridden for this evaluation
+            #[cfg(not(feature = "unchecked"))]
+            if is_ref && state.limits.is_some() {
+                self.check_value_size(state, args[0])?;
+            }
+
             // Restore the original reference
             restore_first_arg(old_this_ptr, args);
 
@@ -331,53 +427,299 @@ impl Engine {
         args: &mut FnCallArgs,
         level: usize,
     ) -> Result<Dynamic, Box<EvalAltResult>> {
+        // Check argument types against annotations
+        self.check_param_types(fn_def, args.iter().map(|v| &**v))?;
+
+        // Actually consume the arguments instead of cloning them
+        let mut arg_values: StaticVec<_> = args.iter_mut().map(|v| mem::take(*v)).collect();
+
         let orig_scope_level = state.scope_level;
         state.scope_level += 1;
 
         let prev_scope_len = scope.len();
         let prev_mods_len = mods.len();
 
+        // Variables of the caller are not in the scope of the function,
+        // so keep the memory they held as of the last scan
+        #[cfg(not(feature = "unchecked"))]
+        let orig_memory = (
+            mem::take(&mut state.memory),
+            mem::take(&mut state.memory_allocated),
+        );
+        #[cfg(not(feature = "unchecked"))]
+        {
+            state.memory_outer += orig_memory.0;
+        }
+
+        // Function called by tail call, if any, which errors are raised in
+        let mut tail_fn: Option<&ScriptFnDef> = None;
+        let orig_fn_def = fn_def;
+        let mut fn_def = fn_def;
+        let mut this_ptr = this_ptr;
+        let mut no_this_ptr = None;
+
+        // Functions with return type annotations that the result must be checked against.
+        // Each function only needs to be checked once, so memory does not grow with tail recursion.
+        let mut return_types: StaticVec<(Option<&ScriptFnDef>, &ScriptFnDef)> = Default::default();
+        // Calls to memoized functions made as tail calls, all of which return the same result
+        let mut memo_keys: StaticVec<(MemoKey, Shared<ScriptFnDef>)> = Default::default();
+
+        let result = loop {
+            if fn_def.return_type.is_some()
+                && return_types.iter().all(|(_, f)| !ptr::eq(*f, fn_def))
+            {
+                return_types.push((tail_fn, fn_def));
+            }
+
+            let result = self
+                .call_script_fn_body(
+                    scope,
+                    mods,
+                    state,
+                    lib,
+                    this_ptr,
+                    fn_def,
+                    arg_values,
+                    level,
+                )
+                .or_else(|err| match *err {
+                    // Convert return statement to return value
+                    EvalAltResult::Return(x, _) => Ok(x),
+                    _ => Err(err),
+                });
+
+            // Remove all local variables
+            scope.rewind(prev_scope_len);
+            mods.truncate(prev_mods_len);
+
+            let (hash, mut values) = match state.tail_call.take() {
+                Some(call) if result.is_ok() => call,
+                _ => break result.map_err(|err| (tail_fn, err)),
+            };
+
+            // Tail call - reuse the current frame for the function called
+            let target = lib.get_fn(hash).unwrap();
+            let target_def = target.get_fn_def();
+
+            // Errors are raised in the calling function
+            if let Err(err) = self
+                .inc_fn_operations(state, &target_def.name, false, values.iter())
+                .and_then(|_| self.check_param_types(target_def, values.iter()))
+            {
+                break Err((tail_fn, err));
+            }
+
+            // Memoized function - use the cached result, if any
+            let memo_key = match &self.memoized_fns {
+                Some(fns) if fns.contains(target_def.name.as_str()) => MemoKey::new(
+                    target_def,
+                    values.iter_mut().collect::<StaticVec<_>>().as_ref(),
+                ),
+                _ => None,
+            };
+
+            if let Some(key) = memo_key {
+                if let Some(value) = self.get_memo(state, &key) {
+                    break Ok(value);
+                }
+                if memo_keys.len() < self.max_memo_entries {
+                    memo_keys.push((key, target.get_shared_fn_def()));
+                }
+            }
+
+            fn_def = target_def;
+            tail_fn = Some(fn_def);
+            arg_values = values;
+            // Not a method call
+            this_ptr = &mut no_this_ptr;
+        };
+
+        state.scope_level = orig_scope_level;
+
+        #[cfg(not(feature = "unchecked"))]
+        {
+            state.memory_outer -= orig_memory.0;
+            state.memory = orig_memory.0;
+            state.memory_allocated = orig_memory.1;
+        }
+
+        // Check return value against annotations, starting from the last function called
+        let result = result.and_then(|value| {
+            while !return_types.is_empty() {
+                let (tail_fn, f) = return_types.pop();
+
+                self.ensure_type(&value, f.return_type.as_ref().unwrap(), f.pos)
+                    .map_err(|err| (tail_fn, err))?;
+            }
+
+            Ok(value)
+        });
+
+        // Errors are raised in the function that failed, called from the function originally called;
+        // the frames of functions in between are reused by tail calls and therefore not kept
+        let value = result.map_err(|(tail_fn, err)| {
+            let err = match tail_fn {
+                Some(f) => Box::new(EvalAltResult::ErrorInFunctionCall(
+                    f.name.to_string(),
+                    None,
+                    f.source.as_ref().map(|s| s.to_string()),
+                    err,
+                    Position::none(),
+                )),
+                None => err,
+            };
+
+            Box::new(EvalAltResult::ErrorInFunctionCall(
+                fn_name.to_string(),
+                None,
+                orig_fn_def.source.as_ref().map(|s| s.to_string()),
+                err,
+                Position::none(),
+            ))
+        })?;
+
+        // Shared values cannot be cached because they would be shared by all callers
+        if !memo_keys.is_empty() && !value.is_shared() {
+            let value = self.check_data_size(state, Ok(value.clone()))?;
+
+            while !memo_keys.is_empty() {
+                let (key, fn_def) = memo_keys.pop();
+                self.set_memo(state, key, fn_def, value.clone());
+            }
+        }
+
+        Ok(value)
+    }
+
+    /// Check the arguments to a script-defined function against the type annotations of its parameters.
+    ///
+    /// A mismatch is an error inside the call to the function, raised at the position of the call,
+    /// so the position of the `ErrorInFunctionCall` returned is `None` and must be set afterwards.
+    #[cfg(not(feature = "no_function"))]
+    fn check_param_types<'a>(
+        &self,
+        fn_def: &ScriptFnDef,
+        args: impl Iterator<Item = &'a Dynamic>,
+    ) -> Result<(), Box<EvalAltResult>> {
+        fn_def
+            .param_types
+            .iter()
+            .zip(args)
+            .filter_map(|(typ, value)| typ.as_ref().map(|typ| (typ, value)))
+            .try_for_each(|(typ, value)| self.ensure_type(value, typ, Position::none()))
+            .map_err(|err| {
+                Box::new(EvalAltResult::ErrorInFunctionCall(
+                    fn_def.name.to_string(),
+                    None,
+                    fn_def.source.as_ref().map(|s| s.to_string()),
+                    err,
+                    Position::none(),
+                ))
+            })
+    }
+
+    /// Bind arguments to the parameters of a script-defined function and run its body.
+    #[cfg(not(feature = "no_function"))]
+    #[allow(clippy::too_many_arguments)]
+    fn call_script_fn_body(
+        &self,
+        scope: &mut Scope,
+        mods: &mut Imports,
+        state: &mut State,
+        lib: &Module,
+        this_ptr: &mut Option<&mut Dynamic>,
+        fn_def: &ScriptFnDef,
+        args: StaticVec<Dynamic>,
+        level: usize,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        // Record the call on the call stack of the debugger, if any
+        if let Some(debug) = &mut state.debug {
+            debug.push_call(&fn_def.name, args.iter().cloned().collect());
+        }
+
         // Put arguments into scope as variables
-        // Actually consume the arguments instead of cloning them
         scope.extend(
             fn_def
                 .params
                 .iter()
-                .zip(args.iter_mut().map(|v| mem::take(*v)))
+                .zip(args.into_iter())
                 .map(|(name, value)| {
                     let var_name = unsafe_cast_var_name_to_lifetime(name.as_str(), state);
                     (var_name, ScopeEntryType::Normal, value)
                 }),
         );
 
+        if let Some(stats) = &mut state.stats {
+            stats.record_call_depth(level + 1);
+            stats.record_scope_size(scope.len());
+        }
+
+        // Run the bytecode of the function body, if any
+        let program = state.program.clone();
+        let code = program.as_ref().and_then(|program| program.get_fn(fn_def));
+
         // Evaluate the function at one higher level of call depth
-        let result = self
-            .eval_stmt(scope, mods, state, lib, this_ptr, &fn_def.body, level + 1)
-            .or_else(|err| match *err {
-                // Convert return statement to return value
-                EvalAltResult::Return(x, _) => Ok(x),
-                EvalAltResult::ErrorInFunctionCall(name, err, _) => {
-                    Err(Box::new(EvalAltResult::ErrorInFunctionCall(
-                        format!("{} > {}", fn_name, name),
-                        err,
-                        Position::none(),
-                    )))
-                }
-                _ => Err(Box::new(EvalAltResult::ErrorInFunctionCall(
-                    fn_name.to_string(),
-                    err,
-                    Position::none(),
-                ))),
-            });
+        let result = match code {
+            Some(code) => self.run_code(scope, mods, state, lib, this_ptr, code, level + 1),
+            None => self.eval_stmt(scope, mods, state, lib, this_ptr, &fn_def.body, level + 1),
+        };
 
-        // Remove all local variables
-        scope.rewind(prev_scope_len);
-        mods.truncate(prev_mods_len);
-        state.scope_level = orig_scope_level;
+        if let Some(debug) = &mut state.debug {
+            debug.pop_call();
+        }
 
         result
     }
 
+    /// Get the cached result of a call to a memoized function, if any.
+    #[cfg(not(feature = "no_function"))]
+    fn get_memo(&self, state: &State, key: &MemoKey) -> Option<Dynamic> {
+        if self.memo_persistent {
+            self.memo_cache.get(key)
+        } else {
+            state.memo_cache.get(key)
+        }
+    }
+
+    /// Cache the result of a call to a memoized function.
+    #[cfg(not(feature = "no_function"))]
+    fn set_memo(
+        &self,
+        state: &mut State,
+        key: MemoKey,
+        fn_def: Shared<ScriptFnDef>,
+        value: Dynamic,
+    ) {
+        // Cached results count toward the memory limit, so only the memory not used by
+        // the script is available to the cache
+        #[cfg(not(feature = "unchecked"))]
+        let (size, max_memory) = match self.limits(state).max_memory {
+            0 => (0, usize::MAX),
+            max_memory => (
+                self.calc_memory(&value, &mut HashSet::new()),
+                max_memory.saturating_sub(
+                    state
+                        .memory_outer
+                        .saturating_add(state.memory)
+                        .saturating_add(state.memory_allocated),
+                ),
+            ),
+        };
+        #[cfg(feature = "unchecked")]
+        let (size, max_memory) = (0, usize::MAX);
+
+        let max_entries = self.max_memo_entries;
+
+        if self.memo_persistent {
+            self.memo_cache
+                .insert(key, fn_def, value, size, max_entries, max_memory);
+        } else {
+            state
+                .memo_cache
+                .insert(key, fn_def, value, size, max_entries, max_memory);
+        }
+    }
+
     // Has a system function an override?
     fn has_override(&self, lib: &Module, hash_fn: u64, hash_script: u64) -> bool {
         // NOTE: We skip script functions for global_module and packages, and native functions for lib
@@ -412,16 +754,21 @@ impl Engine {
         is_ref: bool,
         is_method: bool,
         def_val: Option<bool>,
+        cache: Option<&FnCallCache>,
         level: usize,
     ) -> Result<(Dynamic, bool), Box<EvalAltResult>> {
+        let hash_script = if native_only { 0 } else { hash_script };
+
         // Qualifiers (none) + function name + number of arguments + argument `TypeId`'s.
-        let arg_types = args.iter().map(|a| a.type_id());
-        let hash_fn = calc_fn_hash(empty(), fn_name, args.len(), arg_types);
-        let hashes = (hash_fn, if native_only { 0 } else { hash_script });
+        let hash_fn = |args: &FnCallArgs| {
+            calc_fn_hash(empty(), fn_name, args.len(), args.iter().map(|a| a.type_id()))
+        };
 
         match fn_name {
             // type_of
-            KEYWORD_TYPE_OF if args.len() == 1 && !self.has_override(lib, hashes.0, hashes.1) => {
+            KEYWORD_TYPE_OF
+                if args.len() == 1 && !self.has_override(lib, hash_fn(args), hash_script) =>
+            {
                 Ok((
                     self.map_type_name(args[0].type_name()).to_string().into(),
                     false,
@@ -429,7 +776,9 @@ impl Engine {
             }
 
             // Fn
-            KEYWORD_FN_PTR if args.len() == 1 && !self.has_override(lib, hashes.0, hashes.1) => {
+            KEYWORD_FN_PTR
+                if args.len() == 1 && !self.has_override(lib, hash_fn(args), hash_script) =>
+            {
                 Err(Box::new(EvalAltResult::ErrorRuntime(
                     "'Fn' should not be called in method style. Try Fn(...);".into(),
                     Position::none(),
@@ -437,7 +786,9 @@ impl Engine {
             }
 
             // eval - reaching this point it must be a method-style call
-            KEYWORD_EVAL if args.len() == 1 && !self.has_override(lib, hashes.0, hashes.1) => {
+            KEYWORD_EVAL
+                if args.len() == 1 && !self.has_override(lib, hash_fn(args), hash_script) =>
+            {
                 Err(Box::new(EvalAltResult::ErrorRuntime(
                     "'eval' should not be called in method style. Try eval(...);".into(),
                     Position::none(),
@@ -449,8 +800,8 @@ impl Engine {
                 let mut scope = Scope::new();
                 let mut mods = Imports::new();
                 self.call_fn_raw(
-                    &mut scope, &mut mods, state, lib, fn_name, hashes, args, is_ref, is_method,
-                    def_val, level,
+                    &mut scope, &mut mods, state, lib, fn_name, hash_script, args, is_ref,
+                    is_method, def_val, cache, level,
                 )
             }
         }
@@ -491,9 +842,35 @@ impl Engine {
         let ast = AST::new(statements, lib.clone());
 
         // Evaluate the AST
-        let (result, operations) = self.eval_ast_with_scope_raw(scope, mods, &ast)?;
+        let mut eval_state = State::from_scope(scope);
+
+        // The evaluated script shares the deadline of the caller
+        #[cfg(not(feature = "unchecked"))]
+        #[cfg(not(feature = "no_std"))]
+        {
+            eval_state.deadline = state.deadline;
+        }
+
+        // The evaluated script runs in the same scope, but not in the scope of any calling function,
+        // and with the same limits as the caller
+        #[cfg(not(feature = "unchecked"))]
+        {
+            eval_state.memory_outer = state.memory_outer;
+            eval_state.limits = state.limits.clone();
+        }
+
+        // Statistics of the evaluated script are collected together with those of the caller,
+        // and the debugger steps through it as part of the caller
+        eval_state.stats = state.stats.take();
+        eval_state.debug = state.debug.take();
+
+        let result = self.eval_ast_with_scope_raw(scope, mods, &mut eval_state, &ast);
 
-        state.operations += operations;
+        state.stats = eval_state.stats.take();
+        state.debug = eval_state.debug.take();
+        let result = result?;
+
+        state.operations += eval_state.operations;
         self.inc_operations(state)?;
 
         return Ok(result);
@@ -501,8 +878,10 @@ impl Engine {
 
     /// Call a dot method.
     #[cfg(not(feature = "no_object"))]
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn make_method_call(
         &self,
+        _mods: &Imports,
         state: &mut State,
         lib: &Module,
         target: &mut Target,
@@ -510,7 +889,7 @@ impl Engine {
         idx_val: Dynamic,
         level: usize,
     ) -> Result<(Dynamic, bool), Box<EvalAltResult>> {
-        let ((name, native, pos), _, hash, _, def_val) = match expr {
+        let ((name, native, pos), _, hash, _, def_val, cache) = match expr {
             Expr::FnCall(x) => x.as_ref(),
             _ => unreachable!(),
         };
@@ -538,9 +917,26 @@ impl Engine {
                 .collect::<StaticVec<_>>();
             let args = arg_values.as_mut();
 
+            // Functions imported into the local namespace - arguments are already passed by value
+            #[cfg(not(feature = "no_module"))]
+            let imported = if !lib.contains_fn(hash) && has_imported_fn(_mods, fn_name, args.len())
+            {
+                self.call_imported_fn(_mods, state, lib, fn_name, hash, args, level)
+            } else {
+                None
+            };
+            #[cfg(feature = "no_module")]
+            let imported: Option<Result<Dynamic, Box<EvalAltResult>>> = None;
+
             // Map it to name(args) in function-call style
-            self.exec_fn_call(
-                state, lib, fn_name, *native, hash, args, false, false, *def_val, level,
+            imported.map_or_else(
+                || {
+                    self.exec_fn_call(
+                        state, lib, fn_name, *native, hash, args, false, false, *def_val, None,
+                        level,
+                    )
+                },
+                |result| result.map(|v| (v, false)),
             )
         } else if _fn_name == KEYWORD_FN_PTR_CALL && idx.len() > 0 && idx[0].is::<FnPtr>() {
             // FnPtr call on object
@@ -559,7 +955,7 @@ impl Engine {
 
             // Map it to name(args) in function-call style
             self.exec_fn_call(
-                state, lib, &fn_name, *native, hash, args, is_ref, true, *def_val, level,
+                state, lib, &fn_name, *native, hash, args, is_ref, true, *def_val, None, level,
             )
         } else if _fn_name == KEYWORD_FN_PTR_CURRY && obj.is::<FnPtr>() {
             // Curry call
@@ -581,6 +977,7 @@ impl Engine {
             #[cfg(not(feature = "no_object"))]
             let redirected;
             let mut _hash = *hash;
+            let mut _cache = Some(cache);
 
             // Check if it is a map method call in OOP style
             #[cfg(not(feature = "no_object"))]
@@ -592,6 +989,8 @@ impl Engine {
                         _fn_name = &redirected;
                         // Recalculate the hash based on the new function name
                         _hash = calc_fn_hash(empty(), _fn_name, idx.len(), empty());
+                        // The function called is not fixed at this call site
+                        _cache = None;
                     }
                 }
             };
@@ -601,7 +1000,7 @@ impl Engine {
             let args = arg_values.as_mut();
 
             self.exec_fn_call(
-                state, lib, _fn_name, *native, _hash, args, is_ref, true, *def_val, level,
+                state, lib, _fn_name, *native, _hash, args, is_ref, true, *def_val, _cache, level,
             )
         }
         .map_err(|err| err.new_position(*pos))?;
@@ -629,6 +1028,7 @@ impl Engine {
         def_val: Option<bool>,
         mut hash: u64,
         native: bool,
+        mut cache: Option<&FnCallCache>,
         level: usize,
     ) -> Result<Dynamic, Box<EvalAltResult>> {
         // Handle Fn()
@@ -700,6 +1100,7 @@ impl Engine {
                 let prev_len = scope.len();
                 let expr = args_expr.get(0).unwrap();
                 let script = self.eval_expr(scope, mods, state, lib, this_ptr, expr, level)?;
+                let prev_mods_len = mods.len();
                 let result = self
                     .eval_script_expr(scope, mods, state, lib, &script)
                     .map_err(|err| err.new_position(expr.position()));
@@ -710,6 +1111,11 @@ impl Engine {
                     state.always_search = true;
                 }
 
+                if mods.len() != prev_mods_len {
+                    // Modules imported by the eval may change how functions are resolved
+                    state.invalidate_fn_cache();
+                }
+
                 return result;
             }
         }
@@ -734,6 +1140,8 @@ impl Engine {
                 args_expr = &args_expr.as_ref()[1..];
                 // Recalculate hash
                 hash = calc_fn_hash(empty(), name, curry.len() + args_expr.len(), empty());
+                // The function called is not fixed at this call site
+                cache = None;
             } else {
                 return Err(Box::new(EvalAltResult::ErrorMismatchOutputType(
                     self.map_type_name(type_name::<FnPtr>()).into(),
@@ -743,8 +1151,32 @@ impl Engine {
             }
         }
 
+        // Functions imported into the local namespace - always pass arguments by value
+        #[cfg(not(feature = "no_module"))]
+        if !native
+            && !lib.contains_fn(hash)
+            && has_imported_fn(mods, name, curry.len() + args_expr.len())
+        {
+            let mut arg_values = args_expr
+                .iter()
+                .map(|expr| self.eval_expr(scope, mods, state, lib, this_ptr, expr, level))
+                .collect::<Result<StaticVec<_>, _>>()?;
+            let mut args: StaticVec<_> = curry.iter_mut().chain(arg_values.iter_mut()).collect();
+            let args = args.as_mut();
+
+            return self
+                .call_imported_fn(mods, state, lib, name, hash, args, level)
+                .unwrap_or_else(|| {
+                    self.exec_fn_call(
+                        state, lib, name, native, hash, args, false, false, def_val, cache, level,
+                    )
+                    .map(|(v, _)| v)
+                });
+        }
+
         // Normal function call - except for Fn and eval (handled above)
         let mut arg_values: StaticVec<_>;
+        let mut lhs_target: Target;
         let mut args: StaticVec<_>;
         let mut is_ref = false;
 
@@ -764,11 +1196,12 @@ impl Engine {
                         .collect::<Result<_, _>>()?;
 
                     let (target, _, _, pos) = search_namespace(scope, mods, state, this_ptr, lhs)?;
+                    lhs_target = target;
 
-                    self.inc_operations(state)
+                    self.inc_node_operations(state, NodeType::Variable)
                         .map_err(|err| err.new_position(pos))?;
 
-                    args = once(target)
+                    args = once(lhs_target.as_mut())
                         .chain(curry.iter_mut())
                         .chain(arg_values.iter_mut())
                         .collect();
@@ -789,11 +1222,49 @@ impl Engine {
 
         let args = args.as_mut();
         self.exec_fn_call(
-            state, lib, name, native, hash, args, is_ref, false, def_val, level,
+            state, lib, name, native, hash, args, is_ref, false, def_val, cache, level,
         )
         .map(|(v, _)| v)
     }
 
+    /// Call a function imported into the local namespace via `import { ... } from` or `import * from`.
+    /// Returns `None` if no function matches the arguments.
+    /// Position in `EvalAltResult` is `None` and must be set afterwards.
+    #[cfg(not(feature = "no_module"))]
+    #[allow(clippy::too_many_arguments)]
+    fn call_imported_fn(
+        &self,
+        mods: &Imports,
+        _state: &mut State,
+        lib: &Module,
+        fn_name: &str,
+        hash_script: u64,
+        args: &mut FnCallArgs,
+        _level: usize,
+    ) -> Option<Result<Dynamic, Box<EvalAltResult>>> {
+        // Qualifiers (none) + function name + number of arguments + argument `TypeId`'s.
+        let arg_types = args.iter().map(|a| a.type_id());
+        let hash_fn = calc_fn_hash(empty(), fn_name, args.len(), arg_types);
+
+        let func = mods
+            .iter()
+            .rev()
+            .filter(|(n, _)| n == MARKER_IMPORT)
+            .find_map(|(_, m)| m.get_fn(hash_script).or_else(|| m.get_fn(hash_fn)))?;
+
+        #[cfg(not(feature = "no_function"))]
+        if func.is_script() {
+            let fn_def = func.get_fn_def();
+            let mut scope = Scope::new();
+            let mut mods = Imports::new();
+            return Some(self.call_script_fn(
+                &mut scope, &mut mods, _state, lib, &mut None, fn_name, fn_def, args, _level,
+            ));
+        }
+
+        Some(func.get_native_fn()(self, lib, args))
+    }
+
     /// Call a module-qualified function in normal function-call style.
     /// Position in `EvalAltResult` is `None` and must be set afterwards.
     pub(crate) fn make_qualified_function_call(
@@ -834,7 +1305,7 @@ impl Engine {
                     let (target, _, _, pos) =
                         search_scope_only(scope, state, this_ptr, args_expr.get(0).unwrap())?;
 
-                    self.inc_operations(state)
+                    self.inc_node_operations(state, NodeType::Variable)
                         .map_err(|err| err.new_position(pos))?;
 
                     args = once(target).chain(arg_values.iter_mut()).collect();
@@ -857,7 +1328,7 @@ impl Engine {
         let func = match module.get_qualified_fn(hash_script) {
             Err(err) if matches!(*err, EvalAltResult::ErrorFunctionNotFound(_, _)) => {
                 // Then search in Rust functions
-                self.inc_operations(state)?;
+                self.inc_fn_operations(state, name, true, args.iter().map(|a| &**a))?;
 
                 // Qualified Rust functions are indexed in two steps:
                 // 1) Calculate a hash in a similar manner to script-defined functions,
@@ -883,6 +1354,25 @@ impl Engine {
                 self.call_script_fn(
                     &mut scope, &mut mods, state, lib, &mut None, name, fn_def, args, level,
                 )
+                .map_err(|err| match *err {
+                    // Record the namespace of the function
+                    EvalAltResult::ErrorInFunctionCall(name, _, source, err, pos) => {
+                        let namespace = modules
+                            .iter()
+                            .map(|(m, _)| m.as_str())
+                            .collect::<Vec<_>>()
+                            .join(&Token::DoubleColon.syntax());
+
+                        Box::new(EvalAltResult::ErrorInFunctionCall(
+                            name,
+                            Some(namespace),
+                            source,
+                            err,
+                            pos,
+                        ))
+                    }
+                    _ => err,
+                })
             }
             Ok(f) => f.get_native_fn()(self, lib, args.as_mut()),
             Err(err) => match *err {
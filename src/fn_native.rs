@@ -5,12 +5,20 @@ use crate::engine::Engine;
 use crate::module::Module;
 use crate::result::EvalAltResult;
 use crate::token::{is_valid_identifier, Position};
-use crate::utils::ImmutableString;
+use crate::utils::{ImmutableString, StaticVec};
 
 #[cfg(not(feature = "no_function"))]
-use crate::{module::FuncReturn, parser::ScriptFnDef, scope::Scope, utils::StaticVec};
-
-use crate::stdlib::{boxed::Box, convert::TryFrom, fmt, string::String, vec::Vec};
+use crate::{module::FuncReturn, parser::ScriptFnDef, scope::Scope};
+
+use crate::stdlib::{
+    any::TypeId,
+    boxed::Box,
+    convert::TryFrom,
+    fmt,
+    hash::{Hash, Hasher},
+    string::String,
+    vec::Vec,
+};
 
 #[cfg(not(feature = "no_function"))]
 use crate::stdlib::mem;
@@ -343,3 +351,107 @@ impl From<Shared<ScriptFnDef>> for CallableFunction {
         Self::Script(func)
     }
 }
+
+/// Resolution of a call-site, remembering the argument types it was made for.
+#[derive(Debug, Clone)]
+struct FnCallCacheEntry {
+    /// `State::fn_cache_epoch` at the time of resolution.
+    epoch: usize,
+    /// Hash of the script-defined function searched, zero if native only.
+    hash_script: u64,
+    /// `TypeId`'s of the arguments.
+    arg_types: StaticVec<TypeId>,
+    /// The function resolved, `None` if there is none (e.g. built-in operators).
+    func: Option<CallableFunction>,
+}
+
+/// [INTERNALS] A monomorphic inline cache attached to a function call site.
+/// Exported under the `internals` feature only.
+///
+/// It remembers the last function resolved at the call site together with the argument types
+/// it was resolved for, so that a call with the same argument types skips the function search.
+/// The cache is only valid within the same evaluation and until the next module is imported.
+///
+/// ## WARNING
+///
+/// This type is volatile and may change.
+#[derive(Default)]
+pub struct FnCallCache(
+    #[cfg(not(feature = "sync"))] RefCell<Option<FnCallCacheEntry>>,
+    #[cfg(feature = "sync")] RwLock<Option<FnCallCacheEntry>>,
+);
+
+impl FnCallCache {
+    /// Get the function resolved for a particular set of arguments, if cached.
+    ///
+    /// Returns `Some(None)` if it is cached that no function matches the arguments.
+    pub(crate) fn get(
+        &self,
+        epoch: usize,
+        hash_script: u64,
+        args: &FnCallArgs,
+    ) -> Option<Option<CallableFunction>> {
+        #[cfg(not(feature = "sync"))]
+        let entry = self.0.borrow();
+        #[cfg(feature = "sync")]
+        let entry = self.0.read().unwrap();
+
+        match entry.as_ref() {
+            Some(entry)
+                if entry.epoch == epoch
+                    && entry.hash_script == hash_script
+                    && entry.arg_types.len() == args.len()
+                    && entry
+                        .arg_types
+                        .iter()
+                        .zip(args.iter())
+                        .all(|(typ, arg)| *typ == arg.type_id()) =>
+            {
+                Some(entry.func.clone())
+            }
+            _ => None,
+        }
+    }
+    /// Remember the function resolved for a particular set of arguments.
+    pub(crate) fn set(
+        &self,
+        epoch: usize,
+        hash_script: u64,
+        args: &FnCallArgs,
+        func: Option<CallableFunction>,
+    ) {
+        let entry = FnCallCacheEntry {
+            epoch,
+            hash_script,
+            arg_types: args.iter().map(|arg| arg.type_id()).collect(),
+            func,
+        };
+
+        #[cfg(not(feature = "sync"))]
+        {
+            *self.0.borrow_mut() = Some(entry);
+        }
+        #[cfg(feature = "sync")]
+        {
+            *self.0.write().unwrap() = Some(entry);
+        }
+    }
+}
+
+impl fmt::Debug for FnCallCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FnCallCache")
+    }
+}
+
+/// A cloned call site starts with an empty cache.
+impl Clone for FnCallCache {
+    fn clone(&self) -> Self {
+        Default::default()
+    }
+}
+
+/// The cache is not part of the call site's identity.
+impl Hash for FnCallCache {
+    fn hash<H: Hasher>(&self, _: &mut H) {}
+}
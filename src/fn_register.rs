@@ -48,6 +48,29 @@ pub trait RegisterFn<FN, ARGS, RET> {
     /// # }
     /// ```
     fn register_fn(&mut self, name: &str, f: FN) -> &mut Self;
+
+    /// Register a custom function with the `Engine`, marking it as pure.
+    ///
+    /// A pure function always returns the same result for the same arguments and has no
+    /// side effects, so calls to it with constant arguments can be evaluated during
+    /// `OptimizationLevel::Full`.  Functions registered via `register_fn` are impure.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, RegisterFn};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// // 'add' has no side effects
+    /// engine.register_pure_fn("add", |x: i64, y: i64| x + y);
+    ///
+    /// assert_eq!(engine.eval::<i64>("add(40, 2)")?, 42);
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn register_pure_fn(&mut self, name: &str, f: FN) -> &mut Self;
 }
 
 /// Trait to register fallible custom functions returning `Result<Dynamic, Box<EvalAltResult>>` with the `Engine`.
@@ -78,6 +101,32 @@ pub trait RegisterResultFn<FN, ARGS> {
     ///         .expect_err("expecting division by zero error!");
     /// ```
     fn register_result_fn(&mut self, name: &str, f: FN) -> &mut Self;
+
+    /// Register a custom fallible function with the `Engine`, marking it as pure.
+    ///
+    /// A pure function always returns the same result for the same arguments and has no
+    /// side effects, so calls to it with constant arguments can be evaluated during
+    /// `OptimizationLevel::Full`.  Functions registered via `register_result_fn` are impure.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rhai::{Engine, Dynamic, RegisterResultFn, EvalAltResult};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// engine.register_pure_result_fn("div", |x: i64, y: i64| {
+    ///     if y == 0 {
+    ///         Err("division by zero!".into())
+    ///     } else {
+    ///         Ok((x / y).into())
+    ///     }
+    /// });
+    ///
+    /// engine.eval::<i64>("div(42, 0)")
+    ///         .expect_err("expecting division by zero error!");
+    /// ```
+    fn register_pure_result_fn(&mut self, name: &str, f: FN) -> &mut Self;
 }
 
 // These types are used to build a unique _marker_ tuple type for each combination
@@ -200,6 +249,15 @@ macro_rules! def_register {
                 );
                 self
             }
+
+            fn register_pure_fn(&mut self, name: &str, f: FN) -> &mut Self {
+                let hash = self.global_module.set_fn(name, FnAccess::Public,
+                    &[$(map_type_id::<$par>()),*],
+                    CallableFunction::$abi(make_func!(f : map_dynamic ; $($par => $let => $clone => $arg),*))
+                );
+                self.global_module.set_fn_pure(hash, true);
+                self
+            }
         }
 
         impl<
@@ -214,6 +272,15 @@ macro_rules! def_register {
                 );
                 self
             }
+
+            fn register_pure_result_fn(&mut self, name: &str, f: FN) -> &mut Self {
+                let hash = self.global_module.set_fn(name, FnAccess::Public,
+                    &[$(map_type_id::<$par>()),*],
+                    CallableFunction::$abi(make_func!(f : map_result ; $($par => $let => $clone => $arg),*))
+                );
+                self.global_module.set_fn_pure(hash, true);
+                self
+            }
         }
 
         //def_register!(imp_pop $($par => $mark => $param),*);
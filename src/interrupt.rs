@@ -0,0 +1,99 @@
+//! Module defining the handle used to interrupt running scripts.
+
+use crate::stdlib::{
+    boxed::Box,
+    ptr,
+    string::String,
+    sync::{
+        atomic::{AtomicBool, AtomicPtr, Ordering},
+        Arc,
+    },
+};
+
+/// State shared by all clones of an `InterruptHandle`.
+///
+/// The reason is kept in an atomic pointer to a boxed string instead of behind a lock,
+/// so it works without `std`.
+#[derive(Debug, Default)]
+struct Interrupt {
+    /// Is there a pending interrupt?
+    flag: AtomicBool,
+    /// Reason of the pending interrupt, null if not yet set or already taken.
+    reason: AtomicPtr<String>,
+}
+
+impl Interrupt {
+    /// Replace the reason, returning the previous one, if any.
+    fn swap_reason(&self, reason: Option<String>) -> Option<String> {
+        let new_ptr = reason.map_or(ptr::null_mut(), |r| Box::into_raw(Box::new(r)));
+        let old_ptr = self.reason.swap(new_ptr, Ordering::AcqRel);
+
+        if old_ptr.is_null() {
+            None
+        } else {
+            // SAFETY: non-null pointers are only ever created by `Box::into_raw` above,
+            //         and swapping them out transfers ownership exclusively to this call.
+            Some(*unsafe { Box::from_raw(old_ptr) })
+        }
+    }
+}
+
+impl Drop for Interrupt {
+    fn drop(&mut self) {
+        self.swap_reason(None);
+    }
+}
+
+/// A cloneable handle to interrupt scripts run by an `Engine`, obtained via `Engine::interrupt_handle`.
+///
+/// The handle can be sent to, and used from, any thread.  An interrupt is checked before every operation,
+/// and the first evaluation that sees it fails with `EvalAltResult::ErrorInterrupted` and clears it.
+/// An interrupt raised when no script is running therefore aborts the next evaluation, unless `reset` first.
+///
+/// # Example
+///
+/// ```
+/// use rhai::{Engine, EvalAltResult};
+///
+/// let engine = Engine::new();
+///
+/// let handle = engine.interrupt_handle();
+///
+/// // Interrupt the script from another thread
+/// std::thread::spawn(move || handle.interrupt_with_reason("timed out"));
+///
+/// let result = engine.eval::<()>("loop {}");
+///
+/// assert!(matches!(*result.expect_err("should error"),
+///                  EvalAltResult::ErrorInterrupted(reason, _) if reason == "timed out"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct InterruptHandle(Arc<Interrupt>);
+
+impl InterruptHandle {
+    /// Interrupt the running script.
+    pub fn interrupt(&self) {
+        self.interrupt_with_reason(String::new());
+    }
+    /// Interrupt the running script, with a reason carried in the resulting error.
+    pub fn interrupt_with_reason(&self, reason: impl Into<String>) {
+        self.0.swap_reason(Some(reason.into()));
+        self.0.flag.store(true, Ordering::Release);
+    }
+    /// Is there an interrupt that is not yet seen by a running script?
+    pub fn is_interrupted(&self) -> bool {
+        self.0.flag.load(Ordering::Acquire)
+    }
+    /// Clear any interrupt that is not yet seen by a running script.
+    pub fn reset(&self) {
+        self.0.flag.store(false, Ordering::Release);
+    }
+    /// Clear the pending interrupt, if any, returning its reason.
+    pub(crate) fn take(&self) -> Option<String> {
+        if !self.0.flag.load(Ordering::Relaxed) || !self.0.flag.swap(false, Ordering::AcqRel) {
+            return None;
+        }
+
+        Some(self.0.swap_reason(None).unwrap_or_default())
+    }
+}
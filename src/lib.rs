@@ -75,6 +75,10 @@ extern crate alloc;
 
 mod any;
 mod api;
+mod bytecode;
+mod cost;
+mod debugger;
+mod diagnostic;
 mod engine;
 mod error;
 mod fn_args;
@@ -82,8 +86,12 @@ mod fn_call;
 mod fn_func;
 mod fn_native;
 mod fn_register;
+mod interrupt;
+#[cfg(not(feature = "no_function"))]
+mod memo;
 mod module;
 mod optimize;
+mod options;
 pub mod packages;
 mod parser;
 mod result;
@@ -91,27 +99,37 @@ mod scope;
 #[cfg(feature = "serde")]
 mod serde;
 mod settings;
+mod stats;
 mod stdlib;
 mod syntax;
 mod token;
 mod r#unsafe;
 mod utils;
+#[cfg(not(feature = "no_optimize"))]
+mod visitor;
 
 pub use any::Dynamic;
+pub use bytecode::Backend;
+pub use cost::NodeType;
+pub use debugger::{CallFrame, DebugContext, DebugEvent, DebuggerCommand};
+pub use diagnostic::{Diagnostic, DiagnosticType};
 pub use engine::Engine;
 pub use error::{ParseError, ParseErrorType};
 pub use fn_native::{FnPtr, IteratorFn};
 pub use fn_register::{RegisterFn, RegisterResultFn};
+pub use interrupt::InterruptHandle;
 pub use module::Module;
-pub use parser::{ImmutableString, AST, INT};
-pub use result::EvalAltResult;
+pub use options::EvalOptions;
+pub use parser::{ImmutableString, VarMetadata, AST, INT};
+pub use result::{BacktraceFrame, EvalAltResult};
 pub use scope::Scope;
+pub use stats::EvalStats;
 pub use syntax::{EvalContext, Expression};
-pub use token::Position;
+pub use token::{Position, Span};
 pub use utils::calc_fn_spec as calc_fn_hash;
 
 #[cfg(not(feature = "no_function"))]
-pub use parser::FnAccess;
+pub use parser::{FnAccess, ScriptFnMetadata};
 
 #[cfg(not(feature = "no_function"))]
 pub use fn_func::Func;
@@ -154,6 +172,9 @@ pub mod de {
 #[cfg(not(feature = "no_optimize"))]
 pub use optimize::OptimizationLevel;
 
+#[cfg(not(feature = "no_optimize"))]
+pub use visitor::{AstVisitor, ExpressionMut};
+
 // Expose internal data structures.
 
 #[cfg(feature = "internals")]
@@ -168,6 +189,11 @@ pub use token::{get_next_token, parse_string_literal, InputStream, Token, Tokeni
 #[deprecated(note = "this type is volatile and may change")]
 pub use parser::{CustomExpr, Expr, FloatWrapper, ReturnType, ScriptFnDef, Stmt};
 
+#[cfg(feature = "internals")]
+#[cfg(not(feature = "no_module"))]
+#[deprecated(note = "this type is volatile and may change")]
+pub use parser::ImportNames;
+
 #[cfg(feature = "internals")]
 #[deprecated(note = "this type is volatile and may change")]
 pub use engine::{Imports, Limits, State as EvalState};
@@ -176,6 +202,10 @@ pub use engine::{Imports, Limits, State as EvalState};
 #[deprecated(note = "this type is volatile and may change")]
 pub use module::ModuleRef;
 
+#[cfg(feature = "internals")]
+#[deprecated(note = "this type is volatile and may change")]
+pub use fn_native::FnCallCache;
+
 #[cfg(feature = "internals")]
 #[deprecated(note = "this type is volatile and may change")]
 pub use utils::StaticVec;
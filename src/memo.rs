@@ -0,0 +1,211 @@
+//! Module implementing memoization of script-defined functions.
+
+use crate::any::{Dynamic, Union};
+use crate::fn_native::{FnCallArgs, Shared};
+use crate::parser::{ImmutableString, ScriptFnDef, INT};
+
+use crate::stdlib::{collections::HashMap, vec::Vec};
+
+#[cfg(not(feature = "sync"))]
+use crate::stdlib::cell::RefCell;
+#[cfg(feature = "sync")]
+use crate::stdlib::sync::RwLock;
+
+/// Default maximum number of results of memoized functions to cache.
+pub const MAX_MEMO_ENTRIES: usize = 1024;
+
+/// Hashable form of an argument value.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+enum ArgKey {
+    Unit,
+    Bool(bool),
+    Str(ImmutableString),
+    Char(char),
+    Int(INT),
+    /// Floating-point number, by its bit pattern.
+    #[cfg(not(feature = "no_float"))]
+    Float(u64),
+    #[cfg(not(feature = "no_index"))]
+    Array(Vec<ArgKey>),
+    /// Object map, sorted by property name.
+    #[cfg(not(feature = "no_object"))]
+    Map(Vec<(ImmutableString, ArgKey)>),
+    FnPtr(ImmutableString, Vec<ArgKey>),
+}
+
+impl ArgKey {
+    /// Make a hashable key from a value.  Returns `None` if the value is not hashable
+    /// (e.g. a custom type or a shared value).
+    fn from_dynamic(value: &Dynamic) -> Option<Self> {
+        Some(match &value.0 {
+            Union::Unit(_) => Self::Unit,
+            Union::Bool(b) => Self::Bool(*b),
+            Union::Str(s) => Self::Str(s.clone()),
+            Union::Char(c) => Self::Char(*c),
+            Union::Int(n) => Self::Int(*n),
+            #[cfg(not(feature = "no_float"))]
+            Union::Float(f) => Self::Float(f.to_bits()),
+            #[cfg(not(feature = "no_index"))]
+            Union::Array(a) => Self::Array(a.iter().map(Self::from_dynamic).collect::<Option<_>>()?),
+            #[cfg(not(feature = "no_object"))]
+            Union::Map(m) => {
+                let mut props = m
+                    .iter()
+                    .map(|(k, v)| Self::from_dynamic(v).map(|v| (k.clone(), v)))
+                    .collect::<Option<Vec<_>>>()?;
+                props.sort_by(|(a, _), (b, _)| a.cmp(b));
+                Self::Map(props)
+            }
+            Union::FnPtr(f) => Self::FnPtr(
+                f.get_fn_name().clone(),
+                f.curry().iter().map(Self::from_dynamic).collect::<Option<_>>()?,
+            ),
+            Union::Variant(_) | Union::Shared(_) => return None,
+        })
+    }
+}
+
+/// Key of a memoized function call: the function definition and the argument values.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct MemoKey(usize, Vec<ArgKey>);
+
+impl MemoKey {
+    /// Make a key for a call to a script-defined function.
+    /// Returns `None` if any argument is not hashable, in which case the call is not memoized.
+    ///
+    /// Calls to functions referring to the `global` namespace are not memoized either,
+    /// because their results also depend on the values of global constants.
+    pub fn new(fn_def: &ScriptFnDef, args: &FnCallArgs) -> Option<Self> {
+        if fn_def.uses_global {
+            return None;
+        }
+
+        let args = args
+            .iter()
+            .map(|arg| ArgKey::from_dynamic(arg))
+            .collect::<Option<_>>()?;
+
+        Some(Self(fn_def as *const _ as usize, args))
+    }
+}
+
+/// Cache of results of memoized function calls.
+#[derive(Debug, Clone, Default)]
+pub struct MemoCache {
+    // The function definition is kept alive so its address is not reused by another function.
+    // Each result is kept with its approximate size in bytes and the order in which it is cached.
+    entries: HashMap<MemoKey, (Shared<ScriptFnDef>, Dynamic, usize, u64)>,
+    /// Number of results cached so far, used to order the entries.
+    count: u64,
+    /// Approximate number of bytes held by all cached results.
+    memory: usize,
+}
+
+impl MemoCache {
+    /// Get the cached result of a function call, if any.
+    pub fn get(&self, key: &MemoKey) -> Option<Dynamic> {
+        self.entries.get(key).map(|(_, value, _, _)| value.clone())
+    }
+    /// Cache the result of a function call, which holds `size` bytes.
+    ///
+    /// The oldest results are evicted until there is room for the new result within both
+    /// `max_entries` and `max_memory` bytes.  A result larger than `max_memory` is not cached.
+    pub fn insert(
+        &mut self,
+        key: MemoKey,
+        fn_def: Shared<ScriptFnDef>,
+        value: Dynamic,
+        size: usize,
+        max_entries: usize,
+        max_memory: usize,
+    ) {
+        if max_entries == 0 || size > max_memory {
+            return;
+        }
+
+        if let Some((_, _, old_size, _)) = self.entries.remove(&key) {
+            self.memory -= old_size;
+        }
+
+        while self.entries.len() >= max_entries || self.memory + size > max_memory {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, _, _, order))| *order)
+                .map(|(key, _)| key.clone())
+                .unwrap();
+
+            let (_, _, old_size, _) = self.entries.remove(&oldest).unwrap();
+            self.memory -= old_size;
+        }
+
+        self.count += 1;
+        self.memory += size;
+        self.entries.insert(key, (fn_def, value, size, self.count));
+    }
+    /// Remove all cached results.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.memory = 0;
+    }
+    /// Approximate number of bytes held by all cached results.
+    #[cfg(not(feature = "unchecked"))]
+    pub fn memory(&self) -> usize {
+        self.memory
+    }
+}
+
+/// A `MemoCache` kept by an `Engine` across evaluations.
+#[derive(Debug, Default)]
+pub struct LockedMemoCache(
+    #[cfg(not(feature = "sync"))] RefCell<MemoCache>,
+    #[cfg(feature = "sync")] RwLock<MemoCache>,
+);
+
+impl LockedMemoCache {
+    /// Get the cached result of a function call, if any.
+    pub fn get(&self, key: &MemoKey) -> Option<Dynamic> {
+        #[cfg(not(feature = "sync"))]
+        return self.0.borrow().get(key);
+        #[cfg(feature = "sync")]
+        return self.0.read().unwrap().get(key);
+    }
+    /// Cache the result of a function call, which holds `size` bytes.
+    ///
+    /// The oldest results are evicted until there is room for the new result within both
+    /// `max_entries` and `max_memory` bytes.  A result larger than `max_memory` is not cached.
+    pub fn insert(
+        &self,
+        key: MemoKey,
+        fn_def: Shared<ScriptFnDef>,
+        value: Dynamic,
+        size: usize,
+        max_entries: usize,
+        max_memory: usize,
+    ) {
+        #[cfg(not(feature = "sync"))]
+        self.0
+            .borrow_mut()
+            .insert(key, fn_def, value, size, max_entries, max_memory);
+        #[cfg(feature = "sync")]
+        self.0
+            .write()
+            .unwrap()
+            .insert(key, fn_def, value, size, max_entries, max_memory);
+    }
+    /// Remove all cached results.
+    pub fn clear(&self) {
+        #[cfg(not(feature = "sync"))]
+        self.0.borrow_mut().clear();
+        #[cfg(feature = "sync")]
+        self.0.write().unwrap().clear();
+    }
+    /// Approximate number of bytes held by all cached results.
+    #[cfg(not(feature = "unchecked"))]
+    pub fn memory(&self) -> usize {
+        #[cfg(not(feature = "sync"))]
+        return self.0.borrow().memory();
+        #[cfg(feature = "sync")]
+        return self.0.read().unwrap().memory();
+    }
+}
@@ -10,11 +10,14 @@ use crate::token::{Position, Token};
 use crate::utils::{StaticVec, StraightHasherBuilder};
 
 #[cfg(not(feature = "no_function"))]
-use crate::{fn_native::Shared, parser::ScriptFnDef};
+use crate::{
+    fn_native::Shared,
+    parser::{ScriptFnDef, ScriptFnMetadata},
+};
 
 #[cfg(not(feature = "no_module"))]
 use crate::{
-    engine::Imports,
+    engine::{Imports, MARKER_EXPORT, MARKER_IMPORT},
     parser::AST,
     scope::{Entry as ScopeEntry, Scope},
 };
@@ -64,11 +67,19 @@ pub struct Module {
     /// Module variables.
     variables: HashMap<String, Dynamic>,
 
+    /// Doc comments of module variables.
+    var_comments: HashMap<String, Vec<String>>,
+
     /// Flattened collection of all module variables, including those in sub-modules.
     all_variables: HashMap<u64, Dynamic, StraightHasherBuilder>,
 
-    /// External Rust functions.
-    functions: HashMap<u64, (String, FnAccess, StaticVec<TypeId>, Func), StraightHasherBuilder>,
+    /// External Rust functions, with their purity if explicitly marked.
+    #[allow(clippy::type_complexity)]
+    functions: HashMap<
+        u64,
+        (String, FnAccess, StaticVec<TypeId>, Func, Option<bool>),
+        StraightHasherBuilder,
+    >,
 
     /// Iterator functions, keyed by the type producing the iterator.
     type_iterators: HashMap<TypeId, IteratorFn>,
@@ -98,7 +109,7 @@ impl fmt::Debug for Module {
                 .join(", "),
             self.functions
                 .values()
-                .map(|(_, _, _, f)| f.to_string())
+                .map(|(_, _, _, f, _)| f.to_string())
                 .collect::<Vec<_>>()
                 .join(", "),
         )
@@ -169,6 +180,7 @@ impl Module {
                     .collect()
             },
             variables: self.variables.clone(),
+            var_comments: self.var_comments.clone(),
             functions: self.functions.clone(),
             type_iterators: self.type_iterators.clone(),
             ..Default::default()
@@ -239,6 +251,38 @@ impl Module {
         self
     }
 
+    /// Get the doc comments of a module variable, if any.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rhai::Module;
+    ///
+    /// let mut module = Module::new();
+    /// module.set_var("answer", 42_i64);
+    /// module.set_var_comments("answer", vec!["/// The answer.".to_string()]);
+    /// assert_eq!(module.get_var_comments("answer").unwrap(), ["/// The answer."]);
+    /// ```
+    pub fn get_var_comments(&self, name: &str) -> Option<&[String]> {
+        self.var_comments.get(name).map(Vec::as_slice)
+    }
+
+    /// Set the doc comments of a module variable.
+    pub fn set_var_comments(
+        &mut self,
+        name: impl Into<String>,
+        comments: Vec<String>,
+    ) -> &mut Self {
+        self.var_comments.insert(name.into(), comments);
+        self
+    }
+
+    /// Get a mutable reference to a module variable.
+    #[cfg(not(feature = "no_module"))]
+    pub(crate) fn get_var_mut(&mut self, name: &str) -> Option<&mut Dynamic> {
+        self.variables.get_mut(name)
+    }
+
     /// Get a mutable reference to a modules-qualified variable.
     /// Name and Position in `EvalAltResult` are None and must be set afterwards.
     ///
@@ -269,6 +313,7 @@ impl Module {
                 fn_def.access,
                 Default::default(),
                 fn_def.into(),
+                None,
             ),
         );
         self.indexed = false;
@@ -382,13 +427,62 @@ impl Module {
         let params = arg_types.into_iter().cloned().collect();
 
         self.functions
-            .insert(hash_fn, (name, access, params, func.into()));
+            .insert(hash_fn, (name, access, params, func.into(), None));
 
         self.indexed = false;
 
         hash_fn
     }
 
+    /// Mark a Rust function in the module as pure or impure.
+    ///
+    /// A pure function always returns the same result for the same arguments and has no side effects,
+    /// so calls to it with constant arguments can be evaluated during `OptimizationLevel::Full`.
+    /// Rust functions are impure unless marked otherwise.
+    ///
+    /// The `u64` hash is calculated by the function `crate::calc_fn_hash`.
+    /// It is also returned by the `set_fn_XXX` calls.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rhai::Module;
+    ///
+    /// let mut module = Module::new();
+    /// let hash = module.set_fn_2("add", |x: i64, y: i64| Ok(x + y));
+    /// assert!(!module.is_fn_pure(hash));
+    /// module.set_fn_pure(hash, true);
+    /// assert!(module.is_fn_pure(hash));
+    /// ```
+    pub fn set_fn_pure(&mut self, hash_fn: u64, pure: bool) -> &mut Self {
+        if let Some((_, _, _, _, purity)) = self.functions.get_mut(&hash_fn) {
+            *purity = Some(pure);
+        }
+        self
+    }
+
+    /// Is a Rust function in the module marked as pure?
+    ///
+    /// The `u64` hash is calculated by the function `crate::calc_fn_hash`.
+    /// It is also returned by the `set_fn_XXX` calls.
+    pub fn is_fn_pure(&self, hash_fn: u64) -> bool {
+        matches!(
+            self.functions.get(&hash_fn),
+            Some((_, _, _, _, Some(true)))
+        )
+    }
+
+    /// Mark all Rust functions in the module that are not explicitly marked impure as pure.
+    pub(crate) fn set_unmarked_fns_pure(&mut self) -> &mut Self {
+        self.functions
+            .values_mut()
+            .filter(|(_, _, _, f, _)| !f.is_iter())
+            .for_each(|(_, _, _, _, purity)| {
+                purity.get_or_insert(true);
+            });
+        self
+    }
+
     /// Set a Rust function taking a reference to the scripting `Engine`, the current set of functions,
     /// plus a list of mutable `Dynamic` references into the module, returning a hash key.
     ///
@@ -904,7 +998,7 @@ impl Module {
     /// The `u64` hash is calculated by the function `crate::calc_fn_hash`.
     /// It is also returned by the `set_fn_XXX` calls.
     pub(crate) fn get_fn(&self, hash_fn: u64) -> Option<&Func> {
-        self.functions.get(&hash_fn).map(|(_, _, _, v)| v)
+        self.functions.get(&hash_fn).map(|(_, _, _, v, _)| v)
     }
 
     /// Get a modules-qualified function.
@@ -937,12 +1031,18 @@ impl Module {
     ) -> &mut Self {
         self.variables
             .extend(other.variables.iter().map(|(k, v)| (k.clone(), v.clone())));
+        self.var_comments.extend(
+            other
+                .var_comments
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone())),
+        );
 
         self.functions.extend(
             other
                 .functions
                 .iter()
-                .filter(|(_, (_, _, _, v))| match v {
+                .filter(|(_, (_, _, _, v, _))| match v {
                     #[cfg(not(feature = "no_function"))]
                     Func::Script(ref f) => _filter(f.access, f.name.as_str(), f.params.len()),
                     _ => true,
@@ -959,13 +1059,78 @@ impl Module {
         self
     }
 
+    /// Does the module contain a variable or a public function with a particular name?
+    #[cfg(not(feature = "no_module"))]
+    pub(crate) fn contains_member(&self, name: &str) -> bool {
+        self.variables.contains_key(name)
+            || self
+                .functions
+                .values()
+                .any(|(fn_name, access, _, _, _)| *access == Public && fn_name == name)
+    }
+
+    /// Get an iterator over the names of all variables and public functions in the module.
+    #[cfg(not(feature = "no_module"))]
+    pub(crate) fn iter_member_names(&self) -> impl Iterator<Item = &str> {
+        self.variables.keys().map(String::as_str).chain(
+            self.functions
+                .values()
+                .filter(|(_, access, _, _, _)| *access == Public)
+                .map(|(name, _, _, _, _)| name.as_str()),
+        )
+    }
+
+    /// Create a new module with only the variables and public functions selected by a filter,
+    /// which maps the name of each member to keep into its new name.
+    #[cfg(not(feature = "no_module"))]
+    pub(crate) fn select_members(&self, filter: impl Fn(&str) -> Option<String>) -> Self {
+        let mut module = Module::new();
+
+        self.variables.iter().for_each(|(name, value)| {
+            if let Some(new_name) = filter(name) {
+                if let Some(comments) = self.var_comments.get(name) {
+                    module
+                        .var_comments
+                        .insert(new_name.clone(), comments.clone());
+                }
+                module.variables.insert(new_name, value.clone());
+            }
+        });
+
+        self.functions
+            .values()
+            .filter(|(_, access, _, _, _)| *access == Public)
+            .for_each(|(name, access, params, func, purity)| {
+                let name = match filter(name) {
+                    Some(name) => name,
+                    None => return,
+                };
+
+                #[cfg(not(feature = "no_function"))]
+                if func.is_script() {
+                    let mut fn_def = func.get_fn_def().clone();
+                    fn_def.name = name.into();
+                    module.set_script_fn(fn_def);
+                    return;
+                }
+
+                let hash_fn = module.set_fn(name, *access, params, func.clone());
+
+                if let Some(pure) = purity {
+                    module.set_fn_pure(hash_fn, *pure);
+                }
+            });
+
+        module
+    }
+
     /// Filter out the functions, retaining only some based on a filter predicate.
     #[cfg(not(feature = "no_function"))]
     pub(crate) fn retain_functions(
         &mut self,
         filter: impl Fn(FnAccess, &str, usize) -> bool,
     ) -> &mut Self {
-        self.functions.retain(|_, (_, _, _, v)| match v {
+        self.functions.retain(|_, (_, _, _, v, _)| match v {
             Func::Script(ref f) => filter(f.access, f.name.as_str(), f.params.len()),
             _ => true,
         });
@@ -995,10 +1160,9 @@ impl Module {
     }
 
     /// Get an iterator to the functions in the module.
-    #[cfg(not(feature = "no_function"))]
     pub(crate) fn iter_fn(
         &self,
-    ) -> impl Iterator<Item = &(String, FnAccess, StaticVec<TypeId>, Func)> {
+    ) -> impl Iterator<Item = &(String, FnAccess, StaticVec<TypeId>, Func, Option<bool>)> {
         self.functions.values()
     }
 
@@ -1007,13 +1171,46 @@ impl Module {
     pub fn iter_script_fn<'a>(&'a self) -> impl Iterator<Item = Shared<ScriptFnDef>> + 'a {
         self.functions
             .values()
-            .map(|(_, _, _, f)| f)
+            .map(|(_, _, _, f, _)| f)
             .filter(|f| f.is_script())
             .map(|f| f.get_shared_fn_def())
     }
 
+    /// Get an iterator over the metadata (including doc comments) of all script-defined
+    /// functions in the module.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// # #[cfg(not(feature = "no_module"))]
+    /// # {
+    /// use rhai::{Engine, Module, Scope};
+    ///
+    /// let engine = Engine::new();
+    /// let ast = engine.compile("/// Add one to a number.\nfn inc(x) { x + 1 }")?;
+    /// let module = Module::eval_ast_as_new(Scope::new(), &ast, &engine)?;
+    ///
+    /// let f = module.iter_functions().next().unwrap();
+    /// assert_eq!(f.name, "inc");
+    /// assert_eq!(f.comments, vec!["/// Add one to a number."]);
+    /// # }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(feature = "no_function"))]
+    pub fn iter_functions<'a>(&'a self) -> impl Iterator<Item = ScriptFnMetadata<'a>> + 'a {
+        self.functions
+            .values()
+            .map(|(_, _, _, f, _)| f)
+            .filter(|f| f.is_script())
+            .map(|f| f.get_fn_def().into())
+    }
+
     /// Create a new `Module` by evaluating an `AST`.
     ///
+    /// Doc comments of exported variables are kept, available via `Module::get_var_comments`.
+    ///
     /// # Examples
     ///
     /// ```
@@ -1031,9 +1228,10 @@ impl Module {
     #[cfg(not(feature = "no_module"))]
     pub fn eval_ast_as_new(mut scope: Scope, ast: &AST, engine: &Engine) -> FuncReturn<Self> {
         let mut mods = Imports::new();
+        let mut state = engine.new_state(&scope, ast.uses_global());
 
         // Run the script
-        engine.eval_ast_with_scope_raw(&mut scope, &mut mods, &ast)?;
+        engine.eval_ast_with_scope_raw(&mut scope, &mut mods, &mut state, &ast)?;
 
         // Create new module
         let mut module = Module::new();
@@ -1047,10 +1245,27 @@ impl Module {
                 }
             });
 
-        // Modules left in the scope become sub-modules
-        mods.into_iter().for_each(|(alias, m)| {
-            module.modules.insert(alias.to_string(), m);
-        });
+        ast.iter_variables()
+            .filter(|v| v.exported && !v.comments.is_empty())
+            .for_each(|v| {
+                let comments = v.comments.iter().map(|&s| s.into()).collect();
+                module.var_comments.insert(v.name.into(), comments);
+            });
+
+        mods.into_iter()
+            .for_each(|(alias, m)| match alias.as_ref() {
+                // Members imported into the local namespace are not exported
+                MARKER_IMPORT => (),
+                // Re-exported members and modules become part of the module
+                MARKER_EXPORT => {
+                    module.merge(&m);
+                    module.modules.extend(m.modules);
+                }
+                // Modules left in the scope become sub-modules
+                _ => {
+                    module.modules.insert(alias.to_string(), m);
+                }
+            });
 
         module.merge(ast.lib());
 
@@ -1082,7 +1297,7 @@ impl Module {
                 variables.push((hash_var, value.clone()));
             }
             // Index all Rust functions
-            for (name, access, params, func) in module.functions.values() {
+            for (name, access, params, func, _) in module.functions.values() {
                 match access {
                     // Private functions are not exported
                     FnAccess::Private => continue,
@@ -3,23 +3,45 @@
 use crate::any::Dynamic;
 use crate::calc_fn_hash;
 use crate::engine::{
-    Engine, Imports, KEYWORD_DEBUG, KEYWORD_EVAL, KEYWORD_FN_PTR, KEYWORD_PRINT, KEYWORD_TYPE_OF,
+    map_type_annotation, Engine, Imports, KEYWORD_DEBUG, KEYWORD_EVAL, KEYWORD_FN_PTR,
+    KEYWORD_FN_PTR_CALL, KEYWORD_FN_PTR_CURRY, KEYWORD_PRINT, KEYWORD_SHARED, KEYWORD_TYPE_OF,
 };
+use crate::error::{ParseError, ParseErrorType as PERR};
+use crate::fn_call::run_builtin_binary_op;
+use crate::fn_native::FnPtr;
 use crate::module::Module;
-use crate::parser::{map_dynamic_to_expr, Expr, ScriptFnDef, Stmt, AST};
+use crate::parser::{map_dynamic_to_expr, Expr, ImmutableString, ScriptFnDef, Stmt, AST, INT};
 use crate::scope::{Entry as ScopeEntry, EntryType as ScopeEntryType, Scope};
-use crate::token::is_valid_identifier;
+use crate::token::{is_valid_identifier, Position};
 use crate::utils::StaticVec;
 
+#[cfg(not(feature = "no_optimize"))]
+use crate::visitor::run_passes;
+
+#[cfg(not(feature = "no_float"))]
+use crate::parser::FLOAT;
+
+#[cfg(not(feature = "no_index"))]
+use crate::engine::Array;
+
+#[cfg(not(feature = "no_object"))]
+use crate::engine::Map;
+
 #[cfg(not(feature = "no_function"))]
 use crate::parser::ReturnType;
 
+#[cfg(not(feature = "no_module"))]
+use crate::parser::ImportNames;
+
 #[cfg(feature = "internals")]
 use crate::parser::CustomExpr;
 
 use crate::stdlib::{
+    any::{type_name, TypeId},
+    borrow::Cow,
     boxed::Box,
-    iter::empty,
+    format,
+    iter::{empty, once},
     string::{String, ToString},
     vec,
     vec::Vec,
@@ -34,8 +56,8 @@ pub enum OptimizationLevel {
     None,
     /// Only perform simple optimizations without evaluating functions.
     Simple,
-    /// Full optimizations performed, including evaluating functions.
-    /// Take care that this may cause side effects as it essentially assumes that all functions are pure.
+    /// Full optimizations performed, including evaluating functions marked as pure and inlining
+    /// small script-defined functions.
     Full,
 }
 
@@ -64,6 +86,8 @@ struct State<'a> {
     engine: &'a Engine,
     /// Library of script-defined functions.
     lib: &'a Module,
+    /// Number of `import` statements binding names into the local namespace.
+    namespace_imports: usize,
     /// Optimization level.
     optimization_level: OptimizationLevel,
 }
@@ -76,6 +100,7 @@ impl<'a> State<'a> {
             constants: vec![],
             engine,
             lib,
+            namespace_imports: 0,
             optimization_level: level,
         }
     }
@@ -122,13 +147,6 @@ fn call_fn_with_constant_arguments(
     arg_values: &mut [Dynamic],
 ) -> Option<Dynamic> {
     // Search built-in's and external functions
-    let hash_fn = calc_fn_hash(
-        empty(),
-        fn_name,
-        arg_values.len(),
-        arg_values.iter().map(|a| a.type_id()),
-    );
-
     state
         .engine
         .call_fn_raw(
@@ -137,17 +155,63 @@ fn call_fn_with_constant_arguments(
             &mut Default::default(),
             state.lib,
             fn_name,
-            (hash_fn, 0),
+            0,
             arg_values.iter_mut().collect::<StaticVec<_>>().as_mut(),
             false,
             false,
             None,
+            None,
             0,
         )
         .map(|(v, _)| Some(v))
         .unwrap_or_else(|_| None)
 }
 
+/// Is the native function called with a list of constant arguments known to be pure?
+///
+/// Functions not registered in the `Engine` are pure only if they resolve to built-in operators.
+fn is_pure_fn_call(state: &State, fn_name: &str, arg_values: &[Dynamic]) -> bool {
+    let hash_fn = calc_fn_hash(
+        empty(),
+        fn_name,
+        arg_values.len(),
+        arg_values.iter().map(|a| a.type_id()),
+    );
+
+    // Registered native functions override packages
+    match once(&state.engine.global_module)
+        .chain(state.engine.packages.iter())
+        .find(|m| m.contains_fn(hash_fn))
+    {
+        Some(m) => m.is_fn_pure(hash_fn),
+        None if fn_name == KEYWORD_TYPE_OF => arg_values.len() == 1,
+        // Built-in operators that fail (e.g. on overflow) are still pure
+        None => match arg_values {
+            [x, y] => run_builtin_binary_op(fn_name, x, y).map_or(true, |r| r.is_some()),
+            _ => false,
+        },
+    }
+}
+
+/// Are all native functions with a particular name and number of parameters known to be pure?
+///
+/// Functions not registered in the `Engine` are pure only if they are binary operators, which
+/// resolve to built-in operators.
+#[cfg(not(feature = "no_function"))]
+fn is_pure_fn(state: &State, fn_name: &str, num_args: usize) -> bool {
+    let mut fns = once(&state.engine.global_module)
+        .chain(state.engine.packages.iter())
+        .flat_map(|m| m.iter_fn())
+        .filter(|(name, _, params, _, _)| name == fn_name && params.len() == num_args)
+        .peekable();
+
+    if fns.peek().is_none() {
+        return num_args == 2 && !is_valid_identifier(fn_name.chars());
+    }
+
+    fns.all(|(_, _, _, _, purity)| *purity == Some(true))
+}
+
 /// Optimize a statement.
 fn optimize_stmt(stmt: Stmt, state: &mut State, preserve_result: bool) -> Stmt {
     match stmt {
@@ -246,18 +310,30 @@ fn optimize_stmt(stmt: Stmt, state: &mut State, preserve_result: bool) -> Stmt {
             optimize_stmt(x.2, state, false),
         ))),
         // let id = expr;
-        Stmt::Let(x) if x.1.is_some() => {
-            Stmt::Let(Box::new((x.0, Some(optimize_expr(x.1.unwrap(), state)))))
-        }
+        Stmt::Let(x) if x.1.is_some() => Stmt::Let(Box::new((
+            x.0,
+            Some(optimize_expr(x.1.unwrap(), state)),
+            x.2,
+            x.3,
+        ))),
         // let id;
         stmt @ Stmt::Let(_) => stmt,
         // import expr as id;
         #[cfg(not(feature = "no_module"))]
-        Stmt::Import(x) => Stmt::Import(Box::new((optimize_expr(x.0, state), x.1))),
+        Stmt::Import(x) if matches!(x.1, ImportNames::Alias(_)) => {
+            Stmt::Import(Box::new((optimize_expr(x.0, state), x.1, x.2)))
+        }
+        // import { ... } from expr; or import * from expr;
+        #[cfg(not(feature = "no_module"))]
+        Stmt::Import(x) => {
+            state.namespace_imports += 1;
+            Stmt::Import(Box::new((optimize_expr(x.0, state), x.1, x.2)))
+        }
         // { block }
         Stmt::Block(x) => {
             let orig_len = x.0.len(); // Original number of statements in the block, for change detection
             let orig_constants_len = state.constants.len(); // Original number of constants in the state, for restore later
+            let orig_namespace_imports = state.namespace_imports;
             let pos = x.1;
 
             // Optimize each statement in the block
@@ -266,10 +342,10 @@ fn optimize_stmt(stmt: Stmt, state: &mut State, preserve_result: bool) -> Stmt {
                     .map(|stmt| match stmt {
                         // Add constant into the state
                         Stmt::Const(v) => {
-                            let ((name, pos), expr) = *v;
-                            state.push_constant(&name, expr);
-                            state.set_dirty();
-                            Stmt::Noop(pos) // No need to keep constants
+                            let ((name, _), expr, _, _) = v.as_ref();
+                            state.push_constant(name, expr.clone());
+                            // Keep the constant so that the variable slots resolved during parsing stay valid
+                            Stmt::Const(v)
                         }
                         // Optimize the statement
                         _ => optimize_stmt(stmt, state, preserve_result),
@@ -285,7 +361,7 @@ fn optimize_stmt(stmt: Stmt, state: &mut State, preserve_result: bool) -> Stmt {
                 result.push(stmt);
             }
 
-            // Remove all let/import statements at the end of a block - the new variables will go away anyway.
+            // Remove all let/const/import statements at the end of a block - the new variables will go away anyway.
             // But be careful only remove ones that have no initial values or have values that are pure expressions,
             // otherwise there may be side effects.
             let mut removed = false;
@@ -293,6 +369,7 @@ fn optimize_stmt(stmt: Stmt, state: &mut State, preserve_result: bool) -> Stmt {
             while let Some(expr) = result.pop() {
                 match expr {
                     Stmt::Let(x) if x.1.is_none() => removed = true,
+                    Stmt::Const(_) | Stmt::Noop(_) => removed = true,
                     Stmt::Let(x) if x.1.is_some() => removed = x.1.unwrap().is_pure(),
                     #[cfg(not(feature = "no_module"))]
                     Stmt::Import(x) => removed = x.0.is_pure(),
@@ -343,6 +420,7 @@ fn optimize_stmt(stmt: Stmt, state: &mut State, preserve_result: bool) -> Stmt {
 
             // Pop the stack and remove all the local constants
             state.restore_constants(orig_constants_len);
+            state.namespace_imports = orig_namespace_imports;
 
             match result[..] {
                 // No statements in block - change to No-op
@@ -350,8 +428,8 @@ fn optimize_stmt(stmt: Stmt, state: &mut State, preserve_result: bool) -> Stmt {
                     state.set_dirty();
                     Stmt::Noop(pos)
                 }
-                // Only one let statement - leave it alone
-                [Stmt::Let(_)] => Stmt::Block(Box::new((result.into(), pos))),
+                // Only one let/const statement - leave it alone
+                [Stmt::Let(_)] | [Stmt::Const(_)] => Stmt::Block(Box::new((result.into(), pos))),
                 // Only one import statement - leave it alone
                 #[cfg(not(feature = "no_module"))]
                 [Stmt::Import(_)] => Stmt::Block(Box::new((result.into(), pos))),
@@ -398,7 +476,7 @@ fn optimize_expr(expr: Expr, state: &mut State) -> Expr {
             stmt => Expr::Stmt(Box::new((stmt, x.1))),
         },
         // id op= expr
-        Expr::Assignment(x) => Expr::Assignment(Box::new((x.0, x.1, optimize_expr(x.2, state), x.3))),
+        Expr::Assignment(x) => Expr::Assignment(Box::new((x.0, x.1, optimize_expr(x.2, state), x.3, x.4))),
 
         // lhs.rhs
         #[cfg(not(feature = "no_object"))]
@@ -554,22 +632,41 @@ fn optimize_expr(expr: Expr, state: &mut State) -> Expr {
             }
         }
 
+        // Inline small pure script-defined functions
+        #[cfg(not(feature = "no_function"))]
+        Expr::FnCall(mut x)
+                if x.1.is_none() // Non-qualified
+                && !(x.0).1 // Not native only
+                && state.optimization_level == OptimizationLevel::Full // full optimizations
+                && state.namespace_imports == 0 // no imported functions can override the call
+                && matches!(state.lib.get_fn(x.2), Some(f) if f.is_script()) // script-defined function
+        => {
+            x.3 = x.3.into_iter().map(|a| optimize_expr(a, state)).collect();
+
+            let fn_def = state.lib.get_fn(x.2).unwrap().get_fn_def();
+
+            match inline_fn_call(fn_def, &x.3, state) {
+                Some(expr) => {
+                    state.set_dirty();
+                    optimize_expr(expr.set_position((x.0).2), state)
+                }
+                None => Expr::FnCall(x),
+            }
+        }
+
         // Eagerly call functions
         Expr::FnCall(mut x)
                 if x.1.is_none() // Non-qualified
                 && state.optimization_level == OptimizationLevel::Full // full optimizations
                 && x.3.iter().all(|expr| expr.is_constant()) // all arguments are constants
+                && state.namespace_imports == 0 // no imported functions can override the call
         => {
-            let ((name, _, pos), _, _, args, def_value) = x.as_mut();
+            let ((name, _, pos), _, _, args, def_value, _) = x.as_mut();
 
             // First search in functions lib (can override built-in)
             // Cater for both normal function call style and method call style (one additional arguments)
             #[cfg(not(feature = "no_function"))]
-            let _has_script_fn = state.lib.iter_fn().find(|(_, _, _, f)| {
-                if !f.is_script() { return false; }
-                let fn_def = f.get_fn_def();
-                fn_def.name.as_str() == name && (args.len()..=args.len() + 1).contains(&fn_def.params.len())
-            }).is_some();
+            let _has_script_fn = has_script_fn(state.lib, name, args.len());
 
             #[cfg(feature = "no_function")]
             let _has_script_fn: bool = false;
@@ -582,6 +679,12 @@ fn optimize_expr(expr: Expr, state: &mut State) -> Expr {
 
             let mut arg_values: StaticVec<_> = args.iter().map(Expr::get_constant_value).collect();
 
+            if !is_pure_fn_call(state, name, &arg_values) {
+                // Functions with side effects must be called at runtime
+                x.3 = x.3.into_iter().map(|a| optimize_expr(a, state)).collect();
+                return Expr::FnCall(x);
+            }
+
             // Save the typename of the first argument if it is `type_of()`
             // This is to avoid `call_args` being passed into the closure
             let arg_for_type_of = if name == KEYWORD_TYPE_OF && arg_values.len() == 1 {
@@ -641,6 +744,253 @@ fn optimize_expr(expr: Expr, state: &mut State) -> Expr {
     }
 }
 
+/// Maximum number of nodes in the body of a script-defined function for it to be inlined.
+#[cfg(not(feature = "no_function"))]
+const MAX_INLINE_FN_SIZE: usize = 32;
+
+/// Get the body of a script-defined function if it consists of a single expression.
+#[cfg(not(feature = "no_function"))]
+fn get_single_expr(stmt: &Stmt) -> Option<&Expr> {
+    match stmt {
+        Stmt::Expr(expr) => Some(expr),
+        Stmt::ReturnWithVal(x) if (x.0).0 == ReturnType::Return => x.1.as_ref(),
+        Stmt::Block(x) if x.0.len() == 1 => get_single_expr(&x.0[0]),
+        _ => None,
+    }
+}
+
+/// Calculate the number of nodes in an expression that can be inlined.
+///
+/// Returns `None` if the expression refers to anything other than the parameters,
+/// or may have side effects other than calling native functions.
+#[cfg(not(feature = "no_function"))]
+fn inlinable_size(expr: &Expr, params: &[String], state: &State) -> Option<usize> {
+    const DONT_INLINE_KEYWORDS: [&str; 6] = [
+        KEYWORD_PRINT,
+        KEYWORD_DEBUG,
+        KEYWORD_EVAL,
+        KEYWORD_FN_PTR_CALL,
+        KEYWORD_FN_PTR_CURRY,
+        KEYWORD_SHARED,
+    ];
+
+    match expr {
+        Expr::Expr(x) => inlinable_size(x, params, state),
+        Expr::Variable(x) if x.1.is_none() && params.contains(&(x.0).0) => Some(1),
+        Expr::Property(_) => Some(1),
+        // lhs.prop
+        #[cfg(not(feature = "no_object"))]
+        Expr::Dot(x) if matches!(x.1, Expr::Property(_)) => {
+            inlinable_size(&x.0, params, state).map(|n| n + 2)
+        }
+        Expr::Index(x) | Expr::And(x) | Expr::Or(x) | Expr::In(x) => {
+            let lhs = inlinable_size(&x.0, params, state)?;
+            let rhs = inlinable_size(&x.1, params, state)?;
+            Some(lhs + rhs + 1)
+        }
+        #[cfg(not(feature = "no_index"))]
+        Expr::Array(x) => x.0.iter().try_fold(1, |n, expr| {
+            inlinable_size(expr, params, state).map(|m| n + m)
+        }),
+        #[cfg(not(feature = "no_object"))]
+        Expr::Map(x) => x.0.iter().try_fold(1, |n, (_, expr)| {
+            inlinable_size(expr, params, state).map(|m| n + m)
+        }),
+        // Only native functions can be called, which also rules out recursion
+        Expr::FnCall(x)
+            if x.1.is_none()
+                && !DONT_INLINE_KEYWORDS.contains(&(x.0).0.as_ref())
+                && !has_script_fn(state.lib, &(x.0).0, x.3.len())
+                && is_pure_fn(state, &(x.0).0, x.3.len()) =>
+        {
+            x.3.iter().try_fold(1, |n, expr| {
+                inlinable_size(expr, params, state).map(|m| n + m)
+            })
+        }
+        expr if expr.is_constant() => Some(1),
+        _ => None,
+    }
+}
+
+/// Count the uses of a parameter in an inlinable expression, and check whether it is passed as the
+/// first argument to a function other than an operator, which may modify it in place.
+#[cfg(not(feature = "no_function"))]
+fn param_uses(expr: &Expr, param: &str) -> (usize, bool) {
+    let combine = |(n1, m1): (usize, bool), (n2, m2): (usize, bool)| (n1 + n2, m1 || m2);
+
+    match expr {
+        Expr::Expr(x) => param_uses(x, param),
+        Expr::Variable(x) if x.1.is_none() && (x.0).0 == param => (1, false),
+        Expr::Dot(x) | Expr::Index(x) | Expr::And(x) | Expr::Or(x) | Expr::In(x) => {
+            combine(param_uses(&x.0, param), param_uses(&x.1, param))
+        }
+        #[cfg(not(feature = "no_index"))]
+        Expr::Array(x) => {
+            x.0.iter()
+                .map(|expr| param_uses(expr, param))
+                .fold((0, false), combine)
+        }
+        #[cfg(not(feature = "no_object"))]
+        Expr::Map(x) => {
+            x.0.iter()
+                .map(|(_, expr)| param_uses(expr, param))
+                .fold((0, false), combine)
+        }
+        Expr::FnCall(x) => {
+            let mutated = is_valid_identifier((x.0).0.chars())
+                && match x.3.iter().next() {
+                    Some(Expr::Variable(v)) => v.1.is_none() && (v.0).0 == param,
+                    _ => false,
+                };
+
+            x.3.iter()
+                .map(|expr| param_uses(expr, param))
+                .fold((0, mutated), combine)
+        }
+        _ => (0, false),
+    }
+}
+
+/// Replace all parameters in an inlined expression with the arguments passed.
+#[cfg(not(feature = "no_function"))]
+fn substitute_params(expr: Expr, params: &[String], args: &[Expr]) -> Expr {
+    let substitute = |expr| substitute_params(expr, params, args);
+
+    match expr {
+        Expr::Expr(x) => Expr::Expr(Box::new(substitute(*x))),
+        Expr::Variable(x) if x.1.is_none() && params.contains(&(x.0).0) => {
+            let index = params.iter().position(|p| *p == (x.0).0).unwrap();
+
+            match &args[index] {
+                // Wrap variables so that they are always passed by value, as for a function call
+                arg @ Expr::Variable(_) => Expr::Expr(Box::new(arg.clone())),
+                arg => arg.clone(),
+            }
+        }
+        #[cfg(not(feature = "no_object"))]
+        Expr::Dot(x) => {
+            let (lhs, rhs, pos) = *x;
+            Expr::Dot(Box::new((substitute(lhs), rhs, pos)))
+        }
+        Expr::Index(x) => {
+            let (lhs, rhs, pos) = *x;
+            Expr::Index(Box::new((substitute(lhs), substitute(rhs), pos)))
+        }
+        Expr::And(x) => {
+            let (lhs, rhs, pos) = *x;
+            Expr::And(Box::new((substitute(lhs), substitute(rhs), pos)))
+        }
+        Expr::Or(x) => {
+            let (lhs, rhs, pos) = *x;
+            Expr::Or(Box::new((substitute(lhs), substitute(rhs), pos)))
+        }
+        Expr::In(x) => {
+            let (lhs, rhs, pos) = *x;
+            Expr::In(Box::new((substitute(lhs), substitute(rhs), pos)))
+        }
+        #[cfg(not(feature = "no_index"))]
+        Expr::Array(x) => {
+            let (items, pos) = *x;
+            Expr::Array(Box::new((items.into_iter().map(substitute).collect(), pos)))
+        }
+        #[cfg(not(feature = "no_object"))]
+        Expr::Map(x) => {
+            let (items, pos) = *x;
+            Expr::Map(Box::new((
+                items
+                    .into_iter()
+                    .map(|(key, expr)| (key, substitute(expr)))
+                    .collect(),
+                pos,
+            )))
+        }
+        Expr::FnCall(mut x) => {
+            x.3 = x.3.into_iter().map(substitute).collect();
+            Expr::FnCall(x)
+        }
+        expr => expr,
+    }
+}
+
+/// Calculate the nesting depth of an expression.
+#[cfg(not(feature = "no_function"))]
+#[cfg(not(feature = "unchecked"))]
+fn expr_depth(expr: &Expr) -> usize {
+    match expr {
+        Expr::Expr(x) => expr_depth(x),
+        Expr::Dot(x) | Expr::Index(x) | Expr::And(x) | Expr::Or(x) | Expr::In(x) => {
+            1 + expr_depth(&x.0).max(expr_depth(&x.1))
+        }
+        Expr::Array(x) => 1 + x.0.iter().map(expr_depth).max().unwrap_or(0),
+        Expr::Map(x) => 1 + x.0.iter().map(|(_, expr)| expr_depth(expr)).max().unwrap_or(0),
+        Expr::FnCall(x) => 1 + x.3.iter().map(expr_depth).max().unwrap_or(0),
+        _ => 1,
+    }
+}
+
+/// Inline a call to a small, non-recursive and side-effect-free script-defined function,
+/// substituting the arguments for the parameters.
+///
+/// Returns `None` if the function cannot be inlined.
+#[cfg(not(feature = "no_function"))]
+fn inline_fn_call(fn_def: &ScriptFnDef, args: &[Expr], state: &State) -> Option<Expr> {
+    // Type annotations are checked when the function is called
+    if fn_def.param_types.iter().any(Option::is_some) || fn_def.return_type.is_some() {
+        return None;
+    }
+
+    // Arguments may be substituted more than once, so they must be constants or variables
+    if !args
+        .iter()
+        .all(|arg| arg.is_constant() || matches!(arg, Expr::Variable(x) if x.1.is_none()))
+    {
+        return None;
+    }
+
+    let body = get_single_expr(&fn_def.body)?;
+
+    match inlinable_size(body, &fn_def.params, state) {
+        Some(size) if size <= MAX_INLINE_FN_SIZE => (),
+        _ => return None,
+    }
+
+    // Each use of a parameter is substituted by a copy of the argument, so a parameter that may be
+    // modified in place must not be used more than once
+    if fn_def.params.iter().any(|p| {
+        let (uses, mutated) = param_uses(body, p);
+        mutated && uses > 1
+    }) {
+        return None;
+    }
+
+    let expr = substitute_params(body.clone(), &fn_def.params, args);
+
+    // The inlined expression must not be nested deeper than allowed for a function body
+    #[cfg(not(feature = "unchecked"))]
+    {
+        let max_depth = state.engine.limits.max_function_expr_depth;
+
+        if max_depth > 0 && expr_depth(&expr) > max_depth {
+            return None;
+        }
+    }
+
+    Some(expr)
+}
+
+/// Is there a script-defined function with a particular name, called either in normal function-call
+/// style or in method-call style (one additional argument)?
+#[cfg(not(feature = "no_function"))]
+fn has_script_fn(lib: &Module, name: &str, num_args: usize) -> bool {
+    lib.iter_fn().any(|(_, _, _, f, _)| {
+        if !f.is_script() {
+            return false;
+        }
+        let fn_def = f.get_fn_def();
+        fn_def.name.as_str() == name && (num_args..=num_args + 1).contains(&fn_def.params.len())
+    })
+}
+
 fn optimize(
     statements: Vec<Stmt>,
     engine: &Engine,
@@ -676,6 +1026,7 @@ fn optimize(
     loop {
         state.reset();
         state.restore_constants(orig_constants_len);
+        state.namespace_imports = 0;
 
         let num_statements = result.len();
 
@@ -686,7 +1037,7 @@ fn optimize(
                 match &stmt {
                     Stmt::Const(v) => {
                         // Load constants
-                        let ((name, _), expr) = v.as_ref();
+                        let ((name, _), expr, _, _) = v.as_ref();
                         state.push_constant(&name, expr.clone());
                         stmt // Keep it in the global scope
                     }
@@ -705,6 +1056,12 @@ fn optimize(
             })
             .collect();
 
+        // Run custom passes
+        #[cfg(not(feature = "no_optimize"))]
+        if run_passes(&mut result, &engine.optimizer_passes) {
+            state.set_dirty();
+        }
+
         if !state.is_dirty() {
             break;
         }
@@ -726,6 +1083,342 @@ fn optimize(
     result
 }
 
+/// Mutable state throughout a type-checking pass.
+struct TypeCheckState<'a> {
+    /// An `Engine` instance for looking up registered functions.
+    engine: &'a Engine,
+    /// Script-defined functions.
+    functions: &'a [ScriptFnDef],
+    /// Stack of variables in scope, together with their type annotations (if any).
+    stack: Vec<(&'a str, Option<&'a str>)>,
+    /// Stack of names imported into the local namespace, with `None` for `import * from`.
+    imports: Vec<Option<&'a str>>,
+}
+
+impl<'a> TypeCheckState<'a> {
+    /// Find the type annotation of a variable in scope.
+    fn find_type(&self, name: &str) -> Option<&'a str> {
+        self.stack
+            .iter()
+            .rev()
+            .find(|(n, _)| *n == name)
+            .and_then(|(_, typ)| *typ)
+    }
+    /// Find the return type annotation of a script-defined function.
+    fn find_return_type(&self, name: &str, num_params: usize) -> Option<&'a str> {
+        self.functions
+            .iter()
+            .find(|f| f.name.as_str() == name && f.params.len() == num_params)
+            .and_then(|f| f.return_type.as_deref())
+    }
+    /// Can a name refer to a function imported into the local namespace?
+    fn is_imported(&self, name: &str) -> bool {
+        self.imports.iter().any(|n| match n {
+            Some(n) => *n == name,
+            None => true, // import * from ...
+        })
+    }
+    /// Is there a script-defined function with a particular name and number of parameters?
+    fn is_script_fn(&self, name: &str, num_params: usize) -> bool {
+        self.functions
+            .iter()
+            .any(|f| f.name.as_str() == name && f.params.len() == num_params)
+    }
+}
+
+/// Get the type annotation of an expression, i.e. of an annotated variable or of a call to
+/// a script-defined function with an annotated return type.
+fn annotated_type_of<'a>(expr: &'a Expr, state: &TypeCheckState<'a>) -> Option<&'a str> {
+    match expr {
+        Expr::Expr(x) => annotated_type_of(x, state),
+        Expr::Variable(x) if x.1.is_none() => state.find_type(&(x.0).0),
+        Expr::FnCall(x) if x.1.is_none() => state.find_return_type(&(x.0).0, x.3.len()),
+        _ => None,
+    }
+}
+
+/// Get the name of the type of an expression, if it can be determined statically.
+fn static_type_of<'a>(expr: &'a Expr, state: &TypeCheckState<'a>) -> Option<Cow<'a, str>> {
+    match expr {
+        expr if expr.is_constant() => Some(expr.get_constant_value().type_name().into()),
+        expr => annotated_type_of(expr, state).map(|typ| map_type_annotation(typ).into()),
+    }
+}
+
+/// Map the name of a standard type to its `TypeId`.
+fn type_id_of(name: &str) -> Option<TypeId> {
+    if name == type_name::<INT>() {
+        return Some(TypeId::of::<INT>());
+    }
+    #[cfg(not(feature = "no_float"))]
+    if name == type_name::<FLOAT>() {
+        return Some(TypeId::of::<FLOAT>());
+    }
+    #[cfg(not(feature = "no_index"))]
+    if name == "array" {
+        return Some(TypeId::of::<Array>());
+    }
+    #[cfg(not(feature = "no_object"))]
+    if name == "map" {
+        return Some(TypeId::of::<Map>());
+    }
+
+    match name {
+        "()" => Some(TypeId::of::<()>()),
+        "bool" => Some(TypeId::of::<bool>()),
+        "char" => Some(TypeId::of::<char>()),
+        "string" => Some(TypeId::of::<ImmutableString>()),
+        "Fn" => Some(TypeId::of::<FnPtr>()),
+        _ => None,
+    }
+}
+
+/// Make sure that an expression matches a type annotation, if its type can be determined statically.
+fn check_annotation<'a>(
+    expr: &'a Expr,
+    typ: &str,
+    pos: Position,
+    state: &TypeCheckState<'a>,
+) -> Result<(), ParseError> {
+    match static_type_of(expr, state) {
+        Some(name) if !state.engine.matches_type(&name, typ) => Err(PERR::MismatchedType(
+            typ.to_string(),
+            state.engine.map_type_name(&name).to_string(),
+        )
+        .into_err(pos)),
+        _ => Ok(()),
+    }
+}
+
+/// Check a function call against the signatures of functions registered in the `Engine`.
+fn check_fn_call<'a>(
+    name: &str,
+    args: &'a [Expr],
+    pos: Position,
+    state: &TypeCheckState<'a>,
+) -> Result<(), ParseError> {
+    // Only check calls with arguments of annotated types, when enabled
+    if !state.engine.check_fn_signatures
+        || args
+            .iter()
+            .all(|arg| annotated_type_of(arg, state).is_none())
+    {
+        return Ok(());
+    }
+
+    // Skip operators, special functions and calls to script-defined or imported functions
+    if !is_valid_identifier(name.chars())
+        || [
+            KEYWORD_PRINT,
+            KEYWORD_DEBUG,
+            KEYWORD_TYPE_OF,
+            KEYWORD_EVAL,
+            KEYWORD_FN_PTR,
+            KEYWORD_FN_PTR_CALL,
+            KEYWORD_FN_PTR_CURRY,
+            KEYWORD_SHARED,
+        ]
+        .contains(&name)
+        || state.is_script_fn(name, args.len())
+        || state.is_imported(name)
+    {
+        return Ok(());
+    }
+
+    // Only check if the types of all arguments are known
+    let arg_types: Option<StaticVec<_>> = args.iter().map(|a| static_type_of(a, state)).collect();
+
+    let arg_types = match arg_types {
+        Some(types) => types,
+        None => return Ok(()),
+    };
+
+    let type_ids: Option<StaticVec<_>> = arg_types.iter().map(|t| type_id_of(t)).collect();
+
+    let type_ids = match type_ids {
+        Some(ids) => ids,
+        None => return Ok(()),
+    };
+
+    let hash_fn = calc_fn_hash(empty(), name, args.len(), type_ids.iter().cloned());
+
+    if state.engine.global_module.contains_fn(hash_fn) || state.engine.packages.contains_fn(hash_fn)
+    {
+        return Ok(());
+    }
+
+    // Only report a mismatch when there are registered functions of the same name and arity
+    let registered = once(&state.engine.global_module)
+        .chain(state.engine.packages.iter())
+        .flat_map(|m| m.iter_fn())
+        .any(|(n, _, types, _, _)| n == name && types.len() == args.len());
+
+    if !registered {
+        return Ok(());
+    }
+
+    Err(PERR::MismatchedFnArgs(format!(
+        "{} ({})",
+        name,
+        arg_types
+            .iter()
+            .map(|t| state.engine.map_type_name(t))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))
+    .into_err(pos))
+}
+
+/// Type-check a statement.
+fn check_stmt<'a>(stmt: &'a Stmt, state: &mut TypeCheckState<'a>) -> Result<(), ParseError> {
+    match stmt {
+        Stmt::IfThenElse(x) => {
+            check_expr(&x.0, state)?;
+            check_stmt(&x.1, state)?;
+            x.2.as_ref().map_or(Ok(()), |stmt| check_stmt(stmt, state))
+        }
+        Stmt::While(x) => {
+            check_expr(&x.0, state)?;
+            check_stmt(&x.1, state)
+        }
+        Stmt::Loop(x) => check_stmt(x, state),
+        Stmt::For(x) => {
+            check_expr(&x.1, state)?;
+            state.stack.push((&x.0, None));
+            check_stmt(&x.2, state)?;
+            state.stack.pop();
+            Ok(())
+        }
+        Stmt::Let(x) => {
+            let ((name, pos), expr, typ, _) = x.as_ref();
+
+            if let Some(expr) = expr {
+                check_expr(expr, state)?;
+
+                if let Some(typ) = typ {
+                    check_annotation(expr, typ, *pos, state)?;
+                }
+            }
+
+            state.stack.push((name, typ.as_deref()));
+            Ok(())
+        }
+        Stmt::Const(x) => {
+            let ((name, pos), expr, typ, _) = x.as_ref();
+
+            if let Some(typ) = typ {
+                check_annotation(expr, typ, *pos, state)?;
+            }
+
+            state.stack.push((name, typ.as_deref()));
+            Ok(())
+        }
+        Stmt::Block(x) => {
+            let prev_len = state.stack.len();
+            let prev_imports_len = state.imports.len();
+            let result = x.0.iter().try_for_each(|stmt| check_stmt(stmt, state));
+            state.stack.truncate(prev_len);
+            state.imports.truncate(prev_imports_len);
+            result
+        }
+        Stmt::Expr(x) => check_expr(x, state),
+        Stmt::ReturnWithVal(x) => x.1.as_ref().map_or(Ok(()), |expr| check_expr(expr, state)),
+        #[cfg(not(feature = "no_module"))]
+        Stmt::Import(x) => {
+            match &x.1 {
+                ImportNames::Alias(_) => (),
+                ImportNames::Members(members) => {
+                    state
+                        .imports
+                        .extend(members.iter().map(|((id, _), rename)| {
+                            Some(rename.as_ref().map_or(id, |(name, _)| name).as_str())
+                        }))
+                }
+                ImportNames::All => state.imports.push(None),
+            }
+            check_expr(&x.0, state)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Type-check an expression.
+fn check_expr<'a>(expr: &'a Expr, state: &mut TypeCheckState<'a>) -> Result<(), ParseError> {
+    match expr {
+        Expr::Stmt(x) => check_stmt(&x.0, state),
+        Expr::Expr(x) => check_expr(x, state),
+        Expr::FnCall(x) => {
+            x.3.iter().try_for_each(|arg| check_expr(arg, state))?;
+
+            match x.as_ref() {
+                ((name, _, pos), None, _, args, _, _) => check_fn_call(name, args, *pos, state),
+                _ => Ok(()),
+            }
+        }
+        Expr::Assignment(x) => {
+            check_expr(&x.0, state)?;
+            check_expr(&x.2, state)?;
+
+            match x.as_ref() {
+                (lhs, op, rhs, _, Some(typ)) if op.is_empty() => {
+                    check_annotation(rhs, typ, lhs.position(), state)
+                }
+                _ => Ok(()),
+            }
+        }
+        // Method calls are not checked, only their arguments
+        Expr::Dot(x) => {
+            check_expr(&x.0, state)?;
+
+            match &x.1 {
+                Expr::FnCall(x) => x.3.iter().try_for_each(|arg| check_expr(arg, state)),
+                _ => Ok(()),
+            }
+        }
+        Expr::Index(x) | Expr::In(x) | Expr::And(x) | Expr::Or(x) => {
+            check_expr(&x.0, state)?;
+            check_expr(&x.1, state)
+        }
+        Expr::Array(x) => x.0.iter().try_for_each(|item| check_expr(item, state)),
+        Expr::Map(x) => x.0.iter().try_for_each(|(_, item)| check_expr(item, state)),
+        _ => Ok(()),
+    }
+}
+
+/// Check an AST against type annotations and, if enabled via `Engine::set_check_fn_signatures`,
+/// the signatures of functions registered in the `Engine`.
+///
+/// Only types that can be determined statically (i.e. constants, annotated variables and
+/// script-defined functions with annotated return types) are checked.
+pub(crate) fn check_types(
+    engine: &Engine,
+    statements: &[Stmt],
+    functions: &[ScriptFnDef],
+) -> Result<(), ParseError> {
+    let mut state = TypeCheckState {
+        engine,
+        functions,
+        stack: Default::default(),
+        imports: Default::default(),
+    };
+
+    statements
+        .iter()
+        .try_for_each(|stmt| check_stmt(stmt, &mut state))?;
+
+    functions.iter().try_for_each(|fn_def| {
+        state.stack = fn_def
+            .params
+            .iter()
+            .zip(fn_def.param_types.iter())
+            .map(|(name, typ)| (name.as_str(), typ.as_deref()))
+            .collect();
+        state.imports.clear();
+
+        check_stmt(&fn_def.body, &mut state)
+    })
+}
+
 /// Optimize an AST.
 pub fn optimize_into_ast(
     engine: &Engine,
@@ -742,7 +1435,8 @@ pub fn optimize_into_ast(
         let mut module = Module::new();
 
         if !level.is_none() {
-            // We only need the script library's signatures for optimization purposes
+            // We only need the script library's signatures for optimization purposes,
+            // plus the function bodies under full optimization for inlining
             let mut lib2 = Module::new();
 
             _functions
@@ -751,8 +1445,16 @@ pub fn optimize_into_ast(
                     ScriptFnDef {
                         name: fn_def.name.clone(),
                         access: fn_def.access,
-                        body: Default::default(),
+                        body: match level {
+                            OptimizationLevel::Full => fn_def.body.clone(),
+                            _ => Default::default(),
+                        },
                         params: fn_def.params.clone(),
+                        param_types: fn_def.param_types.clone(),
+                        return_type: fn_def.return_type.clone(),
+                        comments: Default::default(),
+                        uses_global: fn_def.uses_global,
+                        source: fn_def.source.clone(),
                         pos: fn_def.pos,
                     }
                     .into()
@@ -771,9 +1473,11 @@ pub fn optimize_into_ast(
 
                     // {} -> Noop
                     fn_def.body = match body.pop().unwrap_or_else(|| Stmt::Noop(pos)) {
-                        // { return val; } -> val
+                        // { return val; } -> val, but keep { return f(...); } as a tail call
                         Stmt::ReturnWithVal(x)
-                            if x.1.is_some() && (x.0).0 == ReturnType::Return =>
+                            if x.1.is_some()
+                                && !matches!(x.1, Some(Expr::FnCall(_)))
+                                && (x.0).0 == ReturnType::Return =>
                         {
                             Stmt::Expr(Box::new(x.1.unwrap()))
                         }
@@ -0,0 +1,136 @@
+//! Module defining options overriding the defaults of an `Engine` for a single evaluation.
+
+#[cfg(not(feature = "no_optimize"))]
+use crate::optimize::OptimizationLevel;
+
+#[cfg(not(feature = "unchecked"))]
+use crate::engine::Limits;
+
+#[cfg(not(feature = "unchecked"))]
+#[cfg(not(feature = "no_std"))]
+use crate::stdlib::time::Duration;
+
+/// Options overriding the defaults of an `Engine` for a single evaluation, passed to
+/// `Engine::eval_ast_with_scope_and_options`, `Engine::consume_ast_with_scope_and_options`
+/// or `Engine::call_fn_dynamic_with_options`.
+///
+/// Each option left as `None` keeps the setting of the `Engine`.  As with the `Engine` settings,
+/// zero means unlimited for all limits except `max_call_levels`.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+/// # #[cfg(not(feature = "unchecked"))]
+/// # {
+/// use rhai::{Engine, EvalAltResult, EvalOptions, Scope};
+///
+/// let engine = Engine::new();
+///
+/// let ast = engine.compile("let x = 0; while x < 1000 { x += 1; } x")?;
+///
+/// let options = EvalOptions {
+///     max_operations: Some(500),
+///     ..Default::default()
+/// };
+///
+/// let result = engine.eval_ast_with_scope_and_options::<i64>(&mut Scope::new(), &ast, &options);
+///
+/// assert!(matches!(*result.expect_err("should error"), EvalAltResult::ErrorTooManyOperations(_)));
+///
+/// // The limits of the Engine itself are unchanged
+/// assert_eq!(engine.eval_ast::<i64>(&ast)?, 1000);
+/// # }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct EvalOptions {
+    /// Maximum number of operations allowed to run.
+    ///
+    /// Not available under the `unchecked` feature.
+    #[cfg(not(feature = "unchecked"))]
+    pub max_operations: Option<u64>,
+    /// Maximum levels of function calls.
+    ///
+    /// Not available under the `unchecked` feature.
+    #[cfg(not(feature = "unchecked"))]
+    pub max_call_levels: Option<usize>,
+    /// Maximum number of modules allowed to load.
+    ///
+    /// Not available under the `unchecked` feature.
+    #[cfg(not(feature = "unchecked"))]
+    pub max_modules: Option<usize>,
+    /// Maximum length of strings.
+    ///
+    /// Not available under the `unchecked` feature.
+    #[cfg(not(feature = "unchecked"))]
+    pub max_string_size: Option<usize>,
+    /// Maximum length of arrays.
+    ///
+    /// Not available under the `unchecked` or `no_index` features.
+    #[cfg(not(feature = "unchecked"))]
+    #[cfg(not(feature = "no_index"))]
+    pub max_array_size: Option<usize>,
+    /// Maximum number of properties in object maps.
+    ///
+    /// Not available under the `unchecked` or `no_object` features.
+    #[cfg(not(feature = "unchecked"))]
+    #[cfg(not(feature = "no_object"))]
+    pub max_map_size: Option<usize>,
+    /// Maximum number of bytes held by the values of the script, approximately.
+    ///
+    /// Not available under the `unchecked` feature.
+    #[cfg(not(feature = "unchecked"))]
+    pub max_memory: Option<usize>,
+    /// Maximum time allowed for the script to run.
+    ///
+    /// Not available under the `unchecked` or `no_std` features.
+    #[cfg(not(feature = "unchecked"))]
+    #[cfg(not(feature = "no_std"))]
+    pub max_execution_time: Option<Duration>,
+    /// Optimization level to optimize the `AST` again at, with the constants in the scope,
+    /// before running it.  Not used when calling a function.
+    ///
+    /// Not available under the `no_optimize` feature.
+    #[cfg(not(feature = "no_optimize"))]
+    pub optimization_level: Option<OptimizationLevel>,
+}
+
+impl EvalOptions {
+    /// Apply the limits overridden by this to the limits of an `Engine`.
+    #[cfg(not(feature = "unchecked"))]
+    pub(crate) fn apply_to(&self, limits: &Limits) -> Limits {
+        let mut limits = limits.clone();
+
+        if let Some(n) = self.max_operations {
+            limits.max_operations = n;
+        }
+        if let Some(n) = self.max_call_levels {
+            limits.max_call_stack_depth = n;
+        }
+        if let Some(n) = self.max_modules {
+            limits.max_modules = n;
+        }
+        if let Some(n) = self.max_string_size {
+            limits.max_string_size = n;
+        }
+        #[cfg(not(feature = "no_index"))]
+        if let Some(n) = self.max_array_size {
+            limits.max_array_size = n;
+        }
+        #[cfg(not(feature = "no_object"))]
+        if let Some(n) = self.max_map_size {
+            limits.max_map_size = n;
+        }
+        if let Some(n) = self.max_memory {
+            limits.max_memory = n;
+        }
+        #[cfg(not(feature = "no_std"))]
+        if let Some(time) = self.max_execution_time {
+            limits.max_execution_time = time;
+        }
+
+        limits
+    }
+}
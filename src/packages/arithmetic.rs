@@ -451,4 +451,7 @@ def_package!(crate:ArithmeticPackage:"Basic arithmetic", lib, {
             }
         }
     }
+
+    // All built-in functions in this package are pure
+    lib.set_unmarked_fns_pure();
 });
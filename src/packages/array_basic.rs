@@ -176,4 +176,7 @@ def_package!(crate:BasicArrayPackage:"Basic array utilities.", lib, {
         TypeId::of::<Array>(),
         |arr| Box::new(arr.cast::<Array>().into_iter()) as Box<dyn Iterator<Item = Dynamic>>,
     );
+
+    // All built-in functions in this package are pure
+    lib.set_unmarked_fns_pure();
 });
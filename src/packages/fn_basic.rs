@@ -6,4 +6,7 @@ def_package!(crate:BasicFnPackage:"Basic Fn functions.", lib, {
 
     #[cfg(not(feature = "no_object"))]
     lib.set_getter_fn("name", |f: &mut FnPtr| Ok(f.get_fn_name().clone()));
+
+    // All built-in functions in this package are pure
+    lib.set_unmarked_fns_pure();
 });
@@ -111,4 +111,7 @@ def_package!(crate:BasicIteratorPackage:"Basic range iterators.", lib, {
         #[cfg(not(target_arch = "wasm32"))]
         reg_step!(lib, "range", i128, u128);
     }
+
+    // All built-in functions in this package are pure
+    lib.set_unmarked_fns_pure();
 });
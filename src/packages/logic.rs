@@ -65,4 +65,7 @@ def_package!(crate:LogicPackage:"Logical operators.", lib, {
     }
 
     lib.set_fn_1("!", not);
+
+    // All built-in functions in this package are pure
+    lib.set_unmarked_fns_pure();
 });
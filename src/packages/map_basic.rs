@@ -79,4 +79,7 @@ def_package!(crate:BasicMapPackage:"Basic object map utilities.", lib, {
 
     #[cfg(not(feature = "no_index"))]
     lib.set_fn_1_mut("values", map_get_values);
+
+    // All built-in functions in this package are pure
+    lib.set_unmarked_fns_pure();
 });
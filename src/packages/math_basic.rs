@@ -147,4 +147,7 @@ def_package!(crate:BasicMathPackage:"Basic mathematic functions.", lib, {
             lib.set_fn_1("to_int", |x: f64| Ok(x as INT));
         }
     }
+
+    // All built-in functions in this package are pure
+    lib.set_unmarked_fns_pure();
 });
@@ -72,6 +72,10 @@ impl PackagesCollection {
             .find(|f| f.is_some())
             .flatten()
     }
+    /// Get an iterator over all the packages in the `PackagesCollection`.
+    pub fn iter(&self) -> impl Iterator<Item = &Module> {
+        self.0.iter().map(|p| p.as_ref())
+    }
     /// Does the specified TypeId iterator exist in the `PackagesCollection`?
     #[allow(dead_code)]
     pub fn contains_iter(&self, id: TypeId) -> bool {
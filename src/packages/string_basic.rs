@@ -91,4 +91,7 @@ def_package!(crate:BasicStringPackage:"Basic string utilities, including printin
     lib.set_fn_2_mut("+=", |s: &mut ImmutableString, ch: char| { *s += ch; Ok(()) });
     lib.set_fn_2_mut("append", |s: &mut ImmutableString, ch: char| { *s += ch; Ok(()) });
     lib.set_fn_2_mut("append", |s: &mut ImmutableString, s2: ImmutableString| { *s += &s2; Ok(()) });
+
+    // All built-in functions in this package are pure
+    lib.set_unmarked_fns_pure();
 });
@@ -316,4 +316,7 @@ def_package!(crate:MoreStringPackage:"Additional string utilities, including str
             arr.cast::<ImmutableString>().chars().collect::<Vec<_>>().into_iter().map(Into::into)
         ) as Box<dyn Iterator<Item = Dynamic>>,
     );
+
+    // All built-in functions in this package are pure
+    lib.set_unmarked_fns_pure();
 });
@@ -26,7 +26,8 @@ use instant::Instant;
 
 def_package!(crate:BasicTimePackage:"Basic timing utilities.", lib, {
     // Register date/time functions
-    lib.set_fn_0("timestamp", || Ok(Instant::now()));
+    let hash = lib.set_fn_0("timestamp", || Ok(Instant::now()));
+    lib.set_fn_pure(hash, false);
 
     lib.set_fn_2(
         "-",
@@ -101,8 +102,15 @@ def_package!(crate:BasicTimePackage:"Basic timing utilities.", lib, {
         Ok(seconds as INT)
     }
 
-    lib.set_fn_1_mut("elapsed", elapsed);
+    let hash = lib.set_fn_1_mut("elapsed", elapsed);
+    lib.set_fn_pure(hash, false);
 
     #[cfg(not(feature = "no_object"))]
-    lib.set_getter_fn("elapsed", elapsed);
+    {
+        let hash = lib.set_getter_fn("elapsed", elapsed);
+        lib.set_fn_pure(hash, false);
+    }
+
+    // All other built-in functions in this package are pure
+    lib.set_unmarked_fns_pure();
 });
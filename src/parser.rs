@@ -1,23 +1,29 @@
 //! Main module defining the lexer and parser.
 
 use crate::any::{Dynamic, Union};
+use crate::bytecode::Program;
 use crate::calc_fn_hash;
+use crate::cost::NodeType;
+use crate::diagnostic::{self, Diagnostic};
 use crate::engine::{
-    Engine, KEYWORD_THIS, MARKER_BLOCK, MARKER_EXPR, MARKER_IDENT,
-    KEYWORD_FN_PTR_CURRY,
+    Engine, KEYWORD_FN_PTR_CURRY, KEYWORD_GLOBAL, KEYWORD_THIS, MARKER_BLOCK, MARKER_EXPR,
+    MARKER_IDENT,
 };
 use crate::error::{LexError, ParseError, ParseErrorType};
-use crate::fn_native::Shared;
+use crate::fn_native::{FnCallCache, Shared};
 use crate::module::{Module, ModuleRef};
-use crate::optimize::{optimize_into_ast, OptimizationLevel};
+use crate::optimize::{check_types, optimize_into_ast, OptimizationLevel};
 use crate::scope::{EntryType as ScopeEntryType, Scope};
 use crate::syntax::FnCustomSyntaxEval;
-use crate::token::{is_valid_identifier, Position, Token, TokenStream};
+use crate::token::{is_valid_identifier, sort_spans, Position, Span, Token, TokenStream};
 use crate::utils::{StaticVec, StraightHasherBuilder};
 
 #[cfg(not(feature = "no_function"))]
 use crate::engine::FN_ANONYMOUS;
 
+#[cfg(not(feature = "no_module"))]
+use crate::engine::{KEYWORD_FROM, MARKER_IMPORT};
+
 #[cfg(not(feature = "no_object"))]
 use crate::engine::{make_getter, make_setter};
 
@@ -25,6 +31,7 @@ use crate::stdlib::{
     borrow::Cow,
     boxed::Box,
     char,
+    cmp::Ordering,
     collections::HashMap,
     fmt, format,
     hash::{Hash, Hasher},
@@ -69,6 +76,9 @@ pub use crate::utils::ImmutableString;
 
 type FunctionsLib = HashMap<u64, ScriptFnDef, StraightHasherBuilder>;
 
+/// Spans of statements and expressions with their types, sorted by position.
+type SpanTable = Vec<(Position, NodeType, Span)>;
+
 /// Compiled AST (abstract syntax tree) of a Rhai script.
 ///
 /// Currently, `AST` is neither `Send` nor `Sync`. Turn on the `sync` feature to make it `Send + Sync`.
@@ -78,12 +88,29 @@ pub struct AST(
     Vec<Stmt>,
     /// Script-defined functions.
     Module,
+    /// Compiled bytecode, if any.
+    Option<Shared<Program>>,
+    /// Names of the sources (e.g. file names) of the statements, if any, each with the index of
+    /// the first statement from that source.
+    Vec<(usize, Option<Shared<str>>)>,
+    /// Spans of statements and expressions with their types, sorted by position, for each source.
+    Vec<(Option<Shared<str>>, SpanTable)>,
+    /// Does the script refer to the `global` namespace?
+    bool,
 );
 
 impl AST {
     /// Create a new `AST`.
     pub fn new(statements: Vec<Stmt>, lib: Module) -> Self {
-        Self(statements, lib)
+        // Whether the statements refer to the `global` namespace is unknown, so assume they do
+        Self(
+            statements,
+            lib,
+            None,
+            Default::default(),
+            Default::default(),
+            true,
+        )
     }
 
     /// Get the statements.
@@ -101,6 +128,7 @@ impl AST {
 
     /// Get a mutable reference to the statements.
     pub(crate) fn statements_mut(&mut self) -> &mut Vec<Stmt> {
+        self.2 = None;
         &mut self.0
     }
 
@@ -117,6 +145,198 @@ impl AST {
         &self.1
     }
 
+    /// Get the compiled bytecode, if any.
+    pub(crate) fn program(&self) -> Option<&Shared<Program>> {
+        self.2.as_ref()
+    }
+
+    /// Compile the `AST` into bytecode.
+    pub(crate) fn compile_program(&mut self) {
+        self.2 = Some(Shared::new(Program::from_ast(self)));
+    }
+
+    /// Get the name of the source (e.g. file name) of the script, if any.
+    ///
+    /// If the `AST` is merged from scripts with different sources, this is the first of them;
+    /// use `sources` to get all of them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::Engine;
+    ///
+    /// let engine = Engine::new();
+    ///
+    /// let ast = engine.compile_with_source("hello.rhai", "40 + 2")?;
+    /// assert_eq!(ast.source(), Some("hello.rhai"));
+    ///
+    /// let ast = engine.compile("40 + 2")?;
+    /// assert_eq!(ast.source(), None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn source(&self) -> Option<&str> {
+        self.3.iter().find_map(|(_, source)| source.as_deref())
+    }
+
+    /// Get the names of the sources (e.g. file names) of all the scripts merged into the `AST`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::Engine;
+    ///
+    /// let engine = Engine::new();
+    ///
+    /// let ast1 = engine.compile_with_source("a.rhai", "40")?;
+    /// let ast2 = engine.compile_with_source("b.rhai", "2")?;
+    ///
+    /// let ast = ast1.merge(&ast2);
+    ///
+    /// assert_eq!(ast.source(), Some("a.rhai"));
+    /// assert_eq!(ast.sources().collect::<Vec<_>>(), vec!["a.rhai", "b.rhai"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn sources(&self) -> impl Iterator<Item = &str> {
+        self.4.iter().filter_map(|(source, _)| source.as_deref())
+    }
+
+    /// Get the source of the statement at an index.
+    pub(crate) fn source_of_stmt(&self, index: usize) -> Option<&Shared<str>> {
+        self.3
+            .iter()
+            .rev()
+            .find(|(start, _)| *start <= index)
+            .and_then(|(_, source)| source.as_ref())
+    }
+
+    /// Set the name of the source (e.g. file name) of the script, together with the spans of
+    /// statements and expressions with their types, sorted by position.
+    pub(crate) fn set_source(
+        &mut self,
+        source: Option<Shared<str>>,
+        spans: SpanTable,
+    ) {
+        self.3 = vec![(0, source.clone())];
+        self.4 = vec![(source, spans)];
+    }
+
+    /// Get the span of the statement or expression at a position, e.g. the position of an error.
+    ///
+    /// The position of an operator expression (e.g. `x + 1`) is that of the operator, while its span
+    /// covers the entire expression.  When a statement and an expression share the same position
+    /// (e.g. a `while` statement and its condition), the span of the innermost one is returned;
+    /// use `spans` to get all of them.
+    ///
+    /// Spans are only available on an `AST` compiled from a script.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, Position};
+    ///
+    /// let engine = Engine::new();
+    ///
+    /// let ast = engine.compile(r#"let x = 42;
+    ///                             x + "hello" * 2"#)?;
+    ///
+    /// let err = engine.eval_ast::<i64>(&ast).expect_err("should error");
+    ///
+    /// // The error is at the '*' operator
+    /// assert_eq!(err.position(), Position::new(2, 41));
+    ///
+    /// // The span of the operator expression is '"hello" * 2'
+    /// let span = ast.span(err.position()).unwrap();
+    /// assert_eq!(span.start, Position::new(2, 33));
+    /// assert_eq!(span.end, Position::new(2, 43));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn span(&self, pos: Position) -> Option<Span> {
+        self.span_in(self.source(), pos)
+    }
+
+    /// Get the span of the statement or expression at a position in a particular source,
+    /// e.g. the root source and position of an error.
+    ///
+    /// Spans of scripts without a source are found with `None`.
+    pub fn span_in(&self, source: Option<&str>, pos: Position) -> Option<Span> {
+        self.spans_in(source, pos).next().map(|(_, span)| span)
+    }
+
+    /// Get the spans of all statements and expressions at a position, innermost first,
+    /// together with their types.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::{Engine, NodeType, Position};
+    ///
+    /// let engine = Engine::new();
+    ///
+    /// let ast = engine.compile("let x = 0; while x < 10 { x += 1; }")?;
+    ///
+    /// // The position of a 'while' statement is that of its condition
+    /// let spans: Vec<_> = ast.spans(Position::new(1, 20)).collect();
+    ///
+    /// assert_eq!(spans.len(), 2);
+    /// assert_eq!(spans[0].0, NodeType::FnCall);
+    /// assert_eq!(spans[0].1.start, Position::new(1, 18));
+    /// assert_eq!(spans[1].0, NodeType::While);
+    /// assert_eq!(spans[1].1.start, Position::new(1, 12));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn spans(&self, pos: Position) -> impl Iterator<Item = (NodeType, Span)> + '_ {
+        self.spans_in(self.source(), pos)
+    }
+
+    /// Get the spans of all statements and expressions at a position in a particular source,
+    /// innermost first, together with their types.
+    ///
+    /// Spans of scripts without a source are found with `None`.
+    pub fn spans_in(
+        &self,
+        source: Option<&str>,
+        pos: Position,
+    ) -> impl Iterator<Item = (NodeType, Span)> + '_ {
+        let spans = self
+            .4
+            .iter()
+            .find(|(s, _)| s.as_deref() == source)
+            .map_or(&[][..], |(_, spans)| spans.as_slice());
+
+        // Find the first span at the position
+        let start = match spans.binary_search_by(|(p, _, _)| match p.cmp(&pos) {
+            Ordering::Equal => Ordering::Greater,
+            order => order,
+        }) {
+            Ok(index) | Err(index) => index,
+        };
+
+        spans[start..]
+            .iter()
+            .take_while(move |(p, _, _)| *p == pos)
+            .map(|(_, node_type, span)| (*node_type, *span))
+    }
+
+    /// Does the script refer to the `global` namespace?
+    ///
+    /// If not, global constants need not be made available when evaluating it.
+    pub(crate) fn uses_global(&self) -> bool {
+        self.5
+    }
+
+    /// Set whether the script refers to the `global` namespace.
+    pub(crate) fn set_uses_global(&mut self, uses_global: bool) {
+        self.5 = uses_global;
+    }
+
     /// Clone the `AST`'s functions into a new `AST`.
     /// No statements are cloned.
     ///
@@ -135,13 +355,21 @@ impl AST {
     ) -> Self {
         let mut functions: Module = Default::default();
         functions.merge_filtered(&self.1, filter);
-        Self(Default::default(), functions)
+        let mut ast = Self::new(Default::default(), functions);
+        ast.3 = self.3.clone();
+        ast.4 = self.4.clone();
+        ast.5 = self.5;
+        ast
     }
 
     /// Clone the `AST`'s script statements into a new `AST`.
     /// No functions are cloned.
     pub fn clone_statements_only(&self) -> Self {
-        Self(self.0.clone(), Default::default())
+        let mut ast = Self::new(self.0.clone(), Default::default());
+        ast.3 = self.3.clone();
+        ast.4 = self.4.clone();
+        ast.5 = self.5;
+        ast
     }
 
     /// Merge two `AST` into one.  Both `AST`'s are untouched and a new, merged, version
@@ -253,7 +481,7 @@ impl AST {
         other: &Self,
         filter: impl Fn(FnAccess, &str, usize) -> bool,
     ) -> Self {
-        let Self(statements, functions) = self;
+        let Self(statements, functions, ..) = self;
 
         let ast = match (statements.is_empty(), other.0.is_empty()) {
             (false, false) => {
@@ -269,7 +497,38 @@ impl AST {
         let mut functions = functions.clone();
         functions.merge_filtered(&other.1, filter);
 
-        Self::new(ast, functions)
+        // The statements of the second `AST` follow those of the first
+        let offset = statements.len();
+        let mut sources: Vec<(usize, Option<Shared<str>>)> = Vec::new();
+
+        for (start, source) in self.3.iter().cloned().chain(
+            other
+                .3
+                .iter()
+                .map(|(start, source)| (start + offset, source.clone())),
+        ) {
+            match sources.last_mut() {
+                // Sources without any statements are superseded by the next
+                Some(last) if last.0 == start => last.1 = source,
+                Some(last) if last.1 == source => (),
+                _ => sources.push((start, source)),
+            }
+        }
+
+        // Spans are kept apart for each source, so that equal positions in different sources do not collide
+        let mut spans = self.4.clone();
+
+        for (source, other_spans) in other.4.iter() {
+            match spans.iter_mut().find(|(s, _)| s == source) {
+                Some((_, spans)) => {
+                    spans.extend(other_spans.iter().cloned());
+                    *spans = sort_spans(mem::take(spans));
+                }
+                None => spans.push((source.clone(), other_spans.clone())),
+            }
+        }
+
+        Self(ast, functions, None, sources, spans, self.5 || other.5)
     }
 
     /// Filter out the functions, retaining only some based on a filter predicate.
@@ -298,17 +557,146 @@ impl AST {
     #[cfg(not(feature = "no_function"))]
     pub fn retain_functions(&mut self, filter: impl Fn(FnAccess, &str, usize) -> bool) {
         self.1.retain_functions(filter);
+        self.2 = None;
     }
 
     /// Clear all function definitions in the `AST`.
     #[cfg(not(feature = "no_function"))]
     pub fn clear_functions(&mut self) {
         self.1 = Default::default();
+        self.2 = None;
     }
 
     /// Clear all statements in the `AST`, leaving only function definitions.
     pub fn clear_statements(&mut self) {
         self.0 = vec![];
+        self.2 = None;
+    }
+
+    /// Get an iterator over the metadata (including doc comments) of all script-defined
+    /// functions in the `AST`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::Engine;
+    ///
+    /// let engine = Engine::new();
+    ///
+    /// let ast = engine.compile(r#"
+    ///                 /// Add one to a number.
+    ///                 fn inc(x) { x + 1 }
+    ///             "#)?;
+    ///
+    /// let f = ast.iter_functions().next().unwrap();
+    ///
+    /// assert_eq!(f.name, "inc");
+    /// assert_eq!(f.params, vec!["x"]);
+    /// assert_eq!(f.comments, vec!["/// Add one to a number."]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(feature = "no_function"))]
+    pub fn iter_functions<'a>(&'a self) -> impl Iterator<Item = ScriptFnMetadata<'a>> + 'a {
+        self.1
+            .iter_fn()
+            .filter(|(_, _, _, f, _)| f.is_script())
+            .map(|(_, _, _, f, _)| f.get_fn_def().into())
+    }
+
+    /// Get an iterator over the metadata (including doc comments) of all constants and
+    /// exported variables defined at global level in the `AST`.
+    ///
+    /// There is one entry per name. An `export` without its own doc comment takes the
+    /// doc comment of the `let` or `const` statement declaring the variable.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::Engine;
+    ///
+    /// let engine = Engine::new();
+    ///
+    /// let ast = engine.compile(r#"
+    ///                 /// The answer to everything.
+    ///                 const ANSWER = 42;
+    ///             "#)?;
+    ///
+    /// let v = ast.iter_variables().next().unwrap();
+    ///
+    /// assert_eq!(v.name, "ANSWER");
+    /// assert!(!v.exported);
+    /// assert_eq!(v.comments, vec!["/// The answer to everything."]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn iter_variables<'a>(&'a self) -> impl Iterator<Item = VarMetadata<'a>> + 'a {
+        let mut vars: Vec<VarMetadata<'a>> = Vec::new();
+        // Doc comments on the latest `let` or `const` of each name, picked up by its `export`
+        let mut docs: HashMap<&'a str, &'a [String]> = HashMap::new();
+
+        for stmt in self.0.iter() {
+            match stmt {
+                Stmt::Let(x) => {
+                    let ((name, _), _, _, comments) = x.as_ref();
+                    docs.insert(name, comments);
+                }
+                Stmt::Const(x) => {
+                    let ((name, _), _, _, comments) = x.as_ref();
+                    docs.insert(name, comments);
+
+                    let comments = comments.iter().map(String::as_str).collect();
+
+                    match vars.iter_mut().find(|v| v.name == name) {
+                        Some(v) => v.comments = comments,
+                        None => vars.push(VarMetadata {
+                            name,
+                            exported: false,
+                            comments,
+                        }),
+                    }
+                }
+                #[cfg(not(feature = "no_module"))]
+                Stmt::Export(x) => {
+                    let (list, export_comments) = x.as_ref();
+
+                    for ((name, _), _, rename) in
+                        list.iter().filter(|(_, modules, _)| modules.is_none())
+                    {
+                        let alias = rename.as_ref().map_or(name.as_str(), |(alias, _)| alias);
+
+                        // A doc comment on the `export` itself takes precedence
+                        let comments: Vec<_> = if export_comments.is_empty() {
+                            docs.get(name.as_str()).map_or(&[][..], |c| *c)
+                        } else {
+                            export_comments
+                        }
+                        .iter()
+                        .map(String::as_str)
+                        .collect();
+
+                        match vars.iter_mut().find(|v| v.name == alias) {
+                            Some(v) => {
+                                v.exported = true;
+                                if !comments.is_empty() {
+                                    v.comments = comments;
+                                }
+                            }
+                            None => vars.push(VarMetadata {
+                                name: alias,
+                                exported: true,
+                                comments,
+                            }),
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        vars.into_iter()
     }
 }
 
@@ -364,8 +752,18 @@ pub struct ScriptFnDef {
     pub access: FnAccess,
     /// Names of function parameters.
     pub params: StaticVec<String>,
+    /// Type annotations of function parameters, if any.
+    pub param_types: StaticVec<Option<String>>,
+    /// Type annotation of the return value, if any.
+    pub return_type: Option<String>,
+    /// Doc comments attached to the function.
+    pub comments: Vec<String>,
     /// Function body.
     pub body: Stmt,
+    /// Does the function body refer to the `global` namespace?
+    pub uses_global: bool,
+    /// Name of the source (e.g. file name) of the script defining the function, if any.
+    pub source: Option<Shared<str>>,
     /// Position of the function definition.
     pub pos: Position,
 }
@@ -382,13 +780,86 @@ impl fmt::Display for ScriptFnDef {
             self.name,
             self.params
                 .iter()
-                .map(|s| s.as_str())
+                .zip(self.param_types.iter())
+                .map(|(s, t)| match t {
+                    Some(t) => format!("{}: {}", s, t),
+                    None => s.to_string(),
+                })
                 .collect::<Vec<_>>()
                 .join(",")
-        )
+        )?;
+
+        if let Some(typ) = &self.return_type {
+            write!(f, " -> {}", typ)?;
+        }
+
+        Ok(())
     }
 }
 
+/// Metadata of a script-defined function, including its doc comments.
+///
+/// Not available under the `no_function` feature.
+#[cfg(not(feature = "no_function"))]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ScriptFnMetadata<'a> {
+    /// Function name.
+    pub name: &'a str,
+    /// Function access mode.
+    pub access: FnAccess,
+    /// Names of function parameters.
+    pub params: Vec<&'a str>,
+    /// Type annotations of function parameters, if any.
+    pub param_types: Vec<Option<&'a str>>,
+    /// Type annotation of the return value, if any.
+    pub return_type: Option<&'a str>,
+    /// Doc comments attached to the function.
+    pub comments: Vec<&'a str>,
+}
+
+#[cfg(not(feature = "no_function"))]
+impl<'a> From<&'a ScriptFnDef> for ScriptFnMetadata<'a> {
+    fn from(fn_def: &'a ScriptFnDef) -> Self {
+        Self {
+            name: &fn_def.name,
+            access: fn_def.access,
+            params: fn_def.params.iter().map(String::as_str).collect(),
+            param_types: fn_def.param_types.iter().map(Option::as_deref).collect(),
+            return_type: fn_def.return_type.as_deref(),
+            comments: fn_def.comments.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
+/// Metadata of a constant or an exported variable defined at global level, including its doc comments.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct VarMetadata<'a> {
+    /// Name of the variable (or the alias it is exported under).
+    pub name: &'a str,
+    /// Is this an exported variable?
+    pub exported: bool,
+    /// Doc comments attached to the variable.
+    pub comments: Vec<&'a str>,
+}
+
+/// [INTERNALS] A type encapsulating the names bound by an `import` statement.
+/// Exported under the `internals` feature only.
+///
+/// ## WARNING
+///
+/// This type is volatile and may change.
+#[cfg(not(feature = "no_module"))]
+#[derive(Debug, Clone, Hash)]
+pub enum ImportNames {
+    /// `import "path" as name;` - the entire module is bound to an alias.
+    Alias(String),
+    /// `import { id as name, ... } from "path";` - selected members are bound into the local namespace.
+    #[allow(clippy::type_complexity)]
+    Members(Vec<((String, Position), Option<(String, Position)>)>),
+    /// `import * from "path";` - all members are bound into the local namespace.
+    All,
+}
+
 /// [INTERNALS] A type encapsulating the mode of a `return`/`throw` statement.
 /// Exported under the `internals` feature only.
 ///
@@ -406,13 +877,18 @@ pub enum ReturnType {
 struct ParseState<'e, 's> {
     /// Reference to the scripting `Engine`.
     engine: &'e Engine,
-    /// Encapsulates a local stack with variable names to simulate an actual runtime scope.
-    stack: Vec<(String, ScopeEntryType)>,
+    /// Encapsulates a local stack with variable names (and their type annotations, if any)
+    /// to simulate an actual runtime scope.
+    stack: Vec<(String, ScopeEntryType, Option<String>)>,
     /// Tracks a list of external variables(variables that are not explicitly
     /// declared in the scope during AST evaluation).
     externals: &'s mut Vec<String>,
+    /// Parse errors recovered from so far, or `None` if not parsing in recovering mode.
+    errors: Option<&'s mut Vec<ParseError>>,
     /// Encapsulates a local stack with variable names to simulate an actual runtime scope.
     modules: Vec<String>,
+    /// Names bound into the local namespace by `import { ... } from` statements.
+    imported: Vec<String>,
     /// Maximum levels of expression nesting.
     #[cfg(not(feature = "unchecked"))]
     max_expr_depth: usize,
@@ -426,16 +902,21 @@ impl<'e, 's> ParseState<'e, 's> {
     fn new(
         engine: &'e Engine,
         externals: &'s mut Vec<String>,
+        errors: Option<&'s mut Vec<ParseError>>,
         #[cfg(not(feature = "unchecked"))] max_expr_depth: usize,
         #[cfg(not(feature = "unchecked"))] max_function_expr_depth: usize,
     ) -> Self {
         Self {
             engine,
-            #[cfg(not(feature = "unchecked"))] max_expr_depth,
-            #[cfg(not(feature = "unchecked"))] max_function_expr_depth,
+            #[cfg(not(feature = "unchecked"))]
+            max_expr_depth,
+            #[cfg(not(feature = "unchecked"))]
+            max_function_expr_depth,
             externals,
+            errors,
             stack: Default::default(),
             modules: Default::default(),
+            imported: Default::default(),
         }
     }
 
@@ -449,8 +930,10 @@ impl<'e, 's> ParseState<'e, 's> {
             #[cfg(not(feature = "unchecked"))]
             max_function_expr_depth: self.max_function_expr_depth,
             externals: self.externals,
+            errors: self.errors.as_deref_mut(),
             stack: Default::default(),
             modules: Default::default(),
+            imported: Default::default(),
         }
     }
 
@@ -464,15 +947,16 @@ impl<'e, 's> ParseState<'e, 's> {
     /// i.e. the top element of the `ParseState` is offset 1.
     /// Return `None` when the variable name is not found in the `stack`.
     fn access_var(&mut self, name: &str) -> Option<NonZeroUsize> {
-        let mut index = self.stack
+        let mut index = self
+            .stack
             .iter()
             .rev()
             .enumerate()
-            .find(|(_, (n, _))| *n == name)
+            .find(|(_, (n, _, _))| *n == name)
             .and_then(|(i, _)| NonZeroUsize::new(i + 1));
 
         if index.is_some() {
-            return index
+            return index;
         }
 
         #[cfg(not(feature = "no_closures"))]
@@ -483,20 +967,60 @@ impl<'e, 's> ParseState<'e, 's> {
         None
     }
 
-    /// Creates a curry expression from a list of external variables
-    fn make_curry_from_externals(&self, fn_expr: Expr, settings: &ParseSettings) -> Expr {
-        if self.externals.is_empty() {
-            return fn_expr
+    /// In recovering mode, record a parse error.  Otherwise, return the error.
+    ///
+    /// The error is located in the input, i.e. its source and span are attached.
+    fn record(&mut self, input: &TokenStream, err: ParseError) -> Result<(), ParseError> {
+        let err = input.locate_error(err);
+
+        match self.errors {
+            None => Err(err),
+            Some(ref mut errors) => {
+                errors.push(err);
+                Ok(())
+            }
+        }
+    }
+
+    /// In recovering mode, record a parse error and skip to the start of the next statement.
+    /// Otherwise, return the error.
+    ///
+    /// `start` is the position of the statement in error.
+    fn recover(
+        &mut self,
+        input: &mut TokenStream,
+        err: ParseError,
+        start: Position,
+    ) -> Result<(), ParseError> {
+        self.record(input, err)?;
+        synchronize(input, start);
+        Ok(())
+    }
+
+    /// Creates a curry expression from a list of external variables.
+    ///
+    /// The variables are resolved in this `ParseState`, which is the parent context of the
+    /// anonymous function, registering them as external variables here as well if not found.
+    fn make_curry_from_externals(
+        &mut self,
+        fn_expr: Expr,
+        externals: &[String],
+        settings: &ParseSettings,
+    ) -> Expr {
+        if externals.is_empty() {
+            return fn_expr;
         }
 
         let mut args = StaticVec::new();
 
-        for var in self.externals.iter() {
+        for var in externals.iter() {
+            let index = self.access_var(var);
+
             args.push(Expr::Variable(Box::new((
                 (var.clone(), settings.pos),
                 None,
                 0,
-                None,
+                index,
             ))));
         }
 
@@ -505,9 +1029,10 @@ impl<'e, 's> ParseState<'e, 's> {
             Expr::FnCall(Box::new((
                 (KEYWORD_FN_PTR_CURRY.into(), false, settings.pos),
                 None,
-                calc_fn_hash(empty(), KEYWORD_FN_PTR_CURRY, self.externals.len(), empty()),
+                calc_fn_hash(empty(), KEYWORD_FN_PTR_CURRY, externals.len(), empty()),
                 args,
                 None,
+                Default::default(),
             ))),
             settings.pos,
         )))
@@ -586,10 +1111,19 @@ pub enum Stmt {
     Loop(Box<Stmt>),
     /// for id in expr { stmt }
     For(Box<(String, Expr, Stmt)>),
-    /// let id = expr
-    Let(Box<((String, Position), Option<Expr>)>),
-    /// const id = expr
-    Const(Box<((String, Position), Expr)>),
+    /// let id: type = expr
+    #[allow(clippy::type_complexity)]
+    Let(
+        Box<(
+            (String, Position),
+            Option<Expr>,
+            Option<String>,
+            Vec<String>,
+        )>,
+    ),
+    /// const id: type = expr
+    #[allow(clippy::type_complexity)]
+    Const(Box<((String, Position), Expr, Option<String>, Vec<String>)>),
     /// { stmt; ... }
     Block(Box<(StaticVec<Stmt>, Position)>),
     /// { stmt }
@@ -600,12 +1134,21 @@ pub enum Stmt {
     Break(Position),
     /// return/throw
     ReturnWithVal(Box<((ReturnType, Position), Option<Expr>)>),
-    /// import expr as module
+    /// import expr as module, import { id as name, ... } from expr, or import * from expr
     #[cfg(not(feature = "no_module"))]
-    Import(Box<(Expr, (String, Position))>),
-    /// expr id as name, ...
+    Import(Box<(Expr, ImportNames, Position)>),
+    /// export id as name, ... or export module::id as name, ...
     #[cfg(not(feature = "no_module"))]
-    Export(Box<StaticVec<((String, Position), Option<(String, Position)>)>>),
+    Export(
+        Box<(
+            StaticVec<(
+                (String, Position),
+                Option<Box<ModuleRef>>,
+                Option<(String, Position)>,
+            )>,
+            Vec<String>,
+        )>,
+    ),
 }
 
 impl Default for Stmt {
@@ -625,14 +1168,14 @@ impl Stmt {
             Stmt::Block(x) => x.1,
             Stmt::IfThenElse(x) => x.0.position(),
             Stmt::Expr(x) => x.position(),
-            Stmt::While(x) => x.1.position(),
+            Stmt::While(x) => x.0.position(),
             Stmt::Loop(x) => x.position(),
-            Stmt::For(x) => x.2.position(),
+            Stmt::For(x) => x.1.position(),
 
             #[cfg(not(feature = "no_module"))]
-            Stmt::Import(x) => (x.1).1,
+            Stmt::Import(x) => x.2,
             #[cfg(not(feature = "no_module"))]
-            Stmt::Export(x) => (x.get(0).0).1,
+            Stmt::Export(x) => (x.0.get(0).0).1,
         }
     }
 
@@ -763,7 +1306,7 @@ pub enum Expr {
     Stmt(Box<(Stmt, Position)>),
     /// Wrapped expression - should not be optimized away.
     Expr(Box<Expr>),
-    /// func(expr, ... ) - ((function name, native_only, position), optional modules, hash, arguments, optional default value)
+    /// func(expr, ... ) - ((function name, native_only, position), optional modules, hash, arguments, optional default value, call-site cache)
     /// Use `Cow<'static, str>` because a lot of operators (e.g. `==`, `>=`) are implemented as function calls
     /// and the function names are predictable, so no need to allocate a new `String`.
     FnCall(
@@ -773,10 +1316,12 @@ pub enum Expr {
             u64,
             StaticVec<Expr>,
             Option<bool>,
+            FnCallCache,
         )>,
     ),
-    /// expr op= expr
-    Assignment(Box<(Expr, Cow<'static, str>, Expr, Position)>),
+    /// expr op= expr, with the type annotation of the variable assigned to (if any)
+    #[allow(clippy::type_complexity)]
+    Assignment(Box<(Expr, Cow<'static, str>, Expr, Position, Option<String>)>),
     /// lhs.rhs
     Dot(Box<(Expr, Expr, Position)>),
     /// expr[expr]
@@ -1081,6 +1626,65 @@ fn match_token(input: &mut TokenStream, token: Token) -> Result<bool, ParseError
     }
 }
 
+/// Is a token a keyword which starts a statement?
+fn is_stmt_keyword(token: &Token) -> bool {
+    match token {
+        Token::Let
+        | Token::Const
+        | Token::If
+        | Token::While
+        | Token::Loop
+        | Token::For
+        | Token::Continue
+        | Token::Break
+        | Token::Return
+        | Token::Throw => true,
+
+        #[cfg(not(feature = "no_function"))]
+        Token::Fn | Token::Private => true,
+
+        #[cfg(not(feature = "no_module"))]
+        Token::Import | Token::Export => true,
+
+        _ => false,
+    }
+}
+
+/// Skip tokens after a parse error up to the start of the next statement.
+///
+/// Tokens are skipped up to and including a `;`, or up to (but not including) a `}` closing the
+/// current block or a keyword which starts a statement.  Brackets nested inside are skipped as a whole.
+///
+/// `start` is the position of the statement in error.  If no token of it has been consumed,
+/// at least one token is skipped so that parsing does not fail at the same position again.
+fn synchronize(input: &mut TokenStream, start: Position) {
+    let mut skip = input.peek().unwrap().1 == start;
+    let mut level = 0_usize;
+
+    loop {
+        match input.peek().unwrap() {
+            (Token::EOF, _) => return,
+            (Token::SemiColon, _) if level == 0 => {
+                eat_token(input, Token::SemiColon);
+                return;
+            }
+            (Token::RightBrace, _) if level == 0 && !skip => return,
+            (token, _) if level == 0 && !skip && is_stmt_keyword(token) => return,
+            (Token::LeftBrace, _)
+            | (Token::LeftParen, _)
+            | (Token::LeftBracket, _)
+            | (Token::MapStart, _) => level += 1,
+            (Token::RightBrace, _) | (Token::RightParen, _) | (Token::RightBracket, _) => {
+                level = level.saturating_sub(1)
+            }
+            _ => (),
+        }
+
+        input.next().unwrap();
+        skip = false;
+    }
+}
+
 /// Parse ( expr )
 fn parse_paren_expr(
     input: &mut TokenStream,
@@ -1164,6 +1768,7 @@ fn parse_call_expr(
                 hash_script,
                 args,
                 None,
+                Default::default(),
             ))));
         }
         // id...
@@ -1206,6 +1811,7 @@ fn parse_call_expr(
                     hash_script,
                     args,
                     None,
+                    Default::default(),
                 ))));
             }
             // id(...args,
@@ -1430,7 +2036,7 @@ fn parse_array_literal(
 
     let mut arr = StaticVec::new();
 
-    while !input.peek().unwrap().0.is_eof() {
+    loop {
         #[cfg(not(feature = "unchecked"))]
         if state.engine.limits.max_array_size > 0 && arr.len() >= state.engine.limits.max_array_size
         {
@@ -1446,6 +2052,13 @@ fn parse_array_literal(
                 eat_token(input, Token::RightBracket);
                 break;
             }
+            (Token::EOF, pos) => {
+                return Err(PERR::MissingToken(
+                    Token::RightBracket.into(),
+                    "to end this array literal".into(),
+                )
+                .into_err(*pos))
+            }
             _ => {
                 let expr = parse_expr(input, state, lib, settings.level_up())?;
                 arr.push(expr);
@@ -1491,7 +2104,7 @@ fn parse_map_literal(
 
     let mut map = StaticVec::new();
 
-    while !input.peek().unwrap().0.is_eof() {
+    loop {
         const MISSING_RBRACE: &str = "to end this object map literal";
 
         match input.peek().unwrap() {
@@ -1607,6 +2220,15 @@ fn parse_primary(
                 .map(|block| Expr::Stmt(Box::new((block, settings.pos))))
         }
         Token::EOF => return Err(PERR::UnexpectedEOF.into_err(settings.pos)),
+        // ; or } or keyword - do not consume a token ending or starting a statement,
+        //                     so that parsing can resume there in recovering mode
+        token
+            if matches!(token, Token::SemiColon | Token::RightBrace) || is_stmt_keyword(token) =>
+        {
+            return Err(
+                PERR::BadInput(format!("Unexpected '{}'", token.syntax())).into_err(settings.pos)
+            );
+        }
         _ => input.next().unwrap(),
     };
 
@@ -1715,6 +2337,10 @@ fn parse_primary(
             // Qualifiers + variable name
             *hash = calc_fn_hash(modules.iter().map(|(v, _)| v.as_str()), name, 0, empty());
             modules.set_index(state.find_module(&modules.get(0).0));
+
+            if modules.len() == 1 && modules.get(0).0 == KEYWORD_GLOBAL {
+                input.set_uses_global();
+            }
         }
         _ => (),
     }
@@ -1784,6 +2410,7 @@ fn parse_unary(
                         hash,
                         args,
                         None,
+                        Default::default(),
                     ))))
                 }
             }
@@ -1809,6 +2436,7 @@ fn parse_unary(
                 hash,
                 args,
                 Some(false), // NOT operator, when operating on invalid operand, defaults to false
+                Default::default(),
             ))))
         }
         // | ...
@@ -1818,6 +2446,8 @@ fn parse_unary(
             let mut anon_fn_state = ParseState::new(
                 state.engine,
                 &mut externals,
+                state.errors.as_deref_mut(),
+                #[cfg(not(feature = "unchecked"))]
                 state.max_function_expr_depth,
                 #[cfg(not(feature = "unchecked"))]
                 state.max_function_expr_depth,
@@ -1836,11 +2466,8 @@ fn parse_unary(
 
             let (expr, func) = parse_anon_fn(input, &mut anon_fn_state, lib, settings)?;
 
-            #[cfg(not(feature = "no_closures"))]
-            // registering closures in it's parent context
-            for closure in externals.iter() {
-                let _ = state.access_var(closure);
-            }
+            // Captured variables are curried in the parent context
+            let expr = state.make_curry_from_externals(expr, &externals, &settings);
 
             // Qualifiers (none) + function name + number of arguments.
             let hash = calc_fn_hash(empty(), &func.name, func.params.len(), empty());
@@ -1865,18 +2492,30 @@ fn make_assignment_stmt<'a>(
 ) -> Result<Expr, ParseError> {
     match &lhs {
         // var (non-indexed) = rhs
-        Expr::Variable(x) if x.3.is_none() => {
-            Ok(Expr::Assignment(Box::new((lhs, fn_name.into(), rhs, pos))))
-        }
+        Expr::Variable(x) if x.3.is_none() => Ok(Expr::Assignment(Box::new((
+            lhs,
+            fn_name.into(),
+            rhs,
+            pos,
+            None,
+        )))),
         // var (indexed) = rhs
         Expr::Variable(x) => {
             let ((name, name_pos), _, _, index) = x.as_ref();
-            match state.stack[(state.stack.len() - index.unwrap().get())].1 {
-                ScopeEntryType::Normal => {
-                    Ok(Expr::Assignment(Box::new((lhs, fn_name.into(), rhs, pos))))
+            match &state.stack[(state.stack.len() - index.unwrap().get())] {
+                (_, ScopeEntryType::Normal, typ) => {
+                    // The variable's type annotation is checked on every assignment
+                    let typ = typ.clone();
+                    Ok(Expr::Assignment(Box::new((
+                        lhs,
+                        fn_name.into(),
+                        rhs,
+                        pos,
+                        typ,
+                    ))))
                 }
                 // Constant values cannot be assigned to
-                ScopeEntryType::Constant => {
+                (_, ScopeEntryType::Constant, _) => {
                     Err(PERR::AssignmentToConstant(name.clone()).into_err(*name_pos))
                 }
             }
@@ -1884,16 +2523,24 @@ fn make_assignment_stmt<'a>(
         // xxx[???] = rhs, xxx.??? = rhs
         Expr::Index(x) | Expr::Dot(x) => match &x.0 {
             // var[???] (non-indexed) = rhs, var.??? (non-indexed) = rhs
-            Expr::Variable(x) if x.3.is_none() => {
-                Ok(Expr::Assignment(Box::new((lhs, fn_name.into(), rhs, pos))))
-            }
+            Expr::Variable(x) if x.3.is_none() => Ok(Expr::Assignment(Box::new((
+                lhs,
+                fn_name.into(),
+                rhs,
+                pos,
+                None,
+            )))),
             // var[???] (indexed) = rhs, var.??? (indexed) = rhs
             Expr::Variable(x) => {
                 let ((name, name_pos), _, _, index) = x.as_ref();
                 match state.stack[(state.stack.len() - index.unwrap().get())].1 {
-                    ScopeEntryType::Normal => {
-                        Ok(Expr::Assignment(Box::new((lhs, fn_name.into(), rhs, pos))))
-                    }
+                    ScopeEntryType::Normal => Ok(Expr::Assignment(Box::new((
+                        lhs,
+                        fn_name.into(),
+                        rhs,
+                        pos,
+                        None,
+                    )))),
                     // Constant values cannot be assigned to
                     ScopeEntryType::Constant => {
                         Err(PERR::AssignmentToConstant(name.clone()).into_err(*name_pos))
@@ -2165,6 +2812,7 @@ fn parse_binary_op(
     lib: &mut FunctionsLib,
     parent_precedence: u8,
     lhs: Expr,
+    lhs_start: Position,
     mut settings: ParseSettings,
 ) -> Result<Expr, ParseError> {
     settings.pos = lhs.position();
@@ -2188,14 +2836,16 @@ fn parse_binary_op(
 
         let (op_token, pos) = input.next().unwrap();
 
+        let rhs_start = input.peek().unwrap().1;
         let rhs = parse_unary(input, state, lib, settings)?;
+        input.add_span(rhs.position(), NodeType::of_expr(&rhs), rhs_start);
 
         let next_precedence = input.peek().unwrap().0.precedence(custom);
 
         // Bind to right if the next operator has higher precedence
         // If same precedence, then check if the operator binds right
         let rhs = if (precedence == next_precedence && bind_right) || precedence < next_precedence {
-            parse_binary_op(input, state, lib, precedence, rhs, settings)?
+            parse_binary_op(input, state, lib, precedence, rhs, rhs_start, settings)?
         } else {
             // Otherwise bind to left (even if next operator has the same precedence)
             rhs
@@ -2227,17 +2877,33 @@ fn parse_binary_op(
             | Token::PowerOf
             | Token::Ampersand
             | Token::Pipe
-            | Token::XOr => Expr::FnCall(Box::new((op, None, hash, args, None))),
+            | Token::XOr => {
+                Expr::FnCall(Box::new((op, None, hash, args, None, Default::default())))
+            }
 
             // '!=' defaults to true when passed invalid operands
-            Token::NotEqualsTo => Expr::FnCall(Box::new((op, None, hash, args, Some(true)))),
+            Token::NotEqualsTo => Expr::FnCall(Box::new((
+                op,
+                None,
+                hash,
+                args,
+                Some(true),
+                Default::default(),
+            ))),
 
             // Comparison operators default to false when passed invalid operands
             Token::EqualsTo
             | Token::LessThan
             | Token::LessThanEqualsTo
             | Token::GreaterThan
-            | Token::GreaterThanEqualsTo => Expr::FnCall(Box::new((op, None, hash, args, cmp_def))),
+            | Token::GreaterThanEqualsTo => Expr::FnCall(Box::new((
+                op,
+                None,
+                hash,
+                args,
+                cmp_def,
+                Default::default(),
+            ))),
 
             Token::Or => {
                 let rhs = args.pop();
@@ -2272,11 +2938,13 @@ fn parse_binary_op(
             {
                 // Accept non-native functions for custom operators
                 let op = (op.0, false, op.2);
-                Expr::FnCall(Box::new((op, None, hash, args, None)))
+                Expr::FnCall(Box::new((op, None, hash, args, None, Default::default())))
             }
 
             op_token => return Err(PERR::UnknownOperator(op_token.into()).into_err(pos)),
         };
+
+        input.add_span(root.position(), NodeType::of_expr(&root), lhs_start);
     }
 }
 
@@ -2309,7 +2977,9 @@ fn parse_expr(
                 // Adjust the variables stack
                 match syntax.scope_delta {
                     delta if delta > 0 => {
-                        state.stack.push(("".to_string(), ScopeEntryType::Normal))
+                        state
+                            .stack
+                            .push(("".to_string(), ScopeEntryType::Normal, None))
                     }
                     delta if delta < 0 && state.stack.len() <= delta.abs() as usize => {
                         state.stack.clear()
@@ -2355,6 +3025,8 @@ fn parse_expr(
                     }
                 }
 
+                input.add_span(token_pos, NodeType::Custom, token_pos);
+
                 return Ok(Expr::Custom(Box::new((
                     CustomExpr(exprs, syntax.func.clone()),
                     token_pos,
@@ -2366,7 +3038,8 @@ fn parse_expr(
 
     // Parse expression normally.
     let lhs = parse_unary(input, state, lib, settings.level_up())?;
-    parse_binary_op(input, state, lib, 1, lhs, settings.level_up())
+    input.add_span(lhs.position(), NodeType::of_expr(&lhs), settings.pos);
+    parse_binary_op(input, state, lib, 1, lhs, settings.pos, settings.level_up())
 }
 
 /// Make sure that the expression is not a statement expression (i.e. wrapped in `{}`).
@@ -2531,7 +3204,9 @@ fn parse_for(
     let expr = parse_expr(input, state, lib, settings.level_up())?;
 
     let prev_stack_len = state.stack.len();
-    state.stack.push((name.clone(), ScopeEntryType::Normal));
+    state
+        .stack
+        .push((name.clone(), ScopeEntryType::Normal, None));
 
     settings.is_breakable = true;
     let body = parse_block(input, state, lib, settings.level_up())?;
@@ -2547,6 +3222,7 @@ fn parse_let(
     state: &mut ParseState,
     lib: &mut FunctionsLib,
     var_type: ScopeEntryType,
+    comments: Vec<String>,
     mut settings: ParseSettings,
 ) -> Result<Stmt, ParseError> {
     // let/const... (specified in `var_type`)
@@ -2565,6 +3241,13 @@ fn parse_let(
         (_, pos) => return Err(PERR::VariableExpected.into_err(pos)),
     };
 
+    // let name: type ...
+    let typ = if match_token(input, Token::Colon)? {
+        Some(parse_type_annotation(input)?)
+    } else {
+        None
+    };
+
     // let name = ...
     if match_token(input, Token::Equals)? {
         // let name = expr
@@ -2573,13 +3256,27 @@ fn parse_let(
         match var_type {
             // let name = expr
             ScopeEntryType::Normal => {
-                state.stack.push((name.clone(), ScopeEntryType::Normal));
-                Ok(Stmt::Let(Box::new(((name, pos), Some(init_value)))))
+                state
+                    .stack
+                    .push((name.clone(), ScopeEntryType::Normal, typ.clone()));
+                Ok(Stmt::Let(Box::new((
+                    (name, pos),
+                    Some(init_value),
+                    typ,
+                    comments,
+                ))))
             }
             // const name = { expr:constant }
             ScopeEntryType::Constant if init_value.is_constant() => {
-                state.stack.push((name.clone(), ScopeEntryType::Constant));
-                Ok(Stmt::Const(Box::new(((name, pos), init_value))))
+                state
+                    .stack
+                    .push((name.clone(), ScopeEntryType::Constant, typ.clone()));
+                Ok(Stmt::Const(Box::new((
+                    (name, pos),
+                    init_value,
+                    typ,
+                    comments,
+                ))))
             }
             // const name = expr: error
             ScopeEntryType::Constant => {
@@ -2590,17 +3287,41 @@ fn parse_let(
         // let name
         match var_type {
             ScopeEntryType::Normal => {
-                state.stack.push((name.clone(), ScopeEntryType::Normal));
-                Ok(Stmt::Let(Box::new(((name, pos), None))))
+                state
+                    .stack
+                    .push((name.clone(), ScopeEntryType::Normal, typ.clone()));
+                Ok(Stmt::Let(Box::new(((name, pos), None, typ, comments))))
             }
             ScopeEntryType::Constant => {
-                state.stack.push((name.clone(), ScopeEntryType::Constant));
-                Ok(Stmt::Const(Box::new(((name, pos), Expr::Unit(pos)))))
+                state
+                    .stack
+                    .push((name.clone(), ScopeEntryType::Constant, typ.clone()));
+                Ok(Stmt::Const(Box::new((
+                    (name, pos),
+                    Expr::Unit(pos),
+                    typ,
+                    comments,
+                ))))
             }
         }
     }
 }
 
+/// Parse a type annotation, i.e. the type name following a `:` or `->`, or `()` for the unit type.
+fn parse_type_annotation(input: &mut TokenStream) -> Result<String, ParseError> {
+    match input.next().unwrap() {
+        (Token::Identifier(s), _) => Ok(s),
+        (Token::LeftParen, _) => match input.next().unwrap() {
+            (Token::RightParen, _) => Ok("()".into()),
+            (Token::LexError(err), pos) => Err(err.into_err(pos)),
+            (_, pos) => Err(PERR::TypeExpected.into_err(pos)),
+        },
+        (Token::Reserved(s), _) if is_valid_identifier(s.chars()) => Ok(s),
+        (Token::LexError(err), pos) => Err(err.into_err(pos)),
+        (_, pos) => Err(PERR::TypeExpected.into_err(pos)),
+    }
+}
+
 /// Parse an import statement.
 #[cfg(not(feature = "no_module"))]
 fn parse_import(
@@ -2615,51 +3336,179 @@ fn parse_import(
     #[cfg(not(feature = "unchecked"))]
     settings.ensure_level_within_max_limit(state.max_expr_depth)?;
 
-    // import expr ...
-    let expr = parse_expr(input, state, lib, settings.level_up())?;
+    let names = match input.peek().unwrap() {
+        // import { id as name, ... } from ...
+        (Token::LeftBrace, _) => parse_import_members(input, state, lib)?,
+        // import * from ...
+        (Token::Multiply, _) => {
+            eat_token(input, Token::Multiply);
+            ImportNames::All
+        }
+        _ => {
+            // import expr ...
+            let expr = parse_expr(input, state, lib, settings.level_up())?;
+
+            // import expr as ...
+            match input.next().unwrap() {
+                (Token::As, _) => (),
+                (_, pos) => {
+                    return Err(PERR::MissingToken(
+                        Token::As.into(),
+                        "in this import statement".into(),
+                    )
+                    .into_err(pos))
+                }
+            }
+
+            // import expr as name ...
+            let (name, _) = match input.next().unwrap() {
+                (Token::Identifier(s), pos) => (s, pos),
+                (Token::Reserved(s), pos) if is_valid_identifier(s.chars()) => {
+                    return Err(PERR::Reserved(s).into_err(pos));
+                }
+                (Token::LexError(err), pos) => return Err(err.into_err(pos)),
+                (_, pos) => return Err(PERR::VariableExpected.into_err(pos)),
+            };
 
-    // import expr as ...
+            state.modules.push(name.clone());
+            return Ok(Stmt::Import(Box::new((
+                expr,
+                ImportNames::Alias(name),
+                settings.pos,
+            ))));
+        }
+    };
+
+    // import ... from ...
     match input.next().unwrap() {
-        (Token::As, _) => (),
+        (Token::Identifier(s), _) if s == KEYWORD_FROM => (),
         (_, pos) => {
             return Err(
-                PERR::MissingToken(Token::As.into(), "in this import statement".into())
+                PERR::MissingToken(KEYWORD_FROM.into(), "in this import statement".into())
                     .into_err(pos),
             )
         }
     }
 
-    // import expr as name ...
-    let (name, _) = match input.next().unwrap() {
-        (Token::Identifier(s), pos) => (s, pos),
-        (Token::Reserved(s), pos) if is_valid_identifier(s.chars()) => {
-            return Err(PERR::Reserved(s).into_err(pos));
+    // import ... from expr
+    let expr = parse_expr(input, state, lib, settings.level_up())?;
+
+    // Members are bound into the local namespace - keep the module stack aligned with the runtime
+    state.modules.push(MARKER_IMPORT.into());
+
+    if let ImportNames::Members(members) = &names {
+        state.imported.extend(
+            members
+                .iter()
+                .map(|((id, _), rename)| rename.as_ref().map_or(id, |(name, _)| name).clone()),
+        );
+    }
+
+    Ok(Stmt::Import(Box::new((expr, names, settings.pos))))
+}
+
+/// Parse the list of members in an `import { ... } from` statement.
+#[cfg(not(feature = "no_module"))]
+fn parse_import_members(
+    input: &mut TokenStream,
+    state: &mut ParseState,
+    lib: &mut FunctionsLib,
+) -> Result<ImportNames, ParseError> {
+    eat_token(input, Token::LeftBrace);
+
+    let mut members = Vec::new();
+
+    while !match_token(input, Token::RightBrace)? {
+        let (id, id_pos) = match input.next().unwrap() {
+            (Token::Identifier(s), pos) => (s, pos),
+            (Token::Reserved(s), pos) if is_valid_identifier(s.chars()) => {
+                return Err(PERR::Reserved(s).into_err(pos));
+            }
+            (Token::LexError(err), pos) => return Err(err.into_err(pos)),
+            (_, pos) => return Err(PERR::VariableExpected.into_err(pos)),
+        };
+
+        let rename = if match_token(input, Token::As)? {
+            match input.next().unwrap() {
+                (Token::Identifier(s), pos) => Some((s, pos)),
+                (Token::Reserved(s), pos) if is_valid_identifier(s.chars()) => {
+                    return Err(PERR::Reserved(s).into_err(pos));
+                }
+                (Token::LexError(err), pos) => return Err(err.into_err(pos)),
+                (_, pos) => return Err(PERR::VariableExpected.into_err(pos)),
+            }
+        } else {
+            None
+        };
+
+        let (name, pos) = rename.as_ref().map_or((&id, id_pos), |(s, pos)| (s, *pos));
+
+        // Imported names must not collide with other names in the local namespace
+        let is_duplicated = members.iter().any(|((id2, _), rename2): &(_, Option<_>)| {
+            rename2.as_ref().map_or(id2, |(s, _)| s) == name
+        }) || state.imported.iter().any(|s| s == name)
+            || state.modules.iter().any(|s| s == name)
+            || state.stack.iter().any(|(s, _, _)| s == name)
+            || lib.values().any(|f| f.name.as_str() == name);
+
+        if is_duplicated {
+            return Err(PERR::DuplicatedImport(name.to_string()).into_err(pos));
         }
-        (Token::LexError(err), pos) => return Err(err.into_err(pos)),
-        (_, pos) => return Err(PERR::VariableExpected.into_err(pos)),
-    };
 
-    state.modules.push(name.clone());
-    Ok(Stmt::Import(Box::new((expr, (name, settings.pos)))))
+        members.push(((id, id_pos), rename));
+
+        match input.peek().unwrap() {
+            (Token::Comma, _) => {
+                eat_token(input, Token::Comma);
+            }
+            (Token::RightBrace, _) => (),
+            (Token::LexError(err), pos) => return Err(err.clone().into_err(*pos)),
+            (_, pos) => {
+                return Err(PERR::MissingToken(
+                    Token::Comma.into(),
+                    "to separate the list of imported members".into(),
+                )
+                .into_err(*pos))
+            }
+        }
+    }
+
+    Ok(ImportNames::Members(members))
 }
 
 /// Parse an export statement.
 #[cfg(not(feature = "no_module"))]
 fn parse_export(
     input: &mut TokenStream,
-    _state: &mut ParseState,
-    _lib: &mut FunctionsLib,
+    state: &mut ParseState,
+    lib: &mut FunctionsLib,
+    comments: Vec<String>,
     mut settings: ParseSettings,
 ) -> Result<Stmt, ParseError> {
     settings.pos = eat_token(input, Token::Export);
 
     #[cfg(not(feature = "unchecked"))]
-    settings.ensure_level_within_max_limit(_state.max_expr_depth)?;
+    settings.ensure_level_within_max_limit(state.max_expr_depth)?;
+
+    // export import expr as name
+    if let (Token::Import, pos) = input.peek().unwrap() {
+        let pos = *pos;
+
+        let stmt = parse_import(input, state, lib, settings.level_up())?;
+
+        return match stmt {
+            Stmt::Import(ref x) if matches!(x.1, ImportNames::Alias(_)) => Ok(stmt),
+            _ => Err(PERR::BadInput(
+                "Only a module imported under a name can be re-exported".to_string(),
+            )
+            .into_err(pos)),
+        };
+    }
 
     let mut exports = StaticVec::new();
 
     loop {
-        let (id, id_pos) = match input.next().unwrap() {
+        let (mut id, mut id_pos) = match input.next().unwrap() {
             (Token::Identifier(s), pos) => (s.clone(), pos),
             (Token::Reserved(s), pos) if is_valid_identifier(s.chars()) => {
                 return Err(PERR::Reserved(s).into_err(pos));
@@ -2668,6 +3517,29 @@ fn parse_export(
             (_, pos) => return Err(PERR::VariableExpected.into_err(pos)),
         };
 
+        // export module::id ...
+        let mut modules: Option<Box<ModuleRef>> = None;
+
+        while match_token(input, Token::DoubleColon)? {
+            let (id2, pos2) = match input.next().unwrap() {
+                (Token::Identifier(s), pos) => (s, pos),
+                (Token::Reserved(s), pos) if is_valid_identifier(s.chars()) => {
+                    return Err(PERR::Reserved(s).into_err(pos));
+                }
+                (Token::LexError(err), pos) => return Err(err.into_err(pos)),
+                (_, pos) => return Err(PERR::VariableExpected.into_err(pos)),
+            };
+
+            modules
+                .get_or_insert_with(Default::default)
+                .push((mem::replace(&mut id, id2), id_pos));
+            id_pos = pos2;
+        }
+
+        if let Some(ref mut modules) = modules {
+            modules.set_index(state.find_module(&modules.get(0).0));
+        }
+
         let rename = if match_token(input, Token::As)? {
             match input.next().unwrap() {
                 (Token::Identifier(s), pos) => Some((s.clone(), pos)),
@@ -2681,7 +3553,7 @@ fn parse_export(
             None
         };
 
-        exports.push(((id, id_pos), rename));
+        exports.push(((id, id_pos), modules, rename));
 
         match input.peek().unwrap() {
             (Token::Comma, _) => {
@@ -2698,20 +3570,30 @@ fn parse_export(
         }
     }
 
-    // Check for duplicating parameters
+    // Check for duplicating exports - each variable carries only one alias,
+    // and each name can only be exported once
     exports
         .iter()
         .enumerate()
-        .try_for_each(|(i, ((id1, _), _))| {
+        .try_for_each(|(i, ((id1, _), modules1, rename1))| {
+            let name1 = rename1.as_ref().map_or(id1, |(n, _)| n);
+
             exports
                 .iter()
                 .skip(i + 1)
-                .find(|((id2, _), _)| id2 == id1)
-                .map_or_else(|| Ok(()), |((id2, pos), _)| Err((id2, *pos)))
+                .find_map(|((id2, pos2), modules2, rename2)| match rename2 {
+                    Some((name2, pos)) if name2 == name1 => Some((name2, *pos)),
+                    None if id2 == name1 => Some((id2, *pos2)),
+                    _ if modules1.is_none() && modules2.is_none() && id2 == id1 => {
+                        Some((id2, *pos2))
+                    }
+                    _ => None,
+                })
+                .map_or_else(|| Ok(()), Err)
         })
         .map_err(|(id2, pos)| PERR::DuplicatedExport(id2.to_string()).into_err(pos))?;
 
-    Ok(Stmt::Export(Box::new(exports)))
+    Ok(Stmt::Export(Box::new((exports, comments))))
 }
 
 /// Parse a statement block.
@@ -2740,23 +3622,42 @@ fn parse_block(
     let mut statements = StaticVec::new();
     let prev_stack_len = state.stack.len();
     let prev_mods_len = state.modules.len();
+    let prev_imported_len = state.imported.len();
 
     while !match_token(input, Token::RightBrace)? {
         // Parse statements inside the block
         settings.is_global = false;
 
-        let stmt = if let Some(s) = parse_stmt(input, state, lib, settings.level_up())? {
-            s
-        } else {
-            continue;
+        let start = input.peek().unwrap().1;
+        let stmt = match parse_stmt(input, state, lib, settings.level_up()) {
+            Ok(Some(stmt)) => stmt,
+            Ok(None) => continue,
+            Err(err) => {
+                state.recover(input, err, start)?;
+
+                // { ... <error> <EOF> - in recovering mode, end the unterminated block
+                if let (Token::EOF, pos) = input.peek().unwrap() {
+                    let err = PERR::MissingToken(
+                        Token::RightBrace.into(),
+                        "to end this statement block".into(),
+                    )
+                    .into_err(*pos);
+
+                    state.record(input, err)?;
+                    break;
+                }
+                continue;
+            }
         };
 
+        input.add_span(stmt.position(), NodeType::of_stmt(&stmt), start);
+
         // See if it needs a terminating semicolon
         let need_semicolon = !stmt.is_self_terminated();
 
         statements.push(stmt);
 
-        match input.peek().unwrap() {
+        let recovered = match input.peek().unwrap() {
             // { ... stmt }
             (Token::RightBrace, _) => {
                 eat_token(input, Token::RightBrace);
@@ -2765,27 +3666,44 @@ fn parse_block(
             // { ... stmt;
             (Token::SemiColon, _) if need_semicolon => {
                 eat_token(input, Token::SemiColon);
+                false
             }
             // { ... { stmt } ;
-            (Token::SemiColon, _) if !need_semicolon => (),
+            (Token::SemiColon, _) if !need_semicolon => false,
             // { ... { stmt } ???
-            (_, _) if !need_semicolon => (),
+            (_, _) if !need_semicolon => false,
             // { ... stmt <error>
-            (Token::LexError(err), pos) => return Err(err.into_err(*pos)),
+            (Token::LexError(err), pos) => {
+                let err = err.into_err(*pos);
+                state.recover(input, err, start)?;
+                true
+            }
             // { ... stmt ???
             (_, pos) => {
                 // Semicolons are not optional between statements
-                return Err(PERR::MissingToken(
+                let err = PERR::MissingToken(
                     Token::SemiColon.into(),
                     "to terminate this statement".into(),
                 )
-                .into_err(*pos));
+                .into_err(*pos);
+
+                // In recovering mode, carry on as if the semicolon is there
+                state.record(input, err)?;
+                true
             }
+        };
+
+        // { ... stmt <error> <EOF> - in recovering mode, end the unterminated block
+        if recovered && input.peek().unwrap().0.is_eof() {
+            break;
         }
     }
 
     state.stack.truncate(prev_stack_len);
     state.modules.truncate(prev_mods_len);
+    state.imported.truncate(prev_imported_len);
+
+    input.add_span(settings.pos, NodeType::Block, settings.pos);
 
     Ok(Stmt::Block(Box::new((statements, settings.pos))))
 }
@@ -2816,6 +3734,15 @@ fn parse_stmt(
 ) -> Result<Option<Stmt>, ParseError> {
     use ScopeEntryType::{Constant, Normal};
 
+    // Doc comments
+    let mut comments = Vec::new();
+
+    while let (Token::DocComment(_), _) = input.peek().unwrap() {
+        if let (Token::DocComment(comment), _) = input.next().unwrap() {
+            comments.push(comment);
+        }
+    }
+
     let (token, token_pos) = match input.peek().unwrap() {
         (Token::EOF, pos) => return Ok(Some(Stmt::Noop(*pos))),
         x => x,
@@ -2844,12 +3771,16 @@ fn parse_stmt(
                 FnAccess::Public
             };
 
+            let start = settings.pos;
+
             match input.next().unwrap() {
                 (Token::Fn, pos) => {
+                    let imported = &state.imported;
                     let mut _externals = Default::default();
                     let mut state = ParseState::new(
                         state.engine,
                         &mut _externals,
+                        state.errors.as_deref_mut(),
                         #[cfg(not(feature = "unchecked"))]
                         state.max_function_expr_depth,
                         #[cfg(not(feature = "unchecked"))]
@@ -2867,7 +3798,13 @@ fn parse_stmt(
                         pos: pos,
                     };
 
-                    let func = parse_fn(input, &mut state, lib, access, settings)?;
+                    let func = parse_fn(input, &mut state, lib, access, comments, settings)?;
+                    input.add_span(func.pos, NodeType::Other, start);
+
+                    // Functions must not collide with names imported into the local namespace
+                    if imported.iter().any(|s| s == func.name.as_str()) {
+                        return Err(PERR::DuplicatedImport(func.name.to_string()).into_err(pos));
+                    }
 
                     // Qualifiers (none) + function name + number of arguments.
                     let hash = calc_fn_hash(empty(), &func.name, func.params.len(), empty());
@@ -2931,8 +3868,10 @@ fn parse_stmt(
             }
         }
 
-        Token::Let => parse_let(input, state, lib, Normal, settings.level_up()).map(Some),
-        Token::Const => parse_let(input, state, lib, Constant, settings.level_up()).map(Some),
+        Token::Let => parse_let(input, state, lib, Normal, comments, settings.level_up()).map(Some),
+        Token::Const => {
+            parse_let(input, state, lib, Constant, comments, settings.level_up()).map(Some)
+        }
 
         #[cfg(not(feature = "no_module"))]
         Token::Import => parse_import(input, state, lib, settings.level_up()).map(Some),
@@ -2941,7 +3880,7 @@ fn parse_stmt(
         Token::Export if !settings.is_global => Err(PERR::WrongExport.into_err(settings.pos)),
 
         #[cfg(not(feature = "no_module"))]
-        Token::Export => parse_export(input, state, lib, settings.level_up()).map(Some),
+        Token::Export => parse_export(input, state, lib, comments, settings.level_up()).map(Some),
 
         _ => parse_expr_stmt(input, state, lib, settings.level_up()).map(Some),
     }
@@ -2954,6 +3893,7 @@ fn parse_fn(
     state: &mut ParseState,
     lib: &mut FunctionsLib,
     access: FnAccess,
+    comments: Vec<String>,
     mut settings: ParseSettings,
 ) -> Result<ScriptFnDef, ParseError> {
     #[cfg(not(feature = "unchecked"))]
@@ -2982,8 +3922,18 @@ fn parse_fn(
             match input.next().unwrap() {
                 (Token::RightParen, _) => break,
                 (Token::Identifier(s), pos) => {
-                    state.stack.push((s.clone(), ScopeEntryType::Normal));
-                    params.push((s, pos))
+                    // name: type
+                    let typ = if match_token(input, Token::Colon)? {
+                        Some(parse_type_annotation(input)?)
+                    } else {
+                        None
+                    };
+
+                    state
+                        .stack
+                        .push((s.clone(), ScopeEntryType::Normal, typ.clone()));
+
+                    params.push((s, pos, typ))
                 }
                 (Token::LexError(err), pos) => return Err(err.into_err(pos)),
                 (_, pos) => {
@@ -3010,18 +3960,29 @@ fn parse_fn(
     params
         .iter()
         .enumerate()
-        .try_for_each(|(i, (p1, _))| {
+        .try_for_each(|(i, (p1, _, _))| {
             params
                 .iter()
                 .skip(i + 1)
-                .find(|(p2, _)| p2 == p1)
-                .map_or_else(|| Ok(()), |(p2, pos)| Err((p2, *pos)))
+                .find(|(p2, _, _)| p2 == p1)
+                .map_or_else(|| Ok(()), |(p2, pos, _)| Err((p2, *pos)))
         })
         .map_err(|(p, pos)| {
             PERR::FnDuplicatedParam(name.to_string(), p.to_string()).into_err(pos)
         })?;
 
-    // Parse function body
+    // -> type
+    let return_type = match input.peek().unwrap() {
+        (token, _) if token.is_arrow() => {
+            input.next().unwrap();
+            Some(parse_type_annotation(input)?)
+        }
+        _ => None,
+    };
+
+    // Parse function body, recording whether it refers to the `global` namespace
+    let outer_uses_global = input.reset_uses_global();
+
     let body = match input.peek().unwrap() {
         (Token::LeftBrace, _) => {
             settings.is_breakable = false;
@@ -3030,13 +3991,25 @@ fn parse_fn(
         (_, pos) => return Err(PERR::FnMissingBody(name).into_err(*pos)),
     };
 
-    let params = params.into_iter().map(|(p, _)| p).collect();
+    let uses_global = input.uses_global();
+
+    if outer_uses_global {
+        input.set_uses_global();
+    }
+
+    let param_types = params.iter().map(|(_, _, t)| t.clone()).collect();
+    let params = params.into_iter().map(|(p, _, _)| p).collect();
 
     Ok(ScriptFnDef {
         name: name.into(),
         access,
         params,
+        param_types,
+        return_type,
+        comments,
         body,
+        uses_global,
+        source: input.source(),
         pos: settings.pos,
     })
 }
@@ -3060,7 +4033,7 @@ fn parse_anon_fn(
                 match input.next().unwrap() {
                     (Token::Pipe, _) => break,
                     (Token::Identifier(s), pos) => {
-                        state.stack.push((s.clone(), ScopeEntryType::Normal));
+                        state.stack.push((s.clone(), ScopeEntryType::Normal, None));
                         params.push((s, pos))
                     }
                     (Token::LexError(err), pos) => return Err(err.into_err(pos)),
@@ -3102,12 +4075,20 @@ fn parse_anon_fn(
         })
         .map_err(|(p, pos)| PERR::FnDuplicatedParam("".to_string(), p.to_string()).into_err(pos))?;
 
-    // Parse function body
+    // Parse function body, recording whether it refers to the `global` namespace
+    let outer_uses_global = input.reset_uses_global();
+
     settings.is_breakable = false;
     let pos = input.peek().unwrap().1;
     let body = parse_stmt(input, state, lib, settings.level_up())
         .map(|stmt| stmt.unwrap_or_else(|| Stmt::Noop(pos)))?;
 
+    let uses_global = input.uses_global();
+
+    if outer_uses_global {
+        input.set_uses_global();
+    }
+
     let mut static_params = StaticVec::<String>::new();
 
     #[cfg(not(feature = "no_closures"))]
@@ -3136,15 +4117,17 @@ fn parse_anon_fn(
     let script = ScriptFnDef {
         name: fn_name.clone(),
         access: FnAccess::Public,
+        param_types: static_params.iter().map(|_| None).collect(),
         params: static_params,
+        return_type: None,
+        comments: Default::default(),
         body,
+        uses_global,
+        source: input.source(),
         pos: settings.pos,
     };
 
-    let mut expr = state.make_curry_from_externals(
-        Expr::FnPointer(Box::new((fn_name, settings.pos))),
-        &settings,
-    );
+    let expr = Expr::FnPointer(Box::new((fn_name, settings.pos)));
 
     Ok((expr, script))
 }
@@ -3161,6 +4144,7 @@ impl Engine {
         let mut state = ParseState::new(
             self,
             &mut _externals,
+            None,
             #[cfg(not(feature = "unchecked"))]
             self.limits.max_expr_depth,
             #[cfg(not(feature = "unchecked"))]
@@ -3177,30 +4161,40 @@ impl Engine {
             level: 0,
             pos: Position::none(),
         };
-        let expr = parse_expr(input, &mut state, &mut functions, settings)?;
+        let expr = parse_expr(input, &mut state, &mut functions, settings)
+            .map_err(|err| input.locate_error(err))?;
 
         match input.peek().unwrap() {
             (Token::EOF, _) => (),
             // Return error if the expression doesn't end
             (token, pos) => {
-                return Err(
-                    PERR::BadInput(format!("Unexpected '{}'", token.syntax())).into_err(*pos)
-                )
+                let err = PERR::BadInput(format!("Unexpected '{}'", token.syntax())).into_err(*pos);
+                return Err(input.locate_error(err));
             }
         }
 
         let expr = vec![Stmt::Expr(Box::new(expr))];
 
-        Ok(
-            // Optimize AST
-            optimize_into_ast(self, scope, expr, Default::default(), optimization_level),
-        )
+        // Optimize AST
+        let mut ast = optimize_into_ast(self, scope, expr, Default::default(), optimization_level);
+        ast.set_source(input.source(), input.take_spans());
+        ast.set_uses_global(input.uses_global());
+
+        if self.backend.is_bytecode() {
+            ast.compile_program();
+        }
+
+        Ok(ast)
     }
 
     /// Parse the global level statements.
-    fn parse_global_level(
+    ///
+    /// If `errors` is `Some`, parse in recovering mode, recording all parse errors into it
+    /// and skipping the statements in error.
+    pub(crate) fn parse_global_level(
         &self,
         input: &mut TokenStream,
+        errors: Option<&mut Vec<ParseError>>,
     ) -> Result<(Vec<Stmt>, Vec<ScriptFnDef>), ParseError> {
         let mut statements: Vec<Stmt> = Default::default();
         let mut functions = Default::default();
@@ -3208,6 +4202,7 @@ impl Engine {
         let mut state = ParseState::new(
             self,
             &mut _externals,
+            errors,
             #[cfg(not(feature = "unchecked"))]
             self.limits.max_expr_depth,
             #[cfg(not(feature = "unchecked"))]
@@ -3226,12 +4221,18 @@ impl Engine {
                 pos: Position::none(),
             };
 
-            let stmt = if let Some(s) = parse_stmt(input, &mut state, &mut functions, settings)? {
-                s
-            } else {
-                continue;
+            let start = input.peek().unwrap().1;
+            let stmt = match parse_stmt(input, &mut state, &mut functions, settings) {
+                Ok(Some(stmt)) => stmt,
+                Ok(None) => continue,
+                Err(err) => {
+                    state.recover(input, err, start)?;
+                    continue;
+                }
             };
 
+            input.add_span(stmt.position(), NodeType::of_stmt(&stmt), start);
+
             let need_semicolon = !stmt.is_self_terminated();
 
             statements.push(stmt);
@@ -3248,15 +4249,21 @@ impl Engine {
                 // { stmt } ???
                 (_, _) if !need_semicolon => (),
                 // stmt <error>
-                (Token::LexError(err), pos) => return Err(err.into_err(*pos)),
+                (Token::LexError(err), pos) => {
+                    let err = err.into_err(*pos);
+                    state.recover(input, err, start)?;
+                }
                 // stmt ???
                 (_, pos) => {
                     // Semicolons are not optional between statements
-                    return Err(PERR::MissingToken(
+                    let err = PERR::MissingToken(
                         Token::SemiColon.into(),
                         "to terminate this statement".into(),
                     )
-                    .into_err(*pos));
+                    .into_err(*pos);
+
+                    // In recovering mode, carry on as if the semicolon is there
+                    state.record(input, err)?;
                 }
             }
         }
@@ -3271,12 +4278,78 @@ impl Engine {
         scope: &Scope,
         optimization_level: OptimizationLevel,
     ) -> Result<AST, ParseError> {
-        let (statements, lib) = self.parse_global_level(input)?;
+        self.parse_with_diagnostics(input, scope, optimization_level, false)
+            .map(|(ast, _)| ast)
+    }
 
-        Ok(
-            // Optimize AST
-            optimize_into_ast(self, scope, statements, lib, optimization_level),
-        )
+    /// Run the parser on an input stream, returning an AST together with diagnostics
+    /// (if `diagnose` is `true`) on the script before optimization.
+    pub(crate) fn parse_with_diagnostics(
+        &self,
+        input: &mut TokenStream,
+        scope: &Scope,
+        optimization_level: OptimizationLevel,
+        diagnose: bool,
+    ) -> Result<(AST, Vec<Diagnostic>), ParseError> {
+        let (statements, lib) = self
+            .parse_global_level(input, None)
+            .map_err(|err| input.locate_error(err))?;
+
+        // Check types against annotations and registered functions
+        check_types(self, &statements, &lib).map_err(|err| input.locate_error(err))?;
+
+        // Diagnostics must be run before optimization changes the script
+        let diagnostics = if diagnose {
+            diagnostic::diagnose(&statements, &lib)
+        } else {
+            Default::default()
+        };
+
+        // Optimize AST
+        let mut ast = optimize_into_ast(self, scope, statements, lib, optimization_level);
+        ast.set_source(input.source(), input.take_spans());
+        ast.set_uses_global(input.uses_global());
+
+        if self.backend.is_bytecode() {
+            ast.compile_program();
+        }
+
+        Ok((ast, diagnostics))
+    }
+
+    /// Run the parser on an input stream in recovering mode, returning a partial AST
+    /// (leaving out the statements in error) together with all parse errors.
+    pub(crate) fn parse_with_recovery(
+        &self,
+        input: &mut TokenStream,
+        scope: &Scope,
+        optimization_level: OptimizationLevel,
+    ) -> (AST, Vec<ParseError>) {
+        let mut errors = Vec::new();
+
+        let (statements, lib) = match self.parse_global_level(input, Some(&mut errors)) {
+            Ok(result) => result,
+            Err(err) => {
+                errors.push(input.locate_error(err));
+                Default::default()
+            }
+        };
+
+        // Check types against annotations and registered functions
+        if let Err(err) = check_types(self, &statements, &lib) {
+            errors.push(input.locate_error(err));
+        }
+
+        // Optimize AST
+        let mut ast = optimize_into_ast(self, scope, statements, lib, optimization_level);
+        ast.set_source(input.source(), input.take_spans());
+        ast.set_uses_global(input.uses_global());
+
+        if self.backend.is_bytecode() {
+            ast.compile_program();
+        }
+
+        (ast, errors)
     }
 }
 
@@ -3,13 +3,14 @@
 use crate::any::Dynamic;
 use crate::error::ParseErrorType;
 use crate::parser::INT;
-use crate::token::Position;
+use crate::token::{Position, Span, Token};
 
 use crate::stdlib::{
     boxed::Box,
     error::Error,
     fmt,
     string::{String, ToString},
+    vec::Vec,
 };
 
 #[cfg(not(feature = "no_std"))]
@@ -37,8 +38,23 @@ pub enum EvalAltResult {
     /// Call to an unknown function. Wrapped value is the signature of the function.
     ErrorFunctionNotFound(String, Position),
     /// An error has occurred inside a called function.
-    /// Wrapped values are the name of the function and the interior error.
-    ErrorInFunctionCall(String, Box<EvalAltResult>, Position),
+    /// Wrapped values are the name of the function, its namespace if it is called with a qualified name
+    /// (e.g. `foo::bar` for `foo::bar::baz(x)`), the name of the source of the script defining the function,
+    /// if any, and the interior error, which is itself an `ErrorInFunctionCall` if the error occurred inside
+    /// a nested function call.
+    /// The position is that of the call.
+    ErrorInFunctionCall(
+        String,
+        Option<String>,
+        Option<String>,
+        Box<EvalAltResult>,
+        Position,
+    ),
+    /// An error has occurred in a script with a source (e.g. a file).
+    /// Wrapped values are the name of the source, the span of the code in which the error that
+    /// occurred first was raised (see `root_cause`), if known, and the interior error.
+    /// The position is that of the interior error.
+    ErrorInScript(String, Option<Span>, Box<EvalAltResult>),
     /// Access to `this` that is not bounded.
     ErrorUnboundedThis(Position),
     /// Non-boolean operand encountered for boolean operator. Wrapped value is the operator.
@@ -76,12 +92,17 @@ pub enum EvalAltResult {
     /// Returned type is not the same as the required output type.
     /// Wrapped values are the type requested and type of the actual result.
     ErrorMismatchOutputType(String, String, Position),
+    /// A value does not match its type annotation.
+    /// Wrapped values are the type requested and the type of the actual value.
+    ErrorMismatchDataType(String, String, Position),
     /// Inappropriate member access.
     ErrorDotExpr(String, Position),
     /// Arithmetic error encountered. Wrapped value is the error message.
     ErrorArithmetic(String, Position),
     /// Number of operations over maximum limit.
     ErrorTooManyOperations(Position),
+    /// Execution time over maximum limit, or past the deadline of the call.
+    ErrorTimeout(Position),
     /// Modules over maximum limit.
     ErrorTooManyModules(Position),
     /// Call stack over maximum limit.
@@ -90,6 +111,8 @@ pub enum EvalAltResult {
     ErrorDataTooLarge(String, usize, usize, Position),
     /// The script is prematurely terminated.
     ErrorTerminated(Position),
+    /// The script is interrupted via an `InterruptHandle`. Wrapped value is the reason, which may be empty.
+    ErrorInterrupted(String, Position),
     /// Run-time error encountered. Wrapped value is the error message.
     ErrorRuntime(String, Position),
 
@@ -110,7 +133,8 @@ impl EvalAltResult {
             Self::ErrorReadingScriptFile(_, _, _) => "Cannot read from script file",
 
             Self::ErrorParsing(p, _) => p.desc(),
-            Self::ErrorInFunctionCall(_, _, _) => "Error in called function",
+            Self::ErrorInFunctionCall(_, _, _, _, _) => "Error in called function",
+            Self::ErrorInScript(_, _, err) => err.desc(),
             Self::ErrorFunctionNotFound(_, _) => "Function not found",
             Self::ErrorUnboundedThis(_) => "'this' is not bounded",
             Self::ErrorBooleanArgMismatch(_, _) => "Boolean operator expects boolean operands",
@@ -142,14 +166,17 @@ impl EvalAltResult {
             }
             Self::ErrorAssignmentToConstant(_, _) => "Assignment to a constant variable",
             Self::ErrorMismatchOutputType(_, _, _) => "Output type is incorrect",
+            Self::ErrorMismatchDataType(_, _, _) => "Data type is incorrect",
             Self::ErrorInExpr(_) => "Malformed 'in' expression",
             Self::ErrorDotExpr(_, _) => "Malformed dot expression",
             Self::ErrorArithmetic(_, _) => "Arithmetic error",
             Self::ErrorTooManyOperations(_) => "Too many operations",
+            Self::ErrorTimeout(_) => "Execution timed out",
             Self::ErrorTooManyModules(_) => "Too many modules imported",
             Self::ErrorStackOverflow(_) => "Stack overflow",
             Self::ErrorDataTooLarge(_, _, _, _) => "Data size exceeds maximum limit",
             Self::ErrorTerminated(_) => "Script terminated.",
+            Self::ErrorInterrupted(_, _) => "Script interrupted",
             Self::ErrorRuntime(_, _) => "Runtime error",
             Self::ErrorLoopBreak(true, _) => "Break statement not inside a loop",
             Self::ErrorLoopBreak(false, _) => "Continue statement not inside a loop",
@@ -174,8 +201,27 @@ impl fmt::Display for EvalAltResult {
 
             Self::ErrorParsing(p, _) => write!(f, "Syntax error: {}", p)?,
 
-            Self::ErrorInFunctionCall(s, err, _) => {
-                write!(f, "Error in call to function '{}' : {}", s, err)?
+            Self::ErrorInScript(source, _, err)
+                if !matches!(**err, Self::ErrorInFunctionCall(_, _, _, _, _)) =>
+            {
+                return write!(f, "{} in '{}'", err, source);
+            }
+
+            // Write the error that occurred first, followed by the calls leading to it
+            Self::ErrorInFunctionCall(_, _, _, _, _) | Self::ErrorInScript(_, _, _) => {
+                write!(f, "{}", self.root_cause())?;
+
+                for frame in self.backtrace() {
+                    write!(f, "\n    in call to function '{}'", frame)?;
+
+                    match (&frame.source, frame.pos.is_none()) {
+                        (_, true) => (),
+                        (Some(source), false) => write!(f, " ({} in '{}')", frame.pos, source)?,
+                        (None, false) => write!(f, " ({})", frame.pos)?,
+                    }
+                }
+
+                return Ok(());
             }
 
             Self::ErrorFunctionNotFound(s, _)
@@ -195,16 +241,20 @@ impl fmt::Display for EvalAltResult {
             | Self::ErrorInExpr(_)
             | Self::ErrorDotExpr(_, _)
             | Self::ErrorTooManyOperations(_)
+            | Self::ErrorTimeout(_)
             | Self::ErrorTooManyModules(_)
             | Self::ErrorStackOverflow(_)
             | Self::ErrorTerminated(_) => f.write_str(desc)?,
 
             Self::ErrorRuntime(s, _) => f.write_str(if s.is_empty() { desc } else { s })?,
+            Self::ErrorInterrupted(s, _) if s.is_empty() => f.write_str(desc)?,
+            Self::ErrorInterrupted(s, _) => write!(f, "{}: {}", desc, s)?,
 
             Self::ErrorAssignmentToConstant(s, _) => write!(f, "{}: '{}'", desc, s)?,
             Self::ErrorMismatchOutputType(r, s, _) => {
                 write!(f, "{} (expecting {}): {}", desc, s, r)?
             }
+            Self::ErrorMismatchDataType(r, a, _) => write!(f, "{}: {} (expecting {})", desc, a, r)?,
             Self::ErrorArithmetic(s, _) => f.write_str(s)?,
 
             Self::ErrorLoopBreak(_, _) => f.write_str(desc)?,
@@ -256,6 +306,37 @@ impl fmt::Display for EvalAltResult {
     }
 }
 
+/// A call to a function leading to an error, as reported by `EvalAltResult::backtrace`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct BacktraceFrame {
+    /// Name of the function.
+    pub fn_name: String,
+    /// Namespace of the function if it is called with a qualified name, e.g. `foo::bar` for `foo::bar::baz(x)`.
+    pub namespace: Option<String>,
+    /// Name of the source (e.g. file name) of the script containing the call, if any.
+    ///
+    /// This is the source of the calling function; the outermost call is made from the script
+    /// being evaluated, the source of which is available via `AST::source`, so it is always `None`.
+    pub source: Option<String>,
+    /// Position of the call.
+    pub pos: Position,
+}
+
+impl fmt::Display for BacktraceFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.namespace {
+            Some(namespace) => write!(
+                f,
+                "{}{}{}",
+                namespace,
+                Token::DoubleColon.syntax(),
+                self.fn_name
+            ),
+            None => f.write_str(&self.fn_name),
+        }
+    }
+}
+
 impl<T: AsRef<str>> From<T> for Box<EvalAltResult> {
     fn from(err: T) -> Self {
         Box::new(EvalAltResult::ErrorRuntime(
@@ -266,6 +347,123 @@ impl<T: AsRef<str>> From<T> for Box<EvalAltResult> {
 }
 
 impl EvalAltResult {
+    /// Get the calls to functions leading to this error, the innermost call first.
+    ///
+    /// The list is empty if the error did not occur inside a function call.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(not(feature = "no_function"))]
+    /// # {
+    /// use rhai::Engine;
+    ///
+    /// let engine = Engine::new();
+    ///
+    /// let err = engine.eval::<()>(r#"
+    ///     fn inner() { throw "oops"; }
+    ///     fn outer() { inner() }
+    ///     outer()
+    /// "#).expect_err("should error");
+    ///
+    /// let backtrace = err.backtrace();
+    ///
+    /// assert_eq!(backtrace.len(), 2);
+    /// assert_eq!(backtrace[0].fn_name, "inner");
+    /// assert_eq!(backtrace[0].pos.line(), Some(3));
+    /// assert_eq!(backtrace[1].fn_name, "outer");
+    /// assert_eq!(backtrace[1].pos.line(), Some(4));
+    /// # }
+    /// ```
+    pub fn backtrace(&self) -> Vec<BacktraceFrame> {
+        let mut frames = Vec::new();
+        let mut err = self;
+
+        let mut caller_source = None;
+
+        loop {
+            match err {
+                Self::ErrorInFunctionCall(fn_name, namespace, source, inner, pos) => {
+                    frames.push(BacktraceFrame {
+                        fn_name: fn_name.clone(),
+                        namespace: namespace.clone(),
+                        source: caller_source,
+                        pos: *pos,
+                    });
+                    caller_source = source.clone();
+                    err = inner;
+                }
+                Self::ErrorInScript(source, _, inner) => {
+                    caller_source = Some(source.clone());
+                    err = inner;
+                }
+                _ => break,
+            }
+        }
+
+        frames.reverse();
+        frames
+    }
+    /// Get the error that occurred first, inside all the function calls leading to this error.
+    ///
+    /// The error itself is returned if it did not occur inside a function call.
+    pub fn root_cause(&self) -> &Self {
+        match self {
+            Self::ErrorInFunctionCall(_, _, _, err, _) | Self::ErrorInScript(_, _, err) => {
+                err.root_cause()
+            }
+            _ => self,
+        }
+    }
+    /// Get the name of the source (e.g. file name) of the script in which the error that occurred first
+    /// (see `root_cause`) was raised, if any.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::Engine;
+    ///
+    /// let engine = Engine::new();
+    ///
+    /// let ast = engine.compile_with_source("main.rhai", "let x = 1; x + true")?;
+    ///
+    /// let err = engine.eval_ast::<()>(&ast).expect_err("should error");
+    ///
+    /// assert_eq!(err.root_source(), Some("main.rhai"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn root_source(&self) -> Option<&str> {
+        let mut err = self;
+        let mut source = None;
+
+        loop {
+            match err {
+                Self::ErrorInFunctionCall(_, _, fn_source, inner, _) => {
+                    source = fn_source.as_deref();
+                    err = inner;
+                }
+                Self::ErrorInScript(script_source, _, inner) => {
+                    source = Some(script_source.as_str());
+                    err = inner;
+                }
+                _ => return source,
+            }
+        }
+    }
+    /// Get the span of the code in which the error that occurred first (see `root_cause`) was raised,
+    /// within its source (see `root_source`), if known.
+    ///
+    /// Spans are only known for errors in scripts with a source.  Otherwise, use `AST::span` with the
+    /// position of the error.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Self::ErrorInFunctionCall(_, _, _, err, _) => err.span(),
+            Self::ErrorInScript(_, span, err) => err.span().or(*span),
+            _ => None,
+        }
+    }
     /// Get the `Position` of this error.
     pub fn position(&self) -> Position {
         match self {
@@ -275,7 +473,7 @@ impl EvalAltResult {
 
             Self::ErrorParsing(_, pos)
             | Self::ErrorFunctionNotFound(_, pos)
-            | Self::ErrorInFunctionCall(_, _, pos)
+            | Self::ErrorInFunctionCall(_, _, _, _, pos)
             | Self::ErrorUnboundedThis(pos)
             | Self::ErrorBooleanArgMismatch(_, pos)
             | Self::ErrorCharMismatch(pos)
@@ -292,17 +490,22 @@ impl EvalAltResult {
             | Self::ErrorAssignmentToUnknownLHS(pos)
             | Self::ErrorAssignmentToConstant(_, pos)
             | Self::ErrorMismatchOutputType(_, _, pos)
+            | Self::ErrorMismatchDataType(_, _, pos)
             | Self::ErrorInExpr(pos)
             | Self::ErrorDotExpr(_, pos)
             | Self::ErrorArithmetic(_, pos)
             | Self::ErrorTooManyOperations(pos)
+            | Self::ErrorTimeout(pos)
             | Self::ErrorTooManyModules(pos)
             | Self::ErrorStackOverflow(pos)
             | Self::ErrorDataTooLarge(_, _, _, pos)
             | Self::ErrorTerminated(pos)
+            | Self::ErrorInterrupted(_, pos)
             | Self::ErrorRuntime(_, pos)
             | Self::ErrorLoopBreak(_, pos)
             | Self::Return(_, pos) => *pos,
+
+            Self::ErrorInScript(_, _, err) => err.position(),
         }
     }
 
@@ -315,7 +518,7 @@ impl EvalAltResult {
 
             Self::ErrorParsing(_, pos)
             | Self::ErrorFunctionNotFound(_, pos)
-            | Self::ErrorInFunctionCall(_, _, pos)
+            | Self::ErrorInFunctionCall(_, _, _, _, pos)
             | Self::ErrorUnboundedThis(pos)
             | Self::ErrorBooleanArgMismatch(_, pos)
             | Self::ErrorCharMismatch(pos)
@@ -332,17 +535,22 @@ impl EvalAltResult {
             | Self::ErrorAssignmentToUnknownLHS(pos)
             | Self::ErrorAssignmentToConstant(_, pos)
             | Self::ErrorMismatchOutputType(_, _, pos)
+            | Self::ErrorMismatchDataType(_, _, pos)
             | Self::ErrorInExpr(pos)
             | Self::ErrorDotExpr(_, pos)
             | Self::ErrorArithmetic(_, pos)
             | Self::ErrorTooManyOperations(pos)
+            | Self::ErrorTimeout(pos)
             | Self::ErrorTooManyModules(pos)
             | Self::ErrorStackOverflow(pos)
             | Self::ErrorDataTooLarge(_, _, _, pos)
             | Self::ErrorTerminated(pos)
+            | Self::ErrorInterrupted(_, pos)
             | Self::ErrorRuntime(_, pos)
             | Self::ErrorLoopBreak(_, pos)
             | Self::Return(_, pos) => *pos = new_position,
+
+            Self::ErrorInScript(_, _, err) => err.set_position(new_position),
         }
     }
 
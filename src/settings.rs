@@ -1,5 +1,7 @@
 //! Configuration settings for `Engine`.
 
+use crate::bytecode::Backend;
+use crate::cost::NodeType;
 use crate::engine::Engine;
 use crate::optimize::OptimizationLevel;
 use crate::packages::PackageLibrary;
@@ -13,6 +15,10 @@ use crate::stdlib::{format, string::String};
 #[cfg(not(feature = "no_module"))]
 use crate::stdlib::boxed::Box;
 
+#[cfg(not(feature = "unchecked"))]
+#[cfg(not(feature = "no_std"))]
+use crate::stdlib::time::Duration;
+
 impl Engine {
     /// Load a new package into the `Engine`.
     ///
@@ -42,6 +48,226 @@ impl Engine {
         self.optimization_level
     }
 
+    /// Control which backend the `Engine` uses to run scripts.
+    ///
+    /// With `Backend::Bytecode`, scripts are compiled into bytecode together with the `AST`,
+    /// which is kept in the `AST` and run on a virtual machine.
+    /// An `AST` compiled by an `Engine` using another backend, or modified after compilation,
+    /// is compiled on the fly without its functions, which then run on the tree-walking interpreter.
+    pub fn set_backend(&mut self, backend: Backend) -> &mut Self {
+        self.backend = backend;
+        self
+    }
+
+    /// The backend used by the `Engine` to run scripts.
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// Control whether calls to registered functions are checked against their signatures at compile time.
+    /// Default is `false`.
+    ///
+    /// Only calls with arguments of annotated types (i.e. annotated variables and script-defined functions
+    /// with annotated return types) are checked, failing with `ParseErrorType::MismatchedFnArgs` when
+    /// no registered function of the same name and number of parameters takes them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rhai::{Engine, ParseErrorType, RegisterFn};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// engine.register_fn("double", |x: i64| x * 2);
+    /// engine.set_check_fn_signatures(true);
+    ///
+    /// let err = engine.compile(r#"let x: string = "hello"; double(x)"#).expect_err("should error");
+    ///
+    /// assert_eq!(*err.0, ParseErrorType::MismatchedFnArgs("double (string)".into()));
+    /// ```
+    pub fn set_check_fn_signatures(&mut self, check: bool) -> &mut Self {
+        self.check_fn_signatures = check;
+        self
+    }
+
+    /// Are calls to registered functions checked against their signatures at compile time?
+    pub fn check_fn_signatures(&self) -> bool {
+        self.check_fn_signatures
+    }
+
+    /// Memoize calls to script-defined functions with a particular name, caching their results
+    /// keyed by the argument values.
+    ///
+    /// Only mark functions whose result depends on nothing but their arguments - functions with side effects,
+    /// or which call functions with side effects, must never be memoized.
+    ///
+    /// Calls in method-call style, and calls with arguments that are not hashable (e.g. custom types),
+    /// are not memoized.  Results that exceed data size limits are not cached.
+    ///
+    /// Not available under the `no_function` feature.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// use rhai::Engine;
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// engine.memoize_fn("fib");
+    ///
+    /// // 'fib' runs only once for each 'n' instead of an exponential number of times
+    /// assert_eq!(engine.eval::<i64>(r"
+    ///     fn fib(n) { if n < 2 { n } else { fib(n - 1) + fib(n - 2) } }
+    ///     fib(14)
+    /// ")?, 377);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(feature = "no_function"))]
+    pub fn memoize_fn(&mut self, name: &str) -> &mut Self {
+        if self.memoized_fns.is_none() {
+            self.memoized_fns = Some(Default::default());
+        }
+
+        self.memoized_fns.as_mut().unwrap().insert(name.into());
+
+        self
+    }
+
+    /// Control whether the results of memoized functions are kept across evaluations.
+    /// Default is `false`, i.e. results are only cached within one evaluation.
+    ///
+    /// When kept, results are only reused for calls to the same function definition,
+    /// i.e. the same (or a cloned) `AST`.  Use `clear_memo_cache` to remove them.
+    ///
+    /// Not available under the `no_function` feature.
+    #[cfg(not(feature = "no_function"))]
+    pub fn set_memo_persistent(&mut self, persistent: bool) -> &mut Self {
+        self.memo_persistent = persistent;
+        self
+    }
+
+    /// Set the maximum number of results of memoized functions to cache.
+    /// When the cache is full, the oldest result is evicted before caching a new result.
+    /// Zero means caching nothing.
+    ///
+    /// Not available under the `no_function` feature.
+    #[cfg(not(feature = "no_function"))]
+    pub fn set_max_memo_entries(&mut self, max_entries: usize) -> &mut Self {
+        self.max_memo_entries = max_entries;
+        self
+    }
+
+    /// The maximum number of results of memoized functions to cache.
+    ///
+    /// Not available under the `no_function` feature.
+    #[cfg(not(feature = "no_function"))]
+    pub fn max_memo_entries(&self) -> usize {
+        self.max_memo_entries
+    }
+
+    /// Remove all results of memoized functions kept across evaluations.
+    ///
+    /// Not available under the `no_function` feature.
+    #[cfg(not(feature = "no_function"))]
+    pub fn clear_memo_cache(&self) {
+        self.memo_cache.clear();
+    }
+
+    /// Set the number of operations that evaluating a type of `AST` node counts as.
+    /// Default is one operation for every type.
+    ///
+    /// Together with the costs of functions (see `Engine::set_fn_cost`), this forms a deterministic
+    /// measure of the cost of running a script, which is also what the maximum number of operations
+    /// limits and what the progress callback reports.
+    ///
+    /// Notice that a cost of zero makes a type of node free, so a loop made only of free nodes
+    /// never reaches the maximum number of operations.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// # #[cfg(not(feature = "unchecked"))]
+    /// # {
+    /// use rhai::{Engine, EvalAltResult, NodeType};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// engine.set_max_operations(1000);
+    ///
+    /// let script = "let x = 0; for n in range(0, 100) { x += n; } x";
+    ///
+    /// assert_eq!(engine.eval::<i64>(script)?, 4950);
+    ///
+    /// // Make each iteration of a 'for' loop more expensive
+    /// engine.set_node_cost(NodeType::Iteration, 10);
+    ///
+    /// assert!(matches!(
+    ///     *engine.eval::<i64>(script).expect_err("should error"),
+    ///     EvalAltResult::ErrorTooManyOperations(_)
+    /// ));
+    /// # }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_node_cost(&mut self, node: NodeType, cost: u64) -> &mut Self {
+        self.costs
+            .get_or_insert_with(Default::default)
+            .set_node_cost(node, cost);
+        self
+    }
+
+    /// The number of operations that evaluating a type of `AST` node counts as.
+    pub fn node_cost(&self, node: NodeType) -> u64 {
+        self.costs.as_ref().map_or(1, |costs| costs.node_cost(node))
+    }
+
+    /// Set the number of operations that each call to a function with a particular name counts as,
+    /// whether it is a registered function, a script-defined function or an operator (e.g. `"+"`).
+    /// Default is one operation.
+    ///
+    /// Compound assignments (e.g. `x += 1`) count as calls to the compound operator (e.g. `"+="`)
+    /// if it has a cost set, otherwise as calls to the operator (e.g. `"+"`).
+    ///
+    /// To charge native functions extra based on the arguments of each call, register a function
+    /// calculating the extra cost via `Engine::register_fn_cost`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+    /// # #[cfg(not(feature = "unchecked"))]
+    /// # {
+    /// use rhai::{Engine, EvalAltResult};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// engine.set_max_operations(100);
+    ///
+    /// let script = "let x = 1; for n in range(0, 10) { x *= 2; } x";
+    ///
+    /// assert_eq!(engine.eval::<i64>(script)?, 1024);
+    ///
+    /// // Make multiplication expensive
+    /// engine.set_fn_cost("*", 20);
+    ///
+    /// assert!(matches!(
+    ///     *engine.eval::<i64>(script).expect_err("should error"),
+    ///     EvalAltResult::ErrorTooManyOperations(_)
+    /// ));
+    /// # }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_fn_cost(&mut self, name: &str, cost: u64) -> &mut Self {
+        self.costs
+            .get_or_insert_with(Default::default)
+            .set_fn_cost(name.into(), cost);
+        self
+    }
+
     /// Set the maximum levels of function calls allowed for a script in order to avoid
     /// infinite recursion and stack overflows.
     #[cfg(not(feature = "unchecked"))]
@@ -74,6 +300,25 @@ impl Engine {
         self.limits.max_operations
     }
 
+    /// Set the maximum time allowed for a script to run (zero for unlimited).
+    ///
+    /// Not available under the `no_std` feature.
+    #[cfg(not(feature = "unchecked"))]
+    #[cfg(not(feature = "no_std"))]
+    pub fn set_max_execution_time(&mut self, time: Duration) -> &mut Self {
+        self.limits.max_execution_time = time;
+        self
+    }
+
+    /// The maximum time allowed for a script to run (zero for unlimited).
+    ///
+    /// Not available under the `no_std` feature.
+    #[cfg(not(feature = "unchecked"))]
+    #[cfg(not(feature = "no_std"))]
+    pub fn max_execution_time(&self) -> Duration {
+        self.limits.max_execution_time
+    }
+
     /// Set the maximum number of imported modules allowed for a script.
     #[cfg(not(feature = "unchecked"))]
     pub fn set_max_modules(&mut self, modules: usize) -> &mut Self {
@@ -162,6 +407,26 @@ impl Engine {
         self.limits.max_map_size
     }
 
+    /// Set the maximum number of bytes held by the values of a script, approximately (0 for unlimited).
+    ///
+    /// All variables in scope and all values computed are counted, including the elements of strings,
+    /// arrays and object maps.  Use `Engine::register_type_size` to report the sizes of custom types.
+    #[cfg(not(feature = "unchecked"))]
+    pub fn set_max_memory(&mut self, max_bytes: usize) -> &mut Self {
+        self.limits.max_memory = if max_bytes == usize::MAX {
+            0
+        } else {
+            max_bytes
+        };
+        self
+    }
+
+    /// The maximum number of bytes held by the values of a script (0 for unlimited).
+    #[cfg(not(feature = "unchecked"))]
+    pub fn max_memory(&self) -> usize {
+        self.limits.max_memory
+    }
+
     /// Set the module resolution service used by the `Engine`.
     ///
     /// Not available under the `no_module` feature.
@@ -0,0 +1,67 @@
+//! Module defining statistics collected while evaluating a script.
+
+use crate::stdlib::{collections::HashMap, string::String};
+
+#[cfg(not(feature = "no_std"))]
+use crate::stdlib::time::Duration;
+
+/// Statistics of an evaluation, returned by `Engine::eval_ast_with_stats`.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+/// use rhai::{Engine, Scope};
+///
+/// let engine = Engine::new();
+///
+/// let ast = engine.compile("let x = 1; let y = 2; x + y")?;
+///
+/// let (result, stats) = engine.eval_ast_with_stats::<i64>(&mut Scope::new(), &ast);
+///
+/// assert_eq!(result?, 3);
+/// assert_eq!(stats.max_scope_size, 2);
+/// assert_eq!(stats.fn_calls.get("+"), Some(&1));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct EvalStats {
+    /// Number of operations performed.
+    pub operations: u64,
+    /// Deepest level of nested calls to script-defined functions, zero if none is called.
+    pub max_call_depth: usize,
+    /// Largest number of variables in scope at any one time, in the global scope or in any
+    /// function (which each have a separate scope).
+    pub max_scope_size: usize,
+    /// Number of calls to each function and operator, by name.
+    /// Compound assignments built into the `Engine` (e.g. `+=` on numbers) are not counted.
+    pub fn_calls: HashMap<String, u64>,
+    /// Number of modules loaded.
+    pub modules: usize,
+    /// Time taken by the evaluation.
+    ///
+    /// Not available under the `no_std` feature.
+    #[cfg(not(feature = "no_std"))]
+    pub elapsed: Duration,
+}
+
+impl EvalStats {
+    /// Record a call to a function.
+    pub(crate) fn record_fn_call(&mut self, fn_name: &str) {
+        match self.fn_calls.get_mut(fn_name) {
+            Some(count) => *count += 1,
+            None => {
+                self.fn_calls.insert(fn_name.into(), 1);
+            }
+        }
+    }
+    /// Record the level of a call to a script-defined function.
+    pub(crate) fn record_call_depth(&mut self, level: usize) {
+        self.max_call_depth = self.max_call_depth.max(level);
+    }
+    /// Record the number of variables in scope.
+    pub(crate) fn record_scope_size(&mut self, size: usize) {
+        self.max_scope_size = self.max_scope_size.max(size);
+    }
+}
@@ -11,13 +11,18 @@ mod inner {
     #[cfg(not(target_arch = "wasm32"))]
     pub use core::{i128, u128};
 
-    pub use alloc::{borrow, boxed, format, rc, string, sync, vec};
+    pub use alloc::{borrow, boxed, format, rc, string, vec};
 
     pub use core_error as error;
 
     pub mod collections {
         pub use hashbrown::{HashMap, HashSet};
     }
+
+    pub mod sync {
+        pub use alloc::sync::*;
+        pub use core::sync::atomic;
+    }
 }
 
 #[cfg(not(feature = "no_std"))]
@@ -5,7 +5,9 @@ use crate::engine::{
     KEYWORD_SHARED, KEYWORD_PRINT, KEYWORD_THIS, KEYWORD_TYPE_OF,
 };
 
-use crate::error::LexError;
+use crate::cost::NodeType;
+use crate::error::{LexError, ParseError};
+use crate::fn_native::Shared;
 use crate::parser::INT;
 use crate::utils::StaticVec;
 
@@ -19,6 +21,7 @@ use crate::stdlib::{
     collections::HashMap,
     fmt, format,
     iter::Peekable,
+    mem,
     str::{Chars, FromStr},
     string::{String, ToString},
     vec::Vec,
@@ -26,24 +29,26 @@ use crate::stdlib::{
 
 type LERR = LexError;
 
-pub type TokenStream<'a, 't> = Peekable<TokenIterator<'a, 't>>;
+/// Error message for `->`, which is only valid before the return type of a function.
+const ERR_ARROW: &str = "'->' is not a valid symbol. This is not C or C++!";
 
 /// A location (line number + character position) in the input script.
 ///
-/// In order to keep footprint small, both line number and character position have 16-bit unsigned resolution,
-/// meaning they go up to a maximum of 65,535 lines and characters per line.
+/// In order to keep footprint small, the line number has 32-bit unsigned resolution while the
+/// character position has 16-bit unsigned resolution, meaning they go up to a maximum of
+/// 4,294,967,295 lines and 65,535 characters per line.
 /// Advancing beyond the maximum line length or maximum number of lines is not an error but has no effect.
 #[derive(Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy)]
 pub struct Position {
     /// Line number - 0 = none
-    line: u16,
+    line: u32,
     /// Character position - 0 = BOL
     pos: u16,
 }
 
 impl Position {
     /// Create a new `Position`.
-    pub fn new(line: u16, position: u16) -> Self {
+    pub fn new(line: u32, position: u16) -> Self {
         assert!(line != 0, "line cannot be zero");
 
         Self {
@@ -97,7 +102,7 @@ impl Position {
         assert!(!self.is_none(), "cannot advance Position::none");
 
         // Advance up to maximum position
-        if self.line < u16::MAX {
+        if self.line < u32::MAX {
             self.line += 1;
             self.pos = 0;
         }
@@ -136,6 +141,23 @@ impl fmt::Debug for Position {
     }
 }
 
+/// The extent of a statement or expression in the input script, from the start of its first token
+/// to the end of its last token (both inclusive).
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, Default)]
+pub struct Span {
+    /// Position of the first character.
+    pub start: Position,
+    /// Position of the last character.
+    pub end: Position,
+}
+
+impl Span {
+    /// Create a new `Span`.
+    pub fn new(start: Position, end: Position) -> Self {
+        Self { start, end }
+    }
+}
+
 /// [INTERNALS] A Rhai language token.
 /// Exported under the `internals` feature only.
 ///
@@ -306,6 +328,8 @@ pub enum Token {
     LexError(Box<LexError>),
     /// A comment block.
     Comment(String),
+    /// A doc comment (i.e. `///` or `/** */`) at the start of a statement.
+    DocComment(String),
     /// A reserved symbol.
     Reserved(String),
     /// A custom keyword.
@@ -328,6 +352,7 @@ impl Token {
             Identifier(s) => s.clone().into(),
             Reserved(s) => s.clone().into(),
             Custom(s) => s.clone().into(),
+            DocComment(s) => s.clone().into(),
             LexError(err) => err.to_string().into(),
 
             token => match token {
@@ -512,6 +537,29 @@ impl Token {
         }
     }
 
+    // Can this token start a statement with doc comments?
+    pub(crate) fn is_documentable(&self) -> bool {
+        match self {
+            Self::Let | Self::Const => true,
+            #[cfg(not(feature = "no_function"))]
+            Self::Fn | Self::Private => true,
+            #[cfg(not(feature = "no_module"))]
+            Self::Export => true,
+            _ => false,
+        }
+    }
+
+    // Is this token `->`, which is only valid before the return type of a function?
+    pub(crate) fn is_arrow(&self) -> bool {
+        match self {
+            Self::Reserved(s) | Self::Custom(s) => s == "->",
+            Self::LexError(err) => {
+                matches!(err.as_ref(), LERR::ImproperSymbol(s) if s == ERR_ARROW)
+            }
+            _ => false,
+        }
+    }
+
     // If another operator is after these, it's probably an unary operator
     // (not sure about fn name).
     pub fn is_next_unary(&self) -> bool {
@@ -699,6 +747,8 @@ pub struct TokenizeState {
     pub end_with_none: bool,
     /// Include comments?
     pub include_comments: bool,
+    /// Is the tokenizer at the start of a statement (i.e. can the next token be a doc comment)?
+    pub is_stmt_start: bool,
 }
 
 /// [INTERNALS] Trait that encapsulates a peekable character input stream.
@@ -917,6 +967,10 @@ pub fn get_next_token(
     // Save the last token's state
     if let Some((ref token, _)) = result {
         state.non_unary = !token.is_next_unary();
+        state.is_stmt_start = matches!(
+            token,
+            Token::SemiColon | Token::LeftBrace | Token::RightBrace | Token::DocComment(_)
+        );
     }
 
     result
@@ -1203,26 +1257,42 @@ fn get_next_token_inner(
             ('/', '/') => {
                 eat_next(stream, pos);
 
-                let mut comment = if state.include_comments {
-                    "//".to_string()
-                } else {
-                    String::new()
+                let mut comment = "//".to_string();
+
+                // `///` but not `////` is a doc comment
+                let is_doc_comment = state.is_stmt_start && stream.peek_next() == Some('/') && {
+                    eat_next(stream, pos);
+                    comment.push('/');
+                    stream.peek_next() != Some('/')
                 };
 
+                let keep = state.include_comments || is_doc_comment;
+
+                if !keep {
+                    comment.clear();
+                }
+
                 while let Some(c) = stream.get_next() {
                     if c == '\n' {
                         pos.new_line();
                         break;
                     }
 
-                    if state.include_comments {
+                    if keep {
                         comment.push(c);
                     }
                     pos.advance();
                 }
 
+                // Do not keep the `\r` of a CRLF line ending
+                if comment.ends_with('\r') {
+                    comment.pop();
+                }
+
                 if state.include_comments {
                     return Some((Token::Comment(comment), start_pos));
+                } else if is_doc_comment {
+                    return Some((Token::DocComment(comment), start_pos));
                 }
             }
             ('/', '*') => {
@@ -1230,15 +1300,43 @@ fn get_next_token_inner(
 
                 eat_next(stream, pos);
 
-                let mut comment = if state.include_comments {
-                    "/*".to_string()
-                } else {
-                    String::new()
+                let mut comment = "/*".to_string();
+
+                // `/**` but not `/***` or `/**/` is a doc comment
+                let is_doc_comment = state.is_stmt_start && stream.peek_next() == Some('*') && {
+                    eat_next(stream, pos);
+                    comment.push('*');
+
+                    match stream.peek_next() {
+                        Some('/') => {
+                            // `/**/` - an empty comment
+                            eat_next(stream, pos);
+                            comment.push('/');
+                            state.comment_level = 0;
+                            false
+                        }
+                        Some('*') => false,
+                        _ => true,
+                    }
                 };
-                scan_comment(stream, state, pos, &mut comment);
+
+                let keep = state.include_comments || is_doc_comment;
+
+                if !keep {
+                    comment.clear();
+                }
+
+                if state.comment_level > 0 {
+                    let include_comments = state.include_comments;
+                    state.include_comments = keep;
+                    scan_comment(stream, state, pos, &mut comment);
+                    state.include_comments = include_comments;
+                }
 
                 if state.include_comments {
                     return Some((Token::Comment(comment), start_pos));
+                } else if is_doc_comment {
+                    return Some((Token::DocComment(comment), start_pos));
                 }
             }
 
@@ -1475,8 +1573,8 @@ impl<'a> Iterator for TokenIterator<'a, '_> {
                 ("!==", false) => Token::LexError(Box::new(LERR::ImproperSymbol(
                     "'!==' is not a valid operator. This is not JavaScript! Should it be '!='?".to_string(),
                 ))),
-                ("->", false) => Token::LexError(Box::new(LERR::ImproperSymbol(
-                    "'->' is not a valid symbol. This is not C or C++!".to_string()))),
+                // '->' is only valid before function return type annotations, where the parser accepts it.
+                ("->", false) => Token::LexError(Box::new(LERR::ImproperSymbol(ERR_ARROW.to_string()))),
                 ("<-", false) => Token::LexError(Box::new(LERR::ImproperSymbol(
                     "'<-' is not a valid symbol. This is not Go! Should it be '<='?".to_string(),
                 ))),
@@ -1544,6 +1642,207 @@ impl<'a> Iterator for TokenIterator<'a, '_> {
     }
 }
 
+impl TokenIterator<'_, '_> {
+    /// Is the end of the input inside a block comment which is not terminated?
+    pub(crate) fn is_in_comment(&self) -> bool {
+        self.state.comment_level > 0
+    }
+}
+
+/// A stream of tokens which can be peeked one token ahead.
+///
+/// The stream tracks the end of the last token consumed, in order to record the spans of
+/// statements and expressions during parsing.
+pub struct TokenStream<'a, 'e> {
+    /// The underlying tokenizer.
+    iter: TokenIterator<'a, 'e>,
+    /// Peeked token, if any.
+    peeked: Option<Option<(Token, Position)>>,
+    /// End position of the peeked token.
+    peeked_end: Position,
+    /// Doc comments read ahead and the token following them, with their end positions,
+    /// in reverse order.
+    read_ahead: Vec<(Option<(Token, Position)>, Position)>,
+    /// Start and end positions of the last token consumed.
+    last_start: Position,
+    last_end: Position,
+    /// Name of the source (e.g. file name) of the input, if any.
+    source: Option<Shared<str>>,
+    /// Spans of statements and expressions, keyed by their positions and types.
+    spans: Vec<(Position, NodeType, Span)>,
+    /// Has a variable in the `global` namespace been parsed?
+    uses_global: bool,
+}
+
+impl<'a, 'e> TokenStream<'a, 'e> {
+    /// Create a new `TokenStream`.
+    pub fn new(iter: TokenIterator<'a, 'e>) -> Self {
+        Self {
+            iter,
+            peeked: None,
+            peeked_end: Position::none(),
+            read_ahead: Default::default(),
+            last_start: Position::none(),
+            last_end: Position::none(),
+            source: None,
+            spans: Default::default(),
+            uses_global: false,
+        }
+    }
+
+    /// Peek the next token without consuming it.
+    pub fn peek(&mut self) -> Option<&(Token, Position)> {
+        if self.peeked.is_none() {
+            let (next, end) = self.read();
+            self.peeked_end = end;
+            self.peeked = Some(next);
+        }
+        self.peeked.as_ref().unwrap().as_ref()
+    }
+
+    /// Read the next token from the tokenizer, together with its end position.
+    ///
+    /// Doc comments are only kept before statements which can be documented, i.e. functions,
+    /// variables, constants and exports.  Anywhere else they are skipped like normal comments.
+    fn read(&mut self) -> (Option<(Token, Position)>, Position) {
+        if let Some(next) = self.read_ahead.pop() {
+            return next;
+        }
+
+        let next = self.iter.next();
+        let end = self.iter.pos;
+
+        if !matches!(next, Some((Token::DocComment(_), _))) {
+            return (next, end);
+        }
+
+        let mut comments = Vec::new();
+        comments.push((next, end));
+
+        loop {
+            let next = self.iter.next();
+            let end = self.iter.pos;
+
+            match next {
+                Some((Token::DocComment(_), _)) => comments.push((next, end)),
+                Some((ref token, _)) if token.is_documentable() => {
+                    self.read_ahead.push((next, end));
+                    self.read_ahead.extend(comments.into_iter().rev());
+                    return self.read_ahead.pop().unwrap();
+                }
+                _ => return (next, end),
+            }
+        }
+    }
+
+    /// Set the name of the source (e.g. file name) of the input.
+    pub(crate) fn with_source(mut self, source: &str) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// Get the name of the source (e.g. file name) of the input, if any.
+    pub(crate) fn source(&self) -> Option<Shared<str>> {
+        self.source.clone()
+    }
+
+    /// Record that a variable in the `global` namespace is parsed.
+    pub(crate) fn set_uses_global(&mut self) {
+        self.uses_global = true;
+    }
+
+    /// Has a variable in the `global` namespace been parsed?
+    pub(crate) fn uses_global(&self) -> bool {
+        self.uses_global
+    }
+
+    /// Clear the record of variables in the `global` namespace parsed, returning the previous record.
+    pub(crate) fn reset_uses_global(&mut self) -> bool {
+        mem::replace(&mut self.uses_global, false)
+    }
+
+    /// Record the span of a statement or expression of a particular type, at `pos`, which starts
+    /// at `start` and ends with the last token consumed.
+    pub(crate) fn add_span(&mut self, pos: Position, node_type: NodeType, start: Position) {
+        if !pos.is_none() {
+            self.spans
+                .push((pos, node_type, Span::new(start, self.last_end)));
+        }
+    }
+
+    /// Attach the name of the source of the input, and the span of the code in error, to a parse error.
+    ///
+    /// The span is that of the innermost statement or expression recorded at the position of the error,
+    /// or else of the token there if it is the last token consumed or the one peeked.
+    pub(crate) fn locate_error(&self, err: ParseError) -> ParseError {
+        let pos = err.1;
+
+        let span = if pos.is_none() {
+            None
+        } else if let Some((_, _, span)) = self.spans.iter().find(|(p, _, _)| *p == pos) {
+            Some(*span)
+        } else if matches!(self.peeked, Some(Some((_, p))) if p == pos) {
+            Some(Span::new(pos, self.peeked_end))
+        } else if pos == self.last_start {
+            Some(Span::new(pos, self.last_end))
+        } else {
+            Some(Span::new(pos, pos))
+        };
+
+        err.with_location(self.source.as_deref().map(Into::into), span)
+    }
+
+    /// Take the recorded spans, sorted by position.
+    ///
+    /// Spans at the same position (e.g. of a `while` statement and its condition) are kept in the
+    /// order recorded, i.e. innermost first.  Of nodes of the same type at the same position,
+    /// only the innermost is kept.
+    pub(crate) fn take_spans(&mut self) -> Vec<(Position, NodeType, Span)> {
+        sort_spans(mem::take(&mut self.spans))
+    }
+}
+
+impl Iterator for TokenStream<'_, '_> {
+    type Item = (Token, Position);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (next, end) = match self.peeked.take() {
+            Some(next) => (next, self.peeked_end),
+            None => self.read(),
+        };
+
+        if let Some((_, pos)) = next {
+            self.last_start = pos;
+            self.last_end = end;
+        }
+        next
+    }
+}
+
+/// Sort spans by position, keeping the order of spans at the same position.
+/// Of spans of nodes of the same type at the same position, only the first is kept.
+pub(crate) fn sort_spans(
+    mut spans: Vec<(Position, NodeType, Span)>,
+) -> Vec<(Position, NodeType, Span)> {
+    spans.sort_by_key(|(pos, _, _)| *pos);
+
+    let mut result: Vec<(Position, NodeType, Span)> = Vec::with_capacity(spans.len());
+
+    for span in spans {
+        let is_new = result
+            .iter()
+            .rev()
+            .take_while(|(pos, _, _)| *pos == span.0)
+            .all(|(_, node_type, _)| *node_type != span.1);
+
+        if is_new {
+            result.push(span);
+        }
+    }
+
+    result
+}
+
 /// Tokenize an input text stream.
 pub fn lex<'a, 'e>(input: &'a [&'a str], engine: &'e Engine) -> TokenIterator<'a, 'e> {
     TokenIterator {
@@ -1557,6 +1856,7 @@ pub fn lex<'a, 'e>(input: &'a [&'a str], engine: &'e Engine) -> TokenIterator<'a
             comment_level: 0,
             end_with_none: false,
             include_comments: false,
+            is_stmt_start: true,
         },
         pos: Position::new(1, 0),
         stream: MultiInputsStream {
@@ -0,0 +1,244 @@
+//! Module implementing custom optimizer passes over an `AST`.
+
+use crate::any::Dynamic;
+use crate::calc_fn_hash;
+use crate::fn_native::SendSync;
+use crate::parser::{map_dynamic_to_expr, Expr, Stmt};
+use crate::token::Position;
+
+use crate::stdlib::{boxed::Box, iter::empty, string::ToString, vec::Vec};
+
+/// Trait for a custom optimizer pass that visits (and possibly rewrites) the expressions in an `AST`.
+///
+/// Passes are registered via `Engine::register_optimizer_pass` and run alongside the built-in passes
+/// of the optimizer, on both the main script and all script-defined functions, until the `AST`
+/// no longer changes.  Therefore, a pass must never rewrite an expression into one that it would
+/// rewrite again.
+///
+/// Passes do not run under `OptimizationLevel::None`.
+///
+/// Not available under the `no_optimize` feature.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> Result<(), Box<rhai::EvalAltResult>> {
+/// use rhai::{Engine, AstVisitor, ExpressionMut};
+///
+/// // Replace calls to the deprecated 'old_len' with 'len'
+/// struct RenameOldLen;
+///
+/// impl AstVisitor for RenameOldLen {
+///     fn visit_expr(&self, expr: &mut ExpressionMut) {
+///         if expr.get_fn_name() == Some("old_len") {
+///             expr.set_fn_name("len");
+///         }
+///     }
+/// }
+///
+/// let mut engine = Engine::new();
+///
+/// engine.register_optimizer_pass(RenameOldLen);
+///
+/// assert_eq!(engine.eval::<i64>(r#"old_len("hello")"#)?, 5);
+/// # Ok(())
+/// # }
+/// ```
+pub trait AstVisitor: SendSync {
+    /// Visit an expression in the `AST`, after all its sub-expressions have been visited.
+    fn visit_expr(&self, expr: &mut ExpressionMut);
+}
+
+/// A mutable expression sub-tree in an `AST`, as seen by a custom optimizer pass.
+///
+/// Not available under the `no_optimize` feature.
+#[derive(Debug)]
+pub struct ExpressionMut<'a> {
+    /// The expression.
+    expr: &'a mut Expr,
+    /// Is the expression a function call in method-call style?
+    is_method_call: bool,
+    /// Has the expression been changed?
+    changed: bool,
+}
+
+impl ExpressionMut<'_> {
+    /// Get the position of this expression.
+    pub fn position(&self) -> Position {
+        self.expr.position()
+    }
+    /// Is this expression a constant?
+    pub fn is_constant(&self) -> bool {
+        self.expr.is_constant()
+    }
+    /// If this expression is a constant, return its value.  Otherwise `None`.
+    pub fn get_constant_value(&self) -> Option<Dynamic> {
+        if self.expr.is_constant() {
+            Some(self.expr.get_constant_value())
+        } else {
+            None
+        }
+    }
+    /// If this expression is a variable name, return it.  Otherwise `None`.
+    pub fn get_variable_name(&self) -> Option<&str> {
+        match &*self.expr {
+            Expr::Variable(x) if x.1.is_none() => Some((x.0).0.as_str()),
+            _ => None,
+        }
+    }
+    /// If this expression is a call to a function not qualified by a module, return the name of
+    /// the function.  Otherwise `None`.
+    ///
+    /// Operators are also function calls.
+    pub fn get_fn_name(&self) -> Option<&str> {
+        match &*self.expr {
+            Expr::FnCall(x) if x.1.is_none() => Some((x.0).0.as_ref()),
+            _ => None,
+        }
+    }
+    /// If this expression is a function call with only constant arguments, return the argument values.
+    /// Otherwise `None`.
+    ///
+    /// For a function call in method-call style, the object is not included.
+    pub fn get_arg_values(&self) -> Option<Vec<Dynamic>> {
+        match &*self.expr {
+            Expr::FnCall(x) if x.3.iter().all(Expr::is_constant) => {
+                Some(x.3.iter().map(Expr::get_constant_value).collect())
+            }
+            _ => None,
+        }
+    }
+    /// Is this expression a function call in method-call style, i.e. `object.method(args)`?
+    pub fn is_method_call(&self) -> bool {
+        self.is_method_call
+    }
+    /// Change the name of the function called by this expression, keeping the same arguments.
+    ///
+    /// Returns `false` if this expression is not a call to a function not qualified by a module.
+    pub fn set_fn_name(&mut self, name: &str) -> bool {
+        match self.expr {
+            Expr::FnCall(x) if x.1.is_none() => {
+                if (x.0).0 != name {
+                    (x.0).0 = name.to_string().into();
+                    // The new function may be script-defined
+                    (x.0).1 = false;
+                    x.2 = calc_fn_hash(empty(), name, x.3.len(), empty());
+                    x.5 = Default::default();
+                    self.changed = true;
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+    /// Replace this expression with a constant value.
+    ///
+    /// Returns `false` if the value cannot be represented as a constant in the `AST`,
+    /// or this expression is a function call in method-call style.
+    pub fn set_constant_value(&mut self, value: Dynamic) -> bool {
+        if self.is_method_call {
+            return false;
+        }
+
+        match map_dynamic_to_expr(value, self.expr.position()) {
+            Some(expr) => {
+                *self.expr = expr;
+                self.changed = true;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Run custom optimizer passes on a list of statements, returning `true` if anything has changed.
+pub(crate) fn run_passes(statements: &mut [Stmt], passes: &[Box<dyn AstVisitor>]) -> bool {
+    if passes.is_empty() {
+        return false;
+    }
+
+    statements
+        .iter_mut()
+        .fold(false, |changed, stmt| changed | visit_stmt(stmt, passes))
+}
+
+/// Visit all the expressions in a statement.
+fn visit_stmt(stmt: &mut Stmt, passes: &[Box<dyn AstVisitor>]) -> bool {
+    match stmt {
+        Stmt::IfThenElse(x) => {
+            let (expr, if_block, else_block) = x.as_mut();
+            let mut changed = visit_expr(expr, passes, false);
+            changed = visit_stmt(if_block, passes) || changed;
+            if let Some(else_block) = else_block {
+                changed = visit_stmt(else_block, passes) || changed;
+            }
+            changed
+        }
+        Stmt::While(x) => {
+            let changed = visit_expr(&mut x.0, passes, false);
+            visit_stmt(&mut x.1, passes) || changed
+        }
+        Stmt::Loop(x) => visit_stmt(x, passes),
+        Stmt::For(x) => {
+            let changed = visit_expr(&mut x.1, passes, false);
+            visit_stmt(&mut x.2, passes) || changed
+        }
+        Stmt::Let(x) => match &mut x.1 {
+            Some(expr) => visit_expr(expr, passes, false),
+            None => false,
+        },
+        Stmt::Block(x) => run_passes(&mut x.0, passes),
+        Stmt::Expr(x) => visit_expr(x, passes, false),
+        Stmt::ReturnWithVal(x) => match &mut x.1 {
+            Some(expr) => visit_expr(expr, passes, false),
+            None => false,
+        },
+        // Constants must stay constant, module paths are resolved and other statements have no expressions
+        _ => false,
+    }
+}
+
+/// Visit an expression, after all its sub-expressions.
+fn visit_expr(expr: &mut Expr, passes: &[Box<dyn AstVisitor>], is_method_call: bool) -> bool {
+    let changed = match expr {
+        Expr::Stmt(x) => visit_stmt(&mut x.0, passes),
+        Expr::Expr(x) => visit_expr(x, passes, false),
+        Expr::FnCall(x) => x.3.iter_mut().fold(false, |changed, arg| {
+            changed | visit_expr(arg, passes, false)
+        }),
+        // Only the right-hand side is visited because the target of an assignment must stay intact
+        Expr::Assignment(x) => visit_expr(&mut x.2, passes, false),
+        Expr::Dot(x) => {
+            let changed = visit_expr(&mut x.0, passes, false);
+
+            match &mut x.1 {
+                rhs @ Expr::FnCall(_) => visit_expr(rhs, passes, true) || changed,
+                Expr::Property(_) => changed,
+                rhs => visit_expr(rhs, passes, false) || changed,
+            }
+        }
+        Expr::Index(x) | Expr::In(x) | Expr::And(x) | Expr::Or(x) => {
+            let changed = visit_expr(&mut x.0, passes, false);
+            visit_expr(&mut x.1, passes, false) || changed
+        }
+        Expr::Array(x) => x.0.iter_mut().fold(false, |changed, item| {
+            changed | visit_expr(item, passes, false)
+        }),
+        Expr::Map(x) => x.0.iter_mut().fold(false, |changed, (_, item)| {
+            changed | visit_expr(item, passes, false)
+        }),
+        // Properties are not expressions, and custom syntax may treat its expressions as identifiers
+        Expr::Property(_) | Expr::Custom(_) => return false,
+        _ => false,
+    };
+
+    let mut node = ExpressionMut {
+        expr,
+        is_method_call,
+        changed: false,
+    };
+
+    passes.iter().for_each(|pass| pass.visit_expr(&mut node));
+
+    node.changed || changed
+}
@@ -1,5 +1,5 @@
 #![cfg(not(feature = "no_index"))]
-use rhai::{Array, Engine, EvalAltResult, RegisterFn, INT};
+use rhai::{Array, Engine, EvalAltResult, ParseErrorType, RegisterFn, INT};
 
 #[test]
 fn test_arrays() -> Result<(), Box<EvalAltResult>> {
@@ -58,6 +58,20 @@ fn test_arrays() -> Result<(), Box<EvalAltResult>> {
     Ok(())
 }
 
+#[test]
+fn test_arrays_unclosed() {
+    let engine = Engine::new();
+
+    assert!(matches!(
+        *engine.compile("let x = [1, 2").expect_err("should error").0,
+        ParseErrorType::MissingToken(ref token, _) if token == "]"
+    ));
+    assert!(matches!(
+        *engine.compile("let x = [1, 2,").expect_err("should error").0,
+        ParseErrorType::MissingToken(ref token, _) if token == "]"
+    ));
+}
+
 #[test]
 #[cfg(not(feature = "no_object"))]
 fn test_array_with_structs() -> Result<(), Box<EvalAltResult>> {
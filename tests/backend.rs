@@ -0,0 +1,271 @@
+use rhai::{Backend, Engine, EvalAltResult, Scope, INT};
+
+fn engines() -> Vec<Engine> {
+    let tree = Engine::new();
+
+    let mut bytecode = Engine::new();
+    bytecode.set_backend(Backend::Bytecode);
+
+    vec![tree, bytecode]
+}
+
+#[test]
+fn test_backend_default() {
+    let mut engine = Engine::new();
+    assert_eq!(engine.backend(), Backend::TreeWalking);
+
+    engine.set_backend(Backend::Bytecode);
+    assert_eq!(engine.backend(), Backend::Bytecode);
+}
+
+#[test]
+fn test_backend_expressions() -> Result<(), Box<EvalAltResult>> {
+    for engine in engines() {
+        assert_eq!(engine.eval::<INT>("40 + 2")?, 42);
+        assert!(engine.eval::<bool>("let x = 1; x > 0 && x < 2 || false")?);
+        assert_eq!(engine.eval::<INT>("let x = 10; x -= 3; x *= 2; x")?, 14);
+        assert_eq!(
+            engine.eval::<INT>("if 1 > 2 { 1 } else if 2 > 1 { 2 } else { 3 }")?,
+            2
+        );
+        assert_eq!(
+            engine.eval::<String>(r#"let s = "hello"; s + ", world""#)?,
+            "hello, world"
+        );
+        assert_eq!(engine.eval::<()>("let x = 1;")?, ());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_backend_loops() -> Result<(), Box<EvalAltResult>> {
+    for engine in engines() {
+        assert_eq!(
+            engine.eval::<INT>(
+                r"
+                    let x = 0;
+                    let i = 0;
+                    loop {
+                        i += 1;
+                        if i > 10 { break; }
+                        if i % 2 == 0 { continue; }
+                        x += i;
+                    }
+                    x
+                "
+            )?,
+            25
+        );
+
+        assert_eq!(
+            engine.eval::<INT>(
+                r"
+                    let x = 0;
+                    while x < 100 { x += 7; }
+                    x
+                "
+            )?,
+            105
+        );
+
+        #[cfg(not(feature = "no_index"))]
+        assert_eq!(
+            engine.eval::<INT>(
+                r"
+                    let sum = 0;
+                    for x in [1, 2, 3, 4, 5] {
+                        if x == 4 { break; }
+                        sum += x;
+                    }
+                    sum
+                "
+            )?,
+            6
+        );
+
+        assert_eq!(
+            engine.eval::<INT>(
+                r"
+                    let sum = 0;
+                    for x in range(0, 10) {
+                        for y in range(0, x) {
+                            if y > 2 { continue; }
+                            sum += y;
+                        }
+                    }
+                    sum
+                "
+            )?,
+            22
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_backend_functions() -> Result<(), Box<EvalAltResult>> {
+    for engine in engines() {
+        assert_eq!(
+            engine.eval::<INT>(
+                r"
+                    fn fib(n) { if n < 2 { n } else { fib(n - 1) + fib(n - 2) } }
+                    fib(15)
+                "
+            )?,
+            610
+        );
+
+        assert_eq!(
+            engine.eval::<INT>(
+                r"
+                    fn find(n) {
+                        let i = 0;
+                        loop {
+                            if i * i >= n { return i; }
+                            i += 1;
+                        }
+                    }
+                    find(50)
+                "
+            )?,
+            8
+        );
+
+        // Tail calls do not grow the call stack
+        assert_eq!(
+            engine.eval::<INT>(
+                r"
+                    fn sum(n, acc) { if n == 0 { return acc; } return sum(n - 1, acc + n); }
+                    sum(1000, 0)
+                "
+            )?,
+            500500
+        );
+
+        #[cfg(not(feature = "no_object"))]
+        assert_eq!(
+            engine.eval::<INT>(
+                r"
+                    fn inc(n) { this += n; }
+                    let x = 40;
+                    x.inc(2);
+                    x
+                "
+            )?,
+            42
+        );
+
+        assert!(matches!(
+            *engine
+                .eval::<INT>(r#"fn fail(x) { throw x; } fail("boom")"#)
+                .expect_err("should error"),
+            EvalAltResult::ErrorInFunctionCall(fn_name, _, _, err, _)
+                if fn_name == "fail"
+                    && matches!(*err, EvalAltResult::ErrorRuntime(ref s, _) if s == "boom")
+        ));
+
+        // Functions with the same signature in different `AST`'s are not confused
+        let ast1 = engine.compile("fn foo(x) { x + 1 } foo(41)")?;
+        let ast2 = engine.compile("fn foo(x) { x * 2 } foo(21)")?;
+        assert_eq!(engine.eval_ast::<INT>(&ast1)?, 42);
+        assert_eq!(engine.eval_ast::<INT>(&ast2)?, 42);
+        assert_eq!(engine.eval_ast::<INT>(&ast1.merge(&ast2))?, 42);
+        assert_eq!(engine.eval_ast::<INT>(&ast2.merge(&ast1))?, 42);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_backend_scope() -> Result<(), Box<EvalAltResult>> {
+    for engine in engines() {
+        let mut scope = Scope::new();
+        scope.push("x", 40 as INT);
+
+        engine.consume_with_scope(&mut scope, "x += 1; let y = x + 1;")?;
+
+        assert_eq!(scope.get_value::<INT>("x").unwrap(), 41);
+        assert_eq!(scope.get_value::<INT>("y").unwrap(), 42);
+
+        let ast = engine.compile("{ let z = y; } y * 2")?;
+        assert_eq!(engine.eval_ast_with_scope::<INT>(&mut scope, &ast)?, 84);
+        assert!(scope.get_value::<INT>("z").is_none());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_backend_errors() -> Result<(), Box<EvalAltResult>> {
+    for engine in engines() {
+        assert!(matches!(
+            *engine.eval::<()>(r#"throw "hello";"#).expect_err("should error"),
+            EvalAltResult::ErrorRuntime(s, _) if s == "hello"
+        ));
+
+        assert!(matches!(
+            *engine.eval::<INT>("let x = 0; x + y").expect_err("should error"),
+            EvalAltResult::ErrorVariableNotFound(v, _) if v == "y"
+        ));
+
+        assert_eq!(engine.eval::<INT>("let x = 1; return x + 1; x")?, 2);
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "unchecked"))]
+fn test_backend_max_operations() -> Result<(), Box<EvalAltResult>> {
+    for mut engine in engines() {
+        engine.set_max_operations(500);
+
+        assert!(matches!(
+            *engine
+                .eval::<()>("let x = 0; loop { x += 1; }")
+                .expect_err("should error"),
+            EvalAltResult::ErrorTooManyOperations(_)
+        ));
+
+        engine.eval::<()>("let x = 0; while x < 20 { x += 1; }")?;
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "unchecked"))]
+fn test_backend_operation_count() -> Result<(), Box<EvalAltResult>> {
+    use std::sync::{Arc, Mutex};
+
+    let script = r"
+        let x = 0;
+        for i in range(0, 10) {
+            if i % 3 == 0 { continue; }
+            x += i;
+        }
+        x
+    ";
+
+    let mut counts = Vec::new();
+
+    for mut engine in engines() {
+        let count = Arc::new(Mutex::new(0));
+        let counter = count.clone();
+
+        engine.on_progress(move |&n| {
+            *counter.lock().unwrap() = n;
+            true
+        });
+
+        assert_eq!(engine.eval::<INT>(script)?, 27);
+        counts.push(*count.lock().unwrap());
+    }
+
+    assert_eq!(counts[0], counts[1]);
+
+    Ok(())
+}
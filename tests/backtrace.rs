@@ -0,0 +1,163 @@
+#![cfg(not(feature = "no_function"))]
+use rhai::{Backend, Engine, EvalAltResult};
+
+#[test]
+fn test_backtrace() -> Result<(), Box<EvalAltResult>> {
+    let script = r#"
+        fn inner(x) {
+            if x > 1 { throw "too big"; }
+            x
+        }
+        fn middle(x) { inner(x + 1) }
+        fn outer(x) {
+            let y = middle(x);
+            y
+        }
+        outer(0);
+        outer(1);
+    "#;
+
+    for backend in [Backend::TreeWalking, Backend::Bytecode].iter() {
+        let mut engine = Engine::new();
+        engine.set_backend(*backend);
+
+        let err = engine.consume(script).expect_err("should error");
+
+        assert!(matches!(
+            err.root_cause(),
+            EvalAltResult::ErrorRuntime(s, pos) if s == "too big" && pos.line() == Some(3)
+        ));
+
+        let backtrace = err.backtrace();
+
+        assert_eq!(
+            backtrace
+                .iter()
+                .map(|frame| (frame.fn_name.as_str(), frame.pos.line()))
+                .collect::<Vec<_>>(),
+            vec![("inner", Some(6)), ("middle", Some(8)), ("outer", Some(12)),]
+        );
+        assert!(backtrace.iter().all(|frame| frame.namespace.is_none()));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_backtrace_display() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let err = engine
+        .eval::<()>("fn foo() { bar() }\nfn bar() { throw \"boom\"; }\nfoo()")
+        .expect_err("should error");
+
+    assert_eq!(
+        err.to_string(),
+        "boom (line 2, position 18)\n    in call to function 'bar' (line 1, position 12)\n    in call to function 'foo' (line 3, position 1)"
+    );
+
+    // Errors not inside function calls have no backtrace
+    let err = engine
+        .eval::<()>(r#"throw "boom";"#)
+        .expect_err("should error");
+
+    assert!(err.backtrace().is_empty());
+    assert_eq!(err.to_string(), "boom (line 1, position 7)");
+
+    Ok(())
+}
+
+#[test]
+fn test_backtrace_source() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let ast = engine.compile_with_source(
+        "main.rhai",
+        "fn foo() { throw \"boom\"; }\nfn bar() { foo() }\nbar()",
+    )?;
+    let err = engine.consume_ast(&ast).expect_err("should error");
+
+    let backtrace = err.backtrace();
+
+    // The call to 'foo' is made inside 'bar', which is defined in 'main.rhai'
+    assert_eq!(backtrace.len(), 2);
+    assert_eq!(backtrace[0].fn_name, "foo");
+    assert_eq!(backtrace[0].source.as_deref(), Some("main.rhai"));
+    assert_eq!(backtrace[0].pos.line(), Some(2));
+    assert_eq!(backtrace[1].fn_name, "bar");
+    assert_eq!(backtrace[1].source.as_deref(), Some("main.rhai"));
+    assert_eq!(err.root_source(), Some("main.rhai"));
+
+    let err = engine
+        .consume("fn foo() { throw \"boom\"; }\nfoo()")
+        .expect_err("should error");
+
+    assert_eq!(err.backtrace()[0].source, None);
+    assert_eq!(err.root_source(), None);
+
+    Ok(())
+}
+
+#[test]
+fn test_backtrace_call_fn() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let ast = engine.compile("fn foo(x) { bar(x) } fn bar(x) { no_such_fn(x) }")?;
+
+    let err = engine
+        .call_fn::<_, ()>(&mut Default::default(), &ast, "foo", (42_i64,))
+        .expect_err("should error");
+
+    // The call from Rust has no position in the script
+    let backtrace = err.backtrace();
+
+    assert_eq!(backtrace.len(), 2);
+    assert_eq!(backtrace[0].fn_name, "bar");
+    assert_eq!(backtrace[0].pos.line(), Some(1));
+    assert_eq!(backtrace[1].fn_name, "foo");
+    assert!(backtrace[1].pos.is_none());
+    assert!(matches!(
+        err.root_cause(),
+        EvalAltResult::ErrorFunctionNotFound(_, _)
+    ));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_module"))]
+fn test_backtrace_modules() -> Result<(), Box<EvalAltResult>> {
+    use rhai::{module_resolvers::StaticModuleResolver, Module, Scope};
+
+    let mut engine = Engine::new();
+
+    let ast = engine.compile(r#"fn check(x) { if x < 0 { throw "negative"; } x }"#)?;
+    let module = Module::eval_ast_as_new(Scope::new(), &ast, &engine)?;
+
+    let mut resolver = StaticModuleResolver::new();
+    resolver.insert("math", module);
+    engine.set_module_resolver(Some(resolver));
+
+    let err = engine
+        .eval::<()>(
+            r#"
+                fn run(x) {
+                    import "math" as m;
+                    m::check(x)
+                }
+                run(-1)
+            "#,
+        )
+        .expect_err("should error");
+
+    let backtrace = err.backtrace();
+
+    assert_eq!(backtrace.len(), 2);
+    assert_eq!(backtrace[0].fn_name, "check");
+    assert_eq!(backtrace[0].namespace.as_deref(), Some("m"));
+    assert_eq!(backtrace[0].to_string(), "m::check");
+    assert_eq!(backtrace[1].fn_name, "run");
+    assert_eq!(backtrace[1].namespace, None);
+
+    Ok(())
+}
@@ -13,7 +13,7 @@ fn test_fn() -> Result<(), Box<EvalAltResult>> {
         engine
             .compile("fn hello(x, x) { x }")
             .expect_err("should be error"),
-        ParseError(x, _) if *x == ParseErrorType::FnDuplicatedParam("hello".to_string(), "x".to_string())
+        ParseError(x, ..) if *x == ParseErrorType::FnDuplicatedParam("hello".to_string(), "x".to_string())
     ));
 
     Ok(())
@@ -0,0 +1,70 @@
+use rhai::{Engine, ParseError, ParseErrorType};
+
+#[test]
+fn test_check_complete() -> Result<(), ParseError> {
+    let engine = Engine::new();
+
+    assert!(engine.check_complete("")?);
+    assert!(engine.check_complete("let x = 42;\nx + 1")?);
+    assert!(engine.check_complete("if x { 1 } else { 2 }\n")?);
+
+    // Unclosed blocks and brackets
+    assert!(!engine.check_complete("if x > 0 {")?);
+    assert!(!engine.check_complete("{\n    let x = 1;\n")?);
+    assert!(!engine.check_complete("foo(1,\n")?);
+
+    #[cfg(not(feature = "no_index"))]
+    {
+        assert!(!engine.check_complete("let x = [1, 2")?);
+        assert!(!engine.check_complete("let x = [1,\n")?);
+        assert!(engine.compile("let x = [1,").is_err());
+    }
+
+    #[cfg(not(feature = "no_object"))]
+    {
+        assert!(!engine.check_complete("let x = #{a: 1,")?);
+        assert!(engine.compile("let x = #{a: 1,").is_err());
+    }
+    assert!(!engine.check_complete("if x { 1 } else")?);
+
+    // Trailing operators
+    assert!(!engine.check_complete("let x = ")?);
+    assert!(!engine.check_complete("x +\n")?);
+
+    // Unclosed strings and comments
+    assert!(!engine.check_complete(r#"print("hello"#)?);
+    assert!(!engine.check_complete("let x = 1; /* comment")?);
+    assert!(!engine.check_complete("/* outer /* inner */")?);
+    assert!(engine.check_complete("let x = 1; /* comment */")?);
+
+    Ok(())
+}
+
+#[test]
+fn test_check_complete_errors() {
+    let engine = Engine::new();
+
+    // Syntax errors before the end of the input
+    let err = engine
+        .check_complete("let x = ) {")
+        .expect_err("should error");
+    assert_eq!(
+        err,
+        engine.compile("let x = ) {").expect_err("should error")
+    );
+
+    assert!(engine.check_complete("let x = 1 2 +").is_err());
+    assert!(engine.check_complete("x = ;\n{").is_err());
+
+    // Strings cannot span lines
+    assert!(matches!(
+        *engine
+            .check_complete("let s = \"hello\n")
+            .expect_err("should error")
+            .0,
+        ParseErrorType::BadInput(_)
+    ));
+
+    // An unclosed comment after an error is still an error
+    assert!(engine.check_complete("let x = ) /* comment").is_err());
+}
@@ -1,4 +1,4 @@
-use rhai::{Engine, INT};
+use rhai::{Engine, EvalAltResult, INT};
 
 #[test]
 fn test_comments() {
@@ -12,3 +12,177 @@ fn test_comments() {
         .eval::<INT>("let /* I am a multi-line comment, yay! */ x = 5; x")
         .is_ok());
 }
+
+#[test]
+fn test_comments_doc() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let ast = engine.compile(
+        r"
+            /// The answer.
+            /// To everything.
+            const ANSWER = 42;
+
+            //// Not a doc comment
+            const OTHER = 0;
+
+            let x = (
+                /// Doc comments inside expressions are ignored
+                1
+            );
+
+            {
+                foo();
+                /// Trailing doc comments are ignored
+            }
+        ",
+    )?;
+
+    let vars: Vec<_> = ast.iter_variables().collect();
+
+    assert_eq!(vars.len(), 2);
+    assert_eq!(vars[0].name, "ANSWER");
+    assert_eq!(
+        vars[0].comments,
+        vec!["/// The answer.", "/// To everything."]
+    );
+    assert_eq!(vars[1].name, "OTHER");
+    assert!(vars[1].comments.is_empty());
+
+    assert_eq!(
+        engine.eval::<INT>("/// Doc comment on an expression\n40 + 2")?,
+        42
+    );
+
+    // Doc comments not before functions, variables, constants or exports are normal comments
+    assert_eq!(engine.eval::<INT>("if true { 1 } /// c\n else { 2 }")?, 1);
+    assert_eq!(
+        engine.eval::<INT>("let x = if false {1} /// c\n else {2}; x")?,
+        2
+    );
+    assert_eq!(engine.eval::<INT>("{ 1 } /// trailing")?, 1);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_comments_doc_functions() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let ast = engine.compile(
+        r"
+            /** Add two numbers.
+                Returns the sum. */
+            fn add(x: int, y: int) -> int { x + y }
+
+            /**/
+            fn no_docs() { 0 }
+
+            /// Private function
+            private fn hidden() { 0 }
+        ",
+    )?;
+
+    let mut functions: Vec<_> = ast.iter_functions().collect();
+    functions.sort_by(|a, b| a.name.cmp(b.name));
+
+    assert_eq!(functions[0].name, "add");
+    assert_eq!(functions[0].params, vec!["x", "y"]);
+    assert_eq!(functions[0].return_type, Some("int"));
+    assert_eq!(
+        functions[0].comments,
+        vec!["/** Add two numbers.\n                Returns the sum. */"]
+    );
+    assert_eq!(functions[1].name, "hidden");
+    assert_eq!(functions[1].comments, vec!["/// Private function"]);
+    assert_eq!(functions[2].name, "no_docs");
+    assert!(functions[2].comments.is_empty());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_module"))]
+fn test_comments_doc_exports() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let ast = engine.compile(
+        r"
+            let x = 42;
+
+            /// The answer.
+            export x as answer;
+        ",
+    )?;
+
+    let vars: Vec<_> = ast.iter_variables().collect();
+
+    assert_eq!(vars.len(), 1);
+    assert_eq!(vars[0].name, "answer");
+    assert!(vars[0].exported);
+    assert_eq!(vars[0].comments, vec!["/// The answer."]);
+
+    let module = rhai::Module::eval_ast_as_new(Default::default(), &ast, &engine)?;
+    assert_eq!(
+        module.get_var_comments("answer").unwrap(),
+        ["/// The answer."]
+    );
+
+    #[cfg(not(feature = "no_function"))]
+    {
+        let ast = engine.compile("/// Say hello.\nfn hello() { 42 }")?;
+        let module = rhai::Module::eval_ast_as_new(Default::default(), &ast, &engine)?;
+        let fn_def = module.iter_script_fn().next().unwrap();
+        assert_eq!(fn_def.comments, vec!["/// Say hello.".to_string()]);
+
+        let f = module.iter_functions().next().unwrap();
+        assert_eq!(f.name, "hello");
+        assert_eq!(f.comments, vec!["/// Say hello."]);
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_module"))]
+fn test_comments_doc_exports_declared() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let ast = engine.compile("/// A variable.\nlet v = 1; export v;")?;
+
+    let vars: Vec<_> = ast.iter_variables().collect();
+
+    assert_eq!(vars.len(), 1);
+    assert_eq!(vars[0].name, "v");
+    assert!(vars[0].exported);
+    assert_eq!(vars[0].comments, vec!["/// A variable."]);
+
+    let module = rhai::Module::eval_ast_as_new(Default::default(), &ast, &engine)?;
+    assert_eq!(module.get_var_comments("v").unwrap(), ["/// A variable."]);
+
+    let ast = engine.compile("/// A constant.\nconst C = 1; export C;")?;
+
+    let vars: Vec<_> = ast.iter_variables().collect();
+
+    assert_eq!(vars.len(), 1);
+    assert_eq!(vars[0].name, "C");
+    assert!(vars[0].exported);
+    assert_eq!(vars[0].comments, vec!["/// A constant."]);
+
+    Ok(())
+}
+
+#[test]
+fn test_comments_doc_crlf() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let ast = engine.compile("/// a\r\n/// b\r\nconst X = 1;\r\n")?;
+
+    let vars: Vec<_> = ast.iter_variables().collect();
+
+    assert_eq!(vars.len(), 1);
+    assert_eq!(vars[0].comments, vec!["/// a", "/// b"]);
+
+    Ok(())
+}
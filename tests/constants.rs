@@ -1,4 +1,4 @@
-use rhai::{Engine, EvalAltResult, ParseErrorType, INT};
+use rhai::{Engine, EvalAltResult, ParseErrorType, Scope, INT};
 
 #[test]
 fn test_constant() -> Result<(), Box<EvalAltResult>> {
@@ -21,3 +21,75 @@ fn test_constant() -> Result<(), Box<EvalAltResult>> {
 
     Ok(())
 }
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_constant_global() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    assert_eq!(
+        engine.eval::<INT>(
+            r"
+                const RATE = 40;
+                fn calc(x) { global::RATE + x }
+                calc(2)
+            "
+        )?,
+        42
+    );
+
+    assert!(matches!(
+        *engine
+            .eval::<INT>(
+                r"
+                    fn calc(x) { global::RATE + x }
+                    calc(2)
+                "
+            )
+            .expect_err("expects error"),
+        EvalAltResult::ErrorInFunctionCall(fn_name, _, _, err, _)
+            if fn_name == "calc"
+                && matches!(*err, EvalAltResult::ErrorVariableNotFound(ref name, _) if name == "global::RATE")
+    ));
+
+    assert!(matches!(
+        *engine
+            .eval::<INT>(
+                r"
+                    const RATE = 40;
+                    fn calc(x) { global::RATE = x; x }
+                    calc(2)
+                "
+            )
+            .expect_err("expects error"),
+        EvalAltResult::ErrorInFunctionCall(fn_name, _, _, err, _)
+            if fn_name == "calc" && matches!(*err, EvalAltResult::ErrorAssignmentToConstant(_, _))
+    ));
+
+    let mut scope = Scope::new();
+    scope.push_constant("RATE", 40 as INT);
+
+    assert_eq!(
+        engine.eval_with_scope::<INT>(&mut scope, "fn calc(x) { global::RATE + x } calc(2)")?,
+        42
+    );
+
+    // The global namespace is still visible after an `AST` is split or merged
+    let lib = engine.compile("fn calc(x) { global::RATE + x }")?;
+    let ast = engine.compile("calc(2)")?;
+
+    assert_eq!(
+        engine.call_fn::<_, INT>(&mut scope, &lib, "calc", (2 as INT,))?,
+        42
+    );
+    assert_eq!(
+        engine.eval_ast_with_scope::<INT>(&mut scope, &lib.clone_functions_only().merge(&ast))?,
+        42
+    );
+    assert_eq!(
+        engine.eval_ast_with_scope::<INT>(&mut scope, &ast.merge(&lib))?,
+        42
+    );
+
+    Ok(())
+}
@@ -0,0 +1,175 @@
+use rhai::{Backend, Dynamic, Engine, EvalAltResult, NodeType, RegisterFn, INT};
+use std::any::TypeId;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Run a script, returning the number of operations counted.
+fn count(engine: &mut Engine, script: &str) -> Result<u64, Box<EvalAltResult>> {
+    let operations = Arc::new(AtomicU64::new(0));
+    let counter = operations.clone();
+
+    engine.on_progress(move |&ops| {
+        counter.store(ops, Ordering::Relaxed);
+        true
+    });
+
+    engine.consume(script)?;
+
+    Ok(operations.load(Ordering::Relaxed))
+}
+
+#[test]
+fn test_cost_nodes() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let script = "let x = 1; let y = 2; for n in range(0, 10) { x = n; }";
+
+    let base = count(&mut engine, script)?;
+
+    assert_eq!(engine.node_cost(NodeType::Let), 1);
+    engine.set_node_cost(NodeType::Let, 5);
+    assert_eq!(engine.node_cost(NodeType::Let), 5);
+
+    assert_eq!(count(&mut engine, script)?, base + 2 * 4);
+
+    engine.set_node_cost(NodeType::Iteration, 0);
+
+    assert_eq!(count(&mut engine, script)?, base + 2 * 4 - 10);
+
+    Ok(())
+}
+
+#[test]
+fn test_cost_fns() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.register_fn("double", |x: INT| x * 2);
+
+    let script = "let x = 1; x = x + 2; x += 3; double(x)";
+    let base = count(&mut engine, script)?;
+
+    // Both the operator and the compound assignment are charged - built-in compound
+    // assignments are not counted as function calls without a cost
+    engine.set_fn_cost("+", 10);
+    assert_eq!(count(&mut engine, script)?, base + 9 + 10);
+
+    engine.set_fn_cost("+=", 2);
+    assert_eq!(count(&mut engine, script)?, base + 9 + 2);
+
+    engine.set_fn_cost("double", 100);
+    assert_eq!(count(&mut engine, script)?, base + 9 + 2 + 99);
+
+    #[cfg(not(feature = "no_function"))]
+    {
+        let script = "fn inc(x) { x + 1 } inc(1) + inc(2)";
+        let base = count(&mut engine, script)?;
+
+        engine.set_fn_cost("inc", 50);
+        assert_eq!(count(&mut engine, script)?, base + 2 * 49);
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_index"))]
+fn test_cost_fn_callback() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    engine.register_fn("total", |arr: rhai::Array| {
+        arr.into_iter().map(|v| v.cast::<INT>()).sum::<INT>()
+    });
+
+    let script = "let a = [1, 2, 3, 4, 5]; total(a)";
+    let base = count(&mut engine, script)?;
+
+    // Charge one operation for each element of the array
+    engine.register_fn_cost("total", &[TypeId::of::<rhai::Array>()], |args: &[&Dynamic]| {
+        args[0]
+            .read_lock::<rhai::Array>()
+            .map_or(0, |arr| arr.len() as u64)
+    });
+    assert_eq!(count(&mut engine, script)?, base + 5);
+
+    // The fixed cost is kept together with the callback
+    engine.set_fn_cost("total", 10);
+    assert_eq!(count(&mut engine, script)?, base + 9 + 5);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_index"))]
+#[cfg(not(feature = "no_function"))]
+fn test_cost_fn_callback_overloads() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    engine.register_fn("size", |arr: rhai::Array| arr.len() as INT);
+    engine.register_fn("size", |x: INT| x);
+
+    let script_arr = "size([1, 2, 3, 4, 5])";
+    let script_int = "size(5)";
+    let script_fn = "fn size(x) { 0 } size([1, 2, 3, 4, 5])";
+    let base_arr = count(&mut engine, script_arr)?;
+    let base_int = count(&mut engine, script_int)?;
+    let base_fn = count(&mut engine, script_fn)?;
+
+    // Only the overload taking an array is charged for its input
+    engine.register_fn_cost("size", &[TypeId::of::<rhai::Array>()], |args: &[&Dynamic]| {
+        args[0]
+            .read_lock::<rhai::Array>()
+            .map_or(0, |arr| arr.len() as u64)
+    });
+    assert_eq!(count(&mut engine, script_arr)?, base_arr + 5);
+    assert_eq!(count(&mut engine, script_int)?, base_int);
+
+    // Script-defined functions overriding it are not charged
+    assert_eq!(count(&mut engine, script_fn)?, base_fn);
+
+    Ok(())
+}
+
+#[test]
+fn test_cost_backends() -> Result<(), Box<EvalAltResult>> {
+    let script = r"
+        let x = 0;
+        for n in range(0, 10) { if n > 5 { x += n; } }
+        while x > 0 { x -= 7; }
+        x
+    ";
+
+    let mut counts = Vec::new();
+
+    for backend in [Backend::TreeWalking, Backend::Bytecode].iter() {
+        let mut engine = Engine::new();
+        engine.set_backend(*backend);
+        engine.set_node_cost(NodeType::Iteration, 3);
+        engine.set_node_cost(NodeType::If, 2);
+        engine.set_fn_cost("-", 5);
+
+        counts.push(count(&mut engine, script)?);
+    }
+
+    assert_eq!(counts[0], counts[1]);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "unchecked"))]
+fn test_cost_max_operations() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.set_max_operations(100);
+
+    engine.consume("for n in range(0, 10) { }")?;
+
+    engine.set_node_cost(NodeType::Iteration, 20);
+
+    assert!(matches!(
+        *engine
+            .consume("for n in range(0, 10) { }")
+            .expect_err("should error"),
+        EvalAltResult::ErrorTooManyOperations(_)
+    ));
+
+    Ok(())
+}
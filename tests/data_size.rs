@@ -14,12 +14,12 @@ fn test_max_string_size() -> Result<(), Box<EvalAltResult>> {
 
     assert!(matches!(
         engine.compile(r#"let x = "hello, world!";"#).expect_err("should error"),
-        ParseError(x, _) if *x == ParseErrorType::LiteralTooLarge("Length of string literal".to_string(), 10)
+        ParseError(x, ..) if *x == ParseErrorType::LiteralTooLarge("Length of string literal".to_string(), 10)
     ));
 
     assert!(matches!(
         engine.compile(r#"let x = "朝に紅顔、暮に白骨";"#).expect_err("should error"),
-        ParseError(x, _) if *x == ParseErrorType::LiteralTooLarge("Length of string literal".to_string(), 10)
+        ParseError(x, ..) if *x == ParseErrorType::LiteralTooLarge("Length of string literal".to_string(), 10)
     ));
 
     assert!(matches!(
@@ -78,7 +78,7 @@ fn test_max_array_size() -> Result<(), Box<EvalAltResult>> {
         engine
             .compile("let x = [1,2,3,4,5,6,7,8,9,10,11,12,13,14,15];")
             .expect_err("should error"),
-        ParseError(x, _) if *x == ParseErrorType::LiteralTooLarge("Size of array literal".to_string(), 10)
+        ParseError(x, ..) if *x == ParseErrorType::LiteralTooLarge("Size of array literal".to_string(), 10)
     ));
 
     assert!(matches!(
@@ -190,7 +190,7 @@ fn test_max_map_size() -> Result<(), Box<EvalAltResult>> {
         engine
             .compile("let x = #{a:1,b:2,c:3,d:4,e:5,f:6,g:7,h:8,i:9,j:10,k:11,l:12,m:13,n:14,o:15};")
             .expect_err("should error"),
-        ParseError(x, _) if *x == ParseErrorType::LiteralTooLarge("Number of properties in object map literal".to_string(), 10)
+        ParseError(x, ..) if *x == ParseErrorType::LiteralTooLarge("Number of properties in object map literal".to_string(), 10)
     ));
 
     assert!(matches!(
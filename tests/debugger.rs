@@ -0,0 +1,334 @@
+use rhai::{Backend, DebugEvent, DebuggerCommand, Engine, EvalAltResult, NodeType, INT};
+use std::sync::{Arc, RwLock};
+
+/// Attach a debugger which always returns the same command, recording the line, node type and
+/// call stack depth of each pause.
+fn record(
+    engine: &mut Engine,
+    command: DebuggerCommand,
+) -> Arc<RwLock<Vec<(usize, NodeType, usize)>>> {
+    let pauses = Arc::new(RwLock::new(Vec::new()));
+    let logger = pauses.clone();
+
+    engine.attach_debugger(move |context| {
+        logger.write().unwrap().push((
+            context.position().line().unwrap(),
+            context.node(),
+            context.call_stack().len(),
+        ));
+        command
+    });
+
+    pauses
+}
+
+#[test]
+fn test_debugger_breakpoints() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    let pauses = record(&mut engine, DebuggerCommand::Continue);
+
+    let script = r"
+        let x = 0;
+        for n in range(0, 3) {
+            x += n;
+        }
+        x
+    ";
+
+    assert_eq!(engine.eval::<INT>(script)?, 3);
+    assert!(pauses.read().unwrap().is_empty());
+
+    engine
+        .set_breakpoint(2)
+        .set_breakpoint(4)
+        .set_breakpoint(100);
+    assert_eq!(engine.breakpoints().collect::<Vec<_>>(), vec![2, 4, 100]);
+
+    assert_eq!(engine.eval::<INT>(script)?, 3);
+    assert_eq!(
+        *pauses.read().unwrap(),
+        vec![
+            (2, NodeType::Let, 0),
+            (4, NodeType::Other, 0),
+            (4, NodeType::Other, 0),
+            (4, NodeType::Other, 0),
+        ]
+    );
+
+    pauses.write().unwrap().clear();
+    engine.clear_breakpoint(4);
+
+    assert_eq!(engine.eval::<INT>(script)?, 3);
+    assert_eq!(*pauses.read().unwrap(), vec![(2, NodeType::Let, 0)]);
+
+    pauses.write().unwrap().clear();
+    engine.clear_breakpoints().detach_debugger();
+    engine.set_breakpoint(3);
+
+    assert_eq!(engine.eval::<INT>(script)?, 3);
+    assert!(pauses.read().unwrap().is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_debugger_conditional_breakpoints() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let values = Arc::new(RwLock::new(Vec::new()));
+    let logger = values.clone();
+
+    engine.attach_debugger(move |context| {
+        let n = context.scope().get_value::<INT>("n").unwrap();
+        logger.write().unwrap().push(n);
+        DebuggerCommand::Continue
+    });
+
+    engine.set_conditional_breakpoint(4, "n > 5 && n % 2 == 0")?;
+
+    // Errors in a condition do not pause the evaluation
+    engine.set_conditional_breakpoint(4, "no_such_variable")?;
+
+    assert!(engine.set_conditional_breakpoint(4, "n >").is_err());
+
+    engine.consume(
+        r"
+            let x = 0;
+            for n in range(0, 10) {
+                x += n;
+            }
+        ",
+    )?;
+
+    assert_eq!(*values.read().unwrap(), vec![6, 8]);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_index"))]
+fn test_debugger_conditional_breakpoints_read_only() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    engine.attach_debugger(|_| DebuggerCommand::Continue);
+
+    // Conditions are single expressions
+    assert!(engine.set_conditional_breakpoint(3, "x = 5").is_err());
+
+    // Conditions cannot change the variables in scope
+    engine.set_conditional_breakpoint(3, "push(x, 42) == ()")?;
+
+    assert_eq!(
+        engine.eval::<INT>(
+            r"
+                let x = [1, 2, 3];
+                len(x)
+            "
+        )?,
+        3
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_debugger_stepping() -> Result<(), Box<EvalAltResult>> {
+    let script = r"
+        fn add(a, b) {
+            let c = a + b;
+            c
+        }
+        let x = add(1, 2);
+        x + 1
+    ";
+
+    for backend in [Backend::TreeWalking, Backend::Bytecode].iter() {
+        let mut engine = Engine::new();
+        engine.set_backend(*backend);
+        engine.set_breakpoint(6);
+
+        // Step over the function call
+        let pauses = record(&mut engine, DebuggerCommand::StepOver);
+
+        assert_eq!(engine.eval::<INT>(script)?, 4);
+        assert_eq!(
+            *pauses.read().unwrap(),
+            vec![(6, NodeType::Let, 0), (7, NodeType::Other, 0)]
+        );
+
+        // Step into the function call, pausing at every statement and expression
+        let pauses = record(&mut engine, DebuggerCommand::StepInto);
+
+        assert_eq!(engine.eval::<INT>(script)?, 4);
+        assert_eq!(
+            *pauses.read().unwrap(),
+            vec![
+                (6, NodeType::Let, 0),
+                (6, NodeType::FnCall, 0),
+                (6, NodeType::Constant, 0),
+                (6, NodeType::Constant, 0),
+                (2, NodeType::Block, 1),
+                (3, NodeType::Let, 1),
+                (3, NodeType::FnCall, 1),
+                (3, NodeType::Variable, 1),
+                (4, NodeType::Other, 1),
+                (4, NodeType::Variable, 1),
+                (7, NodeType::Other, 0),
+                (7, NodeType::FnCall, 0),
+                (7, NodeType::Constant, 0),
+            ]
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_debugger_step_out() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.set_breakpoint(3);
+
+    let pauses = record(&mut engine, DebuggerCommand::StepOut);
+
+    engine.consume(
+        r"
+            fn inner(x) {
+                x + 1
+            }
+            fn outer(x) {
+                let y = inner(x);
+                y * 2
+            }
+            outer(1);
+            outer(2);
+        ",
+    )?;
+
+    // Stepping out of 'inner' pauses in 'outer', then again at the global level
+    assert_eq!(
+        *pauses.read().unwrap(),
+        vec![
+            (3, NodeType::Other, 2),
+            (7, NodeType::Other, 1),
+            (10, NodeType::Other, 0),
+            (3, NodeType::Other, 2),
+            (7, NodeType::Other, 1),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_debugger_context() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.set_conditional_breakpoint(3, "n == 0")?;
+
+    let frames = Arc::new(RwLock::new(Vec::new()));
+    let logger = frames.clone();
+
+    engine.attach_debugger(move |context| {
+        assert_eq!(context.event(), DebugEvent::Breakpoint(3));
+        assert_eq!(context.scope().get_value::<INT>("n"), Some(0));
+
+        logger.write().unwrap().extend(
+            context
+                .call_stack()
+                .iter()
+                .map(|f| (f.fn_name.clone(), f.args[0].as_int().unwrap(), f.pos.line())),
+        );
+        DebuggerCommand::Continue
+    });
+
+    engine.consume(
+        r"
+            fn count(n) {
+                if n > 0 { count(n - 1); }
+            }
+            count(2);
+        ",
+    )?;
+
+    assert_eq!(
+        *frames.read().unwrap(),
+        vec![
+            ("count".to_string(), 2, Some(5)),
+            ("count".to_string(), 1, Some(3)),
+            ("count".to_string(), 0, Some(3)),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+#[cfg(not(feature = "no_object"))]
+fn test_debugger_this() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.set_breakpoint(3);
+
+    let values = Arc::new(RwLock::new(Vec::new()));
+    let logger = values.clone();
+
+    engine.attach_debugger(move |context| {
+        logger
+            .write()
+            .unwrap()
+            .push(context.this_ptr().map(|v| v.as_int().unwrap()));
+        DebuggerCommand::Continue
+    });
+
+    engine.consume(
+        r"
+            fn inc() {
+                this += 1;
+            }
+            let x = 41;
+            x.inc();
+        ",
+    )?;
+
+    assert_eq!(*values.read().unwrap(), vec![Some(41)]);
+
+    Ok(())
+}
+
+#[test]
+fn test_debugger_abort() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.set_breakpoint(1);
+    engine.attach_debugger(|_| DebuggerCommand::Abort);
+
+    assert!(matches!(
+        *engine.eval::<INT>("let x = 42;\nx").expect_err("should error"),
+        EvalAltResult::ErrorTerminated(pos) if pos.line() == Some(1)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_debugger_eval() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.set_breakpoint(1);
+
+    let pauses = record(&mut engine, DebuggerCommand::StepOver);
+
+    // Statements run by 'eval' are stepped through together with the caller
+    assert_eq!(engine.eval::<INT>(r#"let x = 1; eval("x += 1"); x"#)?, 2);
+    assert_eq!(
+        *pauses.read().unwrap(),
+        vec![
+            (1, NodeType::Let, 0),
+            (1, NodeType::Other, 0),
+            (1, NodeType::Other, 0),
+            (1, NodeType::Other, 0),
+        ]
+    );
+
+    Ok(())
+}
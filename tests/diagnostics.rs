@@ -0,0 +1,98 @@
+use rhai::{DiagnosticType, Engine, EvalAltResult, ParseError, Position, INT};
+
+fn diagnose(engine: &Engine, script: &str) -> Result<Vec<(DiagnosticType, Position)>, ParseError> {
+    Ok(engine
+        .compile_with_diagnostics(script)?
+        .1
+        .into_iter()
+        .map(|d| (d.0, d.1))
+        .collect())
+}
+
+#[test]
+fn test_diagnostics_unused() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    // Global-level variables may be read via the Scope
+    assert!(diagnose(&engine, "let x = 42;")?.is_empty());
+
+    let result = diagnose(&engine, "{ let x = 42; let _y = 1; let z = 0; z = 1; }")?;
+    assert_eq!(result.len(), 2);
+    assert_eq!(result[0], (DiagnosticType::UnusedVariable("x".into()), Position::new(1, 7)));
+    assert_eq!(result[1], (DiagnosticType::UnusedVariable("z".into()), Position::new(1, 31)));
+
+    assert!(diagnose(&engine, "{ let x = 42; x += 1; }")?.is_empty());
+    assert!(diagnose(&engine, "for x in range(0, 10) { }")?.is_empty());
+
+    #[cfg(not(feature = "no_function"))]
+    {
+        assert!(diagnose(&engine, "fn foo(x, y) { let z = x; z }")?.is_empty());
+        assert_eq!(
+            diagnose(&engine, "fn foo(x) { let z = x; 42 }")?,
+            vec![(DiagnosticType::UnusedVariable("z".into()), Position::new(1, 17))]
+        );
+        // 'eval' may use any variable
+        assert!(diagnose(&engine, r#"fn foo(x) { let z = x; eval("z") }"#)?.is_empty());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_diagnostics_unreachable() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    assert_eq!(
+        diagnose(&engine, "let x = 1; return x; x += 1; x")?,
+        vec![(DiagnosticType::UnreachableCode, Position::new(1, 22))]
+    );
+    assert_eq!(
+        diagnose(&engine, "loop { break; print(42); }")?,
+        vec![(DiagnosticType::UnreachableCode, Position::new(1, 15))]
+    );
+    assert!(diagnose(&engine, "let x = 1; if x > 0 { return x; } x")?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_diagnostics_constant_condition() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    assert_eq!(
+        diagnose(&engine, "if true { 42 } else { 123 }")?,
+        vec![(DiagnosticType::ConstantCondition(true), Position::new(1, 4))]
+    );
+    assert_eq!(
+        diagnose(&engine, "const DEBUG = false; if DEBUG { print(42); }")?,
+        vec![(DiagnosticType::ConstantCondition(false), Position::new(1, 25))]
+    );
+    assert_eq!(
+        diagnose(&engine, "while false { }")?,
+        vec![(DiagnosticType::ConstantCondition(false), Position::new(1, 7))]
+    );
+
+    // Diagnostics refer to the script before optimization
+    let (ast, diagnostics) = engine.compile_with_diagnostics("if true { 42 } else { 123 }")?;
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0].to_string(),
+        "Condition is always true (line 1, position 4)"
+    );
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 42);
+
+    Ok(())
+}
+
+#[test]
+fn test_diagnostics_shadowed() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    assert_eq!(
+        diagnose(&engine, "let x = 1; { let x = 2; print(x); }")?,
+        vec![(DiagnosticType::ShadowedVariable("x".into()), Position::new(1, 18))]
+    );
+    assert!(diagnose(&engine, "{ let x = 1; print(x); } { let x = 2; print(x); }")?.is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use rhai::{Engine, EvalAltResult, INT};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn test_interrupt() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+    let handle = engine.interrupt_handle();
+
+    assert!(!handle.is_interrupted());
+
+    let worker = {
+        let handle = handle.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            handle.interrupt();
+        })
+    };
+
+    assert!(matches!(
+        *engine.eval::<()>("loop {}").expect_err("should error"),
+        EvalAltResult::ErrorInterrupted(reason, _) if reason.is_empty()
+    ));
+
+    worker.join().unwrap();
+
+    // The interrupt is cleared once seen
+    assert!(!handle.is_interrupted());
+    assert_eq!(engine.eval::<INT>("40 + 2")?, 42);
+
+    Ok(())
+}
+
+#[test]
+fn test_interrupt_reason() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+    let handle = engine.interrupt_handle();
+
+    handle.interrupt_with_reason("user cancelled");
+    assert!(handle.is_interrupted());
+
+    let err = engine
+        .eval::<INT>("let x = 40; x + 2")
+        .expect_err("should error");
+
+    assert_eq!(err.to_string(), "Script interrupted: user cancelled (line 1, position 5)");
+
+    // A pending interrupt can be cancelled
+    handle.interrupt();
+    handle.reset();
+    assert_eq!(engine.eval::<INT>("let x = 40; x + 2")?, 42);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_interrupt_functions() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+    let handle = engine.interrupt_handle();
+
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        handle.interrupt_with_reason("stop");
+    });
+
+    assert!(matches!(
+        *engine
+            .eval::<()>("fn foo() { loop {} } foo()")
+            .expect_err("should error"),
+        EvalAltResult::ErrorInFunctionCall(_, _, _, err, _)
+            if matches!(*err, EvalAltResult::ErrorInterrupted(ref reason, _) if reason == "stop")
+    ));
+
+    Ok(())
+}
@@ -28,12 +28,12 @@ fn test_loop() -> Result<(), Box<EvalAltResult>> {
 
     assert!(matches!(
         engine.compile("let x = 0; break;").expect_err("should error"),
-        ParseError(x, _) if *x == ParseErrorType::LoopBreak
+        ParseError(x, ..) if *x == ParseErrorType::LoopBreak
     ));
 
     assert!(matches!(
         engine.compile("let x = 0; if x > 0 { continue; }").expect_err("should error"),
-        ParseError(x, _) if *x == ParseErrorType::LoopBreak
+        ParseError(x, ..) if *x == ParseErrorType::LoopBreak
     ));
 
     Ok(())
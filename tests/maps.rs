@@ -1,6 +1,6 @@
 #![cfg(not(feature = "no_object"))]
 
-use rhai::{Engine, EvalAltResult, Map, Scope, INT};
+use rhai::{Engine, EvalAltResult, Map, ParseErrorType, Scope, INT};
 
 #[test]
 fn test_map_indexing() -> Result<(), Box<EvalAltResult>> {
@@ -88,6 +88,20 @@ fn test_map_indexing() -> Result<(), Box<EvalAltResult>> {
     Ok(())
 }
 
+#[test]
+fn test_map_unclosed() {
+    let engine = Engine::new();
+
+    assert!(matches!(
+        *engine.compile("let x = #{a: 1").expect_err("should error").0,
+        ParseErrorType::MissingToken(ref token, _) if token == "}"
+    ));
+    assert!(matches!(
+        *engine.compile("let x = #{a: 1,").expect_err("should error").0,
+        ParseErrorType::MissingToken(ref token, _) if token == "}"
+    ));
+}
+
 #[test]
 fn test_map_assign() -> Result<(), Box<EvalAltResult>> {
     let engine = Engine::new();
@@ -0,0 +1,188 @@
+#![cfg(not(feature = "no_function"))]
+use rhai::{Engine, EvalAltResult, Scope, INT};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+const SCRIPT: &str = r#"
+    fn calc(x, y) { print("calc"); x * 10 + y }
+    calc(4, 2) + calc(4, 2) + calc(2, 4)
+"#;
+
+fn make_engine() -> (Engine, Arc<AtomicUsize>) {
+    let mut engine = Engine::new();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+
+    engine.on_print(move |_| {
+        counter.fetch_add(1, Ordering::Relaxed);
+    });
+
+    (engine, calls)
+}
+
+#[test]
+fn test_memoize() -> Result<(), Box<EvalAltResult>> {
+    let (mut engine, calls) = make_engine();
+
+    assert_eq!(engine.eval::<INT>(SCRIPT)?, 108);
+    assert_eq!(calls.swap(0, Ordering::Relaxed), 3);
+
+    engine.memoize_fn("calc").memoize_fn("total");
+
+    assert_eq!(engine.eval::<INT>(SCRIPT)?, 108);
+    assert_eq!(calls.swap(0, Ordering::Relaxed), 2);
+
+    // Results are not kept across evaluations by default
+    assert_eq!(engine.eval::<INT>(SCRIPT)?, 108);
+    assert_eq!(calls.swap(0, Ordering::Relaxed), 2);
+
+    // Tail calls use cached results
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                fn calc(x, y) { print("calc"); x * 10 + y }
+                fn wrap(x, y) { return calc(x, y); }
+                wrap(4, 2) + calc(4, 2) + wrap(4, 2)
+            "#
+        )?,
+        126
+    );
+    assert_eq!(calls.swap(0, Ordering::Relaxed), 1);
+
+    #[cfg(not(feature = "no_index"))]
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                fn total(list) { print("total"); let t = 0; for x in list { t += x; } t }
+                total([1, 2, 3]) + total([1, 2, 3]) + total([3, 2, 1])
+            "#
+        )?,
+        18
+    );
+    #[cfg(not(feature = "no_index"))]
+    assert_eq!(calls.swap(0, Ordering::Relaxed), 2);
+
+    Ok(())
+}
+
+#[test]
+fn test_memoize_persistent() -> Result<(), Box<EvalAltResult>> {
+    let (mut engine, calls) = make_engine();
+
+    engine.memoize_fn("calc").set_memo_persistent(true);
+
+    let ast = engine.compile(SCRIPT)?;
+
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 108);
+    assert_eq!(calls.swap(0, Ordering::Relaxed), 2);
+
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 108);
+    assert_eq!(calls.swap(0, Ordering::Relaxed), 0);
+
+    // A different script does not reuse the results
+    assert_eq!(
+        engine.eval::<INT>("fn calc(x, y) { print(\"calc\"); x + y } calc(4, 2)")?,
+        6
+    );
+    assert_eq!(calls.swap(0, Ordering::Relaxed), 1);
+
+    engine.clear_memo_cache();
+
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 108);
+    assert_eq!(calls.swap(0, Ordering::Relaxed), 2);
+
+    Ok(())
+}
+
+#[test]
+fn test_memoize_max_entries() -> Result<(), Box<EvalAltResult>> {
+    let (mut engine, calls) = make_engine();
+
+    engine.memoize_fn("calc").set_max_memo_entries(1);
+    assert_eq!(engine.max_memo_entries(), 1);
+
+    // 'calc(4, 2)' is evicted to cache 'calc(2, 4)'
+    assert_eq!(
+        engine.eval::<INT>(&format!("{} + calc(4, 2)", SCRIPT.trim()))?,
+        150
+    );
+    assert_eq!(calls.swap(0, Ordering::Relaxed), 3);
+
+    // Only the oldest result is evicted when the cache is full
+    engine.set_max_memo_entries(2);
+
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                fn calc(x, y) { print("calc"); x * 10 + y }
+                calc(1, 1) + calc(2, 2) + calc(3, 3) + calc(2, 2)
+            "#
+        )?,
+        88
+    );
+    assert_eq!(calls.swap(0, Ordering::Relaxed), 3);
+
+    engine.set_max_memo_entries(0);
+
+    assert_eq!(engine.eval::<INT>(SCRIPT)?, 108);
+    assert_eq!(calls.swap(0, Ordering::Relaxed), 3);
+
+    Ok(())
+}
+
+#[test]
+fn test_memoize_global() -> Result<(), Box<EvalAltResult>> {
+    let (mut engine, calls) = make_engine();
+
+    engine.memoize_fn("calc").set_memo_persistent(true);
+
+    // Functions referring to the 'global' namespace are never memoized
+    let ast = engine.compile(
+        r#"
+            fn calc(x) { print("calc"); x * global::RATE }
+            calc(10) + calc(10)
+        "#,
+    )?;
+
+    let mut scope = Scope::new();
+    scope.push_constant("RATE", 2 as INT);
+
+    assert_eq!(engine.eval_ast_with_scope::<INT>(&mut scope, &ast)?, 40);
+    assert_eq!(calls.swap(0, Ordering::Relaxed), 2);
+
+    let mut scope = Scope::new();
+    scope.push_constant("RATE", 3 as INT);
+
+    assert_eq!(engine.eval_ast_with_scope::<INT>(&mut scope, &ast)?, 60);
+    assert_eq!(calls.swap(0, Ordering::Relaxed), 2);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "unchecked"))]
+fn test_memoize_max_memory() -> Result<(), Box<EvalAltResult>> {
+    use rhai::RegisterFn;
+
+    let (mut engine, calls) = make_engine();
+
+    engine
+        .register_fn("make", |n: INT| "x".repeat(n as usize))
+        .memoize_fn("big");
+
+    const SCRIPT: &str = r#"
+        fn big(n) { print("big"); make(3500) + n }
+        len(big(1)) + len(big(2)) + len(big(1))
+    "#;
+
+    assert_eq!(engine.eval::<INT>(SCRIPT)?, 3 * 3501);
+    assert_eq!(calls.swap(0, Ordering::Relaxed), 2);
+
+    // Cached results count toward the memory limit, so 'big(1)' is evicted to cache 'big(2)'
+    engine.set_max_memory(10_000);
+
+    assert_eq!(engine.eval::<INT>(SCRIPT)?, 3 * 3501);
+    assert_eq!(calls.swap(0, Ordering::Relaxed), 3);
+
+    Ok(())
+}
@@ -0,0 +1,147 @@
+#![cfg(not(feature = "unchecked"))]
+use rhai::{Engine, EvalAltResult, RegisterFn, INT};
+
+#[test]
+#[cfg(not(feature = "no_object"))]
+fn test_max_memory_strings() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.set_max_memory(10_000);
+
+    assert_eq!(engine.max_memory(), 10_000);
+
+    assert_eq!(
+        engine.eval::<INT>(
+            r#"
+                let s = "";
+                for x in range(0, 100) { s = "x"; s.pad(4000, 'x'); }
+                s.len
+            "#
+        )?,
+        4000
+    );
+
+    assert!(matches!(
+        *engine
+            .eval::<()>(
+                r#"
+                    let a = "x"; a.pad(4000, 'x');
+                    let b = "x"; b.pad(4000, 'x');
+                    let c = "x"; c.pad(4000, 'x');
+                "#
+            )
+            .expect_err("should error"),
+        EvalAltResult::ErrorDataTooLarge(_, 10_000, _, _)
+    ));
+
+    engine.set_max_memory(0);
+
+    engine.eval::<()>(
+        r#"
+            let a = "x"; a.pad(4000, 'x');
+            let b = "x"; b.pad(4000, 'x');
+            let c = "x"; c.pad(4000, 'x');
+        "#,
+    )?;
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_index"))]
+#[cfg(not(feature = "no_object"))]
+fn test_max_memory_arrays() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.set_max_memory(100_000);
+
+    // Values that are dropped are not counted
+    engine.eval::<()>(
+        r#"
+            let x = [];
+            for n in range(0, 100) { x = []; x.pad(1000, n); }
+        "#,
+    )?;
+
+    // No single array is over the limit, but all of them together are
+    assert!(matches!(
+        *engine
+            .eval::<()>(
+                r#"
+                    let list = [];
+                    for n in range(0, 100) { let x = []; x.pad(1000, n); list.push(x); }
+                "#
+            )
+            .expect_err("should error"),
+        EvalAltResult::ErrorDataTooLarge(_, 100_000, _, _)
+    ));
+
+    #[cfg(not(feature = "no_function"))]
+    assert!(matches!(
+        *engine
+            .eval::<()>(
+                r#"
+                    fn make(n) { let x = []; x.pad(n, 0); x }
+                    let a = make(3000);
+                    let b = make(3000);
+                    let c = make(3000);
+                "#
+            )
+            .expect_err("should error"),
+        EvalAltResult::ErrorDataTooLarge(_, 100_000, _, _)
+    ));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_index"))]
+#[cfg(not(feature = "no_object"))]
+fn test_max_memory_maps() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.set_max_memory(10_000);
+
+    assert!(matches!(
+        *engine
+            .eval::<()>(
+                r#"
+                    let maps = [];
+                    for n in range(0, 1000) { maps += [#{ a: n, b: n, c: n }]; }
+                "#
+            )
+            .expect_err("should error"),
+        EvalAltResult::ErrorDataTooLarge(_, 10_000, _, _)
+    ));
+
+    Ok(())
+}
+
+#[cfg(not(feature = "no_object"))]
+#[derive(Clone)]
+struct Buffer(Vec<u8>);
+
+#[test]
+#[cfg(not(feature = "no_object"))]
+fn test_max_memory_custom_types() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    engine
+        .register_type::<Buffer>()
+        .register_fn("new_buffer", |size: INT| Buffer(vec![0; size as usize]));
+
+    engine.set_max_memory(10_000);
+
+    let script = "let a = new_buffer(4000); let b = new_buffer(4000); let c = new_buffer(4000);";
+
+    // Without a size reported, only the size of the type itself is counted
+    engine.eval::<()>(script)?;
+
+    engine.register_type_size(|buf: &Buffer| buf.0.len());
+
+    engine.eval::<()>("let a = new_buffer(4000); let b = new_buffer(4000);")?;
+
+    assert!(matches!(
+        *engine.eval::<()>(script).expect_err("should error"),
+        EvalAltResult::ErrorDataTooLarge(_, 10_000, _, _)
+    ));
+
+    Ok(())
+}
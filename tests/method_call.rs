@@ -47,3 +47,47 @@ fn test_method_call_style() -> Result<(), Box<EvalAltResult>> {
 
     Ok(())
 }
+
+#[test]
+fn test_method_call_cache() -> Result<(), Box<EvalAltResult>> {
+    #[derive(Debug, Clone, Eq, PartialEq)]
+    struct TestStruct {
+        x: INT,
+    }
+
+    let mut engine = Engine::new();
+
+    engine
+        .register_type::<TestStruct>()
+        .register_fn("new_ts", || TestStruct { x: 0 })
+        .register_fn("update", |ts: &mut TestStruct, n: INT| ts.x += n)
+        .register_fn("update", |ts: &mut TestStruct, s: &str| ts.x += s.len() as INT);
+
+    // The same call site is resolved for different argument types
+    assert_eq!(
+        engine.eval::<TestStruct>(
+            r#"
+                let ts = new_ts();
+                let i = 0;
+                while i < 10 {
+                    let arg = if i % 2 == 0 { i } else { "hello" };
+                    ts.update(arg);
+                    i += 1;
+                }
+                ts
+            "#
+        )?,
+        TestStruct { x: 45 }
+    );
+
+    let ast = engine.compile("let ts = new_ts(); ts.update(1); ts.update(1); ts")?;
+
+    assert_eq!(engine.eval_ast::<TestStruct>(&ast)?, TestStruct { x: 2 });
+
+    // Functions registered after the first evaluation are picked up
+    engine.register_fn("update", |ts: &mut TestStruct, n: INT| ts.x += n * 10);
+
+    assert_eq!(engine.eval_ast::<TestStruct>(&ast)?, TestStruct { x: 20 });
+
+    Ok(())
+}
@@ -148,7 +148,7 @@ fn test_module_resolver() -> Result<(), Box<EvalAltResult>> {
                     "#
                 )
                 .expect_err("should error"),
-            EvalAltResult::ErrorInFunctionCall(fn_name, _, _) if fn_name == "foo"
+            EvalAltResult::ErrorInFunctionCall(fn_name, _, _, _, _) if fn_name == "foo"
         ));
 
         engine.set_max_modules(1000);
@@ -257,13 +257,267 @@ fn test_module_export() -> Result<(), Box<EvalAltResult>> {
 
     assert!(matches!(
         engine.compile(r"let x = 10; { export x; }").expect_err("should error"),
-        ParseError(x, _) if *x == ParseErrorType::WrongExport
+        ParseError(x, ..) if *x == ParseErrorType::WrongExport
     ));
 
     #[cfg(not(feature = "no_function"))]
     assert!(matches!(
         engine.compile(r"fn abc(x) { export x; }").expect_err("should error"),
-        ParseError(x, _) if *x == ParseErrorType::WrongExport
+        ParseError(x, ..) if *x == ParseErrorType::WrongExport
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_module_import_members() -> Result<(), Box<EvalAltResult>> {
+    let mut resolver = StaticModuleResolver::new();
+
+    let mut module = Module::new();
+    module.set_var("answer", 41 as INT);
+    module.set_fn_1("inc", |x: INT| Ok(x + 1));
+    module.set_fn_2("add", |x: INT, y: INT| Ok(x + y));
+    resolver.insert("hello", module);
+
+    let mut engine = Engine::new();
+    engine.set_module_resolver(Some(resolver));
+
+    assert_eq!(
+        engine.eval::<INT>(r#"import { inc, answer } from "hello"; inc(answer)"#)?,
+        42
+    );
+    assert_eq!(
+        engine.eval::<INT>(r#"import { add as plus, answer as x } from "hello"; plus(x, 1)"#)?,
+        42
+    );
+    assert_eq!(
+        engine.eval::<INT>(r#"import * from "hello"; add(inc(answer), -1) + 1"#)?,
+        42
+    );
+
+    assert!(matches!(
+        *engine
+            .eval::<INT>(r#"{ import * from "hello"; } inc(answer)"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorVariableNotFound(name, _) if name == "answer"
+    ));
+
+    assert!(matches!(
+        *engine
+            .eval::<INT>(r#"let x = 1; import { question } from "hello"; 42"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorVariableNotFound(name, pos)
+            if name == "hello::question" && pos.position() == Some(21)
+    ));
+
+    assert!(matches!(
+        *engine
+            .eval::<INT>(r#"let answer = 1; import * from "hello"; answer"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorParsing(ParseErrorType::DuplicatedImport(name), pos)
+            if name == "answer" && pos.position() == Some(17)
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<INT>(r#"import { inc } from "hello"; import * from "hello"; 42"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorParsing(ParseErrorType::DuplicatedImport(name), _) if name == "inc"
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<INT>(r#"import "hello" as add; import * from "hello"; 42"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorParsing(ParseErrorType::DuplicatedImport(name), _) if name == "add"
+    ));
+
+    assert!(matches!(
+        engine
+            .compile(r#"let x = 42; import { answer as x } from "hello";"#)
+            .expect_err("should error"),
+        ParseError(err, ..) if *err == ParseErrorType::DuplicatedImport("x".to_string())
+    ));
+
+    assert!(matches!(
+        engine
+            .compile(r#"import { inc, add as inc } from "hello";"#)
+            .expect_err("should error"),
+        ParseError(err, ..) if *err == ParseErrorType::DuplicatedImport("inc".to_string())
+    ));
+
+    #[cfg(not(feature = "no_function"))]
+    assert!(matches!(
+        engine
+            .compile(r#"import { inc } from "hello"; fn inc(x) { x + 1 }"#)
+            .expect_err("should error"),
+        ParseError(err, ..) if *err == ParseErrorType::DuplicatedImport("inc".to_string())
+    ));
+
+    #[cfg(not(feature = "unchecked"))]
+    {
+        engine.set_max_modules(5);
+
+        assert!(matches!(
+            *engine
+                .eval::<INT>(
+                    r#"
+                        let sum = 0;
+
+                        for x in range(0, 10) {
+                            import * from "hello";
+                            sum += answer;
+                        }
+
+                        sum
+                    "#
+                )
+                .expect_err("should error"),
+            EvalAltResult::ErrorTooManyModules(_)
+        ));
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_module_import_script_members() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let ast = engine.compile(
+        r#"
+            fn double(x) { x * 2 }
+            private fn secret() { 42 }
+            let half = 21;
+            export half;
+        "#,
+    )?;
+    let module = Module::eval_ast_as_new(Scope::new(), &ast, &engine)?;
+
+    let mut resolver = StaticModuleResolver::new();
+    resolver.insert("helpers", module);
+    engine.set_module_resolver(Some(resolver));
+
+    assert_eq!(
+        engine.eval::<INT>(r#"import * from "helpers"; double(half)"#)?,
+        42
+    );
+    assert_eq!(
+        engine.eval::<INT>(r#"import { double as twice } from "helpers"; twice(21)"#)?,
+        42
+    );
+
+    assert!(matches!(
+        *engine
+            .eval::<INT>(r#"import * from "helpers"; secret()"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorFunctionNotFound(name, _) if name.starts_with("secret")
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<INT>(r#"import { secret } from "helpers"; secret()"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorVariableNotFound(name, pos)
+            if name == "helpers::secret" && pos.position() == Some(10)
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<INT>(r#"fn double(x) { x } import * from "helpers"; double(half)"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorParsing(ParseErrorType::DuplicatedImport(name), _) if name == "double"
+    ));
+
+    // Function pointers can refer to imported functions
+    #[cfg(not(feature = "no_object"))]
+    assert_eq!(
+        engine.eval::<INT>(r#"import * from "helpers"; let f = Fn("double"); f.call(half)"#)?,
+        42
+    );
+    assert_eq!(
+        engine.eval::<INT>(r#"import { double } from "helpers"; call(Fn("double"), 21)"#)?,
+        42
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_module_re_export() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let ast = engine.compile(
+        r#"
+            fn double(x) { x * 2 }
+            private fn secret() { 42 }
+            let half = 21;
+            export half;
+        "#,
+    )?;
+    let module = Module::eval_ast_as_new(Scope::new(), &ast, &engine)?;
+
+    let mut resolver = StaticModuleResolver::new();
+    resolver.insert("helpers", module);
+    engine.set_module_resolver(Some(resolver.clone()));
+
+    let ast = engine.compile(
+        r#"
+            import "helpers" as h;
+            export h::double as twice, h::half;
+            export h as inner;
+            export import "helpers" as other;
+        "#,
+    )?;
+    let module = Module::eval_ast_as_new(Scope::new(), &ast, &engine)?;
+
+    assert!(module.contains_sub_module("h"));
+    assert!(module.contains_sub_module("inner"));
+    assert!(module.contains_sub_module("other"));
+    assert!(module.contains_var("half"));
+
+    resolver.insert("aggregate", module);
+    engine.set_module_resolver(Some(resolver));
+
+    assert_eq!(
+        engine.eval::<INT>(r#"import "aggregate" as a; a::twice(a::half)"#)?,
+        42
+    );
+    assert_eq!(
+        engine.eval::<INT>(r#"import "aggregate" as a; a::inner::double(a::other::half)"#)?,
+        42
+    );
+    assert!(matches!(
+        *engine
+            .eval::<INT>(r#"import "aggregate" as a; a::double(1)"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorFunctionNotFound(name, _) if name.starts_with("a::double")
+    ));
+
+    assert!(matches!(
+        *engine
+            .eval::<()>(r#"import "helpers" as h; export h::secret;"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorVariableNotFound(name, _) if name == "h::secret"
+    ));
+    assert!(matches!(
+        *engine
+            .eval::<()>(r#"export h::half;"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorModuleNotFound(name, _) if name == "h"
+    ));
+
+    assert!(matches!(
+        *engine
+            .compile(r#"export import * from "helpers";"#)
+            .expect_err("should error")
+            .0,
+        ParseErrorType::BadInput(_)
+    ));
+    assert!(matches!(
+        *engine
+            .compile(r#"import "helpers" as h; export h::half, h::double as half;"#)
+            .expect_err("should error")
+            .0,
+        ParseErrorType::DuplicatedExport(name) if name == "half"
     ));
 
     Ok(())
@@ -1,6 +1,8 @@
 #![cfg(not(feature = "no_optimize"))]
 
-use rhai::{Engine, EvalAltResult, OptimizationLevel, INT};
+use rhai::{
+    AstVisitor, Engine, EvalAltResult, ExpressionMut, OptimizationLevel, RegisterFn, INT,
+};
 
 #[test]
 fn test_optimizer_run() -> Result<(), Box<EvalAltResult>> {
@@ -14,6 +16,10 @@ fn test_optimizer_run() -> Result<(), Box<EvalAltResult>> {
             engine.eval::<INT>(r#"const abc = "hello"; if abc < "foo" { 42 } else { 123 }"#)?,
             123
         );
+        assert_eq!(
+            engine.eval::<INT>(r"let r = 0; { let a = 1; const B = 2; let c = 3; r = a + B + c; } r")?,
+            6
+        );
         Ok(())
     }
 
@@ -48,3 +54,168 @@ fn test_optimizer_parse() -> Result<(), Box<EvalAltResult>> {
 
     Ok(())
 }
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_optimizer_inline() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.set_optimization_level(OptimizationLevel::Full);
+
+    let ast = engine.compile("fn add(x, y) { x + y } add(1, 2)")?;
+
+    assert!(format!("{:?}", ast).starts_with("AST([Expr(IntegerConstant((3,"));
+
+    let ast = engine.compile("fn add(x, y) { x + y } let a = 40; add(a, 2)")?;
+
+    assert!(!format!("{:?}", ast.clone_statements_only()).contains("FnCall(((\"add\""));
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 42);
+
+    // Arguments are passed by value
+    let ast = engine.compile("fn sq(x) { x * x } let a = 6; sq(a) + a")?;
+
+    assert!(!format!("{:?}", ast.clone_statements_only()).contains("FnCall(((\"sq\""));
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 42);
+
+    // Parameters modified in place and used more than once are not inlined
+    #[cfg(not(feature = "no_index"))]
+    {
+        let ast =
+            engine.compile("fn two(x) { pop(x) + pop(x) } let b = [1, 40]; two(b) + len(b)")?;
+
+        assert!(format!("{:?}", ast.clone_statements_only()).contains("FnCall(((\"two\""));
+        assert_eq!(engine.eval_ast::<INT>(&ast)?, 43);
+    }
+
+    // Recursive functions are not inlined
+    assert_eq!(
+        engine.eval::<INT>("fn fib(n) { if n < 2 { n } else { fib(n - 1) + fib(n - 2) } } fib(10)")?,
+        55
+    );
+    assert_eq!(
+        engine.eval::<INT>("fn sq(x) { x * x } fn quad(x) { sq(sq(x)) } quad(3)")?,
+        81
+    );
+
+    // Functions with side effects are not inlined
+    let ast = engine.compile("fn hello(x) { print(x); x } hello(42)")?;
+
+    assert!(format!("{:?}", ast.clone_statements_only()).contains("FnCall(((\"hello\""));
+
+    Ok(())
+}
+
+#[test]
+fn test_optimizer_pure() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.set_optimization_level(OptimizationLevel::Full);
+
+    engine.register_fn("sensor", |x: INT| x + 1);
+    engine.register_pure_fn("double", |x: INT| x * 2);
+
+    // Impure functions are not evaluated
+    let ast = engine.compile("sensor(41)")?;
+
+    assert!(format!("{:?}", ast).starts_with("AST([Expr(FnCall(((\"sensor\""));
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 42);
+
+    // Pure functions are evaluated
+    let ast = engine.compile("double(21)")?;
+
+    assert!(format!("{:?}", ast).starts_with("AST([Expr(IntegerConstant((42,"));
+
+    // Overloading a built-in operator with an impure function
+    engine.register_fn("+", |x: INT, y: INT| x * y);
+
+    let ast = engine.compile("if 1 + 1 == 1 { 42 } else { 0 }")?;
+
+    assert!(format!("{:?}", ast).starts_with("AST([IfThenElse("));
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 42);
+
+    // Built-in functions depending on time are impure
+    #[cfg(not(feature = "no_std"))]
+    {
+        let ast = engine.compile("timestamp()")?;
+
+        assert!(format!("{:?}", ast).starts_with("AST([Expr(FnCall(((\"timestamp\""));
+    }
+
+    // Functions not yet registered are impure
+    #[cfg(not(feature = "no_function"))]
+    {
+        let ast = engine.compile("fn next(x) { sample(x) - 1 } next(40)")?;
+
+        assert!(format!("{:?}", ast.clone_statements_only()).contains("FnCall(((\"next\""));
+
+        engine.register_fn("sample", |x: INT| x + 3);
+
+        assert_eq!(engine.eval_ast::<INT>(&ast)?, 42);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_optimizer_passes() -> Result<(), Box<EvalAltResult>> {
+    struct RenameDeprecated;
+
+    impl AstVisitor for RenameDeprecated {
+        fn visit_expr(&self, expr: &mut ExpressionMut) {
+            if expr.get_fn_name() == Some("triple_old") {
+                expr.set_fn_name("triple");
+            }
+        }
+    }
+
+    struct FoldTriple;
+
+    impl AstVisitor for FoldTriple {
+        fn visit_expr(&self, expr: &mut ExpressionMut) {
+            if expr.get_fn_name() != Some("triple") {
+                return;
+            }
+
+            match expr.get_arg_values() {
+                Some(args) if args.len() == 1 && args[0].is::<INT>() => {
+                    let value = args[0].as_int().unwrap() * 3;
+                    assert_eq!(expr.set_constant_value(value.into()), !expr.is_method_call());
+                }
+                _ => (),
+            }
+        }
+    }
+
+    let mut engine = Engine::new();
+
+    engine.register_fn("triple", |x: INT| x * 3);
+    engine.register_fn("triple", |x: &mut INT, y: INT| *x * y);
+    engine.register_optimizer_pass(RenameDeprecated);
+    engine.register_optimizer_pass(FoldTriple);
+
+    // Passes run after renaming and constant propagation
+    let ast = engine.compile("const x = 14; triple_old(x)")?;
+
+    assert!(format!("{:?}", ast).contains("IntegerConstant((42,"));
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 42);
+
+    // Method calls are renamed but not folded
+    #[cfg(not(feature = "no_object"))]
+    assert_eq!(engine.eval::<INT>("let x = 2; x.triple_old(21)")?, 42);
+
+    // Non-constant arguments are not folded
+    assert_eq!(engine.eval::<INT>("let x = 14; triple_old(x)")?, 42);
+
+    #[cfg(not(feature = "no_function"))]
+    {
+        assert_eq!(
+            engine.eval::<INT>("fn foo(x) { triple_old(x) + triple_old(4) } foo(2)")?,
+            18
+        );
+    }
+
+    // Passes do not run without optimization
+    engine.set_optimization_level(OptimizationLevel::None);
+
+    assert!(engine.eval::<INT>("triple_old(14)").is_err());
+
+    Ok(())
+}
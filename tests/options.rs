@@ -0,0 +1,202 @@
+#![cfg(not(feature = "unchecked"))]
+use rhai::{Engine, EvalAltResult, EvalOptions, Scope, INT};
+
+#[test]
+fn test_options_max_operations() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.set_max_operations(500);
+
+    let ast = engine.compile("let x = 0; while x < 1000 { x += 1; } x")?;
+
+    assert!(matches!(
+        *engine.eval_ast::<INT>(&ast).expect_err("should error"),
+        EvalAltResult::ErrorTooManyOperations(_)
+    ));
+
+    // Limits can be raised as well as lowered
+    let options = EvalOptions {
+        max_operations: Some(0),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        engine.eval_ast_with_scope_and_options::<INT>(&mut Scope::new(), &ast, &options)?,
+        1000
+    );
+
+    engine.consume_ast_with_scope_and_options(&mut Scope::new(), &ast, &options)?;
+
+    // Scripts run by 'eval' share the limits
+    let ast = engine.compile(r#"eval("let x = 0; while x < 1000 { x += 1; } x")"#)?;
+
+    assert_eq!(
+        engine.eval_ast_with_scope_and_options::<INT>(&mut Scope::new(), &ast, &options)?,
+        1000
+    );
+
+        // Options not set keep the limits of the Engine
+    let options = EvalOptions {
+        max_string_size: Some(100),
+        ..Default::default()
+    };
+
+    assert!(matches!(
+        *engine
+            .eval_ast_with_scope_and_options::<INT>(&mut Scope::new(), &ast, &options)
+            .expect_err("should error"),
+        EvalAltResult::ErrorTooManyOperations(_)
+    ));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_index"))]
+#[cfg(not(feature = "no_object"))]
+fn test_options_data_sizes() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let options = EvalOptions {
+        max_array_size: Some(10),
+        ..Default::default()
+    };
+
+    let ast = engine.compile("let x = []; for n in range(0, 20) { x.push(n); } x.len()")?;
+
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 20);
+
+    // Functions growing their first argument in place are checked against the options
+    assert!(matches!(
+        *engine
+            .eval_ast_with_scope_and_options::<INT>(&mut Scope::new(), &ast, &options)
+            .expect_err("should error"),
+        EvalAltResult::ErrorDataTooLarge(_, 10, _, _)
+    ));
+
+    let ast = engine.compile("[1, 2, 3] + [4, 5, 6] + [7, 8, 9] + [10, 11, 12]")?;
+
+    assert!(matches!(
+        *engine
+            .eval_ast_with_scope_and_options::<()>(&mut Scope::new(), &ast, &options)
+            .expect_err("should error"),
+        EvalAltResult::ErrorDataTooLarge(_, 10, _, _)
+    ));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_options_call_fn() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let ast = engine.compile(
+        r"
+            fn count(n) { let x = 0; while x < n { x += 1; } x }
+        ",
+    )?;
+
+    let options = EvalOptions {
+        max_operations: Some(100),
+        ..Default::default()
+    };
+
+    let result = engine.call_fn_dynamic_with_options(
+        &mut Scope::new(),
+        &ast,
+        "count",
+        None,
+        [(10 as INT).into()],
+        &options,
+    )?;
+    assert_eq!(result.cast::<INT>(), 10);
+
+    assert!(matches!(
+        *engine
+            .call_fn_dynamic_with_options(
+                &mut Scope::new(),
+                &ast,
+                "count",
+                None,
+                [(1000 as INT).into()],
+                &options,
+            )
+            .expect_err("should error"),
+        EvalAltResult::ErrorInFunctionCall(_, _, _, err, _)
+            if matches!(*err, EvalAltResult::ErrorTooManyOperations(_))
+    ));
+
+    let result = engine.call_fn_dynamic(
+        &mut Scope::new(),
+        &ast,
+        "count",
+        None,
+        [(1000 as INT).into()],
+    )?;
+    assert_eq!(result.cast::<INT>(), 1000);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_std"))]
+fn test_options_max_execution_time() -> Result<(), Box<EvalAltResult>> {
+    use std::time::Duration;
+
+    let engine = Engine::new();
+
+    let ast = engine.compile("loop {}")?;
+
+    let options = EvalOptions {
+        max_execution_time: Some(Duration::from_millis(20)),
+        ..Default::default()
+    };
+
+    assert!(matches!(
+        *engine
+            .consume_ast_with_scope_and_options(&mut Scope::new(), &ast, &options)
+            .expect_err("should error"),
+        EvalAltResult::ErrorTimeout(_)
+    ));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_optimize"))]
+fn test_options_optimization_level() -> Result<(), Box<EvalAltResult>> {
+    use rhai::OptimizationLevel;
+
+    let mut engine = Engine::new();
+    engine.set_optimization_level(OptimizationLevel::None);
+
+    let ast = engine.compile("if DEBUG { while true {} } 42")?;
+
+    let mut scope = Scope::new();
+    scope.push_constant("DEBUG", false);
+
+    let options = EvalOptions {
+        max_operations: Some(2),
+        ..Default::default()
+    };
+
+    assert!(matches!(
+        *engine
+            .eval_ast_with_scope_and_options::<INT>(&mut scope, &ast, &options)
+            .expect_err("should error"),
+        EvalAltResult::ErrorTooManyOperations(_)
+    ));
+
+    // With the constant in scope, the 'if' statement is optimized away
+    let options = EvalOptions {
+        optimization_level: Some(OptimizationLevel::Simple),
+        ..options
+    };
+
+    assert_eq!(
+        engine.eval_ast_with_scope_and_options::<INT>(&mut scope, &ast, &options)?,
+        42
+    );
+
+    Ok(())
+}
@@ -0,0 +1,214 @@
+use rhai::{Backend, Engine, EvalAltResult, NodeType, Position, Span, INT};
+
+#[test]
+fn test_positions_source() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let ast = engine.compile_with_source("main.rhai", "let x = 40;\nx + y")?;
+    assert_eq!(ast.source(), Some("main.rhai"));
+
+    let err = engine.eval_ast::<INT>(&ast).expect_err("should error");
+    let pos = err.position();
+
+    assert_eq!(pos.line(), Some(2));
+    assert_eq!(pos.position(), Some(5));
+    assert_eq!(format!("{:?}", pos), "2:5");
+    assert_eq!(err.root_source(), Some("main.rhai"));
+    assert_eq!(err.span(), Some(Span::new(pos, pos)));
+    assert_eq!(err.to_string(), "Variable not found: 'y' (line 2, position 5) in 'main.rhai'");
+
+    // Positions without a source
+    let ast = engine.compile("let x = 40;\nx + 2")?;
+    assert_eq!(ast.source(), None);
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 42);
+
+    // Merging keeps the source of the first AST that has one
+    let ast = ast.merge(&engine.compile_with_source("other.rhai", "x")?);
+    assert_eq!(ast.source(), Some("other.rhai"));
+
+    // Parse errors
+    let err = engine
+        .compile_with_source("broken.rhai", "let x = ;")
+        .expect_err("should error");
+
+    assert_eq!(err.1.line(), Some(1));
+    assert_eq!(err.source(), Some("broken.rhai"));
+    assert_eq!(err.span(), Some(Span::new(err.1, err.1)));
+    assert!(err.to_string().ends_with("(line 1, position 9 in 'broken.rhai')"));
+
+    // Parse errors without a source
+    let err = engine.compile("let x = ;").expect_err("should error");
+
+    assert_eq!(err.source(), None);
+    assert!(err.span().is_some());
+    assert!(err.to_string().ends_with("(line 1, position 9)"));
+
+    Ok(())
+}
+
+#[test]
+fn test_positions_merge_sources() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let ast1 = engine.compile_with_source("a.rhai", "let x = 40;\nx")?;
+    let ast2 = engine.compile_with_source("b.rhai", "let y = 2;\nx + y + z")?;
+    let ast = ast1.merge(&ast2);
+
+    assert_eq!(ast.source(), Some("a.rhai"));
+    assert_eq!(ast.sources().collect::<Vec<_>>(), vec!["a.rhai", "b.rhai"]);
+
+    // The same position is found in each source separately
+    let pos = Position::new(1, 5);
+
+    assert_eq!(
+        ast.span_in(Some("a.rhai"), pos),
+        Some(Span::new(Position::new(1, 1), Position::new(1, 10)))
+    );
+    assert_eq!(
+        ast.span_in(Some("b.rhai"), pos),
+        Some(Span::new(Position::new(1, 1), Position::new(1, 9)))
+    );
+    assert_eq!(ast.span_in(None, pos), None);
+
+    // Errors in the statements of the second script are reported in its source
+    for backend in [Backend::TreeWalking, Backend::Bytecode].iter() {
+        let mut engine = Engine::new();
+        engine.set_backend(*backend);
+
+        let err = engine.eval_ast::<INT>(&ast).expect_err("should error");
+
+        assert_eq!(err.root_source(), Some("b.rhai"));
+        assert_eq!(err.position(), Position::new(2, 9));
+        assert_eq!(err.span(), Some(Span::new(Position::new(2, 9), Position::new(2, 9))));
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_module"))]
+#[cfg(not(feature = "no_function"))]
+fn test_positions_source_module() -> Result<(), Box<EvalAltResult>> {
+    use rhai::module_resolvers::FileModuleResolver;
+    use std::fs;
+
+    let dir = std::env::temp_dir().join("rhai_test_positions_source_module");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("checks.rhai"),
+        "fn check(x) {\n    if x < 0 { throw \"negative\"; }\n    x\n}",
+    )
+    .unwrap();
+
+    let mut engine = Engine::new();
+    engine.set_module_resolver(Some(FileModuleResolver::new_with_path(&dir)));
+
+    let err = engine
+        .eval::<INT>(
+            r#"
+                import "checks" as c;
+                c::check(-1)
+            "#,
+        )
+        .expect_err("should error");
+
+    // The error inside the module has the path of the module file as its source
+    let path = dir.join("checks.rhai");
+
+    assert_eq!(err.root_source(), Some(path.to_string_lossy().as_ref()));
+    assert_eq!(err.root_cause().position().line(), Some(2));
+
+    // The call is in the main script, which has no source
+    assert_eq!(err.backtrace()[0].source, None);
+    assert_eq!(err.position().line(), Some(3));
+
+    Ok(())
+}
+
+#[test]
+fn test_positions_many_lines() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let script = format!("let x = 42;{}x + y", "\n".repeat(70_000));
+
+    let err = engine.eval::<INT>(&script).expect_err("should error");
+    assert_eq!(err.position(), Position::new(70_001, 5));
+
+    let script = format!("{}let x = ;", "\n".repeat(100_000));
+
+    let err = engine.compile(&script).expect_err("should error");
+    assert_eq!(err.1, Position::new(100_001, 9));
+
+    Ok(())
+}
+
+#[test]
+fn test_positions_spans() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let ast = engine.compile(
+        r#"
+let x = (1 + 2) * 3;
+{
+    let y = x - 1;
+}
+x == 9
+"#,
+    )?;
+
+    let span = |line, pos| {
+        let span = ast.span(Position::new(line, pos)).unwrap();
+        (
+            (span.start.line().unwrap(), span.start.position().unwrap()),
+            (span.end.line().unwrap(), span.end.position().unwrap()),
+        )
+    };
+
+    // let x = (1 + 2) * 3; - the position of a 'let' statement is that of the variable
+    assert_eq!(span(2, 5), ((2, 1), (2, 19)));
+    // (1 + 2) * 3
+    assert_eq!(span(2, 17), ((2, 9), (2, 19)));
+    // 1 + 2
+    assert_eq!(span(2, 12), ((2, 10), (2, 14)));
+    // Block
+    assert_eq!(span(3, 1), ((3, 1), (5, 1)));
+    // let y = x - 1;
+    assert_eq!(span(4, 9), ((4, 5), (4, 17)));
+    // x - 1
+    assert_eq!(span(4, 15), ((4, 13), (4, 17)));
+    // x
+    assert_eq!(span(6, 1), ((6, 1), (6, 1)));
+    // x == 9
+    assert_eq!(span(6, 3), ((6, 1), (6, 6)));
+
+    // No statement or expression at the position
+    assert!(ast.span(Position::new(2, 1)).is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_positions_spans_nested() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let ast = engine.compile("let x = 0; while x < 10 { x += 1; }")?;
+
+    // The 'while' statement has the position of its condition 'x < 10'
+    let spans: Vec<_> = ast
+        .spans(Position::new(1, 20))
+        .map(|(node_type, span)| {
+            (
+                node_type,
+                span.start.position().unwrap(),
+                span.end.position().unwrap(),
+            )
+        })
+        .collect();
+
+    assert_eq!(
+        spans,
+        vec![(NodeType::FnCall, 18, 23), (NodeType::While, 12, 35)]
+    );
+
+    Ok(())
+}
@@ -0,0 +1,127 @@
+use rhai::{Engine, EvalAltResult, ParseErrorType, Position, INT};
+
+#[test]
+fn test_recovery() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let script = r"
+        let x = 1;
+        let y = ;
+        x += 10
+        x += 100;
+        let z = (x + ;
+        x
+    ";
+
+    let (ast, errors) = engine.compile_with_recovery(script);
+
+    assert_eq!(
+        errors
+            .iter()
+            .map(|err| (err.0.as_ref().clone(), err.1))
+            .collect::<Vec<_>>(),
+        vec![
+            (
+                ParseErrorType::BadInput("Unexpected ';'".into()),
+                Position::new(3, 17)
+            ),
+            (
+                ParseErrorType::MissingToken(";".into(), "to terminate this statement".into()),
+                Position::new(5, 9)
+            ),
+            (
+                ParseErrorType::BadInput("Unexpected ';'".into()),
+                Position::new(6, 22)
+            ),
+        ]
+    );
+
+    // The first error is the same as without recovery
+    assert_eq!(engine.compile(script).expect_err("should error"), errors[0]);
+
+    // Statements not in error are kept
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 111);
+
+    // No errors
+    let (ast, errors) = engine.compile_with_recovery("let x = 40; x + 2");
+    assert!(errors.is_empty());
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 42);
+
+    Ok(())
+}
+
+#[test]
+fn test_recovery_blocks() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let (ast, errors) = engine.compile_with_recovery(
+        r"
+            let x = 0;
+            if x == 0 {
+                x = * 2;
+                x += 1;
+            } else {
+                x = [1, 2;
+            }
+            x += ];
+            x
+        ",
+    );
+
+    assert_eq!(
+        errors
+            .iter()
+            .map(|err| err.1.line().unwrap())
+            .collect::<Vec<_>>(),
+        vec![4, 7, 9]
+    );
+
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 1);
+
+    // Unterminated block
+    let script = "let x = 1; { x += 1; let y = ;";
+    let (_, errors) = engine.compile_with_recovery(script);
+
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0], engine.compile(script).expect_err("should error"));
+    assert_eq!(
+        *errors[1].0,
+        ParseErrorType::MissingToken("}".into(), "to end this statement block".into())
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_recovery_functions() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let (ast, errors) = engine.compile_with_recovery(
+        r"
+            fn foo(x) {
+                let y = x +;
+                x * 2
+            }
+            fn bar(x y) {
+                x
+            }
+            fn baz() { 42 }
+            foo(baz())
+        ",
+    );
+
+    assert_eq!(
+        errors
+            .iter()
+            .map(|err| err.1.line().unwrap())
+            .collect::<Vec<_>>(),
+        vec![3, 6]
+    );
+
+    // Functions not in error are kept
+    assert_eq!(engine.eval_ast::<INT>(&ast)?, 84);
+    assert!(ast.iter_functions().all(|f| f.name != "bar"));
+
+    Ok(())
+}
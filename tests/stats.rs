@@ -0,0 +1,149 @@
+use rhai::{Backend, Engine, EvalAltResult, Scope, INT};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+#[test]
+fn test_stats_operations() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    let operations = Arc::new(AtomicU64::new(0));
+    let counter = operations.clone();
+
+    engine.on_progress(move |&ops| {
+        counter.store(ops, Ordering::Relaxed);
+        true
+    });
+
+    let ast = engine.compile("let x = 0; for n in range(0, 10) { x += n; } x")?;
+
+    let (result, stats) = engine.eval_ast_with_stats::<INT>(&mut Scope::new(), &ast);
+
+    assert_eq!(result?, 45);
+    assert_eq!(stats.operations, operations.load(Ordering::Relaxed));
+    assert_eq!(stats.max_call_depth, 0);
+    assert_eq!(stats.modules, 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_stats_scope_size() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let mut scope = Scope::new();
+    scope.push("a", 1 as INT);
+
+    let ast = engine.compile("let x = a; { let y = x; let z = y; x = z; } let w = x;")?;
+
+    let (result, stats) = engine.eval_ast_with_stats::<()>(&mut scope, &ast);
+
+    result?;
+    assert_eq!(stats.max_scope_size, 4);
+
+    #[cfg(not(feature = "no_function"))]
+    {
+        let ast = engine.compile("fn f(a, b, c, d, e) { let x = a + e; x } f(1, 2, 3, 4, 5)")?;
+
+        let (result, stats) = engine.eval_ast_with_stats::<INT>(&mut Scope::new(), &ast);
+
+        assert_eq!(result?, 6);
+        assert_eq!(stats.max_scope_size, 6);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_stats_fn_calls() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    let ast = engine.compile(
+        r#"
+            let x = 1 + 2 + 3;
+            print(x);
+            eval("print(x + 1)");
+        "#,
+    )?;
+
+    let (result, stats) = engine.eval_ast_with_stats::<()>(&mut Scope::new(), &ast);
+
+    result?;
+    assert_eq!(stats.fn_calls.get("print"), Some(&2));
+    assert_eq!(stats.fn_calls.get("+"), Some(&3));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_stats_call_depth() -> Result<(), Box<EvalAltResult>> {
+    for backend in [Backend::TreeWalking, Backend::Bytecode].iter() {
+        let mut engine = Engine::new();
+        engine.set_backend(*backend);
+
+        let ast = engine.compile(
+            r"
+                fn fib(n) { if n < 2 { n } else { fib(n - 1) + fib(n - 2) } }
+                fib(10)
+            ",
+        )?;
+
+        let (result, stats) = engine.eval_ast_with_stats::<INT>(&mut Scope::new(), &ast);
+
+        assert_eq!(result?, 55);
+        assert_eq!(stats.max_call_depth, 10);
+        assert_eq!(stats.fn_calls.get("fib"), Some(&177));
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "unchecked"))]
+fn test_stats_error() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.set_max_operations(100);
+
+    let ast = engine.compile("let x = 0; loop { x += 1; }")?;
+
+    // Statistics are returned even when the script fails
+    let (result, stats) = engine.eval_ast_with_stats::<()>(&mut Scope::new(), &ast);
+
+    assert!(matches!(
+        *result.expect_err("should error"),
+        EvalAltResult::ErrorTooManyOperations(_)
+    ));
+    assert_eq!(stats.operations, 101);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_module"))]
+fn test_stats_modules() -> Result<(), Box<EvalAltResult>> {
+    use rhai::{module_resolvers::StaticModuleResolver, Module};
+
+    let mut resolver = StaticModuleResolver::new();
+
+    let mut module = Module::new();
+    module.set_var("answer", 42 as INT);
+    resolver.insert("hello", module);
+
+    let mut engine = Engine::new();
+    engine.set_module_resolver(Some(resolver));
+
+    let ast = engine.compile(
+        r#"
+            import "hello" as h1;
+            import "hello" as h2;
+            h1::answer + h2::answer
+        "#,
+    )?;
+
+    let (result, stats) = engine.eval_ast_with_stats::<INT>(&mut Scope::new(), &ast);
+
+    assert_eq!(result?, 84);
+    assert_eq!(stats.modules, 2);
+
+    Ok(())
+}
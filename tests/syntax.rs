@@ -73,7 +73,7 @@ fn test_custom_syntax() -> Result<(), Box<EvalAltResult>> {
     // The first symbol must be an identifier
     assert!(matches!(
         *engine.register_custom_syntax(&["!"], 0, |_, _, _, _| Ok(().into())).expect_err("should error"),
-        ParseError(err, _) if *err == ParseErrorType::BadInput("Improper symbol for custom syntax: '!'".to_string())
+        ParseError(err, ..) if *err == ParseErrorType::BadInput("Improper symbol for custom syntax: '!'".to_string())
     ));
 
     Ok(())
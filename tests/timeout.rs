@@ -0,0 +1,111 @@
+#![cfg(not(feature = "unchecked"))]
+use rhai::{Engine, EvalAltResult, Scope, INT};
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_max_execution_time() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.set_max_execution_time(Duration::from_millis(50));
+
+    assert_eq!(engine.max_execution_time(), Duration::from_millis(50));
+    assert_eq!(engine.eval::<INT>("let x = 40; x + 2")?, 42);
+
+    let start = Instant::now();
+
+    assert!(matches!(
+        *engine.eval::<()>("loop {}").expect_err("should error"),
+        EvalAltResult::ErrorTimeout(_)
+    ));
+
+    assert!(start.elapsed() >= Duration::from_millis(50));
+
+    // The clock restarts with every evaluation
+    assert_eq!(engine.eval::<INT>("let x = 40; x + 2")?, 42);
+
+    engine.set_max_execution_time(Duration::from_secs(0));
+
+    engine.eval::<()>("for x in range(0, 10000) {}")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_max_execution_time_eval() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    engine.set_max_execution_time(Duration::from_millis(50));
+
+    // Scripts run by 'eval' share the deadline
+    assert!(matches!(
+        *engine
+            .eval::<()>(r#"eval("loop {}")"#)
+            .expect_err("should error"),
+        EvalAltResult::ErrorTimeout(_)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_deadline() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+    let mut scope = Scope::new();
+
+    let ast = engine.compile("loop {}")?;
+
+    let deadline = Instant::now() + Duration::from_millis(50);
+
+    assert!(matches!(
+        *engine
+            .eval_ast_with_scope_and_deadline::<()>(&mut scope, &ast, deadline)
+            .expect_err("should error"),
+        EvalAltResult::ErrorTimeout(_)
+    ));
+
+    assert!(Instant::now() >= deadline);
+
+    // The earlier deadline applies
+    engine.set_max_execution_time(Duration::from_millis(50));
+
+    let start = Instant::now();
+
+    assert!(matches!(
+        *engine
+            .eval_ast_with_scope_and_deadline::<()>(
+                &mut scope,
+                &ast,
+                start + Duration::from_secs(60)
+            )
+            .expect_err("should error"),
+        EvalAltResult::ErrorTimeout(_)
+    ));
+
+    assert!(start.elapsed() < Duration::from_secs(60));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_deadline_call_fn() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+    let mut scope = Scope::new();
+
+    let ast = engine.compile("fn add(x, y) { x + y } fn spin() { loop {} }")?;
+
+    let deadline = Instant::now() + Duration::from_secs(60);
+
+    let result: INT =
+        engine.call_fn_with_deadline(&mut scope, &ast, "add", (40 as INT, 2 as INT), deadline)?;
+    assert_eq!(result, 42);
+
+    let deadline = Instant::now() + Duration::from_millis(50);
+
+    assert!(matches!(
+        *engine
+            .call_fn_with_deadline::<_, ()>(&mut scope, &ast, "spin", (), deadline)
+            .expect_err("should error"),
+        EvalAltResult::ErrorInFunctionCall(_, _, _, err, _) if matches!(*err, EvalAltResult::ErrorTimeout(_))
+    ));
+
+    Ok(())
+}
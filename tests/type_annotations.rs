@@ -0,0 +1,223 @@
+use rhai::{Backend, Engine, EvalAltResult, ParseErrorType, Position, RegisterFn, INT};
+use std::any::type_name;
+
+#[test]
+fn test_type_annotations_let() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    assert_eq!(engine.eval::<INT>("let x: int = 40 + 2; x")?, 42);
+    assert_eq!(
+        engine.eval::<String>(r#"let x: string = "hello"; x"#)?,
+        "hello"
+    );
+    assert_eq!(engine.eval::<INT>("const X: int = 42; X")?, 42);
+
+    assert!(matches!(
+        *engine.compile("let x: string = 42;").expect_err("should error").0,
+        ParseErrorType::MismatchedType(r, a) if r == "string" && a == type_name::<INT>()
+    ));
+    assert!(matches!(
+        *engine.compile("const X: bool = 42;").expect_err("should error").0,
+        ParseErrorType::MismatchedType(r, _) if r == "bool"
+    ));
+    assert!(matches!(
+        *engine.compile("let x: = 42;").expect_err("should error").0,
+        ParseErrorType::TypeExpected
+    ));
+
+    assert!(matches!(
+        *engine.eval::<()>(r#"let x = "hello"; let y: int = x + 1;"#).expect_err("should error"),
+        EvalAltResult::ErrorMismatchDataType(r, a, _) if r == "int" && a == "string"
+    ));
+
+    // '->' is only valid before the return type of a function
+    assert!(matches!(
+        *engine.compile("let x = 1 -> 2;").expect_err("should error").0,
+        ParseErrorType::BadInput(s) if s.contains("This is not C or C++")
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_type_annotations_assignment() -> Result<(), Box<EvalAltResult>> {
+    for backend in [Backend::TreeWalking, Backend::Bytecode].iter() {
+        let mut engine = Engine::new();
+        engine.set_backend(*backend);
+
+        assert_eq!(engine.eval::<INT>("let x: int = 1; x = 41; x += 1; x")?, 42);
+        assert_eq!(engine.eval::<INT>("let x: int; x = 42; x")?, 42);
+
+        assert!(matches!(
+            *engine.compile(r#"let x: int = 1; x = "hello";"#).expect_err("should error").0,
+            ParseErrorType::MismatchedType(r, a) if r == "int" && a == "string"
+        ));
+
+        // Values that cannot be typed statically are checked at run-time
+        assert!(matches!(
+            *engine.eval::<()>(r#"let s = "hello"; let x: int = 1; x = s;"#).expect_err("should error"),
+            EvalAltResult::ErrorMismatchDataType(r, a, pos)
+                if r == "int" && a == "string" && pos.position() == Some(34)
+        ));
+        assert!(matches!(
+            *engine.eval::<()>(r#"let x: int = 1; x += "hello";"#).expect_err("should error"),
+            EvalAltResult::ErrorMismatchDataType(r, a, _) if r == "int" && a == "string"
+        ));
+        assert!(matches!(
+            *engine.eval::<()>(r#"let s = "hello"; let x: int; { x = s; }"#).expect_err("should error"),
+            EvalAltResult::ErrorMismatchDataType(_, _, _)
+        ));
+
+        // Shadowing variables are not annotated
+        assert_eq!(
+            engine.eval::<String>(r#"let x: int = 1; let x = 2; x = "hello"; x"#)?,
+            "hello"
+        );
+
+        // Parameters
+        #[cfg(not(feature = "no_function"))]
+        {
+            assert!(matches!(
+                *engine.compile(r#"fn f(x: int) { x = "hello"; }"#).expect_err("should error").0,
+                ParseErrorType::MismatchedType(r, _) if r == "int"
+            ));
+            assert!(matches!(
+                *engine.eval::<()>(r#"fn f(x: int, y) { x = y; } f(1, "hello")"#).expect_err("should error"),
+                EvalAltResult::ErrorInFunctionCall(f, _, _, err, _)
+                    if f == "f" && matches!(*err, EvalAltResult::ErrorMismatchDataType(_, _, _))
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+fn test_type_annotations_functions() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    assert_eq!(
+        engine.eval::<INT>("fn add(x: int, y: int) -> int { x + y } add(40, 2)")?,
+        42
+    );
+    assert_eq!(
+        engine.eval::<INT>("fn add(x: int, y) { x + y } add(40, 2)")?,
+        42
+    );
+
+    #[cfg(not(feature = "no_float"))]
+    assert_eq!(
+        engine.eval::<rhai::FLOAT>(
+            "fn area(w: int, h: float) -> float { to_float(w) * h } area(2, 21.0)"
+        )?,
+        42.0
+    );
+
+    // Mismatched arguments are errors inside the call, reported at the call
+    let err = engine
+        .eval::<INT>("fn add(x: int, y: int) { x + y }\n\n add(40, \"2\")")
+        .expect_err("should error");
+
+    assert_eq!(err.position(), Position::new(3, 2));
+    assert!(matches!(
+        *err,
+        EvalAltResult::ErrorInFunctionCall(f, _, _, err, _)
+            if f == "add" && matches!(*err, EvalAltResult::ErrorMismatchDataType(ref r, ref a, _)
+                if r == "int" && a == "string")
+    ));
+
+    assert!(matches!(
+        *engine.eval::<INT>(r#"fn id(x) -> int { x } id("hello")"#).expect_err("should error"),
+        EvalAltResult::ErrorInFunctionCall(f, _, _, err, _)
+            if f == "id" && matches!(*err, EvalAltResult::ErrorMismatchDataType(_, _, _))
+    ));
+
+    assert!(matches!(
+        *engine
+            .compile("fn foo(x:) { x }")
+            .expect_err("should error")
+            .0,
+        ParseErrorType::TypeExpected
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_type_annotations_unit_and_dynamic() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+
+    assert_eq!(engine.eval::<INT>("let x: Dynamic = 1; x = 41; x + 1")?, 42);
+    assert_eq!(
+        engine.eval::<String>(r#"let x: Dynamic = 1; x = "hello"; x"#)?,
+        "hello"
+    );
+    engine.eval::<()>("let x: () = (); let y: unit = x;")?;
+
+    #[cfg(not(feature = "no_function"))]
+    {
+        engine.eval::<()>("fn f() -> () {} f()")?;
+        engine.eval::<()>("fn f() -> unit {} f()")?;
+        assert_eq!(
+            engine.eval::<INT>("fn f(x: Dynamic) -> Dynamic { x } f(42)")?,
+            42
+        );
+
+        assert!(matches!(
+            *engine.eval::<INT>("fn f() -> () { 42 } f()").expect_err("should error"),
+            EvalAltResult::ErrorInFunctionCall(_, _, _, err, _)
+                if matches!(*err, EvalAltResult::ErrorMismatchDataType(ref r, _, _) if r == "()")
+        ));
+    }
+
+    assert!(matches!(
+        *engine.compile("let x: unit = 42;").expect_err("should error").0,
+        ParseErrorType::MismatchedType(r, _) if r == "unit"
+    ));
+    assert!(matches!(
+        *engine.compile("let x: ( = 42;").expect_err("should error").0,
+        ParseErrorType::TypeExpected
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_type_annotations_registered_functions() -> Result<(), Box<EvalAltResult>> {
+    let mut engine = Engine::new();
+
+    engine.register_fn("double", |x: INT| x * 2);
+
+    // Signatures are not checked unless enabled
+    assert!(engine.compile(r#"let x: string = "hello"; double(x)"#).is_ok());
+
+    engine.set_check_fn_signatures(true);
+
+    assert_eq!(engine.eval::<INT>("let x: int = 21; double(x)")?, 42);
+
+    assert!(matches!(
+        *engine.compile(r#"let x: string = "hello"; double(x)"#).expect_err("should error").0,
+        ParseErrorType::MismatchedFnArgs(s) if s == "double (string)"
+    ));
+    assert!(matches!(
+        *engine.compile("let x: bool = true; if false { double(x) }").expect_err("should error").0,
+        ParseErrorType::MismatchedFnArgs(s) if s == "double (bool)"
+    ));
+
+    // Only calls with arguments of annotated types are checked, others are checked at run-time
+    assert!(engine.compile(r#"let x = "hello"; double(x)"#).is_ok());
+    assert!(engine.compile("if false { double(true) }").is_ok());
+    assert!(engine.compile("len(1)").is_ok());
+
+    #[cfg(not(feature = "no_function"))]
+    assert!(matches!(
+        *engine
+            .compile(r#"fn name() -> string { "hello" } double(name())"#)
+            .expect_err("should error")
+            .0,
+        ParseErrorType::MismatchedFnArgs(_)
+    ));
+
+    Ok(())
+}
@@ -52,3 +52,86 @@ fn test_scope_eval() -> Result<(), Box<EvalAltResult>> {
 
     Ok(())
 }
+
+#[test]
+fn test_var_scope_slots() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+    let mut scope = Scope::new();
+
+    scope.push("a", 1 as INT);
+    scope.push("b", 2 as INT);
+
+    assert_eq!(
+        engine.eval_with_scope::<INT>(
+            &mut scope,
+            r"
+                let x = 10;
+                let y = a + x;
+                {
+                    let x = 100;
+                    y += x + b;
+                }
+                for i in range(0, 3) {
+                    let x = i;
+                    y += x;
+                }
+                x + y
+            "
+        )?,
+        126
+    );
+
+    #[cfg(not(feature = "no_function"))]
+    assert_eq!(
+        engine.eval_with_scope::<INT>(
+            &mut scope,
+            r"
+                fn add(x, y) { let z = x * 10; z + y }
+                let x = 1;
+                add(x, 2) + add(3, x)
+            "
+        )?,
+        43
+    );
+
+    assert_eq!(
+        engine.eval_with_scope::<INT>(
+            &mut scope,
+            r#"
+                let x = 1;
+                eval("let x = 42;");
+                x
+            "#
+        )?,
+        42
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "no_function"))]
+#[cfg(not(feature = "no_closures"))]
+#[cfg(not(feature = "no_object"))]
+fn test_var_scope_closure_slots() -> Result<(), Box<EvalAltResult>> {
+    let engine = Engine::new();
+    let mut scope = Scope::new();
+
+    scope.push("base", 1000 as INT);
+
+    assert_eq!(
+        engine.eval_with_scope::<INT>(
+            &mut scope,
+            r"
+                let x = 1;
+                let y = 20;
+                let f = |z| base + x + y + z;
+                let x = 300;
+                f.call(x)
+            "
+        )?,
+        1321
+    );
+
+    Ok(())
+}